@@ -0,0 +1,3 @@
+DROP INDEX demons_last_modified_idx;
+
+ALTER TABLE demons DROP COLUMN last_modified;
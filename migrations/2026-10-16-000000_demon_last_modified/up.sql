@@ -0,0 +1,5 @@
+-- Tracks when the row-state of a demon last changed, to allow mirrors of the list to sync incrementally
+
+ALTER TABLE demons ADD COLUMN last_modified TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
+
+CREATE INDEX demons_last_modified_idx ON demons(last_modified);
@@ -0,0 +1,13 @@
+-- Generic audit log recording the changes made by every PATCH request, regardless of the patched object
+
+CREATE TABLE audit_log (
+    id SERIAL PRIMARY KEY,
+    time TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    userid INTEGER NOT NULL,
+    target TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    changes JSONB NOT NULL
+);
+
+CREATE INDEX audit_log_target_idx ON audit_log(target, target_id);
+CREATE INDEX audit_log_userid_idx ON audit_log(userid);
@@ -0,0 +1,3 @@
+DROP VIEW ranked_players;
+
+DROP TABLE player_scores;
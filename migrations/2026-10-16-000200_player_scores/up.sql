@@ -0,0 +1,23 @@
+-- Stores the demonlist score of each player, as computed by the `score` module of pointercrate-demonlist.
+-- The table is fully recomputed whenever a record or demon changes, and the ranking endpoints read from the
+-- `ranked_players` view on top of it.
+
+CREATE TABLE player_scores (
+    player INTEGER PRIMARY KEY REFERENCES players(id) ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL
+);
+
+CREATE VIEW ranked_players AS
+SELECT players.id,
+       players.name,
+       RANK() OVER (ORDER BY player_scores.score DESC) AS rank,
+       player_scores.score,
+       ROW_NUMBER() OVER (ORDER BY player_scores.score DESC, players.id) AS index,
+       nationalities.nation,
+       nationalities.iso_country_code,
+       nationalities.continent,
+       players.subdivision
+FROM player_scores
+INNER JOIN players ON player_scores.player = players.id
+LEFT OUTER JOIN nationalities ON players.nationality = nationalities.iso_country_code
+WHERE NOT players.banned AND player_scores.score > 0;
@@ -0,0 +1,9 @@
+DROP TRIGGER members_last_modified ON members;
+DROP TRIGGER players_last_modified ON players;
+DROP TRIGGER records_last_modified ON records;
+
+ALTER TABLE members DROP COLUMN last_modified;
+ALTER TABLE players DROP COLUMN last_modified;
+ALTER TABLE records DROP COLUMN last_modified;
+
+DROP FUNCTION set_last_modified();
@@ -0,0 +1,18 @@
+-- Tracks when records, players and users were last changed, to support `Last-Modified` and `If-Unmodified-Since`.
+-- Unlike for demons (see 2026-10-16-000000_demon_last_modified), these are maintained by a trigger, as there are far
+-- too many code paths updating these tables.
+
+CREATE FUNCTION set_last_modified() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.last_modified := NOW() AT TIME ZONE 'utc';
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE records ADD COLUMN last_modified TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
+ALTER TABLE players ADD COLUMN last_modified TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
+ALTER TABLE members ADD COLUMN last_modified TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
+
+CREATE TRIGGER records_last_modified BEFORE UPDATE ON records FOR EACH ROW EXECUTE PROCEDURE set_last_modified();
+CREATE TRIGGER players_last_modified BEFORE UPDATE ON players FOR EACH ROW EXECUTE PROCEDURE set_last_modified();
+CREATE TRIGGER members_last_modified BEFORE UPDATE ON members FOR EACH ROW EXECUTE PROCEDURE set_last_modified();
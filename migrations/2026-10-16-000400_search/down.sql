@@ -0,0 +1,3 @@
+DROP INDEX members_name_trgm_idx;
+DROP INDEX players_name_trgm_idx;
+DROP INDEX demons_name_trgm_idx;
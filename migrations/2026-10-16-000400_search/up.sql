@@ -0,0 +1,7 @@
+-- Trigram indexes backing the `/api/v1/search/` endpoint, which matches names by substring and by similarity.
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX demons_name_trgm_idx ON demons USING GIN ((name::TEXT) gin_trgm_ops);
+CREATE INDEX players_name_trgm_idx ON players USING GIN ((name::TEXT) gin_trgm_ops);
+CREATE INDEX members_name_trgm_idx ON members USING GIN ((name::TEXT) gin_trgm_ops);
@@ -0,0 +1,15 @@
+-- Long-lived refresh tokens. Only a hash of each token is stored, the token itself is only ever shown to the client
+-- it was issued to. Deleting a row revokes the session, including all access tokens issued for it.
+
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+CREATE TABLE sessions (
+    id SERIAL PRIMARY KEY,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    last_used TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+
+CREATE INDEX sessions_member_idx ON sessions(member);
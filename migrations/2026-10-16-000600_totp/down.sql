@@ -0,0 +1,2 @@
+ALTER TABLE members DROP COLUMN totp_enabled;
+ALTER TABLE members DROP COLUMN totp_secret;
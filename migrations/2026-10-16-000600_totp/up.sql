@@ -0,0 +1,6 @@
+-- TOTP based two-factor authentication. The secret is encrypted (via pgcrypto's `pgp_sym_encrypt_bytea`) using a key
+-- derived from the application secret. It only becomes effective once the user confirmed the enrollment by providing
+-- a valid code, at which point `totp_enabled` is set.
+
+ALTER TABLE members ADD COLUMN totp_secret BYTEA;
+ALTER TABLE members ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+DROP INDEX members_verified_email_key;
+ALTER TABLE members DROP COLUMN email_verified;
+ALTER TABLE members DROP COLUMN email;
@@ -0,0 +1,7 @@
+-- Optional email addresses for users. An address is only used for password resets once it has been verified.
+
+ALTER TABLE members ADD COLUMN email CITEXT;
+ALTER TABLE members ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Only verified addresses need to be unique, so that nobody can block an address by setting it without owning it
+CREATE UNIQUE INDEX members_verified_email_key ON members (email) WHERE email_verified;
@@ -0,0 +1,10 @@
+-- Deleted records are moved here (together with their notes) instead of being dropped, so that they can be restored.
+-- Note that columns added to `records` in the future need to be added to this table as well.
+
+CREATE TABLE deleted_records (LIKE records INCLUDING DEFAULTS);
+
+ALTER TABLE deleted_records ADD PRIMARY KEY (id);
+ALTER TABLE deleted_records ADD COLUMN notes JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE deleted_records ADD COLUMN reason TEXT NOT NULL;
+ALTER TABLE deleted_records ADD COLUMN deleted_by INTEGER REFERENCES members(member_id) ON DELETE SET NULL;
+ALTER TABLE deleted_records ADD COLUMN deleted_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
@@ -0,0 +1 @@
+ALTER TABLE demons DROP COLUMN legacy_position;
@@ -0,0 +1,9 @@
+-- Demons beyond the extended list are numbered in the order they dropped off it. Once assigned, a legacy position never
+-- changes, except for being cleared if the demon is moved back onto the extended list.
+
+ALTER TABLE demons ADD COLUMN legacy_position INTEGER UNIQUE;
+
+-- The application re-freezes positions whenever the list changes, so assuming the default extended list size here is fine.
+UPDATE demons SET legacy_position = numbered.legacy_position
+FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY position DESC) AS legacy_position FROM demons WHERE position > 100) AS numbered
+WHERE demons.id = numbered.id;
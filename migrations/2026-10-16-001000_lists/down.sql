@@ -0,0 +1,4 @@
+ALTER TABLE demons DROP CONSTRAINT unique_list_position;
+ALTER TABLE demons ADD CONSTRAINT unique_position UNIQUE (position) DEFERRABLE INITIALLY IMMEDIATE;
+ALTER TABLE demons DROP COLUMN list_id;
+DROP TABLE lists;
@@ -0,0 +1,29 @@
+-- Every demon belongs to exactly one list. Records are scoped to a list through their demon.
+
+CREATE TABLE lists (
+    id SERIAL PRIMARY KEY,
+    slug CITEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL
+);
+
+INSERT INTO lists (id, slug, name) VALUES (1, 'classic', 'Demonlist');
+
+SELECT setval('lists_id_seq', 1);
+
+ALTER TABLE demons ADD COLUMN list_id INTEGER NOT NULL DEFAULT 1 REFERENCES lists(id);
+
+-- Positions are only unique within a list. The name of the original constraint depends on how the database was set up.
+DO $$
+DECLARE
+    position_constraint TEXT;
+BEGIN
+    SELECT conname INTO position_constraint FROM pg_constraint
+    WHERE conrelid = 'demons'::regclass AND contype = 'u'
+      AND conkey = ARRAY[(SELECT attnum FROM pg_attribute WHERE attrelid = 'demons'::regclass AND attname = 'position')];
+
+    IF position_constraint IS NOT NULL THEN
+        EXECUTE format('ALTER TABLE demons DROP CONSTRAINT %I', position_constraint);
+    END IF;
+END $$;
+
+ALTER TABLE demons ADD CONSTRAINT unique_list_position UNIQUE (list_id, position) DEFERRABLE INITIALLY IMMEDIATE;
@@ -0,0 +1,5 @@
+ALTER TABLE deleted_records DROP COLUMN video_checked_at;
+ALTER TABLE deleted_records DROP COLUMN video_status;
+
+ALTER TABLE records DROP COLUMN video_checked_at;
+ALTER TABLE records DROP COLUMN video_status;
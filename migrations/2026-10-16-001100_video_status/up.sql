@@ -0,0 +1,6 @@
+-- Result of the periodic check whether a record's video proof is still reachable
+ALTER TABLE records ADD COLUMN video_status TEXT NOT NULL DEFAULT 'UNCHECKED' CHECK (video_status IN ('UNCHECKED', 'ALIVE', 'DEAD'));
+ALTER TABLE records ADD COLUMN video_checked_at TIMESTAMP WITHOUT TIME ZONE;
+
+ALTER TABLE deleted_records ADD COLUMN video_status TEXT NOT NULL DEFAULT 'UNCHECKED';
+ALTER TABLE deleted_records ADD COLUMN video_checked_at TIMESTAMP WITHOUT TIME ZONE;
@@ -0,0 +1,2 @@
+ALTER TABLE deleted_records DROP COLUMN raw_footage;
+ALTER TABLE records DROP COLUMN raw_footage;
@@ -0,0 +1,3 @@
+-- Link to the unedited footage of a completion. Only visible to list moderators.
+ALTER TABLE records ADD COLUMN raw_footage TEXT;
+ALTER TABLE deleted_records ADD COLUMN raw_footage TEXT;
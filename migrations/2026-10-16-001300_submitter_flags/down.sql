@@ -0,0 +1 @@
+ALTER TABLE submitters DROP COLUMN flagged;
@@ -0,0 +1,2 @@
+-- Set by the submission guard for submitters that show signs of spamming. Flagged submitters cannot submit records until a list moderator unflags them.
+ALTER TABLE submitters ADD COLUMN flagged BOOLEAN NOT NULL DEFAULT FALSE;
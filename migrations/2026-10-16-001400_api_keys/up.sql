@@ -0,0 +1,13 @@
+-- Long-lived API keys, restricted to a set of scopes and a subset of their owner's permissions. Only hashes of the keys are stored.
+CREATE TABLE api_keys (
+    id SERIAL PRIMARY KEY,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL CHECK (scopes <@ ARRAY['read', 'submit', 'write']),
+    permissions INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    last_used TIMESTAMP WITHOUT TIME ZONE
+);
+
+CREATE INDEX api_keys_member_idx ON api_keys(member);
@@ -0,0 +1,3 @@
+-- Optional thumbnail image for demons, either set directly as URL or uploaded by a list moderator
+
+ALTER TABLE demons ADD COLUMN thumbnail TEXT;
@@ -0,0 +1,3 @@
+-- Discord accounts linked to pointercrate accounts, for logging in via Discord's OAuth2 flow
+
+ALTER TABLE members ADD COLUMN discord_id BIGINT UNIQUE;
@@ -0,0 +1 @@
+DROP VIEW demon_victors;
@@ -0,0 +1,11 @@
+-- The number of victors of each demon (players with an approved 100% record) together with the first of them (the one
+-- whose record was submitted earliest). Demons without victors have no row.
+
+CREATE VIEW demon_victors AS
+SELECT DISTINCT ON (records.demon) records.demon,
+       records.player AS first_victor,
+       COUNT(*) OVER (PARTITION BY records.demon) AS victors
+FROM records
+LEFT OUTER JOIN record_additions ON record_additions.id = records.id
+WHERE records.status_ = 'APPROVED' AND records.progress = 100
+ORDER BY records.demon, record_additions.time ASC NULLS FIRST, records.id ASC;
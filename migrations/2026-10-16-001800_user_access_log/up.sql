@@ -0,0 +1,12 @@
+-- Registrations and logins, together with a keyed hash of the IP address they came from. Used to find accounts operated by the same person.
+CREATE TABLE user_access_log (
+    id SERIAL PRIMARY KEY,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('registration', 'login')),
+    ip_hash TEXT NOT NULL,
+    time TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
+
+CREATE INDEX user_access_log_member_idx ON user_access_log(member);
+CREATE INDEX user_access_log_ip_hash_idx ON user_access_log(ip_hash);
+CREATE INDEX user_access_log_time_idx ON user_access_log(time);
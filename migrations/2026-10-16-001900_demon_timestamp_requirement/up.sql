@@ -0,0 +1,2 @@
+-- Whether submissions for a demon need to link to the point in the video where the completion happens
+ALTER TABLE demons ADD COLUMN requires_timestamp BOOLEAN NOT NULL DEFAULT FALSE;
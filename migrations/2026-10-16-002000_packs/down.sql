@@ -0,0 +1,2 @@
+DROP TABLE pack_demons;
+DROP TABLE packs;
@@ -0,0 +1,15 @@
+-- Packs are named groups of demons. A player completes a pack by having approved 100% records on at least `required` of its demons.
+
+CREATE TABLE packs (
+    id SERIAL PRIMARY KEY,
+    name CITEXT NOT NULL UNIQUE,
+    required SMALLINT NOT NULL CHECK (required > 0)
+);
+
+CREATE TABLE pack_demons (
+    pack INTEGER NOT NULL REFERENCES packs(id) ON DELETE CASCADE,
+    demon INTEGER NOT NULL REFERENCES demons(id) ON DELETE CASCADE,
+    PRIMARY KEY (pack, demon)
+);
+
+CREATE INDEX pack_demons_demon ON pack_demons(demon);
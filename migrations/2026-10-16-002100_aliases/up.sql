@@ -0,0 +1,16 @@
+-- Alternate spellings of player and demon names (transliterations, common typos). Looking up a player or demon by name falls back to
+-- these if no player/demon with the given name exists. Every alias belongs to exactly one player or demon.
+
+CREATE TABLE aliases (
+    id SERIAL PRIMARY KEY,
+    name CITEXT NOT NULL CHECK (name <> ''),
+    player INTEGER REFERENCES players(id) ON DELETE CASCADE,
+    demon INTEGER REFERENCES demons(id) ON DELETE CASCADE,
+    CHECK ((player IS NULL) <> (demon IS NULL))
+);
+
+CREATE UNIQUE INDEX unique_player_alias ON aliases(name) WHERE player IS NOT NULL;
+CREATE UNIQUE INDEX unique_demon_alias ON aliases(name) WHERE demon IS NOT NULL;
+
+CREATE INDEX aliases_player ON aliases(player);
+CREATE INDEX aliases_demon ON aliases(demon);
@@ -0,0 +1 @@
+DROP INDEX demons_level_id_unique;
@@ -0,0 +1,16 @@
+-- Level ids were only ever assigned automatically, so there might be duplicates. These need to be resolved by hand, since
+-- there is no telling which of the demons the level id actually belongs to.
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(format('%s (list %s)', level_id, list_id), ', ') INTO duplicates FROM (
+        SELECT level_id, list_id FROM demons WHERE level_id IS NOT NULL GROUP BY list_id, level_id HAVING COUNT(*) > 1
+    ) AS duplicated;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'The following level ids are assigned to more than one demon: %', duplicates;
+    END IF;
+END $$;
+
+CREATE UNIQUE INDEX demons_level_id_unique ON demons (list_id, level_id);
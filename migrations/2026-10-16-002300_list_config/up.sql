@@ -0,0 +1,9 @@
+-- Single row table. NULL means the value from the environment (LIST_SIZE and EXTENDED_LIST_SIZE) is used.
+CREATE TABLE list_config (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    list_size SMALLINT CHECK (list_size > 0),
+    extended_list_size SMALLINT CHECK (extended_list_size > 0),
+    CHECK (list_size IS NULL OR extended_list_size IS NULL OR list_size <= extended_list_size)
+);
+
+INSERT INTO list_config DEFAULT VALUES;
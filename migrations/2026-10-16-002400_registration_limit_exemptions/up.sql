@@ -0,0 +1,8 @@
+-- IP addresses (hashed the same way as in user_access_log) exempt from the limit on registrations per day, e.g. schools or
+-- event venues
+CREATE TABLE registration_limit_exemptions (
+    id SERIAL PRIMARY KEY,
+    ip_hash TEXT NOT NULL UNIQUE,
+    note TEXT NOT NULL,
+    created TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc')
+);
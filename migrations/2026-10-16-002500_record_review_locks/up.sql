@@ -0,0 +1,6 @@
+-- Submissions claimed by a member for review. Other members cannot change the status of a claimed record until the claim expires.
+CREATE TABLE record_review_locks (
+    record INTEGER PRIMARY KEY REFERENCES records(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    expires TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Opt-in email notifications. Users without a row here receive no notifications.
+CREATE TABLE notification_preferences (
+    member INTEGER PRIMARY KEY REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    record_decisions BOOLEAN NOT NULL DEFAULT FALSE,
+    queue_size BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- The times at which notification digests were sent, so that restarts neither skip nor repeat any notifications
+CREATE TABLE notification_digests (
+    sent TIMESTAMP WITHOUT TIME ZONE PRIMARY KEY
+);
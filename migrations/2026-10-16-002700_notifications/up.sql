@@ -0,0 +1,11 @@
+-- In-app notifications, shown to users in their inbox
+CREATE TABLE notifications (
+    id SERIAL PRIMARY KEY,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    kind TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    read BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX notifications_member_idx ON notifications(member, id);
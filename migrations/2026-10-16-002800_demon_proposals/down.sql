@@ -0,0 +1,2 @@
+DROP TABLE demon_proposal_votes;
+DROP TABLE demon_proposals;
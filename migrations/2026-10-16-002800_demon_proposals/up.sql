@@ -0,0 +1,25 @@
+-- Placements of new demons prepared by list helpers, which are only executed once enough list moderators approve them
+CREATE TABLE demon_proposals (
+    id SERIAL PRIMARY KEY,
+    name CITEXT NOT NULL,
+    position SMALLINT NOT NULL,
+    requirement SMALLINT NOT NULL,
+    verifier TEXT NOT NULL,
+    publisher TEXT NOT NULL,
+    creators TEXT[] NOT NULL,
+    video TEXT,
+    level_id BIGINT,
+    list_id INTEGER NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+    proposed_by INTEGER REFERENCES members(member_id) ON DELETE SET NULL ON UPDATE CASCADE,
+    created TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    status TEXT NOT NULL DEFAULT 'OPEN' CHECK (status IN ('OPEN', 'APPROVED', 'REJECTED')),
+    -- The demon created when the proposal was approved
+    demon INTEGER REFERENCES demons(id) ON DELETE SET NULL
+);
+
+CREATE TABLE demon_proposal_votes (
+    proposal INTEGER NOT NULL REFERENCES demon_proposals(id) ON DELETE CASCADE,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    approve BOOLEAN NOT NULL,
+    PRIMARY KEY (proposal, member)
+);
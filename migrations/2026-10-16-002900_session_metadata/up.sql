@@ -0,0 +1,4 @@
+-- Where a session was started from, so that users can recognize (and revoke) sessions they did not start themselves. Only the
+-- network prefix of the IP address is stored.
+ALTER TABLE sessions ADD COLUMN ip TEXT;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
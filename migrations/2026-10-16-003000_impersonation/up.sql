@@ -0,0 +1,2 @@
+-- The administrator who made a change while impersonating the user the change is attributed to, if any
+ALTER TABLE audit_log ADD COLUMN impersonated_by INTEGER;
@@ -0,0 +1,5 @@
+ALTER TABLE deleted_records DROP COLUMN archive_attempted_at;
+ALTER TABLE deleted_records DROP COLUMN archive_url;
+
+ALTER TABLE records DROP COLUMN archive_attempted_at;
+ALTER TABLE records DROP COLUMN archive_url;
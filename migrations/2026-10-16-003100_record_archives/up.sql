@@ -0,0 +1,6 @@
+-- Link to a snapshot of a record's video proof in the Wayback Machine, and the time archiving it was last attempted
+ALTER TABLE records ADD COLUMN archive_url TEXT;
+ALTER TABLE records ADD COLUMN archive_attempted_at TIMESTAMP WITHOUT TIME ZONE;
+
+ALTER TABLE deleted_records ADD COLUMN archive_url TEXT;
+ALTER TABLE deleted_records ADD COLUMN archive_attempted_at TIMESTAMP WITHOUT TIME ZONE;
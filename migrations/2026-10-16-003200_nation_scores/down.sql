@@ -0,0 +1,32 @@
+DROP VIEW nations_with_score;
+
+DROP TABLE nation_scores;
+
+-- Restores the view this migration replaced
+CREATE VIEW nations_with_score AS
+SELECT RANK() OVER (ORDER BY scores.total_score DESC) AS rank,
+       scores.total_score AS score,
+       nationalities.iso_country_code,
+       nationalities.nation,
+       nationalities.continent
+FROM (
+    SELECT nationality, SUM(record_score(progress::FLOAT, position::FLOAT, 150::FLOAT, requirement)) AS total_score
+    FROM (
+        SELECT DISTINCT ON (position, nationality) position, requirement, progress, nationality
+        FROM (
+            SELECT demons.position, demons.requirement, records.progress, players.nationality
+            FROM records
+            INNER JOIN demons ON demons.id = records.demon
+            INNER JOIN players ON players.id = records.player
+            WHERE records.status_ = 'APPROVED' AND NOT players.banned AND demons.position <= 150
+            UNION
+            SELECT demons.position, demons.requirement, 100, players.nationality
+            FROM demons
+            INNER JOIN players ON players.id = demons.verifier
+            WHERE demons.position <= 150 AND NOT players.banned
+        ) AS completions
+        ORDER BY position, nationality, progress DESC
+    ) AS best_completions
+    GROUP BY nationality
+) AS scores
+INNER JOIN nationalities ON nationalities.iso_country_code = scores.nationality;
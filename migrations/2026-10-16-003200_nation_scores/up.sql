@@ -0,0 +1,69 @@
+-- Stores the demonlist score of each nation, as computed by the `score` module of pointercrate-demonlist. A nation's
+-- score is computed like a player's, taking the best progress any of its players achieved on each demon. Like
+-- `player_scores`, the table is only refreshed for the players and nations affected by a change.
+
+CREATE TABLE nation_scores (
+    iso_country_code VARCHAR(2) PRIMARY KEY REFERENCES nationalities(iso_country_code) ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL
+);
+
+DROP VIEW IF EXISTS nations_with_score;
+
+CREATE VIEW nations_with_score AS
+SELECT RANK() OVER (ORDER BY nation_scores.score DESC) AS rank,
+       nation_scores.score,
+       nationalities.iso_country_code,
+       nationalities.nation,
+       nationalities.continent
+FROM nation_scores
+INNER JOIN nationalities ON nationalities.iso_country_code = nation_scores.iso_country_code
+WHERE nation_scores.score > 0;
+
+-- Seed both score tables using the default score formula (SCORE_CUTOFF 150, SCORE_PROGRESS_DIVISOR 10), so that the
+-- rankings are not empty until the first refresh. Instances with a different formula should refresh the rankings
+-- (`pointercrate-admin recompute-scores`) after migrating.
+CREATE FUNCTION seed_record_score(position SMALLINT, requirement SMALLINT, progress SMALLINT) RETURNS DOUBLE PRECISION AS $$
+    SELECT CASE WHEN progress = 100 THEN beaten ELSE beaten * POWER(5, (progress - requirement)::DOUBLE PRECISION / (100 - requirement)) / 10 END
+    FROM (
+        SELECT CASE
+            WHEN position > 150 THEN 0
+            WHEN position > 125 THEN 150 * EXP((1 - position) * LN(1.0 / 30) / (-149))
+            WHEN position > 50 THEN 60 * POWER(2.333, (51 - position) * (LN(30) / 99)) + 1.884
+            WHEN position > 20 THEN -100 * POWER(1.01327, position - 26.489) + 200
+            WHEN position > 0 THEN (250 - 100.39) * POWER(1.168, 1 - position) + 100.39
+            ELSE 0
+        END::DOUBLE PRECISION AS beaten
+    ) AS beaten_score
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE TEMPORARY TABLE seed_completions AS
+SELECT completions.player, players.nationality, demons.id AS demon, demons.position, demons.requirement, MAX(completions.progress) AS progress
+FROM (
+    SELECT player, demon, progress FROM records WHERE status_ = 'APPROVED'
+    UNION ALL
+    SELECT verifier, id, 100::SMALLINT FROM demons
+) AS completions
+INNER JOIN demons ON completions.demon = demons.id
+INNER JOIN players ON completions.player = players.id
+WHERE NOT players.banned AND demons.list_id = 1
+GROUP BY completions.player, players.nationality, demons.id, demons.position, demons.requirement;
+
+DELETE FROM player_scores;
+
+INSERT INTO player_scores (player, score)
+SELECT player, SUM(seed_record_score(position, requirement, progress))
+FROM seed_completions
+GROUP BY player;
+
+INSERT INTO nation_scores (iso_country_code, score)
+SELECT nationality, SUM(seed_record_score(position, requirement, progress))
+FROM (
+    SELECT nationality, position, requirement, MAX(progress) AS progress
+    FROM seed_completions
+    WHERE nationality IS NOT NULL
+    GROUP BY nationality, demon, position, requirement
+) AS national_completions
+GROUP BY nationality;
+
+DROP TABLE seed_completions;
+DROP FUNCTION seed_record_score;
@@ -0,0 +1 @@
+ALTER TABLE members DROP COLUMN totp_last_step;
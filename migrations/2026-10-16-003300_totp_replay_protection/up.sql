@@ -0,0 +1,8 @@
+-- TOTP secrets are now encrypted by pointercrate itself instead of via pgcrypto, so that the encryption key is never
+-- sent to the database. Secrets encrypted the old way cannot be converted here, as the key is not known to the
+-- database, so these enrollments are reset and the affected users have to enroll again.
+UPDATE members SET totp_secret = NULL, totp_enabled = FALSE WHERE totp_secret IS NOT NULL;
+
+-- The time step of the last accepted code. Codes are only accepted for later time steps, so that a code cannot be used
+-- twice.
+ALTER TABLE members ADD COLUMN totp_last_step BIGINT;
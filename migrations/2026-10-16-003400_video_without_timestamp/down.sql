@@ -0,0 +1,2 @@
+DROP INDEX records_video_without_timestamp;
+DROP FUNCTION video_without_timestamp(TEXT);
@@ -0,0 +1,7 @@
+-- Validated video URLs only ever carry timestamps in one of the forms produced by video::validate
+-- ('&t=90s' for YouTube, '?t=0h1m30s' for Twitch, '#t=90s' for Vimeo), always at the very end
+CREATE FUNCTION video_without_timestamp(video TEXT) RETURNS TEXT AS $$
+    SELECT regexp_replace(video, '(&t=[0-9]+s|\?t=[0-9]+h[0-9]+m[0-9]+s|#t=[0-9]+s)$', '')
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX records_video_without_timestamp ON records (video_without_timestamp(video::TEXT));
@@ -0,0 +1,14 @@
+-- Changes made while impersonating are flagged in audit_log, but the per-table modification logs do not know about
+-- impersonation, and reading data leaves no trace at all. Thus every issued impersonation token, and every request
+-- made using one, is recorded here.
+CREATE TABLE impersonation_log (
+    id SERIAL PRIMARY KEY,
+    time TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    administrator INTEGER NOT NULL REFERENCES members(member_id) ON UPDATE CASCADE,
+    member INTEGER NOT NULL REFERENCES members(member_id) ON UPDATE CASCADE,
+
+    -- The request made using the token (e.g. 'PATCH /api/v1/records/1/'), NULL for the issuing of the token
+    request TEXT
+);
+
+CREATE INDEX impersonation_log_member_idx ON impersonation_log(member);
@@ -0,0 +1,8 @@
+CREATE OR REPLACE VIEW demon_victors AS
+SELECT DISTINCT ON (records.demon) records.demon,
+       records.player AS first_victor,
+       COUNT(*) OVER (PARTITION BY records.demon) AS victors
+FROM records
+LEFT OUTER JOIN record_additions ON record_additions.id = records.id
+WHERE records.status_ = 'APPROVED' AND records.progress = 100
+ORDER BY records.demon, record_additions.time ASC NULLS FIRST, records.id ASC;
@@ -0,0 +1,11 @@
+-- Banned players cannot be victors of a demon, even if their records were kept approved when banning them
+
+CREATE OR REPLACE VIEW demon_victors AS
+SELECT DISTINCT ON (records.demon) records.demon,
+       records.player AS first_victor,
+       COUNT(*) OVER (PARTITION BY records.demon) AS victors
+FROM records
+INNER JOIN players ON players.id = records.player
+LEFT OUTER JOIN record_additions ON record_additions.id = records.id
+WHERE records.status_ = 'APPROVED' AND records.progress = 100 AND NOT players.banned
+ORDER BY records.demon, record_additions.time ASC NULLS FIRST, records.id ASC;
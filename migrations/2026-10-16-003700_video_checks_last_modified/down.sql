@@ -0,0 +1,4 @@
+DROP TRIGGER records_last_modified ON records;
+CREATE TRIGGER records_last_modified BEFORE UPDATE ON records FOR EACH ROW EXECUTE PROCEDURE set_last_modified();
+
+DROP FUNCTION set_record_last_modified();
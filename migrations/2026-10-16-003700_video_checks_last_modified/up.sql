@@ -0,0 +1,15 @@
+-- The periodic dead link checks are not modifications of the record itself, so they should not change its
+-- `last_modified` timestamp (which would cause spurious 412s for moderators editing the record at the same time)
+
+CREATE FUNCTION set_record_last_modified() RETURNS TRIGGER AS $$
+BEGIN
+    IF (to_jsonb(NEW) - 'video_status' - 'video_checked_at' - 'last_modified') IS DISTINCT FROM
+       (to_jsonb(OLD) - 'video_status' - 'video_checked_at' - 'last_modified') THEN
+        NEW.last_modified := NOW() AT TIME ZONE 'utc';
+    END IF;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER records_last_modified ON records;
+CREATE TRIGGER records_last_modified BEFORE UPDATE ON records FOR EACH ROW EXECUTE PROCEDURE set_record_last_modified();
@@ -0,0 +1 @@
+ALTER TABLE submitters DROP COLUMN unflagged_at;
@@ -0,0 +1,3 @@
+-- When a list moderator last unflagged the submitter. The submission guard only considers submissions made after this
+-- point, as otherwise the submitter would be flagged again on their very next submission.
+ALTER TABLE submitters ADD COLUMN unflagged_at TIMESTAMP WITHOUT TIME ZONE;
@@ -0,0 +1,8 @@
+DROP TRIGGER players_demon_victors_modified ON players;
+DROP FUNCTION set_victor_demons_modified();
+
+DROP TRIGGER records_demon_victors_modified ON records;
+DROP FUNCTION set_demon_victors_modified();
+
+DROP INDEX demons_changed_idx;
+ALTER TABLE demons DROP COLUMN victors_modified;
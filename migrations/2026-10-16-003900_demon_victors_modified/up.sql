@@ -0,0 +1,40 @@
+-- Tracks when the victors of a demon (see the demon_victors view) last changed. Kept separate from `last_modified`, as
+-- that one is used for `If-Unmodified-Since` checks when patching a demon, and new victors should not cause those to fail.
+-- Incremental syncs (`changed_since`) consider both.
+
+ALTER TABLE demons ADD COLUMN victors_modified TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc');
+
+CREATE INDEX demons_changed_idx ON demons(GREATEST(last_modified, victors_modified));
+
+CREATE FUNCTION set_demon_victors_modified() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' AND OLD.status_ = 'APPROVED' AND OLD.progress = 100 THEN
+        IF TG_OP = 'DELETE' OR (OLD.status_, OLD.progress, OLD.demon, OLD.player) IS DISTINCT FROM (NEW.status_, NEW.progress, NEW.demon, NEW.player) THEN
+            UPDATE demons SET victors_modified = NOW() AT TIME ZONE 'utc' WHERE id = OLD.demon;
+        END IF;
+    END IF;
+
+    IF TG_OP <> 'DELETE' AND NEW.status_ = 'APPROVED' AND NEW.progress = 100 THEN
+        IF TG_OP = 'INSERT' OR (OLD.status_, OLD.progress, OLD.demon, OLD.player) IS DISTINCT FROM (NEW.status_, NEW.progress, NEW.demon, NEW.player) THEN
+            UPDATE demons SET victors_modified = NOW() AT TIME ZONE 'utc' WHERE id = NEW.demon;
+        END IF;
+    END IF;
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER records_demon_victors_modified AFTER INSERT OR UPDATE OR DELETE ON records FOR EACH ROW EXECUTE PROCEDURE set_demon_victors_modified();
+
+-- Banned players are not counted as victors, and the first victor's name is part of the demon objects
+CREATE FUNCTION set_victor_demons_modified() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE demons SET victors_modified = NOW() AT TIME ZONE 'utc'
+    WHERE id IN (SELECT demon FROM records WHERE player = NEW.id AND status_ = 'APPROVED' AND progress = 100);
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER players_demon_victors_modified AFTER UPDATE OF name, banned ON players FOR EACH ROW
+    WHEN ((OLD.name, OLD.banned) IS DISTINCT FROM (NEW.name, NEW.banned)) EXECUTE PROCEDURE set_victor_demons_modified();
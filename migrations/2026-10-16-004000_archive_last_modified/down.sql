@@ -0,0 +1,9 @@
+CREATE OR REPLACE FUNCTION set_record_last_modified() RETURNS TRIGGER AS $$
+BEGIN
+    IF (to_jsonb(NEW) - 'video_status' - 'video_checked_at' - 'last_modified') IS DISTINCT FROM
+       (to_jsonb(OLD) - 'video_status' - 'video_checked_at' - 'last_modified') THEN
+        NEW.last_modified := NOW() AT TIME ZONE 'utc';
+    END IF;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
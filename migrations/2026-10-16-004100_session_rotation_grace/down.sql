@@ -0,0 +1,4 @@
+DROP INDEX sessions_previous_token_hash_idx;
+
+ALTER TABLE sessions DROP COLUMN rotated_at;
+ALTER TABLE sessions DROP COLUMN previous_token_hash;
@@ -0,0 +1,7 @@
+-- The refresh token a session had before it was last rotated, and when that happened. Clients sending several requests
+-- at once with an expired access token refresh the session concurrently, and all but the first would otherwise be
+-- logged out. The previous token is therefore still accepted for a short grace period (without being rotated again).
+ALTER TABLE sessions ADD COLUMN previous_token_hash TEXT;
+ALTER TABLE sessions ADD COLUMN rotated_at TIMESTAMP WITHOUT TIME ZONE;
+
+CREATE INDEX sessions_previous_token_hash_idx ON sessions(previous_token_hash);
@@ -0,0 +1,218 @@
+//! Module for compressing responses
+//!
+//! [`Compression`] compresses textual responses (JSON, HTML, CSS, ...) whose body is larger than
+//! [`compression_min_size`](pointercrate_core::config::compression_min_size) bytes, using brotli or
+//! gzip depending on the request's `Accept-Encoding` header. Streamed responses are left alone, as
+//! are those of routes listed in
+//! [`compression_excluded_routes`](pointercrate_core::config::compression_excluded_routes).
+//!
+//! Since the compressed body differs from the uncompressed one byte-wise, strong `ETag`s of
+//! compressed responses are turned into weak ones.
+//!
+//! The fairing is attached by [`setup`](crate::setup).
+
+use flate2::write::GzEncoder;
+use log::error;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Header, Status},
+    tokio::task,
+    Request, Response,
+};
+use std::io::{Cursor, Write};
+
+/// brotli quality level (0 - 11). Higher levels are too slow for compressing on the fly.
+const BROTLI_QUALITY: u32 = 5;
+
+/// base 2 logarithm of the brotli window size
+const BROTLI_WINDOW: u32 = 22;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut compressed = Vec::new();
+
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+
+                    writer.write_all(data)?;
+                }
+
+                Ok(compressed)
+            },
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+
+                encoder.write_all(data)?;
+                encoder.finish()
+            },
+        }
+    }
+}
+
+/// Picks the encoding the client prefers from the given `Accept-Encoding` header, preferring
+/// brotli if the client likes both equally
+fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut best: Option<(Encoding, f32)> = None;
+
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';').map(str::trim);
+        let encoding = match parts.next() {
+            Some(name) if name.eq_ignore_ascii_case("br") => Encoding::Brotli,
+            Some(name) if name.eq_ignore_ascii_case("gzip") => Encoding::Gzip,
+            _ => continue,
+        };
+        let quality = parts
+            .find_map(|parameter| parameter.strip_prefix("q="))
+            .and_then(|quality| quality.parse().ok())
+            .unwrap_or(1.0);
+
+        let better = match best {
+            None => true,
+            Some((_, best_quality)) => quality > best_quality || (quality == best_quality && encoding == Encoding::Brotli),
+        };
+
+        if quality > 0.0 && better {
+            best = Some((encoding, quality))
+        }
+    }
+
+    best.map(|(encoding, _)| encoding)
+}
+
+fn is_compressible(content_type: &ContentType) -> bool {
+    content_type.top() == "text"
+        || [ContentType::JSON, ContentType::JavaScript, ContentType::XML, ContentType::SVG]
+            .iter()
+            .any(|compressible| compressible.media_type() == content_type.media_type())
+}
+
+pub struct Compression {
+    min_size: usize,
+    excluded_routes: Vec<String>,
+}
+
+impl Compression {
+    pub fn new(min_size: usize, excluded_routes: Vec<String>) -> Self {
+        Compression { min_size, excluded_routes }
+    }
+
+    pub fn from_config() -> Self {
+        Compression::new(
+            pointercrate_core::config::compression_min_size(),
+            pointercrate_core::config::compression_excluded_routes(),
+        )
+    }
+
+    fn should_compress(&self, request: &Request<'_>, response: &Response<'_>) -> bool {
+        if let Some(route) = request.route() {
+            if self.excluded_routes.iter().any(|excluded| *excluded == route.uri.to_string()) {
+                return false
+            }
+        }
+
+        let compressible = response
+            .content_type()
+            .map(|content_type| is_compressible(&content_type))
+            .unwrap_or(false);
+        let large_enough = response.body().preset_size().map(|size| size >= self.min_size).unwrap_or(false);
+
+        response.status() != Status::NotModified && !response.headers().contains("Content-Encoding") && compressible && large_enough
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if !self.should_compress(request, response) {
+            return
+        }
+
+        // Whether a response is compressed depends on this header, so caches need to take it into account
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+
+        let encoding = match request.headers().get_one("Accept-Encoding").and_then(negotiate) {
+            Some(encoding) => encoding,
+            None => return,
+        };
+
+        let body = match response.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Failed to read response body for compression: {}", err);
+
+                response.set_status(Status::InternalServerError);
+                response.set_sized_body(0, Cursor::new(""));
+
+                return
+            },
+        };
+
+        // Compression is CPU bound, so keep it off the async worker threads
+        let compressed = task::spawn_blocking(move || {
+            let compressed = encoding.compress(&body);
+
+            (body, compressed)
+        })
+        .await;
+
+        let (body, compressed) = match compressed {
+            Ok((body, Ok(compressed))) => (body, compressed),
+            Ok((body, Err(err))) => {
+                error!("Failed to compress response: {}", err);
+
+                response.set_sized_body(body.len(), Cursor::new(body));
+
+                return
+            },
+            Err(err) => {
+                error!("Compression task failed: {}", err);
+
+                response.set_status(Status::InternalServerError);
+                response.set_sized_body(0, Cursor::new(""));
+
+                return
+            },
+        };
+
+        if compressed.len() >= body.len() {
+            response.set_sized_body(body.len(), Cursor::new(body));
+
+            return
+        }
+
+        let weak_etag = response
+            .headers()
+            .get_one("ETag")
+            .filter(|etag| etag.starts_with('"'))
+            .map(|etag| format!("W/{}", etag));
+
+        if let Some(etag) = weak_etag {
+            response.set_raw_header("ETag", etag);
+        }
+
+        response.set_header(Header::new("Content-Encoding", encoding.name()));
+        response.set_sized_body(compressed.len(), Cursor::new(compressed));
+    }
+}
@@ -0,0 +1,74 @@
+//! Module for tracking who issued the request currently being processed
+//!
+//! This information is only ever used to enrich server-side logs (e.g. when an error response is
+//! generated) and is never exposed to clients.
+
+use crate::logging::RequestId;
+use rocket::Request;
+use std::{
+    fmt::{Display, Formatter},
+    net::IpAddr,
+};
+
+/// The id of the user that was successfully authenticated for some request
+///
+/// Stored in the request-local cache by the authentication guards. Since rocket only ever
+/// initializes the request-local cache once, the first successful authentication wins.
+struct AuthenticatedUserId(Option<i32>);
+
+/// The permissions of the current request, if they were restricted (e.g. because the request was
+/// authenticated using an API key)
+struct RestrictedPermissions(Option<u16>);
+
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// The id assigned to the request for correlating log lines (see [`crate::logging`])
+    pub request_id: String,
+    pub ip: Option<IpAddr>,
+    pub user_id: Option<i32>,
+
+    /// The permissions the request was restricted to, if any
+    pub permissions: Option<u16>,
+}
+
+impl RequestContext {
+    /// Captures the context of the given request
+    pub fn of(request: &Request<'_>) -> Self {
+        RequestContext {
+            request_id: RequestId::of(request).to_string(),
+            ip: request.client_ip(),
+            user_id: request.local_cache(|| AuthenticatedUserId(None)).0,
+            permissions: request.local_cache(|| RestrictedPermissions(None)).0,
+        }
+    }
+
+    /// Records that the given request was issued by the user with the given id
+    pub fn record_user(request: &Request<'_>, user_id: i32) {
+        request.local_cache(|| AuthenticatedUserId(Some(user_id)));
+    }
+
+    /// Records that the given request was restricted to the given permissions
+    pub fn record_permissions(request: &Request<'_>, permissions: u16) {
+        request.local_cache(|| RestrictedPermissions(Some(permissions)));
+    }
+}
+
+impl Display for RequestContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] ", self.request_id)?;
+
+        match self.user_id {
+            Some(user_id) => write!(f, "user {}", user_id)?,
+            None => write!(f, "unauthenticated user")?,
+        }
+
+        if let Some(permissions) = self.permissions {
+            write!(f, " restricted to permissions {:#06x}", permissions)?;
+        }
+
+        match self.ip {
+            Some(ip) => write!(f, " ({})", ip),
+            None => write!(f, " (unknown IP)"),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Module for answering `OPTIONS` requests and for cross-origin resource sharing (CORS)
+//!
+//! Rocket answers `HEAD` requests using the `GET` route for the same path, but does not know about
+//! `OPTIONS`. [`Cors`] turns the `404 NOT FOUND` response to an `OPTIONS` request for a path that
+//! some route handles into a `204 NO CONTENT` response with an `Allow` header listing the supported
+//! methods. Additionally, if the request's origin is one of
+//! [`cors_allowed_origins`](pointercrate_core::config::cors_allowed_origins), it adds the CORS
+//! headers browsers need to let third-party sites access the API (including answering preflight
+//! requests).
+//!
+//! The fairing is attached by [`setup`](crate::setup).
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{Header, Method, Status},
+    Request, Response,
+};
+use std::io::Cursor;
+
+/// The non-standard response headers clients might be interested in. Browsers hide all response
+/// headers not listed here from cross-origin scripts.
+const EXPOSED_HEADERS: &str = "ETag, Links, Location, X-Total-Count, X-Page-Count, X-Request-Id, X-Records-Rejected";
+
+/// How long (in seconds) browsers may cache the response to a preflight request
+const PREFLIGHT_MAX_AGE: u32 = 86400;
+
+pub struct Cors {
+    allowed_origins: Vec<String>,
+}
+
+impl Cors {
+    pub fn new(allowed_origins: Vec<String>) -> Self {
+        Cors { allowed_origins }
+    }
+
+    pub fn from_config() -> Self {
+        Cors::new(pointercrate_core::config::cors_allowed_origins())
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed == "*" || allowed == origin)
+    }
+}
+
+/// Checks whether the path of the given route (e.g. `/api/v2/demons/<demon_id>`) matches the given
+/// request path, ignoring trailing slashes
+fn route_matches(route_path: &str, request_path: &[&str]) -> bool {
+    let mut requested = request_path.iter();
+
+    for segment in route_path.split('/').filter(|segment| !segment.is_empty()) {
+        if segment.starts_with('<') && segment.ends_with("..>") {
+            return true
+        }
+
+        match requested.next() {
+            Some(requested) if segment.starts_with('<') || segment == *requested => (),
+            _ => return false,
+        }
+    }
+
+    requested.next().is_none()
+}
+
+/// Determines the methods routes exist for at the path of the given request
+fn allowed_methods(request: &Request<'_>) -> Vec<Method> {
+    let path: Vec<&str> = request.uri().path().segments().collect();
+    let mut methods = Vec::new();
+
+    for route in request.rocket().routes() {
+        if route_matches(route.uri.path(), &path) && !methods.contains(&route.method) {
+            methods.push(route.method);
+        }
+    }
+
+    if methods.contains(&Method::Get) && !methods.contains(&Method::Head) {
+        methods.push(Method::Head);
+    }
+
+    if !methods.is_empty() && !methods.contains(&Method::Options) {
+        methods.push(Method::Options);
+    }
+
+    methods
+}
+
+fn join(methods: &[Method]) -> String {
+    methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ")
+}
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "OPTIONS and CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let is_options = request.method() == Method::Options;
+        let methods = if is_options { allowed_methods(request) } else { Vec::new() };
+
+        // Only fill in responses rocket generated because no OPTIONS route matched
+        if is_options && response.status() == Status::NotFound && !methods.is_empty() {
+            response.set_status(Status::NoContent);
+            response.remove_header("Content-Type");
+            response.set_sized_body(0, Cursor::new(""));
+            response.set_header(Header::new("Allow", join(&methods)));
+        }
+
+        let allows_all = self.allowed_origins.iter().any(|allowed| allowed == "*");
+
+        // Unless all origins are allowed, whether the CORS headers are present depends on the origin,
+        // so caches must not reuse any response (not even one without those headers) for requests
+        // from a different origin
+        if !allows_all && !self.allowed_origins.is_empty() {
+            response.adjoin_header(Header::new("Vary", "Origin"));
+        }
+
+        let origin = match request.headers().get_one("Origin") {
+            Some(origin) if self.allows(origin) => origin,
+            _ => return,
+        };
+
+        if allows_all {
+            response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
+        } else {
+            response.set_header(Header::new("Access-Control-Allow-Origin", origin.to_string()));
+        }
+
+        response.set_header(Header::new("Access-Control-Expose-Headers", EXPOSED_HEADERS));
+
+        // Preflight request
+        if is_options && request.headers().contains("Access-Control-Request-Method") && !methods.is_empty() {
+            response.set_header(Header::new("Access-Control-Allow-Methods", join(&methods)));
+            response.set_header(Header::new("Access-Control-Max-Age", PREFLIGHT_MAX_AGE.to_string()));
+
+            if let Some(headers) = request.headers().get_one("Access-Control-Request-Headers") {
+                response.set_header(Header::new("Access-Control-Allow-Headers", headers.to_string()));
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Module for generating an OpenAPI 3.0 description of the pointercrate API
+//!
+//! The document is built from rocket's route table at request time, so every mounted API endpoint
+//! shows up automatically, including its path and query parameters. Since request guards are
+//! opaque to rocket, parameters read via [`crate::query::Query`] or request bodies are not part of
+//! the generated operations.
+//!
+//! The schemas of the request and response models are contributed by the API crates via
+//! [`register_schemas`] and listed under `#/components/schemas/`. The document is served at
+//! `/api/v1/openapi.json`, mounted by [`setup`](crate::setup).
+
+use rocket::{
+    request::{FromRequest, Outcome},
+    serde::json::Json,
+    Build, Orbit, Request, Rocket, Route, State,
+};
+use serde_json::{json, Map, Value};
+use std::sync::RwLock;
+
+/// The model schemas registered via [`register_schemas`]
+#[derive(Debug, Default)]
+pub struct Schemas(RwLock<Map<String, Value>>);
+
+/// Adds the given named schemas to the OpenAPI document
+///
+/// Can be called from any crate's setup, regardless of whether [`setup`](crate::setup) already ran.
+pub fn register_schemas(rocket: Rocket<Build>, schemas: Vec<(&str, Value)>) -> Rocket<Build> {
+    let rocket = match rocket.state::<Schemas>() {
+        Some(_) => rocket,
+        None => rocket.manage(Schemas::default()),
+    };
+
+    let mut registered = rocket.state::<Schemas>().unwrap().0.write().unwrap();
+
+    for (name, schema) in schemas {
+        registered.insert(name.to_string(), schema);
+    }
+
+    drop(registered);
+
+    rocket
+}
+
+/// The schema of the error responses of all endpoints
+pub(crate) fn error_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["message", "error_code", "data"],
+        "properties": {
+            "message": {"type": "string"},
+            "error_code": {"type": "integer", "description": "The HTTP status code followed by two digits identifying the error"},
+            "data": {"type": "object"}
+        }
+    })
+}
+
+/// Builder for an OpenAPI 3.0 document
+#[derive(Debug)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    paths: Map<String, Value>,
+    schemas: Map<String, Value>,
+}
+
+impl OpenApi {
+    pub fn new(title: &str, version: &str) -> Self {
+        OpenApi {
+            title: title.to_string(),
+            version: version.to_string(),
+            paths: Map::new(),
+            schemas: Map::new(),
+        }
+    }
+
+    /// Registers a named schema under `#/components/schemas/`
+    pub fn schema(mut self, name: &str, schema: Value) -> Self {
+        self.schemas.insert(name.to_string(), schema);
+        self
+    }
+
+    /// Adds an operation for every route whose path starts with `/api/`
+    pub fn routes<'a>(mut self, routes: impl Iterator<Item = &'a Route>) -> Self {
+        for route in routes {
+            let path = route.uri.path();
+
+            if !path.starts_with("/api/") {
+                continue
+            }
+
+            let (path, mut parameters) = path_template(path);
+
+            if let Some(query) = route.uri.query() {
+                parameters.extend(query_parameters(query));
+            }
+
+            let mut operation = json!({
+                "tags": [tag(&path)],
+                "parameters": parameters,
+                "responses": {
+                    "200": {"description": "Success"},
+                    "default": {
+                        "description": "Error",
+                        "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Error"}}}
+                    }
+                }
+            });
+
+            if let Some(ref name) = route.name {
+                operation["operationId"] = json!(format!("{}_{}", route.method.as_str().to_lowercase(), name));
+            }
+
+            let item = self.paths.entry(path).or_insert_with(|| json!({}));
+
+            item[route.method.as_str().to_lowercase()] = operation;
+        }
+
+        self
+    }
+
+    pub fn build(self) -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": self.title,
+                "version": self.version
+            },
+            "paths": self.paths,
+            "components": {
+                "schemas": self.schemas
+            }
+        })
+    }
+}
+
+/// Converts a rocket path (`/api/v1/records/<record_id>/`) into an OpenAPI path template
+/// (`/api/v1/records/{record_id}/`), collecting the path parameters along the way
+fn path_template(path: &str) -> (String, Vec<Value>) {
+    let mut parameters = Vec::new();
+
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            match dynamic_name(segment) {
+                Some(name) => {
+                    parameters.push(json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": {"type": "string"}
+                    }));
+
+                    format!("{{{}}}", name)
+                },
+                None => segment.to_string(),
+            }
+        })
+        .collect();
+
+    (segments.join("/"), parameters)
+}
+
+fn query_parameters(query: &str) -> Vec<Value> {
+    query
+        .split('&')
+        .filter_map(dynamic_name)
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "query",
+                "required": false,
+                "schema": {"type": "string"}
+            })
+        })
+        .collect()
+}
+
+/// Extracts the parameter name from a dynamic rocket segment (`<name>` or `<name..>`)
+fn dynamic_name(segment: &str) -> Option<&str> {
+    if segment.starts_with('<') && segment.ends_with('>') {
+        Some(segment[1..segment.len() - 1].trim_end_matches(".."))
+    } else {
+        None
+    }
+}
+
+/// Groups operations by the first segment after the API version (e.g. `records`)
+fn tag(path: &str) -> &str {
+    path.split('/').filter(|segment| !segment.is_empty()).nth(2).unwrap_or("misc")
+}
+
+/// Request guard granting access to the routes mounted on the running rocket instance
+pub struct RouteTable<'r>(&'r Rocket<Orbit>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RouteTable<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RouteTable(request.rocket()))
+    }
+}
+
+#[rocket::get("/openapi.json")]
+pub fn openapi(table: RouteTable<'_>, schemas: &State<Schemas>) -> Json<Value> {
+    let mut document = OpenApi::new("pointercrate", env!("CARGO_PKG_VERSION"));
+
+    for (name, schema) in schemas.0.read().unwrap().iter() {
+        document = document.schema(name, schema.clone());
+    }
+
+    Json(document.routes(table.0.routes()).build())
+}
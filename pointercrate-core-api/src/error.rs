@@ -1,5 +1,5 @@
-use crate::response::Page;
-use log::{error, warn};
+use crate::{context::RequestContext, logging::redacted_uri, response::Page};
+use log::{debug, error, warn};
 use pointercrate_core::error::PointercrateError;
 use pointercrate_core_pages::error::ErrorFragment;
 use rocket::{
@@ -10,6 +10,7 @@ use rocket::{
 };
 use serde::Serialize;
 use serde_json::Value;
+use std::time::Duration;
 
 pub type Result<T> = std::result::Result<T, ErrorResponder>;
 
@@ -18,6 +19,9 @@ pub struct ErrorResponder {
     message: String,
     error_code: u16,
     data: Value,
+
+    #[serde(skip)]
+    retry_after: Option<Duration>,
 }
 
 impl<'r> Responder<'r, 'static> for ErrorResponder {
@@ -33,11 +37,30 @@ impl<'r> Responder<'r, 'static> for ErrorResponder {
 
         let status = Status::from_code(self.error_code / 100).unwrap_or(Status::InternalServerError);
 
+        let context = RequestContext::of(request);
+
         if status.code >= 500 {
-            error!("Encountered an internal server error: {:?}", self);
+            error!(
+                "Encountered an internal server error while handling {} {} for {}: {:?}",
+                request.method(),
+                redacted_uri(request),
+                context,
+                self
+            );
+        } else {
+            debug!(
+                "Request {} {} by {} failed with error {}: {}",
+                request.method(),
+                redacted_uri(request),
+                context,
+                self.error_code,
+                self.message
+            );
         }
 
-        if accept == MediaType::HTML {
+        let retry_after = self.retry_after;
+
+        let mut response = if accept == MediaType::HTML {
             Response::build_from(
                 Page(ErrorFragment {
                     status: self.error_code / 100,
@@ -47,10 +70,19 @@ impl<'r> Responder<'r, 'static> for ErrorResponder {
                 .respond_to(request)?,
             )
             .status(status)
-            .ok()
+            .finalize()
         } else {
-            Response::build_from(Json(self).respond_to(request)?).status(status).ok()
+            Response::build_from(Json(self).respond_to(request)?).status(status).finalize()
+        };
+
+        // Round up, so that clients honoring the header do not retry a fraction of a second too early
+        if let Some(retry_after) = retry_after {
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+            response.set_raw_header("Retry-After", seconds.to_string());
         }
+
+        Ok(response)
     }
 }
 
@@ -59,6 +91,7 @@ impl<E: PointercrateError> From<E> for ErrorResponder {
         ErrorResponder {
             message: error.to_string(),
             error_code: error.error_code(),
+            retry_after: error.retry_after(),
             data: serde_json::to_value(error).expect("failed to serialize error to json"),
         }
     }
@@ -1,4 +1,5 @@
 use crate::response::Response2;
+use chrono::{DateTime, NaiveDateTime, SubsecRound};
 use pointercrate_core::{error::CoreError, etag::Taggable};
 use rocket::{
     http::{Method, Status},
@@ -10,23 +11,70 @@ use rocket::{
 
 pub struct Tagged<T: Taggable>(pub T);
 
-pub struct Precondition(Vec<String> /* ensure private constructor for type level proof of header */);
+/// Proof that a request carried either an `If-Match` or an `If-Unmodified-Since` header
+///
+/// If both are present, `If-Match` takes precedence, as mandated by RFC 7232
+#[derive(Clone)]
+pub struct Precondition {
+    // private fields ensure private constructor for type level proof of header
+    etags: Vec<String>,
+    unmodified_since: Option<NaiveDateTime>,
+}
+
+/// Wraps some response to additionally carry a `Last-Modified` header with the given timestamp
+/// (interpreted as UTC)
+pub struct Dated<R>(pub R, pub NaiveDateTime);
+
+/// Formats the given UTC timestamp as an HTTP-date
+fn http_date(timestamp: NaiveDateTime) -> String {
+    timestamp.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Splits the value of an `If-Match` or `If-None-Match` header into the ETags it lists, stripping
+/// quotes and weakness indicators
+pub(crate) fn etags(header: &str) -> impl Iterator<Item = &str> {
+    header.split(',').map(|etag| etag.trim().trim_start_matches("W/").trim_matches('"'))
+}
 
 impl Precondition {
+    /// Checks the `If-Match` header against the given object
+    ///
+    /// Fails with `428 PRECONDITION REQUIRED` if only `If-Unmodified-Since` was provided, as not
+    /// all objects keep track of when they were last modified. Use
+    /// [`Precondition::require_match_or_unmodified`] for objects that do.
     pub fn require_etag_match<T: Taggable>(&self, taggable: &T) -> Result<(), CoreError> {
+        if self.etags.is_empty() {
+            return Err(CoreError::PreconditionRequired)
+        }
+
         let patch_etag = taggable.patch_part().to_string();
 
         if self
-            .0
+            .etags
             .iter()
             .filter_map(|if_match| if_match.split(';').next())
-            .any(|e| e == &patch_etag)
+            .any(|e| e == patch_etag || e == "*")
         {
             Ok(())
         } else {
             Err(CoreError::PreconditionFailed)
         }
     }
+
+    /// Checks the `If-Match` header against the given object, or, if not present, the
+    /// `If-Unmodified-Since` header against the given modification timestamp
+    pub fn require_match_or_unmodified<T: Taggable>(&self, taggable: &T, last_modified: NaiveDateTime) -> Result<(), CoreError> {
+        match self.unmodified_since {
+            // HTTP-dates only have second precision
+            Some(unmodified_since) if self.etags.is_empty() =>
+                if last_modified.trunc_subsecs(0) <= unmodified_since {
+                    Ok(())
+                } else {
+                    Err(CoreError::PreconditionFailed)
+                },
+            _ => self.require_etag_match(taggable),
+        }
+    }
 }
 
 #[rocket::async_trait]
@@ -34,10 +82,23 @@ impl<'r> FromRequest<'r> for Precondition {
     type Error = CoreError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        match request.headers().get_one("if-match") {
-            Some(if_match) => Outcome::Success(Precondition(if_match.split(',').map(ToString::to_string).collect())),
-            None => Outcome::Failure((Status::PreconditionRequired, CoreError::PreconditionRequired)),
+        let etags = match request.headers().get_one("if-match") {
+            Some(if_match) => etags(if_match).map(ToString::to_string).collect(),
+            None => Vec::new(),
+        };
+
+        // Unparsable dates are ignored, as per RFC 7232
+        let unmodified_since = request
+            .headers()
+            .get_one("if-unmodified-since")
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.naive_utc());
+
+        if etags.is_empty() && unmodified_since.is_none() {
+            return Outcome::Failure((Status::PreconditionRequired, CoreError::PreconditionRequired))
         }
+
+        Outcome::Success(Precondition { etags, unmodified_since })
     }
 }
 
@@ -46,15 +107,19 @@ impl<'r, T: Taggable> Responder<'r, 'static> for Tagged<T> {
         let response_etag = self.0.etag_string();
 
         match request.method() {
+            // Only the get part is relevant for caching, see the documentation of the etag module in
+            // pointercrate-core
             Method::Get =>
                 if let Some(if_none_match) = request.headers().get_one("if-none-match") {
-                    if if_none_match.contains(&response_etag) {
-                        return Response::build().status(Status::NotModified).ok()
+                    let get_part = self.0.get_part().to_string();
+
+                    if etags(if_none_match).any(|etag| etag == "*" || etag.rsplit(';').next() == Some(&get_part)) {
+                        return Response::build().status(Status::NotModified).raw_header("etag", response_etag).ok()
                     }
                 },
             Method::Patch | Method::Delete =>
-                if let Some(if_none_match) = request.headers().get_one("if-match") {
-                    if if_none_match.contains(&response_etag) {
+                if let Some(if_match) = request.headers().get_one("if-match") {
+                    if etags(if_match).any(|etag| etag == response_etag) {
                         return Response::build().status(Status::NotModified).ok()
                     }
                 },
@@ -67,6 +132,16 @@ impl<'r, T: Taggable> Responder<'r, 'static> for Tagged<T> {
     }
 }
 
+impl<'r, R: Responder<'r, 'static>> Responder<'r, 'static> for Dated<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let mut response = self.0.respond_to(request)?;
+
+        response.set_raw_header("last-modified", http_date(self.1));
+
+        Ok(response)
+    }
+}
+
 pub trait TaggableExt: Taggable {
     fn require_match(self, precondition: Precondition) -> Result<Self, CoreError>
     where
@@ -75,6 +150,14 @@ pub trait TaggableExt: Taggable {
         precondition.require_etag_match(&self)?;
         Ok(self)
     }
+
+    fn require_match_at(self, precondition: Precondition, last_modified: NaiveDateTime) -> Result<Self, CoreError>
+    where
+        Self: Sized,
+    {
+        precondition.require_match_or_unmodified(&self, last_modified)?;
+        Ok(self)
+    }
 }
 
 impl<T: Taggable> TaggableExt for T {}
@@ -0,0 +1,68 @@
+//! Module containing a health check endpoint for load balancers and monitoring
+//!
+//! The endpoint is mounted at `/api/v1/health/` by [`setup`](crate::setup).
+
+use crate::response::Response2;
+use log::error;
+use pointercrate_core::pool::PointercratePool;
+use rocket::{http::Status, serde::json::Json, State};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// Whether a query could be sent to the database and answered
+    pub database_reachable: bool,
+    pub pool: PoolStatistics,
+    pub build: BuildInformation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolStatistics {
+    /// The number of connections the pool currently holds
+    pub connections: u32,
+
+    /// The number of connections not currently in use
+    pub idle: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuildInformation {
+    pub version: &'static str,
+
+    /// The commit this binary was built from, if `POINTERCRATE_COMMIT` was set at compile time
+    pub commit: Option<&'static str>,
+}
+
+/// Reports the health of this instance, responding with `503 SERVICE UNAVAILABLE` if the database
+/// cannot be reached
+#[rocket::get("/")]
+pub async fn health(pool: &State<PointercratePool>) -> Response2<Json<HealthReport>> {
+    let database_reachable = match pool.ping().await {
+        Ok(()) => true,
+        Err(err) => {
+            error!("Health check failed to reach the database: {}", err);
+
+            false
+        },
+    };
+
+    let report = HealthReport {
+        database_reachable,
+        pool: PoolStatistics {
+            connections: pool.size(),
+            idle: pool.num_idle(),
+        },
+        build: BuildInformation {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: option_env!("POINTERCRATE_COMMIT"),
+        },
+    };
+
+    let status = if database_reachable {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+
+    Response2::json(report).status(status).with_header("Cache-Control", "no-store")
+}
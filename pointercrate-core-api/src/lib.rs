@@ -1,5 +1,26 @@
+pub mod compression;
+pub mod context;
+pub mod cors;
+pub mod docs;
 pub mod error;
 pub mod etag;
+pub mod health;
+pub mod logging;
 pub mod query;
 #[macro_use]
 pub mod response;
+pub mod stream;
+
+use rocket::{Build, Rocket};
+
+/// Attaches the fairings and mounts the endpoints provided by this crate
+///
+/// Called by `pointercrate_user_api::setup`, so it does not need to be called separately.
+pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
+    docs::register_schemas(rocket, vec![("Error", docs::error_schema())])
+        .attach(logging::RequestLogger)
+        .attach(cors::Cors::from_config())
+        .attach(compression::Compression::from_config())
+        .mount("/api/v1/health/", rocket::routes![health::health])
+        .mount("/api/v1/", rocket::routes![docs::openapi])
+}
@@ -0,0 +1,132 @@
+//! Module for structured access logging
+//!
+//! [`RequestLogger`] assigns every request an id (taken from the `X-Request-Id` header if a reverse
+//! proxy already set one) and, once the response is ready, emits a single JSON log line describing
+//! the request under the `pointercrate::access` target. The id is echoed back in the `X-Request-Id`
+//! response header and is part of [`RequestContext`], so it also shows up in error logs.
+//!
+//! Query strings are never logged, as they can contain secrets (e.g. the OAuth `code` and `state`
+//! parameters). The fairing is attached by [`setup`](crate::setup).
+
+use crate::context::RequestContext;
+use log::info;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    Data, Request, Response,
+};
+use serde::Serialize;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Upper limit on the length of request ids supplied by clients, to keep log lines reasonably
+/// sized
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The id of the request currently being processed, stored in the request-local cache
+pub(crate) struct RequestId(pub(crate) String);
+
+/// The time at which [`RequestLogger`] first saw the request currently being processed
+struct RequestStart(Option<Instant>);
+
+/// The log line emitted for every request
+#[derive(Serialize)]
+struct AccessLogEntry<'a> {
+    request_id: &'a str,
+    method: &'a str,
+
+    /// The requested URI, with the query string redacted (see [`redacted_uri`])
+    uri: String,
+
+    /// The route that handled the request, if any matched
+    route: Option<String>,
+    status: u16,
+    latency_ms: Option<f64>,
+    user_id: Option<i32>,
+    ip: Option<String>,
+}
+
+impl RequestId {
+    /// Gets the id of the given request, assigning a new one if it has none yet
+    pub(crate) fn of<'r>(request: &'r Request<'_>) -> &'r str {
+        &request
+            .local_cache(|| {
+                let supplied = request
+                    .headers()
+                    .get_one(REQUEST_ID_HEADER)
+                    .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH && id.chars().all(|c| c.is_ascii_graphic()));
+
+                match supplied {
+                    Some(id) => RequestId(id.to_string()),
+                    None => RequestId(generate_request_id()),
+                }
+            })
+            .0
+    }
+}
+
+/// The URI of the given request, for logging. Query strings are replaced by `?<redacted>`, as they
+/// can contain secrets.
+pub(crate) fn redacted_uri(request: &Request<'_>) -> String {
+    let uri = request.uri();
+
+    match uri.query() {
+        Some(_) => format!("{}?<redacted>", uri.path().as_str()),
+        None => uri.path().as_str().to_string(),
+    }
+}
+
+/// Generates an id that is unique for the lifetime of this process (and, since it includes the
+/// current time, very likely across restarts as well)
+fn generate_request_id() -> String {
+    format!(
+        "{:x}-{:x}",
+        chrono::Utc::now().timestamp_millis(),
+        REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+pub struct RequestLogger;
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Structured access log",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Some(Instant::now())));
+
+        RequestId::of(request);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let context = RequestContext::of(request);
+        let latency = request.local_cache(|| RequestStart(None)).0.map(|start| start.elapsed());
+
+        let entry = AccessLogEntry {
+            request_id: &context.request_id,
+            method: request.method().as_str(),
+            uri: redacted_uri(request),
+            route: request.route().map(|route| route.uri.to_string()),
+            status: response.status().code,
+            latency_ms: latency.map(|latency| latency.as_secs_f64() * 1000.0),
+            user_id: context.user_id,
+            ip: context.ip.map(|ip| ip.to_string()),
+        };
+
+        // Serializing a struct of strings and numbers cannot fail
+        info!(target: "pointercrate::access", "{}", serde_json::to_string(&entry).unwrap());
+
+        response.set_header(Header::new(REQUEST_ID_HEADER, context.request_id));
+    }
+}
@@ -1,12 +1,49 @@
+use log::debug;
 use rocket::{
-    http::Status,
+    http::{MediaType, Status},
     request::{FromRequest, Outcome},
     Request,
 };
 use serde::de::DeserializeOwned;
 
+/// Name of the query parameter with which paginated requests can ask for the total number of
+/// matching objects. It is not part of any pagination data, and thus ignored by [`Query`].
+const COUNT_PARAMETER: &str = "count";
+
+/// Name of the query parameter with which requests can choose the format of the response (see
+/// [`ResponseFormat`]). Ignored by [`Query`].
+const FORMAT_PARAMETER: &str = "format";
+
 pub struct Query<T: DeserializeOwned>(pub T);
 
+/// Whether a request asked for the total number of objects matching its pagination data (via
+/// `?count=true`)
+///
+/// Counting can be expensive, so endpoints should only do it if explicitly requested.
+pub struct CountRequested(pub bool);
+
+/// The format in which an endpoint supporting multiple formats should respond
+///
+/// Chosen via the `format` query parameter (`json` or `csv`), falling back to the `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Csv,
+}
+
+/// Removes the [`COUNT_PARAMETER`] and [`FORMAT_PARAMETER`] from the given query string
+fn strip_reserved(query: &str) -> Result<String, serde_urlencoded::de::Error> {
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query)?;
+
+    Ok(serde_urlencoded::to_string(
+        pairs
+            .into_iter()
+            .filter(|(key, _)| key != COUNT_PARAMETER && key != FORMAT_PARAMETER)
+            .collect::<Vec<_>>(),
+    )
+    .unwrap())
+}
+
 #[rocket::async_trait]
 impl<'r, T: DeserializeOwned> FromRequest<'r> for Query<T> {
     type Error = serde_urlencoded::de::Error;
@@ -15,9 +52,79 @@ impl<'r, T: DeserializeOwned> FromRequest<'r> for Query<T> {
         match request.uri().query() {
             None => Outcome::Success(Query(serde_urlencoded::from_str("").unwrap())),
             Some(query) =>
-                match serde_urlencoded::from_str(query.as_str()) {
+                match strip_reserved(query.as_str()).and_then(|query| serde_urlencoded::from_str(&query)) {
                     Ok(t) => Outcome::Success(Query(t)),
-                    Err(err) => Outcome::Failure((Status::BadRequest, err)),
+                    // The query string is syntactically fine, but names unknown fields or has values of the wrong type
+                    Err(err) => {
+                        // Not logging the query string itself, as it might contain sensitive data
+                        debug!("Rejecting query string: {}", err);
+
+                        Outcome::Failure((Status::UnprocessableEntity, err))
+                    },
+                },
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CountRequested {
+    type Error = serde_urlencoded::de::Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let query = match request.uri().query() {
+            None => return Outcome::Success(CountRequested(false)),
+            Some(query) => query,
+        };
+
+        match serde_urlencoded::from_str::<Vec<(String, String)>>(query.as_str()) {
+            Ok(pairs) =>
+                match pairs.iter().find(|(key, _)| key == COUNT_PARAMETER) {
+                    None => Outcome::Success(CountRequested(false)),
+                    Some((_, value)) if value == "true" => Outcome::Success(CountRequested(true)),
+                    Some((_, value)) if value == "false" => Outcome::Success(CountRequested(false)),
+                    Some((_, value)) => {
+                        debug!("Rejecting invalid value '{}' for count parameter", value);
+
+                        Outcome::Failure((
+                            Status::UnprocessableEntity,
+                            serde::de::Error::custom(format!("invalid value for '{}', expected true or false", COUNT_PARAMETER)),
+                        ))
+                    },
+                },
+            Err(err) => Outcome::Failure((Status::UnprocessableEntity, err)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ResponseFormat {
+    type Error = serde_urlencoded::de::Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let pairs = match request.uri().query() {
+            None => Vec::new(),
+            Some(query) =>
+                match serde_urlencoded::from_str::<Vec<(String, String)>>(query.as_str()) {
+                    Ok(pairs) => pairs,
+                    Err(err) => return Outcome::Failure((Status::UnprocessableEntity, err)),
+                },
+        };
+
+        match pairs.iter().find(|(key, _)| key == FORMAT_PARAMETER) {
+            Some((_, value)) if value == "json" => Outcome::Success(ResponseFormat::Json),
+            Some((_, value)) if value == "csv" => Outcome::Success(ResponseFormat::Csv),
+            Some((_, value)) => {
+                debug!("Rejecting invalid value '{}' for format parameter", value);
+
+                Outcome::Failure((
+                    Status::UnprocessableEntity,
+                    serde::de::Error::custom(format!("invalid value for '{}', expected json or csv", FORMAT_PARAMETER)),
+                ))
+            },
+            None =>
+                match request.accept() {
+                    Some(accept) if accept.preferred().media_type() == &MediaType::CSV => Outcome::Success(ResponseFormat::Csv),
+                    _ => Outcome::Success(ResponseFormat::Json),
                 },
         }
     }
@@ -1,14 +1,24 @@
-use crate::etag::Tagged;
-use pointercrate_core::etag::Taggable;
+use crate::{
+    etag::{etags, Tagged},
+    query::ResponseFormat,
+};
+use pointercrate_core::{csv, etag::Taggable};
 use pointercrate_core_pages::{PageConfiguration, PageFragment};
 use rocket::{
-    http::{ContentType, Header, Status},
+    http::{ContentType, Header, Method, Status},
+    request::{FromRequest, Outcome},
     response::Responder,
     serde::json::Json,
     Request, Response,
 };
 use serde::Serialize;
-use std::{borrow::Cow, io::Cursor};
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::Cursor,
+    sync::Arc,
+};
 
 pub struct Page<T: PageFragment>(pub T);
 
@@ -25,6 +35,112 @@ impl<'r, 'o: 'r, T: PageFragment> Responder<'r, 'o> for Page<T> {
     }
 }
 
+/// Request guard for rendering pages ahead of responding, e.g. to cache the result as a
+/// [`RenderedPage`]
+pub struct PageRenderer<'r>(&'r PageConfiguration);
+
+impl<'r> PageRenderer<'r> {
+    pub fn render<T: PageFragment>(&self, fragment: &T) -> RenderedPage {
+        let html = self.0.render_fragment(fragment).0;
+
+        let mut hasher = DefaultHasher::new();
+        html.hash(&mut hasher);
+
+        RenderedPage {
+            etag: hasher.finish().to_string(),
+            html: Arc::new(html),
+            max_age: None,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for PageRenderer<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.rocket().state::<PageConfiguration>() {
+            Some(page_config) => Outcome::Success(PageRenderer(page_config)),
+            None => Outcome::Failure((Status::InternalServerError, ())),
+        }
+    }
+}
+
+/// An already rendered HTML page, which is cheap to clone
+///
+/// Its ETag is a hash of the rendered HTML, and a `GET` request whose `If-None-Match` header lists
+/// it is answered with `304 NOT MODIFIED`.
+#[derive(Clone)]
+pub struct RenderedPage {
+    html: Arc<String>,
+    etag: String,
+    max_age: Option<u32>,
+}
+
+impl RenderedPage {
+    /// Allows browsers and shared caches (e.g. a CDN) to serve this page for the given number of
+    /// seconds without revalidating it. By default, caches have to revalidate on every request.
+    pub fn max_age(mut self, seconds: u32) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+}
+
+impl<'r> Responder<'r, 'static> for RenderedPage {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let cache_control = match self.max_age {
+            Some(max_age) => format!("public, max-age={}", max_age),
+            None => "no-cache".to_string(),
+        };
+
+        let not_modified = request.method() == Method::Get
+            && request
+                .headers()
+                .get_one("if-none-match")
+                .map(|if_none_match| etags(if_none_match).any(|etag| etag == "*" || etag == self.etag))
+                .unwrap_or(false);
+
+        if not_modified {
+            return Response::build()
+                .status(Status::NotModified)
+                .raw_header("etag", format!("\"{}\"", self.etag))
+                .raw_header("cache-control", cache_control)
+                .ok()
+        }
+
+        let html = (*self.html).clone();
+
+        Response::build()
+            .status(Status::Ok)
+            .header(ContentType::HTML)
+            .raw_header("etag", format!("\"{}\"", self.etag))
+            .raw_header("cache-control", cache_control)
+            .sized_body(html.len(), Cursor::new(html))
+            .ok()
+    }
+}
+
+/// Responder serializing a list of objects either as JSON or as a CSV table (see
+/// [`pointercrate_core::csv`])
+pub struct Tabular<T>(pub T, pub ResponseFormat);
+
+impl<'r, 'o: 'r, T: Serialize> Responder<'r, 'o> for Tabular<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+        match self.1 {
+            ResponseFormat::Json => Json(self.0).respond_to(request),
+            ResponseFormat::Csv => {
+                let value = serde_json::to_value(&self.0).map_err(|_| Status::InternalServerError)?;
+                let csv = csv::to_csv(&value);
+
+                Response::build()
+                    .header(ContentType::CSV)
+                    .sized_body(csv.len(), Cursor::new(csv))
+                    .ok()
+            },
+        }
+    }
+}
+
 pub struct Response2<T> {
     content: T,
     status: Status,
@@ -35,6 +151,15 @@ impl<T: Serialize> Response2<Json<T>> {
     pub fn json(content: T) -> Self {
         Response2::new(Json(content))
     }
+
+    /// Responds in the given format instead of always using JSON
+    pub fn format(self, format: ResponseFormat) -> Response2<Tabular<T>> {
+        Response2 {
+            content: Tabular(self.content.0, format),
+            status: self.status,
+            headers: self.headers,
+        }
+    }
 }
 
 impl<T: Taggable> Response2<Tagged<T>> {
@@ -57,6 +182,19 @@ impl<T> Response2<T> {
         self
     }
 
+    /// Adds `X-Total-Count` and `X-Page-Count` headers, if the total number of objects is known
+    pub fn with_total_count(self, total: Option<i64>, per_page: usize) -> Self {
+        match total {
+            Some(total) => {
+                let pages = (total as usize + per_page - 1) / per_page;
+
+                self.with_header("X-Total-Count", total.to_string())
+                    .with_header("X-Page-Count", pages.to_string())
+            },
+            None => self,
+        }
+    }
+
     pub fn status(mut self, status: Status) -> Self {
         self.status = status;
         self
@@ -78,6 +216,14 @@ impl<'r, 'o: 'r, T: Responder<'r, 'o>> Responder<'r, 'o> for Response2<T> {
 
 #[macro_export]
 macro_rules! pagination_response {
+    // Variant additionally emitting the total number of objects matching the pagination data, if
+    // known (see `Response2::with_total_count`)
+    (total = $total:expr, $endpoint: expr, $objects:expr, $pagination:expr, $($rest:tt)*) => {{
+        let per_page = $pagination.limit.unwrap_or(50) as usize;
+
+        pointercrate_core_api::pagination_response!($endpoint, $objects, $pagination, $($rest)*)
+            .map(|response| response.with_total_count($total, per_page))
+    }};
     ($endpoint: expr, $objects:expr, $pagination:expr, $min_id:expr, $max_id:expr, $before_field:ident, $after_field:ident, $($id_field:tt)*) => {{
         use pointercrate_core_api::response::Response2;
 
@@ -86,75 +232,58 @@ macro_rules! pagination_response {
         let mut rel = String::new();
 
         let limit = $pagination.limit.unwrap_or(50) as usize;
-        let next_page_exists = $objects.len() > limit;
+        let more_exist = $objects.len() > limit;
 
         if !$objects.is_empty() {
-            if next_page_exists {
-                log::debug!("A new page exists!");
+            if more_exist {
+                $objects.pop();  // remove the things from the next page
+            }
+
+            let before = $pagination.$before_field;
+            let after = $pagination.$after_field;
+
+            // If only 'before' is set, the page was retrieved using 'ORDER BY ... DESC' so we need to
+            // reverse the list order! If both are set, we interpret this as all objects _up to 'before'_
+            // being paginated in regular order.
+            let reversed = before.is_some() && after.is_none();
 
-                $objects.pop();  // remove the things from then next page
+            if reversed {
+                log::debug!("Before value set, assuming result is reverse ordered!");
+
+                $objects.reverse();
             }
 
-            let last = $objects.last().unwrap().$($id_field)*;
             let first = $objects.first().unwrap().$($id_field)*;
+            let last = $objects.last().unwrap().$($id_field)*;
+
+            // The extra object we fetched only tells us whether there is more data in the direction we
+            // paginated in. For the other direction, we have to fall back to comparing with the extremal
+            // values. Note that these are computed without taking filters into account, so we might
+            // still generate links to empty pages there.
+            let (prev_exists, next_exists) = if reversed {
+                (more_exist, last < $max_id)
+            } else {
+                (after.is_some() && first > $min_id, more_exist)
+            };
+
+            if next_exists {
+                $pagination.$after_field = Some(last);
+                $pagination.$before_field = if reversed { None } else { before };
+
+                rel.push_str(&format!(
+                    ",<{}?{}>; rel=next",
+                    $endpoint, serde_urlencoded::to_string(&$pagination).unwrap()
+                ));
+            }
+
+            if prev_exists {
+                $pagination.$after_field = None;
+                $pagination.$before_field = Some(first);
 
-            match ($pagination.$before_field, $pagination.$after_field) {
-                (None, after) => {
-                    log::debug!("No before value set, assuming result is correctly ordered!");
-
-                    // no 'before' value set.
-                    // if 'after' is none, we're on the first page, otherwise we have ot generate a 'prev' link
-
-                    if next_page_exists {
-
-                        $pagination.$after_field = Some(last);
-                        $pagination.$before_field = None;
-
-                        rel.push_str(&format!(
-                            ",<{}?{}>; rel=next",
-                            $endpoint, serde_urlencoded::to_string(&$pagination).unwrap()
-                        ));
-                    }
-
-                    if after.is_some() {
-                        $pagination.$after_field = None;
-                        $pagination.$before_field = Some(first);
-
-                        rel.push_str(&format!(
-                            ",<{}?{}>; rel=prev",
-                            $endpoint, serde_urlencoded::to_string(&$pagination).unwrap()
-                        ));
-                    }
-                }
-                (Some(_), None) => {
-                    log::debug!("Before value set, assuming result is reverse ordered!");
-
-                    // A previous page exists. This means "first" and "last" are actually to opposite of what the variables are named.
-                    $pagination.$before_field = Some(last);
-                    $pagination.$after_field = None;
-
-                    // In this case, the page was retrieved using 'ORDER BY ... DESC' so we need to reverse list order!
-                    $objects.reverse();
-
-                    if next_page_exists {
-                        rel.push_str(&format!(
-                            ",<{}?{}>; rel=prev",
-                            $endpoint, serde_urlencoded::to_string(&$pagination).unwrap()
-                        ));
-                    }
-                    $pagination.$after_field = Some(first);
-                    $pagination.$before_field = None;
-
-                    rel.push_str(&format!(
-                        ",<{}?{}>; rel=next",
-                        $endpoint, serde_urlencoded::to_string(&$pagination).unwrap()
-                    ));
-                }
-                (Some(_before), Some(_after)) => {
-                    // We interpret this as that all objects _up to 'before'_ are supposed to be paginated.
-                    // This means we keep the 'before' value and handle the 'after' value just as above.
-                    // tODO: implement
-                }
+                rel.push_str(&format!(
+                    ",<{}?{}>; rel=prev",
+                    $endpoint, serde_urlencoded::to_string(&$pagination).unwrap()
+                ));
             }
         }
 
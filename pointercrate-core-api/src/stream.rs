@@ -0,0 +1,97 @@
+//! Module for responding with large collections without loading them into memory all at once
+//!
+//! [`batched`] turns a function loading a page of objects (ordered by ascending id) into a stream
+//! of all objects, fetching one page after the other. [`JsonStream`] writes such a stream to the
+//! client as a JSON array, serializing objects as they arrive.
+
+use log::error;
+use rocket::{
+    futures::{
+        stream::{self, Stream, StreamExt},
+        Future,
+    },
+    http::ContentType,
+    response::{stream::TextStream, Responder},
+    Request,
+};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// The number of objects to fetch per batch, which is the largest page size all paginations support
+pub const BATCH_SIZE: u8 = 100;
+
+/// Responder writing the items of the wrapped stream as a JSON array
+///
+/// Since the status code has already been sent once the first item arrives, an error produced by
+/// the stream cannot be reported properly. Instead, the response is cut off without closing the
+/// array, so that clients at least notice they did not receive everything.
+pub struct JsonStream<S>(pub S);
+
+impl<'r, S, T, E> Responder<'r, 'r> for JsonStream<S>
+where
+    S: Stream<Item = Result<T, E>> + Send + Unpin + 'r,
+    T: Serialize,
+    E: Display,
+{
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'r> {
+        let body = stream::unfold(Some((self.0, true)), |state| {
+            async move {
+                let (mut items, first) = state?;
+                let separator = if first { "[" } else { "," };
+
+                match items.next().await {
+                    Some(Ok(item)) =>
+                        match serde_json::to_string(&item) {
+                            Ok(json) => Some((format!("{}{}", separator, json), Some((items, false)))),
+                            Err(err) => {
+                                error!("Failed to serialize streamed object: {}", err);
+
+                                None
+                            },
+                        },
+                    Some(Err(err)) => {
+                        error!("Aborting streamed response: {}", err);
+
+                        None
+                    },
+                    None if first => Some(("[]".to_string(), None)),
+                    None => Some(("]".to_string(), None)),
+                }
+            }
+        });
+
+        let mut response = TextStream(Box::pin(body)).respond_to(request)?;
+
+        response.set_header(ContentType::JSON);
+
+        Ok(response)
+    }
+}
+
+/// Streams all objects returned by successive calls to `fetch`
+///
+/// `fetch` is called with the id of the last object of the previous batch (or `None` for the first
+/// batch), and has to return the objects with larger ids in ascending order. The stream ends once
+/// `fetch` returns an empty batch or fails.
+pub fn batched<T, E, F, Fut>(fetch: F, id_of: fn(&T) -> i32) -> impl Stream<Item = Result<T, E>>
+where
+    F: FnMut(Option<i32>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, E>>,
+{
+    stream::unfold(Some((fetch, None)), move |state| {
+        async move {
+            let (mut fetch, after) = state?;
+
+            match fetch(after).await {
+                Ok(batch) if batch.is_empty() => None,
+                Ok(batch) => {
+                    let last = batch.last().map(id_of);
+
+                    Some((batch.into_iter().map(Ok).collect::<Vec<_>>(), Some((fetch, last))))
+                },
+                Err(err) => Some((vec![Err(err)], None)),
+            }
+        }
+    })
+    .flat_map(stream::iter)
+}
@@ -1,9 +1,16 @@
 //! Module containing some basic structures for dealing with audit logs
 
+use crate::{
+    error::{CoreError, Result},
+    pool::create_active_user_table,
+    util::non_nullable,
+};
 use chrono::NaiveDateTime;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{PgConnection, Row};
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug)]
 pub struct NamedId {
     pub id: i32,
     pub name: Option<String>,
@@ -24,3 +31,206 @@ pub enum AuditLogEntryType<T> {
     Modification(T),
     Deletion,
 }
+
+/// Helper for writing entries into the generic `audit_log` table whenever some object is patched
+///
+/// A snapshot of the object is taken before the patch is applied. Once the patch has been applied,
+/// the top-level fields that changed are written into the audit log as a JSON object of the form
+/// `{"field": {"old": ..., "new": ...}}`, attributed to the user the connection is currently
+/// audited for (see [`crate::pool::audit_connection`]), or to user 0 if it is not audited.
+pub struct PatchLog {
+    target: &'static str,
+    target_id: i32,
+    before: Value,
+}
+
+impl PatchLog {
+    pub fn start<T: Serialize>(target: &'static str, target_id: i32, object: &T) -> Self {
+        PatchLog {
+            target,
+            target_id,
+            before: serde_json::to_value(object).unwrap_or(Value::Null),
+        }
+    }
+
+    /// Must be called inside the same transaction that applied the patch
+    pub async fn finish<T: Serialize>(self, object: &T, connection: &mut PgConnection) -> Result<()> {
+        let after = serde_json::to_value(object).unwrap_or(Value::Null);
+        let changes = diff(self.before, after);
+
+        if changes.is_empty() {
+            return Ok(())
+        }
+
+        create_active_user_table(&mut *connection).await?;
+
+        // Changes made on connections not audited for any user (e.g. by the admin CLI) are attributed to
+        // user 0, like those of background jobs
+        sqlx::query!(
+            "INSERT INTO audit_log (userid, impersonated_by, target, target_id, changes) VALUES (COALESCE((SELECT id FROM active_user \
+             LIMIT 1), 0), (SELECT impersonated_by FROM active_user LIMIT 1), $1, $2, $3::TEXT::JSONB)",
+            self.target,
+            self.target_id,
+            Value::Object(changes).to_string()
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn diff(before: Value, after: Value) -> Map<String, Value> {
+    let mut changes = Map::new();
+
+    match (before, after) {
+        (Value::Object(mut before), Value::Object(after)) =>
+            for (key, new) in after {
+                let old = before.remove(&key).unwrap_or(Value::Null);
+
+                if old != new {
+                    changes.insert(key, serde_json::json!({"old": old, "new": new}));
+                }
+            },
+        (before, after) =>
+            if before != after {
+                changes.insert("value".to_string(), serde_json::json!({"old": before, "new": after}));
+            },
+    }
+
+    changes
+}
+
+/// An entry in the generic audit log
+#[derive(Serialize, Debug)]
+pub struct GenericAuditLogEntry {
+    pub id: i32,
+    pub time: NaiveDateTime,
+    pub user: NamedId,
+
+    /// The id of the administrator who made this change while impersonating `user`, if any
+    pub impersonated_by: Option<i32>,
+
+    /// The kind of object that was patched, e.g. `"record"` or `"demon"`
+    pub target: String,
+
+    /// The id of the object that was patched
+    pub target_id: i32,
+
+    /// The top-level fields that changed, mapped to their old and new values
+    pub changes: Value,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AuditLogPagination {
+    #[serde(rename = "before", default, deserialize_with = "non_nullable")]
+    pub before_id: Option<i32>,
+
+    #[serde(rename = "after", default, deserialize_with = "non_nullable")]
+    pub after_id: Option<i32>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub limit: Option<u8>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub target: Option<String>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub target_id: Option<i32>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub user: Option<i32>,
+}
+
+impl AuditLogPagination {
+    pub async fn page(&self, connection: &mut PgConnection) -> Result<Vec<GenericAuditLogEntry>> {
+        if let Some(limit) = self.limit {
+            if limit < 1 || limit > 100 {
+                return Err(CoreError::InvalidPaginationLimit)
+            }
+        }
+
+        if let (Some(after), Some(before)) = (self.before_id, self.after_id) {
+            if after < before {
+                return Err(CoreError::AfterSmallerBefore)
+            }
+        }
+
+        let query = if self.before_id.is_some() && self.after_id.is_none() {
+            "SELECT audit_log.id, time, userid, members.name, impersonated_by, target, target_id, changes::TEXT FROM audit_log LEFT OUTER \
+             JOIN members ON members.member_id = userid WHERE (audit_log.id < $1 OR $1 IS NULL) AND (audit_log.id > $2 OR $2 IS NULL) AND \
+             (target = $3 OR $3 IS NULL) AND (target_id = $4 OR $4 IS NULL) AND (userid = $5 OR $5 IS NULL) ORDER BY audit_log.id DESC \
+             LIMIT $6"
+        } else {
+            "SELECT audit_log.id, time, userid, members.name, impersonated_by, target, target_id, changes::TEXT FROM audit_log LEFT OUTER \
+             JOIN members ON members.member_id = userid WHERE (audit_log.id < $1 OR $1 IS NULL) AND (audit_log.id > $2 OR $2 IS NULL) AND \
+             (target = $3 OR $3 IS NULL) AND (target_id = $4 OR $4 IS NULL) AND (userid = $5 OR $5 IS NULL) ORDER BY audit_log.id ASC \
+             LIMIT $6"
+        };
+
+        let rows = sqlx::query(query)
+            .bind(self.before_id)
+            .bind(self.after_id)
+            .bind(&self.target)
+            .bind(self.target_id)
+            .bind(self.user)
+            .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .fetch_all(connection)
+            .await?;
+
+        let mut entries = Vec::new();
+
+        for row in rows {
+            let changes: String = row.get("changes");
+
+            entries.push(GenericAuditLogEntry {
+                id: row.get("id"),
+                time: row.get("time"),
+                user: NamedId {
+                    id: row.get("userid"),
+                    name: row.get("name"),
+                },
+                impersonated_by: row.get("impersonated_by"),
+                target: row.get("target"),
+                target_id: row.get("target_id"),
+                changes: serde_json::from_str(&changes).unwrap_or(Value::Null),
+            })
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Gets the maximal and minimal audit log entry id currently in use
+///
+/// The returned tuple is of the form (max, min)
+pub async fn extremal_audit_log_ids(connection: &mut PgConnection) -> Result<(i32, i32)> {
+    let row = sqlx::query!(r#"SELECT COALESCE(MAX(id), 0) AS "max_id!: i32", COALESCE(MIN(id), 0) AS "min_id!: i32" FROM audit_log"#)
+        .fetch_one(connection)
+        .await?;
+
+    Ok((row.max_id, row.min_id))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audit::diff;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_only_contains_changed_fields() {
+        let changes = diff(
+            json!({"id": 1, "name": "a", "video": null}),
+            json!({"id": 1, "name": "b", "video": "c"}),
+        );
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes["name"], json!({"old": "a", "new": "b"}));
+        assert_eq!(changes["video"], json!({"old": null, "new": "c"}));
+    }
+
+    #[test]
+    fn test_diff_no_changes() {
+        assert!(diff(json!({"id": 1, "name": "a"}), json!({"id": 1, "name": "a"})).is_empty());
+    }
+}
@@ -1,12 +1,368 @@
-use crate::util::from_env_or_default;
-use std::{fs::File, io::Read};
+//! Module for reading configuration values
+//!
+//! Configuration is read from environment variables and, optionally, a TOML file (see [`load`]),
+//! with environment variables taking precedence. Both are read exactly once: the configuration of
+//! pointercrate-core is parsed into a [`Config`] right away, while the other crates read their keys
+//! via [`var`] and check them in their own `config::validate` functions, which their `setup`
+//! functions call. This way, misconfiguration is reported at startup instead of on the first
+//! request that needs the value in question.
+
+use derive_more::Display;
+use lazy_static::lazy_static;
+use std::{collections::HashMap, fmt::Debug, fs::File, io::Read, path::Path, str::FromStr};
+use toml::Value;
+
+#[derive(Debug, Display)]
+pub enum ConfigError {
+    #[display(fmt = "Failed to read configuration file {}: {}", path, error)]
+    Io { path: String, error: std::io::Error },
+
+    #[display(fmt = "Malformed configuration file {}: {}", path, error)]
+    Malformed { path: String, error: toml::de::Error },
+
+    #[display(
+        fmt = "Configuration key {} has unsupported type {}, expected a string, number, boolean or array",
+        key,
+        kind
+    )]
+    UnsupportedValue { key: String, kind: &'static str },
+
+    #[display(fmt = "Required configuration key {} is not set", key)]
+    Missing { key: &'static str },
+
+    #[display(fmt = "Configuration key {} is invalid: {}", key, reason)]
+    Invalid { key: &'static str, reason: String },
+}
+
+impl std::error::Error for ConfigError {}
+
+lazy_static! {
+    static ref CONFIG: Result<Config, ConfigError> = Config::read();
+}
+
+/// The configuration of pointercrate-core
+pub struct Config {
+    database_url: String,
+    database_replica_url: Option<String>,
+    database_replica_timeout: u64,
+    secret: Vec<u8>,
+    signing_keys: Vec<Vec<u8>>,
+    cors_allowed_origins: Vec<String>,
+    compression_min_size: usize,
+    compression_excluded_routes: Vec<String>,
+
+    /// The values from the configuration file, keyed by the environment variable they correspond to
+    file: HashMap<String, String>,
+}
+
+/// Loads the configuration, returning an error if it is invalid
+///
+/// The configuration file is read from `CONFIG_FILE` (defaults to `pointercrate.toml`, which does
+/// not need to exist). Every key in the file corresponds to the environment variable of the same
+/// name, uppercased, with tables flattened using `_` (so `client_id` in a `[discord]` table
+/// corresponds to `DISCORD_CLIENT_ID`). Arrays are joined using `,`.
+///
+/// The configuration is only read on the first call, all later calls return the same result.
+/// Reading any configuration value loads it as well, panicking if it is invalid, so this should be
+/// called at startup to report errors properly.
+pub fn load() -> Result<&'static Config, &'static ConfigError> {
+    CONFIG.as_ref()
+}
+
+fn config() -> &'static Config {
+    match load() {
+        Ok(config) => config,
+        Err(err) => panic!("Invalid configuration: {}", err),
+    }
+}
+
+/// The value of the given configuration key, taken from the environment or the configuration file
+pub fn var(key: &str) -> Option<String> {
+    std::env::var(key).ok().or_else(|| config().file.get(key).cloned())
+}
+
+/// Checks that the given configuration key, if set, can be parsed as a `T`
+pub fn check<T: FromStr>(key: &'static str) -> Result<(), ConfigError>
+where
+    <T as FromStr>::Err: Debug,
+{
+    match var(key) {
+        Some(value) =>
+            value.parse::<T>().map(|_| ()).map_err(|err| {
+                ConfigError::Invalid {
+                    key,
+                    reason: format!("'{}' is not valid: {:?}", value, err),
+                }
+            }),
+        None => Ok(()),
+    }
+}
+
+impl Config {
+    fn read() -> Result<Config, ConfigError> {
+        let (path, required) = match std::env::var("CONFIG_FILE") {
+            Ok(path) => (path, true),
+            Err(_) => ("pointercrate.toml".to_string(), false),
+        };
+
+        let file = if required || Path::new(&path).exists() {
+            let mut contents = String::new();
+
+            File::open(&path)
+                .and_then(|mut file| file.read_to_string(&mut contents))
+                .map_err(|error| ConfigError::Io { path: path.clone(), error })?;
+
+            let table = contents
+                .parse::<Value>()
+                .map_err(|error| ConfigError::Malformed { path: path.clone(), error })?;
+
+            flatten(&table)?.into_iter().collect()
+        } else {
+            HashMap::new()
+        };
+
+        Config::from_values(file)
+    }
+
+    fn from_values(file: HashMap<String, String>) -> Result<Config, ConfigError> {
+        let lookup = |key: &str| std::env::var(key).ok().or_else(|| file.get(key).cloned());
+
+        let database_url = lookup("DATABASE_URL").ok_or(ConfigError::Missing { key: "DATABASE_URL" })?;
+        let secret = read_key_file("SECRET_FILE", &lookup("SECRET_FILE").unwrap_or_else(|| ".secret".to_string()))?;
+
+        // New tokens are signed with the first key, all others are only used for verifying existing
+        // tokens, which allows rotating keys
+        let signing_keys = match lookup("SIGNING_KEY_FILES") {
+            Some(paths) =>
+                split_list(&paths)
+                    .iter()
+                    .map(|path| read_key_file("SIGNING_KEY_FILES", path))
+                    .collect::<Result<_, _>>()?,
+            None => vec![secret.clone()],
+        };
+
+        Ok(Config {
+            database_replica_url: lookup("DATABASE_REPLICA_URL").filter(|url| !url.is_empty()),
+            database_replica_timeout: parse(&lookup, "DATABASE_REPLICA_TIMEOUT", 2)?,
+            cors_allowed_origins: lookup("CORS_ALLOWED_ORIGINS")
+                .map(|origins| {
+                    split_list(&origins)
+                        .into_iter()
+                        .map(|origin| origin.trim_end_matches('/').to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            compression_min_size: parse(&lookup, "COMPRESSION_MIN_SIZE", 1024)?,
+            compression_excluded_routes: lookup("COMPRESSION_EXCLUDED_ROUTES")
+                .map(|routes| split_list(&routes))
+                .unwrap_or_default(),
+            database_url,
+            secret,
+            signing_keys,
+            file,
+        })
+    }
+}
+
+fn parse<T: FromStr>(lookup: impl Fn(&str) -> Option<String>, key: &'static str, default: T) -> Result<T, ConfigError>
+where
+    <T as FromStr>::Err: Debug,
+{
+    match lookup(key) {
+        Some(value) =>
+            value.parse().map_err(|err| {
+                ConfigError::Invalid {
+                    key,
+                    reason: format!("'{}' is not valid: {:?}", value, err),
+                }
+            }),
+        None => Ok(default),
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn read_key_file(key: &'static str, path: &str) -> Result<Vec<u8>, ConfigError> {
+    File::open(path).and_then(|file| file.bytes().collect()).map_err(|err| {
+        ConfigError::Invalid {
+            key,
+            reason: format!("cannot open {}: {}", path, err),
+        }
+    })
+}
+
+/// Flattens a parsed configuration file into (environment variable, value) pairs
+fn flatten(value: &Value) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut variables = Vec::new();
+
+    if let Value::Table(table) = value {
+        for (key, value) in table {
+            flatten_into(&mut variables, key.to_uppercase(), value)?;
+        }
+    }
+
+    Ok(variables)
+}
+
+fn flatten_into(variables: &mut Vec<(String, String)>, key: String, value: &Value) -> Result<(), ConfigError> {
+    let value = match value {
+        Value::Table(table) => {
+            for (nested, value) in table {
+                flatten_into(variables, format!("{}_{}", key, nested.to_uppercase()), value)?;
+            }
+
+            return Ok(())
+        },
+        Value::Array(values) =>
+            values
+                .iter()
+                .map(|value| scalar(&key, value))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(","),
+        value => scalar(&key, value)?,
+    };
+
+    variables.push((key, value));
+
+    Ok(())
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String, ConfigError> {
+    match value {
+        Value::String(string) => Ok(string.clone()),
+        Value::Integer(integer) => Ok(integer.to_string()),
+        Value::Float(float) => Ok(float.to_string()),
+        Value::Boolean(boolean) => Ok(boolean.to_string()),
+        value =>
+            Err(ConfigError::UnsupportedValue {
+                key: key.to_string(),
+                kind: value.type_str(),
+            }),
+    }
+}
 
 pub fn database_url() -> String {
-    std::env::var("DATABASE_URL").expect("DATABASE_URL is not set")
+    config().database_url.clone()
 }
 
+/// The URL of a read-only replica of the database, set via `DATABASE_REPLICA_URL`. Read-only
+/// requests are served from it if set (see
+/// [`PointercratePool::read_connection`](crate::pool::PointercratePool::read_connection)).
+pub fn database_replica_url() -> Option<String> {
+    config().database_replica_url.clone()
+}
+
+/// The number of seconds to wait for a connection to the database replica before falling back to
+/// the primary database. Defaults to 2.
+pub fn database_replica_timeout() -> u64 {
+    config().database_replica_timeout
+}
+
+/// The application secret, read from the file at `SECRET_FILE` (defaults to `.secret`)
 pub fn secret() -> Vec<u8> {
-    let path: String = from_env_or_default("SECRET_FILE", ".secret".into());
-    let file = File::open(path).expect("Unable to open secret file");
-    file.bytes().collect::<Result<Vec<u8>, _>>().unwrap()
+    config().secret.clone()
+}
+
+/// The keys JSON Web Tokens are signed with, read from the files listed (comma separated) in
+/// `SIGNING_KEY_FILES`
+///
+/// New tokens are signed with the first key, all others are only used for verifying existing
+/// tokens, which allows rotating keys. Defaults to just the application [`secret`].
+pub fn signing_keys() -> Vec<Vec<u8>> {
+    config().signing_keys.clone()
+}
+
+/// The origins (e.g. `https://example.com`) browsers may access the API from, given as a comma
+/// separated list in `CORS_ALLOWED_ORIGINS`. A single `*` allows all origins. Defaults to none.
+pub fn cors_allowed_origins() -> Vec<String> {
+    config().cors_allowed_origins.clone()
+}
+
+/// The minimal size (in bytes) of responses to compress, set via `COMPRESSION_MIN_SIZE`. Smaller
+/// responses are not worth the effort. Defaults to 1024.
+pub fn compression_min_size() -> usize {
+    config().compression_min_size
+}
+
+/// Routes (given by their path, e.g. `/api/v1/records/<record_id>`) whose responses should never be
+/// compressed, as a comma separated list in `COMPRESSION_EXCLUDED_ROUTES`. Defaults to none.
+pub fn compression_excluded_routes() -> Vec<String> {
+    config().compression_excluded_routes.clone()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{flatten, Config, ConfigError};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_tables_are_flattened() {
+        let value = "database_url = 'postgres://localhost'\n[discord]\nclient_id = 1234\nauto_register = true"
+            .parse()
+            .unwrap();
+
+        assert_eq!(flatten(&value).unwrap(), vec![
+            ("DATABASE_URL".to_string(), "postgres://localhost".to_string()),
+            ("DISCORD_AUTO_REGISTER".to_string(), "true".to_string()),
+            ("DISCORD_CLIENT_ID".to_string(), "1234".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_arrays_are_joined() {
+        let value = "record_webhooks = ['https://a', 'https://b']".parse().unwrap();
+
+        assert_eq!(flatten(&value).unwrap(), vec![(
+            "RECORD_WEBHOOKS".to_string(),
+            "https://a,https://b".to_string()
+        )]);
+    }
+
+    #[test]
+    fn test_nested_arrays_are_rejected() {
+        let value = "list_sizes = [[1, 2]]".parse().unwrap();
+
+        assert!(flatten(&value).is_err());
+    }
+
+    #[test]
+    fn test_missing_database_url_is_reported() {
+        if std::env::var_os("DATABASE_URL").is_some() {
+            return
+        }
+
+        assert!(matches!(
+            Config::from_values(HashMap::new()),
+            Err(ConfigError::Missing { key: "DATABASE_URL" })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_values_are_reported() {
+        let file = vec![
+            ("DATABASE_URL".to_string(), "postgres://localhost".to_string()),
+            ("SECRET_FILE".to_string(), "Cargo.toml".to_string()),
+            ("COMPRESSION_MIN_SIZE".to_string(), "a lot".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        if std::env::var_os("COMPRESSION_MIN_SIZE").is_some() {
+            return
+        }
+
+        assert!(matches!(
+            Config::from_values(file),
+            Err(ConfigError::Invalid {
+                key: "COMPRESSION_MIN_SIZE",
+                ..
+            })
+        ));
+    }
 }
@@ -0,0 +1,143 @@
+//! Module for turning lists of JSON objects into CSV tables
+//!
+//! Every object becomes one row. Nested objects are flattened into columns named by joining the
+//! keys with a `.` (e.g. `player.name`), while arrays are kept as JSON. The set of columns is the
+//! union of all keys, in the order they are first encountered (which, since serde_json's
+//! `preserve_order` feature is enabled, is the order of the fields in the serialized structs).
+//!
+//! Spreadsheet applications interpret cells starting with `=`, `+`, `-` or `@` as formulas (and
+//! skip leading tabs and carriage returns when doing so), so text cells starting with any of these
+//! are prefixed with a `'` to prevent user provided values (e.g. player names) from being
+//! evaluated.
+
+use serde_json::{Map, Value};
+
+/// Converts the given JSON array of objects into a CSV document (with header row) according to RFC
+/// 4180
+///
+/// Values that are not arrays are treated as single-element arrays.
+pub fn to_csv(value: &Value) -> String {
+    let rows: Vec<Vec<(String, &Value)>> = match value {
+        Value::Array(values) => values.iter().map(flatten).collect(),
+        value => vec![flatten(value)],
+    };
+
+    let mut columns: Vec<&str> = Vec::new();
+
+    for (key, _) in rows.iter().flatten() {
+        if !columns.contains(&key.as_str()) {
+            columns.push(key);
+        }
+    }
+
+    let mut csv = String::new();
+
+    write_row(&mut csv, columns.iter().map(|column| column.to_string()));
+
+    for row in &rows {
+        write_row(
+            &mut csv,
+            columns.iter().map(|column| {
+                row.iter()
+                    .find(|(key, _)| key == column)
+                    .map(|(_, value)| cell(value))
+                    .unwrap_or_default()
+            }),
+        );
+    }
+
+    csv
+}
+
+fn flatten(value: &Value) -> Vec<(String, &Value)> {
+    let mut fields = Vec::new();
+
+    match value {
+        Value::Object(object) => flatten_into(&mut fields, "", object),
+        value => fields.push(("value".to_string(), value)),
+    }
+
+    fields
+}
+
+fn flatten_into<'a>(fields: &mut Vec<(String, &'a Value)>, prefix: &str, object: &'a Map<String, Value>) {
+    for (key, value) in object {
+        let key = format!("{}{}", prefix, key);
+
+        match value {
+            Value::Object(nested) => flatten_into(fields, &format!("{}.", key), nested),
+            value => fields.push((key, value)),
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) if string.starts_with(|c| matches!(c, '=' | '+' | '-' | '@' | '\t' | '\r')) => format!("'{}", string),
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn write_row(csv: &mut String, cells: impl Iterator<Item = String>) {
+    let cells: Vec<String> = cells.map(|cell| escape(&cell)).collect();
+
+    csv.push_str(&cells.join(","));
+    csv.push_str("\r\n");
+}
+
+fn escape(cell: &str) -> String {
+    if cell.contains(|c| matches!(c, ',' | '"' | '\r' | '\n')) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::csv::to_csv;
+    use serde_json::json;
+
+    #[test]
+    fn test_nested_objects_are_flattened() {
+        let csv = to_csv(&json!([
+            {"id": 1, "player": {"id": 2, "name": "a"}, "video": null},
+            {"id": 3, "player": {"id": 4, "name": "b"}, "video": "c"},
+        ]));
+
+        assert_eq!(csv, "id,player.id,player.name,video\r\n1,2,a,\r\n3,4,b,c\r\n");
+    }
+
+    #[test]
+    fn test_special_characters_are_escaped() {
+        let csv = to_csv(&json!([{"name": "a, \"b\"\nc"}]));
+
+        assert_eq!(csv, "name\r\n\"a, \"\"b\"\"\nc\"\r\n");
+    }
+
+    #[test]
+    fn test_formulas_are_neutralized() {
+        let csv = to_csv(&json!([{"name": "=HYPERLINK(\"a\")", "other": "@b", "tab": "\t=c", "cr": "\r=d", "score": -1}]));
+
+        assert_eq!(
+            csv,
+            "name,other,tab,cr,score\r\n\"'=HYPERLINK(\"\"a\"\")\",'@b,'\t=c,\"'\r=d\",-1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_columns_keep_field_order() {
+        let csv = to_csv(&json!([{"name": "a", "id": 1}]));
+
+        assert_eq!(csv, "name,id\r\na,1\r\n");
+    }
+
+    #[test]
+    fn test_missing_fields_are_empty() {
+        let csv = to_csv(&json!([{"a": 1}, {"b": 2}]));
+
+        assert_eq!(csv, "a,b\r\n1,\r\n,2\r\n");
+    }
+}
@@ -1,6 +1,6 @@
 use crate::permission::Permission;
 use derive_more::Display;
-use log::error;
+use log::{error, warn};
 use serde::Serialize;
 use sqlx::postgres::PgDatabaseError;
 use std::{error::Error, time::Duration};
@@ -12,6 +12,12 @@ pub trait PointercrateError: Error + Serialize + From<CoreError> {
     fn status_code(&self) -> u16 {
         self.error_code() / 100
     }
+
+    /// How long the client has to wait before retrying the request, should this error be the
+    /// result of a ratelimit being hit
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
 }
 
 #[derive(Serialize, Display, Debug, Eq, PartialEq, Clone)]
@@ -88,6 +94,13 @@ pub enum CoreError {
     )]
     Conflict,
 
+    /// `409 CONFLICT` variant returned if a request's transaction could not be serialized with
+    /// respect to concurrently running ones, even after retrying
+    ///
+    /// Error Code `40909`
+    #[display(fmt = "The resource was concurrently modified by another request. Please try again.")]
+    ConcurrentModification,
+
     /// `411 LENGTH REQUIRED`
     ///
     /// Error Code `41100`
@@ -229,6 +242,7 @@ impl PointercrateError for CoreError {
             CoreError::NotFound => 40400,
             CoreError::MethodNotAllowed => 40500,
             CoreError::Conflict => 40900,
+            CoreError::ConcurrentModification => 40909,
             CoreError::LengthRequired => 41200,
             CoreError::PreconditionFailed => 41200,
             CoreError::PayloadTooLarge => 41300,
@@ -247,14 +261,32 @@ impl PointercrateError for CoreError {
             CoreError::DatabaseConnectionError => 50005,
         }
     }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            CoreError::Ratelimited { remaining, .. } => Some(*remaining),
+            _ => None,
+        }
+    }
 }
 
+/// SQLSTATEs indicating that a transaction was aborted because of concurrent transactions
+/// (`serialization_failure` and `deadlock_detected`). Transactions failing this way can simply be
+/// retried.
+const SERIALIZATION_FAILURES: [&str; 2] = ["40001", "40P01"];
+
 impl From<sqlx::Error> for CoreError {
     fn from(error: sqlx::Error) -> Self {
         match error {
             sqlx::Error::Database(database_error) => {
                 let database_error = database_error.downcast::<PgDatabaseError>();
 
+                if SERIALIZATION_FAILURES.contains(&database_error.code()) {
+                    warn!("Transaction aborted due to concurrent modification: {}", database_error.message());
+
+                    return CoreError::ConcurrentModification
+                }
+
                 error!("Database error: {:?}. ", database_error);
 
                 CoreError::DatabaseError
@@ -38,3 +38,7 @@ pub trait Taggable: Hash + Serialize {
         format!("{};{}", self.patch_part(), self.get_part())
     }
 }
+
+/// Allows endpoints for looking up an object that might not exist (e.g. the first victor of a
+/// demon) to also respond with ETags
+impl<T: Taggable> Taggable for Option<T> {}
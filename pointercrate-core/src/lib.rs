@@ -1,5 +1,6 @@
 pub mod audit;
 pub mod config;
+pub mod csv;
 pub mod error;
 pub mod etag;
 pub mod permission;
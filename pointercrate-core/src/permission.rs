@@ -1,7 +1,10 @@
 use crate::error::CoreError;
 use derive_more::Display;
 use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
 
 #[derive(Serialize, Debug, Display, Eq, PartialEq, Clone, Copy, Hash)]
 #[serde(transparent)]
@@ -33,11 +36,41 @@ impl Into<u16> for Permission {
     }
 }
 
+/// A named set of permissions that is usually granted as a whole (e.g. "List Moderator" plus
+/// "Moderator" for a member of the list team)
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct Role {
+    name: &'static str,
+    permissions: Vec<Permission>,
+}
+
+impl Role {
+    pub fn new(name: &'static str, permissions: Vec<Permission>) -> Role {
+        Role { name, permissions }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn permissions(&self) -> &[Permission] {
+        &self.permissions
+    }
+
+    pub fn bits(&self) -> u16 {
+        self.permissions.iter().fold(0x0, |mask, perm| mask | perm.bit)
+    }
+}
+
 #[derive(Clone)]
 pub struct PermissionsManager {
     permissions: HashSet<Permission>,
     implication_map: HashMap<Permission, HashSet<Permission>>,
     assignable_map: HashMap<Permission, HashSet<Permission>>,
+
+    /// Shared between all clones, so that roles can still be registered once the manager is
+    /// managed by rocket (see [`PermissionsManager::register_role`])
+    roles: Arc<RwLock<Vec<Role>>>,
 }
 
 impl PermissionsManager {
@@ -52,9 +85,49 @@ impl PermissionsManager {
             permissions: permission_set,
             implication_map: HashMap::new(),
             assignable_map: HashMap::new(),
+            roles: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
+    pub fn role(self, role: Role) -> Self {
+        self.register_role(role);
+        self
+    }
+
+    /// Registers the given role, replacing any previously registered role of the same name
+    ///
+    /// Meant to be called from the `setup` functions of the individual crates, which register the
+    /// roles built from the permissions they define.
+    pub fn register_role(&self, role: Role) {
+        let mut roles = self.roles.write().unwrap();
+
+        roles.retain(|registered| registered.name != role.name);
+        roles.push(role);
+    }
+
+    pub fn roles(&self) -> Vec<Role> {
+        self.roles.read().unwrap().clone()
+    }
+
+    pub fn role_by_name(&self, name: &str) -> Option<Role> {
+        self.roles.read().unwrap().iter().find(|role| role.name == name).cloned()
+    }
+
+    pub fn permission_by_name(&self, name: &str) -> Option<Permission> {
+        self.permissions.iter().find(|perm| perm.name == name).copied()
+    }
+
+    /// All roles whose permissions are fully contained in the given bitstring
+    pub fn roles_of_bits(&self, bits: u16) -> Vec<Role> {
+        self.roles
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|role| role.bits() & bits == role.bits())
+            .cloned()
+            .collect()
+    }
+
     // we should probably verify that added permissions are all part of what was in the constructor but
     // wherhaklsrödj
     pub fn assigns(mut self, perm1: Permission, perm2: Permission) -> Self {
@@ -128,6 +201,22 @@ impl PermissionsManager {
         perms
     }
 
+    /// Computes the new permission bitstring of a user who currently has the permissions `current`,
+    /// after someone with the permissions `assigner` set all the permissions they can assign to
+    /// `requested`
+    ///
+    /// Permissions the assigner cannot assign are left untouched. If `requested` contains any
+    /// permissions the assigner cannot assign, those are returned as the error.
+    pub fn reassign(&self, assigner: u16, current: u16, requested: u16) -> Result<u16, HashSet<Permission>> {
+        let assignable_bitmask = self.assignable_by_bits(assigner).iter().fold(0x0, |mask, perm| mask | perm.bit);
+
+        if requested & assignable_bitmask != requested {
+            return Err(self.bits_to_permissions(requested & !assignable_bitmask))
+        }
+
+        Ok((current & !assignable_bitmask) | requested)
+    }
+
     pub fn require_permission(&self, permissions_we_have: u16, permission_required: Permission) -> Result<(), CoreError> {
         if !self.implied_by_bits(permissions_we_have).contains(&permission_required) {
             return Err(CoreError::MissingPermissions {
@@ -154,7 +243,7 @@ mod test {
         };
     }
 
-    use crate::permission::{Permission, PermissionsManager};
+    use crate::permission::{Permission, PermissionsManager, Role};
     use std::collections::HashSet;
 
     const PERM1: Permission = Permission::new("1", 0x1);
@@ -189,4 +278,26 @@ mod test {
     fn test_assignment() {
         assert_eq!(permission_manager().assignable_by(PERM4), set![PERM2, PERM5, PERM6]);
     }
+
+    #[test]
+    fn test_reassignment() {
+        // PERM4 can assign PERM2 and PERM5, the user's PERM1 is left alone
+        assert_eq!(permission_manager().reassign(0x8, 0x1 | 0x2, 0x10), Ok(0x1 | 0x10));
+        assert_eq!(permission_manager().reassign(0x8, 0x1, 0x1 | 0x2), Err(set![PERM1]));
+    }
+
+    #[test]
+    fn test_roles() {
+        let manager = permission_manager().role(Role::new("role", vec![PERM1, PERM4]));
+
+        assert_eq!(manager.role_by_name("role").as_ref().map(Role::bits), Some(0x1 | 0x8));
+        assert_eq!(manager.roles_of_bits(0x1 | 0x8 | 0x10).len(), 1);
+        assert!(manager.roles_of_bits(0x1).is_empty());
+
+        // Registering a role on a clone (like rocket's managed state) is visible everywhere
+        manager.clone().register_role(Role::new("role", vec![PERM1]));
+
+        assert_eq!(manager.role_by_name("role").as_ref().map(Role::bits), Some(0x1));
+        assert_eq!(manager.roles().len(), 1);
+    }
 }
@@ -1,9 +1,24 @@
 use crate::{config, error::Result};
-use log::trace;
+use log::{trace, warn};
 use sqlx::{pool::PoolConnection, postgres::PgPoolOptions, PgConnection, Pool, Postgres, Transaction};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
+/// For how long to stop trying the replica after failing to connect to it
+const REPLICA_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
 pub struct PointercratePool {
     connection_pool: Pool<Postgres>,
+
+    /// Pool of connections to a read-only replica of the database, if one is configured
+    replica_pool: Option<Pool<Postgres>>,
+
+    /// Until when read-only connections go straight to the primary, because connecting to the
+    /// replica recently failed
+    replica_down_until: Arc<Mutex<Option<Instant>>>,
 }
 
 impl PointercratePool {
@@ -12,17 +27,41 @@ impl PointercratePool {
     }
 
     pub async fn init() -> Self {
+        // The replica is connected to lazily, so that it being down does not prevent startup
+        let replica_pool = config::database_replica_url().map(|url| {
+            pool_options()
+                .connect_timeout(Duration::from_secs(config::database_replica_timeout()))
+                .connect_lazy(&url)
+                .expect("Malformed DATABASE_REPLICA_URL")
+        });
+
         PointercratePool {
-            connection_pool: PgPoolOptions::default()
-                .max_connections(20)
-                .max_lifetime(Some(std::time::Duration::from_secs(60 * 60 * 24)))
-                .idle_timeout(Some(std::time::Duration::from_secs(60 * 5)))
+            connection_pool: pool_options()
                 .connect(&config::database_url())
                 .await
                 .expect("Failed to connect to pointercrate database"),
+            replica_pool,
+            replica_down_until: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Performs a round-trip to the database, to check whether it is reachable
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query!("SELECT 1 AS one").fetch_one(&self.connection_pool).await?;
+
+        Ok(())
+    }
+
+    /// The number of connections currently held by the pool, both idle and in use
+    pub fn size(&self) -> u32 {
+        self.connection_pool.size()
+    }
+
+    /// The number of idle connections currently held by the pool
+    pub fn num_idle(&self) -> usize {
+        self.connection_pool.num_idle()
+    }
+
     /// Gets a connection from the connection pool
     pub async fn connection(&self) -> Result<PoolConnection<Postgres>> {
         let mut connection = self.connection_pool.acquire().await?;
@@ -32,6 +71,38 @@ impl PointercratePool {
         Ok(connection)
     }
 
+    /// Gets a connection for running read-only queries
+    ///
+    /// If a replica is configured, the connection goes to the replica, falling back to the primary
+    /// database if the replica cannot be reached. After a failure, the replica is not tried again
+    /// for a while, so that not every request has to wait for the connection attempt to time out.
+    ///
+    /// Since replicas lag behind the primary, data read via such a connection might be slightly
+    /// outdated. Never write through it, and never cache what was read through it.
+    pub async fn read_connection(&self) -> Result<PoolConnection<Postgres>> {
+        if let Some(ref replica_pool) = self.replica_pool {
+            let replica_down = matches!(*self.replica_down_until.lock().unwrap(), Some(until) if until > Instant::now());
+
+            if !replica_down {
+                // No auditing needed (or possible, as hot standbys do not allow temporary tables), as
+                // nothing is modified via this connection
+                match replica_pool.acquire().await {
+                    Ok(connection) => return Ok(connection),
+                    Err(err) => {
+                        warn!(
+                            "Failed to connect to database replica, falling back to primary for the next {:?}: {}",
+                            REPLICA_BACKOFF, err
+                        );
+
+                        *self.replica_down_until.lock().unwrap() = Some(Instant::now() + REPLICA_BACKOFF);
+                    },
+                }
+            }
+        }
+
+        self.connection().await
+    }
+
     pub async fn transaction(&self) -> Result<Transaction<'static, Postgres>> {
         let mut connection = self.connection_pool.begin().await?;
 
@@ -39,21 +110,69 @@ impl PointercratePool {
 
         Ok(connection)
     }
+
+    /// Begins a transaction with isolation level `SERIALIZABLE`
+    ///
+    /// Such transactions fail with
+    /// [`CoreError::ConcurrentModification`](crate::error::CoreError::ConcurrentModification) if
+    /// they could not be serialized with respect to concurrently running transactions.
+    pub async fn serializable_transaction(&self) -> Result<Transaction<'static, Postgres>> {
+        let mut connection = self.connection_pool.begin().await?;
+
+        // Needs to happen before any other query is run in the transaction
+        sqlx::query!("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+            .execute(&mut connection)
+            .await?;
+
+        audit_connection(&mut *connection, 0).await?;
+
+        Ok(connection)
+    }
+}
+
+fn pool_options() -> PgPoolOptions {
+    PgPoolOptions::default()
+        .max_connections(20)
+        .max_lifetime(Some(Duration::from_secs(60 * 60 * 24)))
+        .idle_timeout(Some(Duration::from_secs(60 * 5)))
+}
+
+/// Creates the (connection local) table holding the user changes made via the given connection are
+/// attributed to, if it does not exist yet
+///
+/// Queries reading from `active_user` need to call this first if the connection might not have been
+/// set up via [`audit_connection`] (e.g. in background jobs). On such connections, the table stays
+/// empty, meaning changes are attributed to nobody.
+pub async fn create_active_user_table(connection: &mut PgConnection) -> Result<()> {
+    sqlx::query!("CREATE TEMPORARY TABLE IF NOT EXISTS active_user (id INTEGER, impersonated_by INTEGER)")
+        .execute(connection)
+        .await?;
+
+    Ok(())
 }
 
 pub async fn audit_connection(connection: &mut PgConnection, user_id: i32) -> Result<()> {
+    impersonated_audit_connection(connection, user_id, None).await
+}
+
+/// Like [`audit_connection`], but additionally records the administrator impersonating the given
+/// user, if any, so that their changes are flagged in the audit log
+pub async fn impersonated_audit_connection(connection: &mut PgConnection, user_id: i32, impersonated_by: Option<i32>) -> Result<()> {
     trace!(
-        "Creating connection of which usage will be attributed to user {} in audit logs",
-        user_id
+        "Creating connection of which usage will be attributed to user {} (impersonated by {:?}) in audit logs",
+        user_id,
+        impersonated_by
     );
 
-    sqlx::query!("CREATE TEMPORARY TABLE IF NOT EXISTS active_user (id INTEGER)")
-        .execute(&mut *connection)
-        .await?;
+    create_active_user_table(&mut *connection).await?;
     sqlx::query!("DELETE FROM active_user").execute(&mut *connection).await?;
-    sqlx::query!("INSERT INTO active_user (id) VALUES ($1)", user_id)
-        .execute(connection)
-        .await?;
+    sqlx::query!(
+        "INSERT INTO active_user (id, impersonated_by) VALUES ($1, $2)",
+        user_id,
+        impersonated_by
+    )
+    .execute(connection)
+    .await?;
 
     Ok(())
 }
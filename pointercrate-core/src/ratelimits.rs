@@ -27,6 +27,10 @@ macro_rules! ratelimits {
         ratelimits!(@struct@ $struct_name [$($field: $type | $init,)* $name: KeyedRateLimiter<IpAddr> | KeyedRateLimiter::new(nonzero!($capacity), Duration::from_secs($seconds))] $($remaining)*);
     };
 
+    (@struct@ $struct_name: ident [$($field: ident: $type: ty | $init: expr),*] $name: ident[$capacity: tt per $seconds: tt per user] => $message: expr, $($remaining: tt)*) => {
+        ratelimits!(@struct@ $struct_name [$($field: $type | $init,)* $name: KeyedRateLimiter<i32> | KeyedRateLimiter::new(nonzero!($capacity), Duration::from_secs($seconds))] $($remaining)*);
+    };
+
     (@method@ $name: ident[$capacity: tt per $seconds: tt] => $message: expr, $($remaining: tt)*) => {
         pub(crate) fn $name(&self) -> Result<(), CoreError> {
             let now = Instant::now();
@@ -55,6 +59,20 @@ macro_rules! ratelimits {
         ratelimits!(@method@  $($remaining)*);
     };
 
+    (@method@ $name: ident[$capacity: tt per $seconds: tt per user] => $message: expr, $($remaining: tt)*) => {
+        pub(crate) fn $name(&self, user_id: i32) -> Result<(), CoreError> {
+            let now = Instant::now();
+
+            self.$name.clone().check_at(user_id, now).map_err(|too_early| {
+                CoreError::Ratelimited {
+                    message: $message.to_string(),
+                    remaining: too_early.earliest_possible() - now,
+                }
+            })
+        }
+        ratelimits!(@method@  $($remaining)*);
+    };
+
     (@struct@ $struct_name: ident [$($field: ident: $type: ty | $init: expr),*]) => {
         pub struct $struct_name {
             $(
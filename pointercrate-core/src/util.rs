@@ -1,13 +1,25 @@
+use log::error;
 use serde::{de::Error, Deserialize, Deserializer};
 use std::{fmt::Debug, str::FromStr};
 
+/// Reads the given configuration key (see [`config::var`](crate::config::var)), falling back to
+/// `default` if it is not set
+///
+/// Malformed values should have been rejected at startup by the `config::validate` function of the
+/// crate the key belongs to. Should one slip through anyway, it is logged and the default is used,
+/// instead of panicking in the middle of a request.
 pub fn from_env_or_default<T: FromStr>(key: &str, default: T) -> T
 where
     <T as FromStr>::Err: Debug,
 {
-    match std::env::var(key) {
-        Ok(value) => value.parse().unwrap(),
-        Err(_) => default,
+    match crate::config::var(key) {
+        Some(value) =>
+            value.parse().unwrap_or_else(|err| {
+                error!("Invalid value '{}' for configuration key {}, using default: {:?}", value, key, err);
+
+                default
+            }),
+        None => default,
     }
 }
 
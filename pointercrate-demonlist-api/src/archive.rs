@@ -0,0 +1,100 @@
+//! Module for periodically submitting the video proof of approved records to the Wayback Machine
+//!
+//! Only active if [`config::archive_videos`] is enabled. Every so often, a batch of not yet
+//! archived videos is submitted to the Wayback Machine's "Save Page Now" service, and the link to
+//! the resulting snapshot is stored alongside the record. Failed submissions are retried once all
+//! other videos have been attempted. Storing the archive link neither changes the record's ETag nor
+//! its `last_modified` timestamp.
+//!
+//! Mirroring the videos themselves into S3 compatible storage is not implemented, as downloading
+//! videos from YouTube and other hosts requires tooling outside this codebase.
+
+use crate::config;
+use log::{error, info, warn};
+use pointercrate_core::pool::audit_connection;
+use pointercrate_demonlist::{error::Result, record::FullRecord};
+use reqwest::{header::CONTENT_LOCATION, Client};
+use rocket::tokio;
+use sqlx::{Pool, Postgres};
+use std::time::Duration;
+
+const WAYBACK_MACHINE: &str = "https://web.archive.org";
+
+pub fn spawn(pool: Pool<Postgres>) {
+    if !config::archive_videos() {
+        return
+    }
+
+    tokio::spawn(async move {
+        let client = Client::new();
+        let interval = Duration::from_secs(config::archive_interval());
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            if let Err(err) = archive_batch(&client, &pool).await {
+                error!("INTERNAL SERVER ERROR: Failure to archive batch of record videos: {:?}", err);
+            }
+        }
+    });
+}
+
+async fn archive_batch(client: &Client, pool: &Pool<Postgres>) -> Result<()> {
+    let mut connection = pool.acquire().await?;
+
+    audit_connection(&mut connection, 0).await?;
+
+    let batch = FullRecord::videos_to_archive(config::archive_batch_size(), &mut connection).await?;
+
+    info!("Submitting {} record videos to the Wayback Machine", batch.len());
+
+    for archival in batch {
+        let archive_url = submit(client, &archival.video).await;
+
+        FullRecord::set_archive_url(archival.record_id, archive_url.as_deref(), &mut connection).await?;
+    }
+
+    Ok(())
+}
+
+/// Asks the Wayback Machine to take a snapshot of the given video, returning the snapshot's URL on
+/// success
+async fn submit(client: &Client, video: &str) -> Option<String> {
+    let response = match client.get(&format!("{}/save/{}", WAYBACK_MACHINE, video)).send().await {
+        Ok(response) => response,
+        Err(err) => {
+            warn!("Failed to submit video {} to the Wayback Machine: {:?}", video, err);
+
+            return None
+        },
+    };
+
+    if !response.status().is_success() {
+        warn!(
+            "Wayback Machine responded with {} when archiving video {}",
+            response.status(),
+            video
+        );
+
+        return None
+    }
+
+    // The snapshot's path is either given in the Content-Location header, or we have been redirected
+    // to it
+    let snapshot = response
+        .headers()
+        .get(CONTENT_LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .map(ToString::to_string)
+        .or_else(|| response.url().as_str().strip_prefix(WAYBACK_MACHINE).map(ToString::to_string))
+        .filter(|path| path.starts_with("/web/"));
+
+    match snapshot {
+        Some(path) => Some(format!("{}{}", WAYBACK_MACHINE, path)),
+        None => {
+            warn!("Wayback Machine did not tell us where the snapshot of video {} is", video);
+
+            None
+        },
+    }
+}
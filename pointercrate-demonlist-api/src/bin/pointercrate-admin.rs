@@ -0,0 +1,140 @@
+//! Command line tool for maintenance tasks, working directly against the database
+//!
+//! Useful for setting up a new instance, or when the web server itself is down. Reads the same
+//! configuration as the server (in particular `DATABASE_URL` and the webhook configuration).
+
+use pointercrate_core::pool::PointercratePool;
+use pointercrate_demonlist::{player::DatabasePlayer, record::FullRecord, score, LIST_ADMINISTRATOR};
+use pointercrate_demonlist_api::webhook::{self, RecordEvent};
+use pointercrate_user::{AuthenticatedUser, Registration, ADMINISTRATOR};
+use std::{io::BufRead, process::exit};
+
+const USAGE: &str = "Usage: pointercrate-admin <command>
+
+Commands:
+    create-admin <name>                 Creates a new user with administrator permissions. The password is read from stdin.
+    recompute-scores                    Recomputes the scores of all players
+    prune-players                       Deletes all players no longer referenced by any record, demon or claim
+    resend-webhook <record id> <event>  Executes the webhooks for the given record again. <event> is one of 'submitted', 'approved'
+                                        or 'rejected'";
+
+#[rocket::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    if let Err(err) = pointercrate_core::config::load() {
+        eprintln!("Invalid configuration: {}", err);
+
+        exit(1)
+    }
+
+    if let Err(err) = pointercrate_demonlist::config::validate().and_then(|_| pointercrate_user::config::validate()) {
+        eprintln!("Invalid configuration: {}", err);
+
+        exit(1)
+    }
+
+    let result = match &args[..] {
+        ["create-admin", name] => create_admin(name).await,
+        ["recompute-scores"] => recompute_scores().await,
+        ["prune-players"] => prune_players().await,
+        ["resend-webhook", record_id, event] => resend_webhook(record_id, event).await,
+        _ => {
+            eprintln!("{}", USAGE);
+
+            exit(2)
+        },
+    };
+
+    if let Err(message) = result {
+        eprintln!("Error: {}", message);
+
+        exit(1)
+    }
+}
+
+async fn create_admin(name: &str) -> Result<(), String> {
+    let mut password = String::new();
+
+    std::io::stdin()
+        .lock()
+        .read_line(&mut password)
+        .map_err(|err| format!("Failed to read password: {}", err))?;
+
+    let registration = Registration {
+        name: name.to_string(),
+        password: password.trim_end_matches(&['\r', '\n'][..]).to_string(),
+        email: None,
+    };
+
+    AuthenticatedUser::validate_password(&registration.password).map_err(|err| err.to_string())?;
+
+    let pool = PointercratePool::init().await;
+    let mut connection = pool.transaction().await.map_err(|err| err.to_string())?;
+
+    let mut user = AuthenticatedUser::register(registration, &mut connection)
+        .await
+        .map_err(|err| err.to_string())?
+        .into_inner();
+
+    user.set_permissions(ADMINISTRATOR.bit() | LIST_ADMINISTRATOR.bit(), &mut connection)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    connection.commit().await.map_err(|err| err.to_string())?;
+
+    println!("Created administrator {}", user);
+
+    Ok(())
+}
+
+async fn recompute_scores() -> Result<(), String> {
+    let pool = PointercratePool::init().await;
+    let mut connection = pool.transaction().await.map_err(|err| err.to_string())?;
+
+    score::refresh_player_scores(&mut connection).await.map_err(|err| err.to_string())?;
+
+    connection.commit().await.map_err(|err| err.to_string())?;
+
+    println!("Recomputed player scores");
+
+    Ok(())
+}
+
+async fn prune_players() -> Result<(), String> {
+    let pool = PointercratePool::init().await;
+    let mut connection = pool.transaction().await.map_err(|err| err.to_string())?;
+
+    let deleted = DatabasePlayer::prune_orphaned(&mut connection)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    connection.commit().await.map_err(|err| err.to_string())?;
+
+    println!("Deleted {} orphaned players", deleted);
+
+    Ok(())
+}
+
+async fn resend_webhook(record_id: &str, event: &str) -> Result<(), String> {
+    let record_id: i32 = record_id.parse().map_err(|_| format!("Invalid record id '{}'", record_id))?;
+    let event = match event {
+        "submitted" => RecordEvent::Submitted,
+        "approved" => RecordEvent::Approved,
+        "rejected" => RecordEvent::Rejected,
+        _ => return Err(format!("Unknown event '{}'", event)),
+    };
+
+    let pool = PointercratePool::init().await;
+    let mut connection = pool.connection().await.map_err(|err| err.to_string())?;
+
+    let record = FullRecord::by_id(record_id, &mut connection).await.map_err(|err| err.to_string())?;
+
+    // Unlike the server, we wait for delivery to finish, since the process exits afterwards
+    webhook::execute(event, webhook::embed(event, &record)).await;
+
+    println!("Executed {:?} webhooks for record {}", event, record_id);
+
+    Ok(())
+}
@@ -0,0 +1,210 @@
+//! In-process cache for the busiest read paths (the list overview, demon pages and the stats
+//! viewer ranking)
+//!
+//! Besides the data itself, fully rendered HTML pages are cached as well. All entries are dropped
+//! whenever a [`ListEvent`] is published. Since not every change to the
+//! list causes an event (e.g. changing a demon's video does not), entries additionally expire after
+//! a configurable time-to-live (see [`config::list_cache_ttl`]). Each part of the cache holds at
+//! most [`config::list_cache_capacity`] entries, so that arbitrary query parameters cannot make it
+//! grow without bound.
+
+use crate::{
+    config,
+    events::{ListEvent, ListEvents},
+};
+use log::{debug, info};
+use pointercrate_core_api::response::RenderedPage;
+use pointercrate_demonlist::{
+    demon::{current_list, Demon, FullDemon},
+    error::Result,
+    player::{RankedPlayer, RankingPagination},
+};
+use rocket::tokio::sync::broadcast::{error::RecvError, Receiver};
+use sqlx::PgConnection;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// A map whose entries expire after a fixed amount of time, holding at most a fixed number of
+/// entries
+struct TtlMap<K, V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> TtlMap<K, V> {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        TtlMap {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+
+        match entries.get(key) {
+            Some((inserted, value)) if inserted.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let ttl = self.ttl;
+
+            entries.retain(|_, (inserted, _)| inserted.elapsed() < ttl);
+
+            // Still full, so make room by evicting the oldest entry
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (inserted, _))| *inserted)
+                    .map(|(key, _)| key.clone());
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+struct Caches {
+    demonlist: TtlMap<u64, Vec<Demon>>,
+    demons: TtlMap<(i16, u64), FullDemon>,
+    rankings: TtlMap<(RankingPagination, u64), (Vec<RankedPlayer>, i64)>,
+    pages: TtlMap<(String, u64), RenderedPage>,
+
+    /// Incremented every time the cache is invalidated
+    version: AtomicU64,
+}
+
+#[derive(Clone)]
+pub struct ListCache(Arc<Caches>);
+
+impl ListCache {
+    pub fn new() -> Self {
+        let ttl = Duration::from_secs(config::list_cache_ttl());
+        let capacity = config::list_cache_capacity();
+
+        ListCache(Arc::new(Caches {
+            demonlist: TtlMap::new(ttl, capacity),
+            demons: TtlMap::new(ttl, capacity),
+            rankings: TtlMap::new(ttl, capacity),
+            pages: TtlMap::new(ttl, capacity),
+            version: AtomicU64::new(0),
+        }))
+    }
+
+    /// Drops all cached data
+    pub fn invalidate(&self) {
+        debug!("Invalidating list cache");
+
+        self.0.version.fetch_add(1, Ordering::SeqCst);
+        self.0.demonlist.clear();
+        self.0.demons.clear();
+        self.0.rankings.clear();
+        self.0.pages.clear();
+    }
+
+    /// Invalidates this cache every time a [`ListEvent`] changing the list is published on the
+    /// given bus
+    pub fn invalidate_on(&self, events: &ListEvents) {
+        let cache = self.clone();
+        let mut receiver: Receiver<ListEvent> = events.subscribe();
+
+        rocket::tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if !event.changes_list() => (),
+                    // If we lagged behind, we missed some events, so invalidating is exactly the right thing to do
+                    Ok(_) | Err(RecvError::Lagged(_)) => cache.invalidate(),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+
+            info!("List event bus closed, no longer invalidating list cache");
+        });
+    }
+
+    /// The current state of the classic list, see [`current_list`]
+    pub async fn demonlist(&self, connection: &mut PgConnection) -> Result<Vec<Demon>> {
+        let version = self.version();
+
+        if let Some(demonlist) = self.0.demonlist.get(&version) {
+            return Ok(demonlist)
+        }
+
+        let demonlist = current_list(connection).await?;
+
+        // Keyed by the version from before loading, so that data loaded concurrently with an
+        // invalidation is never served afterwards
+        self.0.demonlist.insert(version, demonlist.clone());
+
+        Ok(demonlist)
+    }
+
+    /// The demon at the given position of the classic list, see [`FullDemon::by_position`]
+    pub async fn demon(&self, position: i16, connection: &mut PgConnection) -> Result<FullDemon> {
+        let version = self.version();
+
+        if let Some(demon) = self.0.demons.get(&(position, version)) {
+            return Ok(demon)
+        }
+
+        let demon = FullDemon::by_position(position, connection).await?;
+
+        self.0.demons.insert((position, version), demon.clone());
+
+        Ok(demon)
+    }
+
+    /// A cached page of the stats viewer ranking, together with the maximal ranking index
+    pub fn ranking(&self, pagination: &RankingPagination) -> Option<(Vec<RankedPlayer>, i64)> {
+        self.0.rankings.get(&(pagination.clone(), self.version()))
+    }
+
+    /// Caches the given page of the stats viewer ranking, which was loaded at the given
+    /// [`ListCache::version`]
+    pub fn insert_ranking(&self, pagination: RankingPagination, version: u64, players: Vec<RankedPlayer>, max_index: i64) {
+        self.0.rankings.insert((pagination, version), (players, max_index));
+    }
+
+    /// The current version of the cached data, which changes every time the cache is invalidated
+    ///
+    /// Needs to be retrieved before loading any data a page is rendered from, so that pages
+    /// rendered from data that was invalidated in the meantime are never served (see
+    /// [`ListCache::insert_page`]).
+    pub fn version(&self) -> u64 {
+        self.0.version.load(Ordering::SeqCst)
+    }
+
+    /// A cached rendered page. `key` needs to uniquely identify the page, including everything it
+    /// depends on besides the list itself (e.g. query parameters).
+    pub fn page(&self, key: &str) -> Option<RenderedPage> {
+        self.0.pages.get(&(key.to_string(), self.version()))
+    }
+
+    /// Caches the given rendered page, which was rendered from data loaded at the given
+    /// [`ListCache::version`]
+    pub fn insert_page(&self, key: String, version: u64, page: RenderedPage) {
+        self.0.pages.insert((key, version), page);
+    }
+}
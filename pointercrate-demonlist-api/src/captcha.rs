@@ -0,0 +1,105 @@
+//! Module for verifying CAPTCHA responses of anonymous record submissions
+//!
+//! Verification is only enabled if [`config::require_captcha`] is set. Clients learn the provider
+//! and site key to render the widget with from the `/api/v1/list_information/` endpoint, and pass
+//! the response token generated by the widget in the `X-Captcha-Response` header. Should the
+//! provider itself be unreachable, the submission is rejected, as we cannot tell whether the
+//! submitter is human.
+
+use crate::config;
+use log::{debug, error, warn};
+use pointercrate_demonlist::error::DemonlistError;
+use rocket::{
+    request::{FromRequest, Outcome},
+    Request,
+};
+use serde::Deserialize;
+use std::{convert::Infallible, net::IpAddr};
+
+/// The CAPTCHA response token a request carried in its `X-Captcha-Response` header, if any
+pub struct CaptchaResponse(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CaptchaResponse {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(CaptchaResponse(
+            request.headers().get_one("x-captcha-response").map(ToString::to_string),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct SiteverifyResponse {
+    success: bool,
+
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+pub(crate) fn siteverify_url(provider: &str) -> Option<&'static str> {
+    match provider {
+        "hcaptcha" => Some("https://hcaptcha.com/siteverify"),
+        "recaptcha" => Some("https://www.google.com/recaptcha/api/siteverify"),
+        _ => None,
+    }
+}
+
+/// Verifies the given CAPTCHA response with the configured provider
+pub async fn verify(response: CaptchaResponse, ip: IpAddr) -> Result<(), DemonlistError> {
+    if !config::require_captcha() {
+        return Ok(())
+    }
+
+    // All of these are checked at startup by `config::validate`
+    let (provider, secret, url) = match (config::captcha_provider(), config::captcha_secret()) {
+        (Some(provider), Some(secret)) =>
+            match siteverify_url(&provider) {
+                Some(url) => (provider, secret, url),
+                None => return Err(DemonlistError::CaptchaFailed),
+            },
+        _ => return Err(DemonlistError::CaptchaFailed),
+    };
+
+    let token = match response.0 {
+        Some(token) => token,
+        None => return Err(DemonlistError::CaptchaFailed),
+    };
+
+    // Both providers use the same request and response format
+    let response = reqwest::Client::new()
+        .post(url)
+        .form(&[
+            ("secret", secret.as_str()),
+            ("response", token.as_str()),
+            ("remoteip", &ip.to_string()),
+        ])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    let data = match response {
+        Ok(response) => response.json::<SiteverifyResponse>().await,
+        Err(err) => Err(err),
+    };
+
+    let data = match data {
+        Ok(data) => data,
+        Err(err) => {
+            error!("Failed to verify CAPTCHA response via {}: {}", provider, err);
+
+            return Err(DemonlistError::CaptchaFailed)
+        },
+    };
+
+    if !data.success {
+        warn!("CAPTCHA verification for {} failed: {:?}", ip, data.error_codes);
+
+        return Err(DemonlistError::CaptchaFailed)
+    }
+
+    debug!("CAPTCHA verification for {} succeeded", ip);
+
+    Ok(())
+}
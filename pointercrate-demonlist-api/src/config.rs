@@ -1,7 +1,189 @@
+use pointercrate_core::config::{check, ConfigError};
+
+/// Checks the configuration of pointercrate-demonlist-api, so that malformed values are reported at
+/// startup
+pub fn validate() -> Result<(), ConfigError> {
+    check::<u32>("WEBHOOK_MAX_ATTEMPTS")?;
+    check::<u64>("SHUTDOWN_DEADLINE")?;
+    check::<u64>("GD_REFRESH_INTERVAL")?;
+    check::<u64>("LIST_CONFIG_REFRESH_INTERVAL")?;
+    check::<u64>("DEAD_LINK_CHECK_INTERVAL")?;
+    check::<i64>("DEAD_LINK_CHECK_BATCH_SIZE")?;
+    check::<u64>("LIST_CACHE_TTL")?;
+    check::<usize>("LIST_CACHE_CAPACITY")?;
+    check::<u32>("PAGE_MAX_AGE")?;
+    check::<u64>("DEMON_IMAGE_MAX_SIZE")?;
+    check::<i64>("QUEUE_NOTIFICATION_THRESHOLD")?;
+    check::<bool>("ARCHIVE_VIDEOS")?;
+    check::<u64>("ARCHIVE_INTERVAL")?;
+    check::<i64>("ARCHIVE_BATCH_SIZE")?;
+    check::<bool>("REQUIRE_CAPTCHA")?;
+
+    if require_captcha() {
+        match captcha_provider() {
+            Some(provider) if crate::captcha::siteverify_url(&provider).is_some() => (),
+            Some(provider) =>
+                return Err(ConfigError::Invalid {
+                    key: "CAPTCHA_PROVIDER",
+                    reason: format!("unknown provider '{}', expected 'hcaptcha' or 'recaptcha'", provider),
+                }),
+            None => return Err(ConfigError::Missing { key: "CAPTCHA_PROVIDER" }),
+        }
+
+        if captcha_secret().is_none() {
+            return Err(ConfigError::Missing { key: "CAPTCHA_SECRET" })
+        }
+
+        if captcha_site_key().is_none() {
+            return Err(ConfigError::Missing { key: "CAPTCHA_SITE_KEY" })
+        }
+    }
+
+    Ok(())
+}
+
 pub fn submission_webhook() -> Option<String> {
-    std::env::var("DISCORD_WEBHOOK").ok()
+    pointercrate_core::config::var("DISCORD_WEBHOOK")
+}
+
+/// Webhooks notified about all record events (submissions, approvals and rejections), given as a
+/// comma separated list of URLs
+pub fn record_webhooks() -> Vec<String> {
+    match pointercrate_core::config::var("RECORD_WEBHOOKS") {
+        Some(urls) =>
+            urls.split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn webhook_max_attempts() -> u32 {
+    pointercrate_core::util::from_env_or_default("WEBHOOK_MAX_ATTEMPTS", 5)
+}
+
+/// How long (in seconds) shutting down waits for pending webhook deliveries to finish
+pub fn shutdown_deadline() -> u64 {
+    pointercrate_core::util::from_env_or_default("SHUTDOWN_DEADLINE", 10)
 }
 
 pub fn abstract_api_key() -> Option<String> {
-    std::env::var("ABSTRACT_API_KEY").ok()
+    pointercrate_core::config::var("ABSTRACT_API_KEY")
+}
+
+/// Base URL of a Geometry Dash server mirror to fetch level data from, instead of the official
+/// servers
+pub fn gd_mirror() -> Option<String> {
+    pointercrate_core::config::var("GD_MIRROR")
+}
+
+/// How often (in seconds) the cached level data of all demons is refreshed
+pub fn gd_refresh_interval() -> u64 {
+    pointercrate_core::util::from_env_or_default("GD_REFRESH_INTERVAL", 3600)
+}
+
+/// How often (in seconds) the list sizes are reloaded from the database, so that changes made via
+/// other instances are picked up
+pub fn list_config_refresh_interval() -> u64 {
+    pointercrate_core::util::from_env_or_default("LIST_CONFIG_REFRESH_INTERVAL", 60)
+}
+
+/// API key for the YouTube Data API. If set, submitted YouTube videos are checked for availability
+pub fn youtube_api_key() -> Option<String> {
+    pointercrate_core::config::var("YOUTUBE_API_KEY")
+}
+
+/// How often (in seconds) a batch of record videos is checked for dead links
+pub fn dead_link_check_interval() -> u64 {
+    pointercrate_core::util::from_env_or_default("DEAD_LINK_CHECK_INTERVAL", 600)
+}
+
+/// How many record videos are checked for dead links per batch
+pub fn dead_link_check_batch_size() -> i64 {
+    pointercrate_core::util::from_env_or_default("DEAD_LINK_CHECK_BATCH_SIZE", 50)
+}
+
+/// How long (in seconds) cached list data may be served before it is reloaded from the database
+pub fn list_cache_ttl() -> u64 {
+    pointercrate_core::util::from_env_or_default("LIST_CACHE_TTL", 60)
+}
+
+/// How many entries each part of the list cache (e.g. the rendered pages) may hold at most
+pub fn list_cache_capacity() -> usize {
+    pointercrate_core::util::from_env_or_default("LIST_CACHE_CAPACITY", 1000)
+}
+
+/// How long (in seconds) browsers and shared caches may serve the list pages without revalidating
+/// them. Defaults to 0, meaning they have to revalidate on every request.
+pub fn page_max_age() -> u32 {
+    pointercrate_core::util::from_env_or_default("PAGE_MAX_AGE", 0)
+}
+
+/// Whether anonymous record submissions need to pass a CAPTCHA. Disabled by default. If enabled,
+/// [`captcha_provider`], [`captcha_secret`] and [`captcha_site_key`] need to be configured as well.
+pub fn require_captcha() -> bool {
+    pointercrate_core::util::from_env_or_default("REQUIRE_CAPTCHA", false)
+}
+
+/// The CAPTCHA provider anonymous record submissions are verified with. Either `hcaptcha` or
+/// `recaptcha`
+pub fn captcha_provider() -> Option<String> {
+    pointercrate_core::config::var("CAPTCHA_PROVIDER")
+}
+
+/// The secret key for the configured [`captcha_provider`]
+pub fn captcha_secret() -> Option<String> {
+    pointercrate_core::config::var("CAPTCHA_SECRET")
+}
+
+/// The public site key for the configured [`captcha_provider`], which clients need to render the
+/// provider's widget
+pub fn captcha_site_key() -> Option<String> {
+    pointercrate_core::config::var("CAPTCHA_SITE_KEY")
+}
+
+/// Directory uploaded demon thumbnails are stored in. If unset, thumbnails cannot be uploaded (but
+/// can still be set as URLs)
+pub fn demon_image_directory() -> Option<String> {
+    pointercrate_core::config::var("DEMON_IMAGE_DIRECTORY")
+}
+
+/// Public URL under which the contents of [`demon_image_directory`] are served
+pub fn demon_image_url() -> String {
+    pointercrate_core::config::var("DEMON_IMAGE_URL").unwrap_or_else(|| "/static/demons".to_string())
+}
+
+/// Maximal size (in bytes) of uploaded demon thumbnails
+pub fn demon_image_max_size() -> u64 {
+    pointercrate_core::util::from_env_or_default("DEMON_IMAGE_MAX_SIZE", 1024 * 1024)
+}
+
+/// The URL under which this site is publicly reachable, without trailing slash. Used for generating
+/// absolute links, e.g. in the Atom feed of list changes
+pub fn site_url() -> String {
+    pointercrate_core::util::from_env_or_default("SITE_URL", "https://pointercrate.com".to_string())
+}
+
+/// List moderators who opted in are notified if more than this many submissions are waiting for
+/// review when the daily notification digest is sent
+pub fn queue_notification_threshold() -> i64 {
+    pointercrate_core::util::from_env_or_default("QUEUE_NOTIFICATION_THRESHOLD", 100)
+}
+
+/// Whether the videos of approved records are submitted to the Wayback Machine. Disabled by default
+pub fn archive_videos() -> bool {
+    pointercrate_core::util::from_env_or_default("ARCHIVE_VIDEOS", false)
+}
+
+/// How often (in seconds) a batch of record videos is submitted to the Wayback Machine
+pub fn archive_interval() -> u64 {
+    pointercrate_core::util::from_env_or_default("ARCHIVE_INTERVAL", 3600)
+}
+
+/// How many record videos are submitted to the Wayback Machine per batch. Kept small by default,
+/// since the Wayback Machine rate limits anonymous submissions
+pub fn archive_batch_size() -> i64 {
+    pointercrate_core::util::from_env_or_default("ARCHIVE_BATCH_SIZE", 10)
 }
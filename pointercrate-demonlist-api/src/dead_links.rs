@@ -0,0 +1,101 @@
+//! Module for periodically checking whether the video proof of approved records is still reachable
+//!
+//! Every few minutes, a batch of the least recently checked videos is requested. Videos that
+//! respond with `404 NOT FOUND` or `410 GONE` are flagged as dead, and list moderators are notified
+//! via the record webhooks (by publishing a [`ListEvent::VideoDead`]) the first time a video is
+//! found to be dead.
+//! Network errors, server errors and authorization errors (which region or age restricted videos
+//! respond with) are inconclusive and leave the status unchanged.
+
+use crate::{
+    config,
+    events::{ListEvent, ListEvents},
+};
+use log::{debug, error, info, warn};
+use pointercrate_core::pool::audit_connection;
+use pointercrate_demonlist::{
+    error::Result,
+    record::{FullRecord, VideoStatus},
+};
+use reqwest::{Client, StatusCode, Url};
+use rocket::tokio;
+use sqlx::{Pool, Postgres};
+use std::{sync::Arc, time::Duration};
+
+pub fn spawn(pool: Pool<Postgres>, events: ListEvents) {
+    tokio::spawn(async move {
+        let client = Client::new();
+        let interval = Duration::from_secs(config::dead_link_check_interval());
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            if let Err(err) = check_batch(&client, &pool, &events).await {
+                error!("INTERNAL SERVER ERROR: Failure to check batch of record videos: {:?}", err);
+            }
+        }
+    });
+}
+
+async fn check_batch(client: &Client, pool: &Pool<Postgres>, events: &ListEvents) -> Result<()> {
+    let mut connection = pool.acquire().await?;
+
+    audit_connection(&mut connection, 0).await?;
+
+    let batch = FullRecord::videos_to_check(config::dead_link_check_batch_size(), &mut connection).await?;
+
+    info!("Checking {} record videos for dead links", batch.len());
+
+    for check in batch {
+        let status = match probe(client, &check.video).await {
+            Some(status) => status,
+            None => check.status,
+        };
+
+        FullRecord::set_video_status(check.record_id, status, &mut connection).await?;
+
+        if status == VideoStatus::Dead && check.status != VideoStatus::Dead {
+            let record = FullRecord::by_id(check.record_id, &mut connection).await?;
+
+            events.publish(ListEvent::VideoDead { record: Arc::new(record) });
+        }
+    }
+
+    Ok(())
+}
+
+/// Requests the given video, returning `None` if no conclusion about its status can be drawn
+async fn probe(client: &Client, video: &str) -> Option<VideoStatus> {
+    // YouTube serves a regular page for deleted and private videos, but its oembed endpoint properly
+    // responds with an error status
+    let url = if video.starts_with("https://www.youtube.com/") {
+        match Url::parse_with_params("https://www.youtube.com/oembed", &[("url", video), ("format", "json")]) {
+            Ok(url) => url.to_string(),
+            Err(err) => {
+                warn!("Failed to build oembed URL for video {}: {:?}", video, err);
+
+                return None
+            },
+        }
+    } else {
+        video.to_string()
+    };
+
+    match client.head(&url).send().await {
+        Ok(response) =>
+            match response.status() {
+                status if status.is_success() || status.is_redirection() => Some(VideoStatus::Alive),
+                StatusCode::NOT_FOUND | StatusCode::GONE => Some(VideoStatus::Dead),
+                status => {
+                    debug!("Inconclusive response {} when checking video {}", status, video);
+
+                    None
+                },
+            },
+        Err(err) => {
+            warn!("Failed to check video {}: {:?}", video, err);
+
+            None
+        },
+    }
+}
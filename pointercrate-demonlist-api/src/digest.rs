@@ -0,0 +1,168 @@
+//! Module for the daily notification digest
+//!
+//! Once a day, users who opted in (see [`NotificationPreferences`]) receive an email listing the
+//! records of their verified claimed player that were approved or rejected since the last digest.
+//! List moderators who opted in are additionally notified if more than
+//! [`config::queue_notification_threshold`] submissions are waiting for review.
+//!
+//! The time of every digest is stored in the database, so that restarts neither skip nor repeat
+//! any decisions, and so that multiple instances of pointercrate do not send the same digest twice.
+//!
+//! [`NotificationPreferences`]: pointercrate_user::NotificationPreferences
+
+use crate::config;
+use chrono::{Duration, NaiveDateTime, Utc};
+use log::{error, info};
+use pointercrate_demonlist::{error::Result, LIST_ADMINISTRATOR, LIST_MODERATOR};
+use pointercrate_user_api::mail::{Mail, Mailer};
+use rocket::tokio;
+use sqlx::{Pool, Postgres};
+use std::collections::BTreeMap;
+
+/// How often (in seconds) to check whether the next digest is due
+const CHECK_INTERVAL: u64 = 3600;
+
+pub fn spawn(pool: Pool<Postgres>, mailer: Mailer) {
+    tokio::spawn(async move {
+        loop {
+            match compose_if_due(&pool).await {
+                Ok(mails) =>
+                    for mail in mails {
+                        mailer.send(mail)
+                    },
+                Err(err) => error!("INTERNAL SERVER ERROR: Failed to compose notification digest: {:?}", err),
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(CHECK_INTERVAL)).await;
+        }
+    });
+}
+
+/// A record decision to tell the owner of the record's player about
+struct Decision {
+    email: String,
+    user_name: String,
+    demon: String,
+    progress: i16,
+    status: String,
+}
+
+/// Composes the digest mails if the last digest was sent more than a day ago, and marks the digest
+/// as sent
+async fn compose_if_due(pool: &Pool<Postgres>) -> Result<Vec<Mail>> {
+    let mut transaction = pool.begin().await?;
+
+    // Make concurrently running instances wait until we decided whether to send a digest
+    sqlx::query!("LOCK TABLE notification_digests IN EXCLUSIVE MODE")
+        .execute(&mut transaction)
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let last_sent: Option<NaiveDateTime> = sqlx::query!("SELECT MAX(sent) AS last_sent FROM notification_digests")
+        .fetch_one(&mut transaction)
+        .await?
+        .last_sent;
+
+    if let Some(last_sent) = last_sent {
+        if now - last_sent < Duration::days(1) {
+            return Ok(Vec::new())
+        }
+    }
+
+    // The first digest ever sent covers the last day
+    let since = last_sent.unwrap_or_else(|| now - Duration::days(1));
+
+    let decisions = sqlx::query_as!(
+        Decision,
+        r#"SELECT members.email::TEXT AS "email!", COALESCE(members.display_name, members.name)::TEXT AS "user_name!", demons.name::TEXT AS
+         "demon!", records.progress, records.status_::TEXT AS "status!" FROM notification_preferences INNER JOIN members ON
+         members.member_id = notification_preferences.member INNER JOIN player_claims ON player_claims.member_id = members.member_id AND
+         player_claims.verified INNER JOIN records ON records.player = player_claims.player_id INNER JOIN demons ON demons.id = records.demon
+         WHERE notification_preferences.record_decisions AND members.email_verified AND records.status_ IN ('APPROVED', 'REJECTED') AND
+         EXISTS (SELECT 1 FROM record_modifications WHERE record_modifications.id = records.id AND record_modifications.status_ IN
+         ('SUBMITTED', 'UNDER_CONSIDERATION') AND record_modifications.time > $1) ORDER BY members.member_id, records.id"#,
+        since
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+
+    let mut mails = decision_mails(decisions);
+
+    let queue_size = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM records WHERE status_ = 'SUBMITTED'"#)
+        .fetch_one(&mut transaction)
+        .await?
+        .count;
+
+    if queue_size > config::queue_notification_threshold() {
+        let moderators = sqlx::query!(
+            r#"SELECT members.email::TEXT AS "email!", COALESCE(members.display_name, members.name)::TEXT AS "user_name!" FROM
+             notification_preferences INNER JOIN members ON members.member_id = notification_preferences.member WHERE
+             notification_preferences.queue_size AND members.email_verified AND members.permissions & CAST($1::INTEGER AS BIT(16)) <>
+             CAST(0 AS BIT(16))"#,
+            (LIST_MODERATOR.bit() | LIST_ADMINISTRATOR.bit()) as i32
+        )
+        .fetch_all(&mut transaction)
+        .await?;
+
+        for moderator in moderators {
+            mails.push(queue_mail(moderator.email, &moderator.user_name, queue_size))
+        }
+    }
+
+    sqlx::query!("INSERT INTO notification_digests (sent) VALUES ($1)", now)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    info!("Composed notification digest ({} mails)", mails.len());
+
+    Ok(mails)
+}
+
+/// Groups the given decisions by recipient, producing one mail per recipient
+fn decision_mails(decisions: Vec<Decision>) -> Vec<Mail> {
+    let mut by_recipient: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+
+    for decision in decisions {
+        let lines = &mut by_recipient
+            .entry(decision.email)
+            .or_insert_with(|| (decision.user_name, Vec::new()))
+            .1;
+
+        lines.push(format!(
+            "* {}% on {}: {}",
+            decision.progress,
+            decision.demon,
+            decision.status.to_lowercase()
+        ));
+    }
+
+    by_recipient
+        .into_iter()
+        .map(|(email, (user_name, lines))| {
+            Mail {
+                to: email,
+                subject: "Your records have been reviewed".to_string(),
+                body: format!(
+                    "Hello {},\n\nthe following records of yours have been reviewed since the last digest:\n\n{}\n\nYou are receiving \
+                     this email because you enabled notifications about record decisions. You can disable them in your account settings.",
+                    user_name,
+                    lines.join("\n")
+                ),
+            }
+        })
+        .collect()
+}
+
+fn queue_mail(to: String, user_name: &str, queue_size: i64) -> Mail {
+    Mail {
+        to,
+        subject: "Many records are waiting for review".to_string(),
+        body: format!(
+            "Hello {},\n\nthere are currently {} submitted records waiting for review.\n\nYou are receiving this email because you \
+             enabled notifications about the size of the submission queue. You can disable them in your account settings.",
+            user_name, queue_size
+        ),
+    }
+}
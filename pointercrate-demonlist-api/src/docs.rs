@@ -0,0 +1,187 @@
+//! Schemas of the models of the demonlist API, for the OpenAPI document (see
+//! [`pointercrate_core_api::docs`])
+
+use serde_json::{json, Value};
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+pub(crate) fn schemas() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "RecordStatus",
+            json!({"type": "string", "enum": ["approved", "submitted", "rejected", "under consideration"]}),
+        ),
+        (
+            "DatabasePlayer",
+            json!({
+                "type": "object",
+                "required": ["id", "name", "banned"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "banned": {"type": "boolean"}
+                }
+            }),
+        ),
+        (
+            "Nationality",
+            json!({
+                "type": "object",
+                "required": ["country_code", "nation", "subdivision"],
+                "properties": {
+                    "country_code": {"type": "string"},
+                    "nation": {"type": "string"},
+                    "subdivision": {
+                        "type": "object",
+                        "nullable": true,
+                        "required": ["iso_code", "name"],
+                        "properties": {
+                            "iso_code": {"type": "string"},
+                            "name": {"type": "string"}
+                        }
+                    }
+                }
+            }),
+        ),
+        (
+            "MinimalDemon",
+            json!({
+                "type": "object",
+                "required": ["id", "position", "name"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "position": {"type": "integer"},
+                    "name": {"type": "string"}
+                }
+            }),
+        ),
+        (
+            "Demon",
+            json!({
+                "allOf": [reference("MinimalDemon"), {
+                    "type": "object",
+                    "required": ["requirement", "requires_timestamp", "video", "publisher", "verifier", "level_id", "thumbnail", "victors", "first_victor"],
+                    "properties": {
+                        "requirement": {"type": "integer"},
+                        "requires_timestamp": {"type": "boolean"},
+                        "video": {"type": "string", "format": "uri", "nullable": true},
+                        "publisher": reference("DatabasePlayer"),
+                        "verifier": reference("DatabasePlayer"),
+                        "level_id": {"type": "integer", "nullable": true},
+                        "thumbnail": {"type": "string", "format": "uri", "nullable": true},
+                        "victors": {"type": "integer"},
+                        "first_victor": {"allOf": [reference("DatabasePlayer")], "nullable": true},
+                        "pending": {"type": "integer", "description": "Only included for members of the list team"}
+                    }
+                }]
+            }),
+        ),
+        (
+            "FullDemon",
+            json!({
+                "allOf": [reference("Demon"), {
+                    "type": "object",
+                    "required": ["creators", "records"],
+                    "properties": {
+                        "creators": {"type": "array", "items": reference("DatabasePlayer")},
+                        "records": {"type": "array", "items": reference("MinimalRecordP")}
+                    }
+                }]
+            }),
+        ),
+        (
+            "MinimalRecordP",
+            json!({
+                "type": "object",
+                "required": ["id", "progress", "video", "status", "player", "nationality"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "progress": {"type": "integer", "minimum": 0, "maximum": 100},
+                    "video": {"type": "string", "format": "uri", "nullable": true},
+                    "status": reference("RecordStatus"),
+                    "player": reference("DatabasePlayer"),
+                    "nationality": {"allOf": [reference("Nationality")], "nullable": true}
+                }
+            }),
+        ),
+        (
+            "Note",
+            json!({
+                "type": "object",
+                "required": ["id", "content", "transferred", "author", "editors"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "content": {"type": "string"},
+                    "transferred": {"type": "boolean"},
+                    "author": {"type": "string", "nullable": true},
+                    "editors": {"type": "array", "items": {"type": "string"}}
+                }
+            }),
+        ),
+        (
+            "Submitter",
+            json!({
+                "type": "object",
+                "required": ["id", "banned", "flagged"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "banned": {"type": "boolean"},
+                    "flagged": {"type": "boolean"}
+                }
+            }),
+        ),
+        (
+            "FullRecord",
+            json!({
+                "type": "object",
+                "required": ["id", "progress", "video", "status", "video_status", "archive_url", "player", "demon", "submitter", "notes"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "progress": {"type": "integer", "minimum": 0, "maximum": 100},
+                    "video": {"type": "string", "format": "uri", "nullable": true},
+                    "status": reference("RecordStatus"),
+                    "video_status": {"type": "string", "enum": ["unchecked", "alive", "dead"]},
+                    "archive_url": {"type": "string", "format": "uri", "nullable": true},
+                    "raw_footage": {"type": "string", "format": "uri", "description": "Only included for list moderators"},
+                    "player": reference("DatabasePlayer"),
+                    "demon": reference("MinimalDemon"),
+                    "submitter": {"allOf": [reference("Submitter")], "nullable": true},
+                    "notes": {"type": "array", "items": reference("Note")}
+                }
+            }),
+        ),
+        (
+            "Submission",
+            json!({
+                "type": "object",
+                "required": ["progress", "player", "demon"],
+                "properties": {
+                    "progress": {"type": "integer", "minimum": 0, "maximum": 100},
+                    "player": {"type": "string"},
+                    "demon": {"type": "integer"},
+                    "video": {"type": "string", "format": "uri", "nullable": true},
+                    "status": reference("RecordStatus"),
+                    "note": {"type": "string", "nullable": true},
+                    "raw_footage": {"type": "string", "format": "uri", "nullable": true},
+                    "create_player": {"type": "boolean", "default": false}
+                }
+            }),
+        ),
+        (
+            "PatchRecord",
+            json!({
+                "type": "object",
+                "properties": {
+                    "progress": {"type": "integer", "minimum": 0, "maximum": 100},
+                    "video": {"type": "string", "format": "uri", "nullable": true},
+                    "status": reference("RecordStatus"),
+                    "player": {"type": "string"},
+                    "demon": {"type": "string"},
+                    "demon_id": {"type": "integer"}
+                }
+            }),
+        ),
+    ]
+}
@@ -0,0 +1,49 @@
+//! Module for describing records to sites that unfurl links (e.g. Discord) via oEmbed
+//! (<https://oembed.com/>)
+//!
+//! Record pages advertise the oEmbed endpoint using a `<link rel="alternate">` tag, so consumers
+//! find it from just the link to the page.
+
+use crate::config;
+use pointercrate_demonlist::record::FullRecord;
+use serde::Serialize;
+
+/// Dimensions of YouTube's `mqdefault` preview images (see
+/// [`pointercrate_demonlist::video::thumbnail`])
+const THUMBNAIL_WIDTH: u32 = 320;
+const THUMBNAIL_HEIGHT: u32 = 180;
+
+#[derive(Debug, Serialize)]
+pub struct OEmbed {
+    version: &'static str,
+    r#type: &'static str,
+    title: String,
+    author_name: String,
+    provider_name: &'static str,
+    provider_url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail_height: Option<u32>,
+}
+
+impl OEmbed {
+    pub fn of(record: &FullRecord) -> Self {
+        let thumbnail_url = record.video.as_deref().and_then(pointercrate_demonlist::video::thumbnail);
+
+        OEmbed {
+            version: "1.0",
+            r#type: "link",
+            title: format!("{} - {}% on {}", record.player.name, record.progress, record.demon.name),
+            author_name: record.player.name.clone(),
+            provider_name: "pointercrate",
+            provider_url: config::site_url(),
+            thumbnail_width: thumbnail_url.as_ref().map(|_| THUMBNAIL_WIDTH),
+            thumbnail_height: thumbnail_url.as_ref().map(|_| THUMBNAIL_HEIGHT),
+            thumbnail_url,
+        }
+    }
+}
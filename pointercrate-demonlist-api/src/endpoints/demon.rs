@@ -1,41 +1,89 @@
-use pointercrate_core::{audit::AuditLogEntry, pool::PointercratePool};
+use crate::{
+    cache::ListCache,
+    config,
+    endpoints::{record, PATCH_ATTEMPTS},
+    events::{ListEvent, ListEvents},
+    feed,
+    images::ImageStorage,
+    youtube,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use pointercrate_core::{audit::AuditLogEntry, error::CoreError, etag::Taggable, pool::PointercratePool};
 use pointercrate_core_api::{
     error::Result,
-    etag::{Precondition, TaggableExt, Tagged},
+    etag::{Dated, Precondition, TaggableExt, Tagged},
     pagination_response,
-    query::Query,
-    response::Response2,
+    query::{CountRequested, Query, ResponseFormat},
+    response::{Response2, Tabular},
 };
 use pointercrate_demonlist::{
-    creator::{Creator, PostCreator},
-    demon::{audit::DemonModificationData, Demon, DemonIdPagination, DemonPositionPagination, FullDemon, PatchDemon, PostDemon},
+    alias::{Alias, PostAlias},
+    creator::{creators_of, Creator, PostCreator},
+    demon::{
+        audit::{recent_list_changes, DemonModificationData},
+        beginning_of_time,
+        image::ImageFormat,
+        legacy, list_at, Demon, DemonIdPagination, DemonPositionPagination, DemonsChangedSince, FullDemon, LegacyDemon, LegacyPagination,
+        MinimalDemon, ModifiedDemon, PatchDemon, PostDemon, TimeShiftedDemon,
+    },
     error::DemonlistError,
+    list::CLASSIC_LIST,
     player::DatabasePlayer,
-    LIST_ADMINISTRATOR, LIST_MODERATOR,
+    record::{DemonRecordStatistics, MinimalRecordP, MinimalRecordPD, RecordNeighbors, RecordPagination},
+    video, LIST_ADMINISTRATOR, LIST_HELPER, LIST_MODERATOR,
 };
+use pointercrate_integrate::gd::{LevelMetadata, PgCache};
+use pointercrate_user::error::UserError;
 use pointercrate_user_api::auth::TokenAuth;
-use rocket::{http::Status, serde::json::Json, State};
+use rocket::{
+    data::{Data, ToByteUnit},
+    http::{ContentType, Status},
+    serde::json::Json,
+    State,
+};
+use serde::Serialize;
 
 #[rocket::get("/")]
-pub async fn paginate(pool: &State<PointercratePool>, pagination: Query<DemonIdPagination>) -> Result<Response2<Json<Vec<Demon>>>> {
+pub async fn paginate(
+    auth: Option<TokenAuth>, pool: &State<PointercratePool>, pagination: Query<DemonIdPagination>, count: CountRequested,
+) -> Result<Response2<Json<Vec<Demon>>>> {
     let mut pagination = pagination.0;
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
+
+    pagination.include_pending = auth.map(|auth| auth.has_permission(LIST_HELPER)).unwrap_or(false);
 
     let mut demons = pagination.page(&mut connection).await?;
+    let total = if count.0 {
+        Some(pagination.count(&mut connection).await?)
+    } else {
+        None
+    };
     let (max_id, min_id) = Demon::extremal_demon_ids(&mut connection).await?;
 
-    pagination_response!("/api/v2/demons/", demons, pagination, min_id, max_id, before_id, after_id, base.id)
+    pagination_response!(
+        total = total,
+        "/api/v2/demons/",
+        demons,
+        pagination,
+        min_id,
+        max_id,
+        before_id,
+        after_id,
+        base.id
+    )
 }
 
 #[rocket::get("/listed")]
 pub async fn paginate_listed(
-    pool: &State<PointercratePool>, pagination: Query<DemonPositionPagination>,
-) -> Result<Response2<Json<Vec<Demon>>>> {
+    auth: Option<TokenAuth>, pool: &State<PointercratePool>, pagination: Query<DemonPositionPagination>, format: ResponseFormat,
+) -> Result<Response2<Tabular<Vec<Demon>>>> {
     let mut pagination = pagination.0;
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
+
+    pagination.include_pending = auth.map(|auth| auth.has_permission(LIST_HELPER)).unwrap_or(false);
 
     let mut demons = pagination.page(&mut connection).await?;
-    let max_position = Demon::max_position(&mut connection).await?;
+    let max_position = Demon::max_position(CLASSIC_LIST, &mut connection).await?;
 
     pagination_response!(
         "/api/v2/demons/listed/",
@@ -47,11 +95,147 @@ pub async fn paginate_listed(
         after_position,
         base.position
     )
+    .map(|response| response.format(format))
+}
+
+/// Reconstructs the list as it was at the given point in time (an RFC 3339 timestamp)
+///
+/// Requesting a point in time before the list existed results in an empty list, while points in the
+/// future are treated as "now".
+#[rocket::get("/listed?<at>")]
+pub async fn listed_at(pool: &State<PointercratePool>, at: &str) -> Result<Json<Vec<TimeShiftedDemon>>> {
+    let at = DateTime::<FixedOffset>::parse_from_rfc3339(at).map_err(|_| CoreError::UnprocessableEntity)?;
+
+    if at < beginning_of_time() {
+        return Ok(Json(Vec::new()))
+    }
+
+    let now = Utc::now();
+    let at = if at > now { now.into() } else { at };
+
+    Ok(Json(list_at(&mut *pool.read_connection().await?, at).await?))
+}
+
+/// Atom feed of the most recent additions to and movements on the list
+#[rocket::get("/feed.xml")]
+pub async fn feed(pool: &State<PointercratePool>) -> Result<(ContentType, String)> {
+    let changes = recent_list_changes(feed::FEED_SIZE, &mut *pool.read_connection().await?).await?;
+
+    Ok((ContentType::new("application", "atom+xml"), feed::render(&changes)))
+}
+
+#[rocket::get("/legacy")]
+pub async fn paginate_legacy(
+    pool: &State<PointercratePool>, pagination: Query<LegacyPagination>,
+) -> Result<Response2<Json<Vec<LegacyDemon>>>> {
+    let mut pagination = pagination.0;
+    let mut connection = pool.read_connection().await?;
+
+    let mut demons = pagination.page(&mut connection).await?;
+    let (max_legacy_position, min_legacy_position) = legacy::extremal_legacy_positions(&mut connection).await?;
+
+    pagination_response!(
+        "/api/v2/demons/legacy/",
+        demons,
+        pagination,
+        min_legacy_position,
+        max_legacy_position,
+        before_legacy_position,
+        after_legacy_position,
+        legacy_position
+    )
+}
+
+#[rocket::get("/changed")]
+pub async fn changed_since(pool: &State<PointercratePool>, query: Query<DemonsChangedSince>) -> Result<Json<Vec<ModifiedDemon>>> {
+    let mut connection = pool.read_connection().await?;
+
+    Ok(Json(
+        pointercrate_demonlist::demon::changed_since(query.0.since, &mut connection).await?,
+    ))
+}
+
+/// A [`FullDemon`] together with the metadata of its level cached from the Geometry Dash servers
+#[derive(Debug, Serialize, Hash)]
+pub struct DemonWithLevel {
+    #[serde(flatten)]
+    demon: FullDemon,
+
+    /// `None` if the level has not (yet) been found on the Geometry Dash servers
+    level: Option<LevelMetadata>,
+}
+
+impl Taggable for DemonWithLevel {
+    fn patch_part(&self) -> u64 {
+        self.demon.patch_part()
+    }
 }
 
 #[rocket::get("/<demon_id>")]
-pub async fn get(demon_id: i32, pool: &State<PointercratePool>) -> Result<Tagged<FullDemon>> {
-    Ok(Tagged(FullDemon::by_id(demon_id, &mut *pool.connection().await?).await?))
+pub async fn get(demon_id: i32, pool: &State<PointercratePool>, gd: &State<PgCache>) -> Result<Dated<Tagged<DemonWithLevel>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let demon = FullDemon::by_id(demon_id, &mut connection).await?;
+    let last_modified = Demon::last_modified(demon_id, &mut connection).await?;
+
+    let level = gd
+        .data_for_demon(
+            reqwest::Client::new(),
+            demon.demon.level_id,
+            demon.demon.base.name.clone(),
+            demon.demon.base.id,
+        )
+        .await
+        .ok()
+        .and_then(|result| result.metadata());
+
+    Ok(Dated(Tagged(DemonWithLevel { demon, level }), last_modified))
+}
+
+#[rocket::get("/<demon_id>/records")]
+pub async fn paginate_records(
+    demon_id: i32, auth: Option<TokenAuth>, pool: &State<PointercratePool>, pagination: Query<RecordPagination>, count: CountRequested,
+) -> Result<Response2<Json<Vec<MinimalRecordPD>>>> {
+    let mut pagination = pagination.0;
+
+    // Make sure we return a 404 for unknown demons instead of an empty page
+    MinimalDemon::by_id(demon_id, &mut *pool.read_connection().await?).await?;
+
+    pagination.demon_id = Some(demon_id);
+
+    record::scoped_pagination(&format!("/api/v2/demons/{}/records/", demon_id), pagination, count, auth, pool).await
+}
+
+#[rocket::get("/<demon_id>/records/neighbors?<progress>")]
+pub async fn record_neighbors(demon_id: i32, progress: i16, pool: &State<PointercratePool>) -> Result<Tagged<RecordNeighbors>> {
+    let mut connection = pool.read_connection().await?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut connection).await?;
+
+    Ok(Tagged(
+        pointercrate_demonlist::record::record_neighbors(&demon, progress, &mut connection).await?,
+    ))
+}
+
+#[rocket::get("/<demon_id>/first_victor")]
+pub async fn first_victor(demon_id: i32, pool: &State<PointercratePool>) -> Result<Tagged<Option<MinimalRecordP>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut connection).await?;
+
+    Ok(Tagged(pointercrate_demonlist::record::first_victor(&demon, &mut connection).await?))
+}
+
+/// Aggregate statistics about the records on the given demon. Statistics about records that were
+/// not approved are only included for members of the list team.
+#[rocket::get("/<demon_id>/stats")]
+pub async fn stats(demon_id: i32, auth: Option<TokenAuth>, pool: &State<PointercratePool>) -> Result<Json<DemonRecordStatistics>> {
+    let include_unapproved = auth.map(|auth| auth.has_permission(LIST_HELPER)).unwrap_or(false);
+    let mut connection = pool.read_connection().await?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut connection).await?;
+
+    Ok(Json(DemonRecordStatistics::of(&demon, include_unapproved, &mut connection).await?))
 }
 
 #[rocket::get("/<demon_id>/audit")]
@@ -67,14 +251,28 @@ pub async fn audit(demon_id: i32, mut auth: TokenAuth) -> Result<Json<Vec<AuditL
     Ok(Json(log))
 }
 
+/// Validates a verification video the same way videos of submitted records are validated, including
+/// checking whether it is actually available
+pub(crate) async fn check_video(video: &str) -> std::result::Result<(), DemonlistError> {
+    youtube::check_availability(&video::validate(video)?).await
+}
+
 #[rocket::post("/", data = "<data>")]
-pub async fn post(mut auth: TokenAuth, data: Json<PostDemon>) -> Result<Response2<Tagged<FullDemon>>> {
+pub async fn post(mut auth: TokenAuth, data: Json<PostDemon>, events: &State<ListEvents>) -> Result<Response2<Tagged<FullDemon>>> {
     auth.require_permission(LIST_MODERATOR)?;
 
+    if let Some(video) = data.video() {
+        check_video(video).await?;
+    }
+
     let demon = FullDemon::create_from(data.0, &mut auth.connection).await?;
 
     auth.commit().await?;
 
+    events.publish(ListEvent::DemonAdded {
+        demon: demon.demon.base.clone(),
+    });
+
     let demon_id = demon.demon.base.id;
 
     Ok(Response2::tagged(demon)
@@ -82,23 +280,158 @@ pub async fn post(mut auth: TokenAuth, data: Json<PostDemon>) -> Result<Response
         .with_header("Location", format!("/api/v2/demons/{}/", demon_id)))
 }
 
-#[rocket::patch("/<demon_id>", data = "<patch>")]
-pub async fn patch(demon_id: i32, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchDemon>) -> Result<Tagged<FullDemon>> {
+/// Patches the given demon
+///
+/// If the requirement is changed and `purge_below_requirement` is set, approved records below the
+/// new requirement are rejected (instead of deleted). Their number is reported in the
+/// `X-Records-Rejected` header.
+#[rocket::patch("/<demon_id>?<purge_below_requirement>", data = "<patch>")]
+pub async fn patch(
+    demon_id: i32, purge_below_requirement: Option<bool>, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchDemon>,
+    pool: &State<PointercratePool>, events: &State<ListEvents>, cache: &State<ListCache>,
+) -> Result<Response2<Tagged<FullDemon>>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    if let Some(Some(ref video)) = patch.video {
+        check_video(video).await?;
+    }
+
+    let purge_below_requirement = purge_below_requirement.unwrap_or(false);
+    let mut attempt = 1;
+
+    let (demon, old_position, rejected) = loop {
+        // Since the precondition is checked again, a retry fails if the demon itself was modified
+        // concurrently
+        let result = async {
+            let demon = FullDemon::by_id(demon_id, &mut auth.connection)
+                .await?
+                .require_match_at(precondition.clone(), Demon::last_modified(demon_id, &mut auth.connection).await?)?;
+            let old_position = demon.demon.base.position;
+
+            let rejected = match patch.requirement {
+                Some(requirement) if purge_below_requirement => demon.demon.reject_records_below(requirement, &mut auth.connection).await?,
+                _ => 0,
+            };
+
+            let demon = demon.apply_patch(patch.0.clone(), &mut auth.connection).await?;
+
+            Ok::<_, DemonlistError>((demon, old_position, rejected))
+        }
+        .await;
+
+        // The commit is part of the retried operation, as that is where serialization failures
+        // usually surface
+        match result {
+            Ok(result) =>
+                match auth.commit_in_place(pool).await {
+                    Ok(()) => break result,
+                    Err(UserError::Core(CoreError::ConcurrentModification)) if attempt < PATCH_ATTEMPTS => (),
+                    Err(err) => return Err(err.into()),
+                },
+            Err(DemonlistError::Core(CoreError::ConcurrentModification)) if attempt < PATCH_ATTEMPTS => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        attempt += 1;
+
+        auth.restart_transaction(pool).await?;
+    };
+
+    cache.invalidate();
+
+    if demon.demon.base.position != old_position {
+        events.publish(ListEvent::DemonMoved {
+            demon: demon.demon.base.clone(),
+            from: old_position,
+        });
+    }
+
+    let response = Response2::tagged(demon);
+
+    if purge_below_requirement {
+        return Ok(response.with_header("X-Records-Rejected", rejected.to_string()))
+    }
+
+    Ok(response)
+}
+
+/// Uploads a new thumbnail for the given demon. Only mounted if image storage is configured.
+///
+/// The request body is the raw image data
+#[rocket::put("/<demon_id>/image", data = "<image>")]
+pub async fn put_image(
+    demon_id: i32, mut auth: TokenAuth, precondition: Precondition, image: Data<'_>, storage: &State<Box<dyn ImageStorage>>,
+    cache: &State<ListCache>,
+) -> Result<Tagged<FullDemon>> {
     auth.require_permission(LIST_MODERATOR)?;
 
     let demon = FullDemon::by_id(demon_id, &mut auth.connection)
         .await?
-        .require_match(precondition)?
-        .apply_patch(patch.0, &mut auth.connection)
-        .await?;
+        .require_match_at(precondition, Demon::last_modified(demon_id, &mut auth.connection).await?)?;
+
+    let image = image
+        .open(config::demon_image_max_size().bytes())
+        .into_bytes()
+        .await
+        .map_err(|err| CoreError::InternalServerError { message: err.to_string() })?;
+
+    if !image.is_complete() {
+        return Err(CoreError::PayloadTooLarge.into())
+    }
+
+    let format = ImageFormat::detect(&image)?;
+
+    // Include a timestamp so that clients do not keep showing cached versions of previous thumbnails
+    let name = format!("{}-{}", demon_id, Utc::now().timestamp());
+    let url = storage
+        .store(&name, format, &image)
+        .await
+        .map_err(|err| CoreError::InternalServerError { message: err.to_string() })?;
+
+    let patch = PatchDemon {
+        thumbnail: Some(Some(url)),
+        ..PatchDemon::default()
+    };
+    let demon = demon.apply_patch(patch, &mut auth.connection).await?;
 
     auth.commit().await?;
 
+    cache.invalidate();
+
     Ok(Tagged(demon))
 }
 
+#[rocket::post("/<demon_id>/merge/<duplicate_id>")]
+pub async fn merge(demon_id: i32, duplicate_id: i32, mut auth: TokenAuth, cache: &State<ListCache>) -> Result<Tagged<FullDemon>> {
+    auth.require_permission(LIST_ADMINISTRATOR)?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut auth.connection).await?;
+    let duplicate = MinimalDemon::by_id(duplicate_id, &mut auth.connection).await?;
+
+    demon.merge(duplicate, &mut auth.connection).await?;
+
+    let demon = FullDemon::by_id(demon_id, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    cache.invalidate();
+
+    Ok(Tagged(demon))
+}
+
+#[rocket::get("/<demon_id>/creators")]
+pub async fn creators(demon_id: i32, pool: &State<PointercratePool>) -> Result<Json<Vec<DatabasePlayer>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut connection).await?;
+
+    Ok(Json(creators_of(&demon, &mut connection).await?))
+}
+
 #[rocket::post("/<demon_id>/creators", data = "<creator>")]
-pub async fn post_creator(demon_id: i32, mut auth: TokenAuth, creator: Json<PostCreator>) -> Result<Response2<Json<()>>> {
+pub async fn post_creator(
+    demon_id: i32, mut auth: TokenAuth, creator: Json<PostCreator>, events: &State<ListEvents>,
+) -> Result<Response2<Json<()>>> {
     auth.require_permission(LIST_MODERATOR)?;
 
     let demon = Demon::by_id(demon_id, &mut auth.connection).await?;
@@ -108,14 +441,15 @@ pub async fn post_creator(demon_id: i32, mut auth: TokenAuth, creator: Json<Post
 
     auth.commit().await?;
 
-    Ok(Response2::json(()).status(Status::Created).with_header(
-        "Location",
-        format!("/api/v2/demons/{}/creators/{}/", demon.base.position, player.id),
-    ))
+    events.publish(ListEvent::CreatorsChanged { demon_id });
+
+    Ok(Response2::json(())
+        .status(Status::Created)
+        .with_header("Location", format!("/api/v2/demons/{}/creators/{}/", demon.base.id, player.id)))
 }
 
 #[rocket::delete("/<demon_id>/creators/<player_id>")]
-pub async fn delete_creator(demon_id: i32, player_id: i32, mut auth: TokenAuth) -> Result<Status> {
+pub async fn delete_creator(demon_id: i32, player_id: i32, mut auth: TokenAuth, events: &State<ListEvents>) -> Result<Status> {
     auth.require_permission(LIST_MODERATOR)?;
 
     let demon = Demon::by_id(demon_id, &mut auth.connection).await?;
@@ -128,5 +462,44 @@ pub async fn delete_creator(demon_id: i32, player_id: i32, mut auth: TokenAuth)
 
     auth.commit().await?;
 
+    events.publish(ListEvent::CreatorsChanged { demon_id });
+
+    Ok(Status::NoContent)
+}
+
+#[rocket::get("/<demon_id>/aliases")]
+pub async fn aliases(demon_id: i32, pool: &State<PointercratePool>) -> Result<Json<Vec<Alias>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut connection).await?;
+
+    Ok(Json(demon.aliases(&mut connection).await?))
+}
+
+#[rocket::post("/<demon_id>/aliases", data = "<alias>")]
+pub async fn post_alias(demon_id: i32, mut auth: TokenAuth, alias: Json<PostAlias>) -> Result<Response2<Json<Alias>>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    let demon = MinimalDemon::by_id(demon_id, &mut auth.connection).await?;
+    let alias = demon.add_alias(alias.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    let location = format!("/api/v2/demons/{}/aliases/{}/", demon_id, alias.id);
+
+    Ok(Response2::json(alias).status(Status::Created).with_header("Location", location))
+}
+
+#[rocket::delete("/<demon_id>/aliases/<alias_id>")]
+pub async fn delete_alias(demon_id: i32, alias_id: i32, mut auth: TokenAuth) -> Result<Status> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    MinimalDemon::by_id(demon_id, &mut auth.connection)
+        .await?
+        .remove_alias(alias_id, &mut auth.connection)
+        .await?;
+
+    auth.commit().await?;
+
     Ok(Status::NoContent)
 }
@@ -0,0 +1,51 @@
+use pointercrate_core::pool::PointercratePool;
+use pointercrate_core_api::{error::Result, pagination_response, query::Query, response::Response2};
+use pointercrate_demonlist::{
+    demon::{Demon, DemonPositionPagination},
+    list::DemonList,
+    LIST_HELPER,
+};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{serde::json::Json, State};
+
+#[rocket::get("/")]
+pub async fn lists(pool: &State<PointercratePool>) -> Result<Json<Vec<DemonList>>> {
+    let mut connection = pool.read_connection().await?;
+
+    Ok(Json(DemonList::all(&mut connection).await?))
+}
+
+#[rocket::get("/<slug>")]
+pub async fn get(slug: String, pool: &State<PointercratePool>) -> Result<Json<DemonList>> {
+    let mut connection = pool.read_connection().await?;
+
+    Ok(Json(DemonList::by_slug(&slug, &mut connection).await?))
+}
+
+#[rocket::get("/<slug>/demons")]
+pub async fn paginate_demons(
+    slug: String, auth: Option<TokenAuth>, pool: &State<PointercratePool>, pagination: Query<DemonPositionPagination>,
+) -> Result<Response2<Json<Vec<Demon>>>> {
+    let mut pagination = pagination.0;
+    let mut connection = pool.read_connection().await?;
+
+    pagination.include_pending = auth.map(|auth| auth.has_permission(LIST_HELPER)).unwrap_or(false);
+
+    let list = DemonList::by_slug(&slug, &mut connection).await?;
+
+    pagination.list_id = Some(list.id);
+
+    let mut demons = pagination.page(&mut connection).await?;
+    let max_position = Demon::max_position(list.id, &mut connection).await?;
+
+    pagination_response!(
+        &format!("/api/v1/lists/{}/demons/", list.slug),
+        demons,
+        pagination,
+        1,
+        max_position,
+        before_position,
+        after_position,
+        base.position
+    )
+}
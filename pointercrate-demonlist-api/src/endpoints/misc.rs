@@ -1,15 +1,52 @@
-use pointercrate_demonlist::config;
-use rocket::response::content::Json;
+use crate::cache::ListCache;
+use pointercrate_core::pool::PointercratePool;
+use pointercrate_core_api::error::Result;
+use pointercrate_demonlist::{
+    config,
+    list_config::{ListConfig, PatchListConfig},
+    LIST_ADMINISTRATOR,
+};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{response::content::Json as JsonContent, serde::json::Json, State};
 use serde_json::json;
 
 #[rocket::get("/")]
-pub fn list_information() -> Json<String> {
+pub async fn list_information(pool: &State<PointercratePool>) -> Result<JsonContent<String>> {
+    // Refreshes the values returned by `config::list_size` and `config::extended_list_size`
+    // The loaded values are cached process-wide, so they must not come from a lagging replica
+    ListConfig::load(&mut *pool.connection().await?).await?;
+
+    // Tells clients which CAPTCHA widget (if any) they need to render for anonymous submissions
+    let captcha = match (crate::config::require_captcha(), crate::config::captcha_site_key()) {
+        (true, Some(site_key)) => json!({"provider": crate::config::captcha_provider(), "site_key": site_key}),
+        _ => serde_json::Value::Null,
+    };
+
     let data = json! {
         {
             "list_size": config::list_size(),
-            "extended_list_size": config::extended_list_size()
+            "extended_list_size": config::extended_list_size(),
+            "captcha": captcha
         }
     };
 
-    Json(data.to_string())
+    Ok(JsonContent(data.to_string()))
+}
+
+/// Resizes the list. Setting a size to `null` makes it fall back to the environment configuration.
+#[rocket::patch("/", data = "<patch>")]
+pub async fn patch_list_information(
+    mut auth: TokenAuth, patch: Json<PatchListConfig>, cache: &State<ListCache>,
+) -> Result<Json<ListConfig>> {
+    auth.require_permission(LIST_ADMINISTRATOR)?;
+
+    let list_config = ListConfig::load(&mut auth.connection).await?;
+    let list_config = list_config.apply_patch(patch.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    list_config.cache();
+    cache.invalidate();
+
+    Ok(Json(list_config))
 }
@@ -1,6 +1,18 @@
 pub(crate) mod demon;
+pub(crate) mod list;
 pub(crate) mod misc;
 pub(crate) mod nationality;
+pub(crate) mod pack;
 pub(crate) mod player;
+pub(crate) mod proposal;
 pub(crate) mod record;
+pub(crate) mod roulette;
+pub(crate) mod search;
+pub(crate) mod staff;
+pub(crate) mod stream;
 pub(crate) mod submitter;
+pub(crate) mod user;
+
+/// How often a `PATCH` request is attempted before giving up because of concurrent modifications
+/// (see [`pointercrate_user_api::auth::Auth::restart_transaction`])
+pub(crate) const PATCH_ATTEMPTS: u32 = 3;
@@ -1,11 +1,14 @@
 use pointercrate_core::pool::PointercratePool;
-use pointercrate_core_api::{error::Result, etag::Tagged, query::Query};
-use pointercrate_demonlist::nationality::{Nationality, NationalityRankingPagination, NationalityRecord, RankedNation, Subdivision};
+use pointercrate_core_api::{error::Result, etag::Tagged, pagination_response, query::Query, response::Response2};
+use pointercrate_demonlist::{
+    nationality::{NationalRankingPagination, Nationality, NationalityRankingPagination, NationalityRecord, RankedNation, Subdivision},
+    player::RankedPlayer,
+};
 use rocket::{serde::json::Json, State};
 
 #[rocket::get("/<iso_code>/subdivisions")]
 pub async fn subdivisions(pool: &State<PointercratePool>, iso_code: String) -> Result<Json<Vec<Subdivision>>> {
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
 
     // good code
     let nationality = Nationality::by_country_code_or_name(iso_code.to_uppercase().as_ref(), &mut connection).await?;
@@ -15,12 +18,36 @@ pub async fn subdivisions(pool: &State<PointercratePool>, iso_code: String) -> R
 
 #[rocket::get("/ranking")]
 pub async fn ranking(pool: &State<PointercratePool>, pagination: Query<NationalityRankingPagination>) -> Result<Json<Vec<RankedNation>>> {
-    Ok(Json(pagination.0.page(&mut *pool.connection().await?).await?))
+    Ok(Json(pagination.0.page(&mut *pool.read_connection().await?).await?))
+}
+
+#[rocket::get("/<iso_code>/ranking")]
+pub async fn national_ranking(
+    pool: &State<PointercratePool>, iso_code: String, query: Query<NationalRankingPagination>,
+) -> Result<Response2<Json<Vec<RankedPlayer>>>> {
+    let mut pagination = query.0;
+    let mut connection = pool.read_connection().await?;
+
+    let nationality = Nationality::by_country_code_or_name(iso_code.to_uppercase().as_ref(), &mut connection).await?;
+
+    let mut players = pagination.page(&nationality, &mut connection).await?;
+    let max_index = nationality.ranked_player_count(&mut connection).await?;
+
+    pagination_response!(
+        &format!("/api/v1/nationalities/{}/ranking/", nationality.iso_country_code),
+        players,
+        pagination,
+        1,
+        max_index,
+        before_index,
+        after_index,
+        index
+    )
 }
 
 #[rocket::get("/<iso_code>")]
 pub async fn nation(pool: &State<PointercratePool>, iso_code: String) -> Result<Tagged<NationalityRecord>> {
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
 
     // good code
     let nationality = Nationality::by_country_code_or_name(iso_code.to_uppercase().as_ref(), &mut connection).await?;
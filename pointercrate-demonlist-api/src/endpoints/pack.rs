@@ -0,0 +1,71 @@
+use pointercrate_core::pool::PointercratePool;
+use pointercrate_core_api::{
+    error::Result,
+    etag::{Precondition, TaggableExt, Tagged},
+    response::Response2,
+};
+use pointercrate_demonlist::{
+    pack::{Pack, PatchPack, PostPack},
+    LIST_MODERATOR,
+};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{http::Status, serde::json::Json, State};
+
+#[rocket::get("/")]
+pub async fn packs(pool: &State<PointercratePool>) -> Result<Json<Vec<Pack>>> {
+    let mut connection = pool.read_connection().await?;
+
+    Ok(Json(Pack::all(&mut connection).await?))
+}
+
+#[rocket::get("/<pack_id>")]
+pub async fn get(pack_id: i32, pool: &State<PointercratePool>) -> Result<Tagged<Pack>> {
+    let mut connection = pool.read_connection().await?;
+
+    Ok(Tagged(Pack::by_id(pack_id, &mut connection).await?))
+}
+
+#[rocket::post("/", data = "<data>")]
+pub async fn post(mut auth: TokenAuth, data: Json<PostPack>) -> Result<Response2<Tagged<Pack>>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    let pack = Pack::create_from(data.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    let pack_id = pack.id;
+
+    Ok(Response2::tagged(pack)
+        .status(Status::Created)
+        .with_header("Location", format!("/api/v1/packs/{}/", pack_id)))
+}
+
+#[rocket::patch("/<pack_id>", data = "<patch>")]
+pub async fn patch(pack_id: i32, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchPack>) -> Result<Tagged<Pack>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    let pack = Pack::by_id(pack_id, &mut auth.connection)
+        .await?
+        .require_match(precondition)?
+        .apply_patch(patch.0, &mut auth.connection)
+        .await?;
+
+    auth.commit().await?;
+
+    Ok(Tagged(pack))
+}
+
+#[rocket::delete("/<pack_id>")]
+pub async fn delete(pack_id: i32, mut auth: TokenAuth, precondition: Precondition) -> Result<Status> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    Pack::by_id(pack_id, &mut auth.connection)
+        .await?
+        .require_match(precondition)?
+        .delete(&mut auth.connection)
+        .await?;
+
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
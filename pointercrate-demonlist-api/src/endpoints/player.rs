@@ -1,30 +1,39 @@
-use crate::{config, ratelimits::DemonlistRatelimits};
+use crate::{
+    cache::ListCache,
+    config,
+    endpoints::{record, PATCH_ATTEMPTS},
+    events::{ListEvent, ListEvents},
+    ratelimits::DemonlistRatelimits,
+};
 use log::error;
 use pointercrate_core::{config::database_url, error::CoreError, pool::PointercratePool};
 use pointercrate_core_api::{
     error::Result,
-    etag::{Precondition, TaggableExt, Tagged},
+    etag::{Dated, Precondition, TaggableExt, Tagged},
     pagination_response,
-    query::Query,
-    response::Response2,
+    query::{CountRequested, Query, ResponseFormat},
+    response::{Response2, Tabular},
 };
 use pointercrate_demonlist::{
+    alias::{Alias, PostAlias},
     error::DemonlistError,
     nationality::Nationality,
+    pack::{Pack, PackProgress},
     player::{
         claim::{ListedClaim, PatchVerified, PlayerClaim, PlayerClaimPagination},
-        DatabasePlayer, FullPlayer, PatchPlayer, Player, PlayerPagination, RankedPlayer, RankingPagination,
+        DatabasePlayer, FullPlayer, PatchPlayer, Player, PlayerPagination, RankedPlayer, RankingPagination, TimelineEvent,
     },
-    LIST_HELPER,
+    record::{MinimalRecordPD, RecordPagination},
+    score, LIST_ADMINISTRATOR, LIST_HELPER, LIST_MODERATOR,
 };
-use pointercrate_user::MODERATOR;
+use pointercrate_user::{error::UserError, MODERATOR};
 use pointercrate_user_api::auth::TokenAuth;
 use rocket::{http::Status, serde::json::Json, State};
 use serde::Deserialize;
 use std::net::IpAddr;
 
 #[rocket::get("/")]
-pub async fn paginate(mut auth: TokenAuth, query: Query<PlayerPagination>) -> Result<Response2<Json<Vec<Player>>>> {
+pub async fn paginate(mut auth: TokenAuth, query: Query<PlayerPagination>, count: CountRequested) -> Result<Response2<Json<Vec<Player>>>> {
     let mut pagination = query.0;
 
     if !auth.has_permission(LIST_HELPER) {
@@ -32,9 +41,15 @@ pub async fn paginate(mut auth: TokenAuth, query: Query<PlayerPagination>) -> Re
     }
 
     let mut players = pagination.page(&mut auth.connection).await?;
+    let total = if count.0 {
+        Some(pagination.count(&mut auth.connection).await?)
+    } else {
+        None
+    };
     let (max_id, min_id) = Player::extremal_player_ids(&mut auth.connection).await?;
 
     pagination_response!(
+        total = total,
         "/api/v1/players/",
         players,
         pagination,
@@ -46,14 +61,22 @@ pub async fn paginate(mut auth: TokenAuth, query: Query<PlayerPagination>) -> Re
     )
 }
 #[rocket::get("/", rank = 1)]
-pub async fn unauthed_paginate(pool: &State<PointercratePool>, query: Query<PlayerPagination>) -> Result<Response2<Json<Vec<Player>>>> {
+pub async fn unauthed_paginate(
+    pool: &State<PointercratePool>, query: Query<PlayerPagination>, count: CountRequested,
+) -> Result<Response2<Json<Vec<Player>>>> {
     let mut pagination = query.0;
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
 
     let mut players = pagination.page(&mut connection).await?;
+    let total = if count.0 {
+        Some(pagination.count(&mut connection).await?)
+    } else {
+        None
+    };
     let (max_id, min_id) = Player::extremal_player_ids(&mut connection).await?;
 
     pagination_response!(
+        total = total,
         "/api/v1/players/",
         players,
         pagination,
@@ -65,13 +88,32 @@ pub async fn unauthed_paginate(pool: &State<PointercratePool>, query: Query<Play
     )
 }
 
+/// Paginates the stats viewer ranking, ordered by score
+///
+/// Supports filtering via the `name_contains`, `nation`, `continent` and `subdivision` query
+/// parameters. Passing `nation=null` selects all players without a nationality.
 #[rocket::get("/ranking")]
-pub async fn ranking(pool: &State<PointercratePool>, query: Query<RankingPagination>) -> Result<Response2<Json<Vec<RankedPlayer>>>> {
+pub async fn ranking(
+    pool: &State<PointercratePool>, cache: &State<ListCache>, query: Query<RankingPagination>, format: ResponseFormat,
+) -> Result<Response2<Tabular<Vec<RankedPlayer>>>> {
     let mut pagination = query.0;
-    let mut connection = pool.connection().await?;
 
-    let mut players = pagination.page(&mut connection).await?;
-    let max_index = RankedPlayer::max_index(&mut connection).await?;
+    let (mut players, max_index) = match cache.ranking(&pagination) {
+        Some(cached) => cached,
+        None => {
+            let version = cache.version();
+
+            // The result ends up in the cache, so it must not come from a lagging replica
+            let mut connection = pool.connection().await?;
+
+            let players = pagination.page(&mut connection).await?;
+            let max_index = RankedPlayer::max_index(&mut connection).await?;
+
+            cache.insert_ranking(pagination.clone(), version, players.clone(), max_index);
+
+            (players, max_index)
+        },
+    };
 
     pagination_response!(
         "/api/v1/players/ranking/",
@@ -83,31 +125,196 @@ pub async fn ranking(pool: &State<PointercratePool>, query: Query<RankingPaginat
         after_index,
         index
     )
+    .map(|response| response.format(format))
+}
+
+/// Recomputes the scores of all players, e.g. after the score formula was reconfigured
+#[rocket::post("/ranking/refresh")]
+pub async fn refresh_ranking(mut auth: TokenAuth, cache: &State<ListCache>) -> Result<Status> {
+    auth.require_permission(LIST_ADMINISTRATOR)?;
+
+    score::refresh_player_scores(&mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    cache.invalidate();
+
+    Ok(Status::NoContent)
 }
 
 #[rocket::get("/<player_id>")]
-pub async fn get(player_id: i32, pool: &State<PointercratePool>) -> Result<Tagged<FullPlayer>> {
-    let mut connection = pool.connection().await?;
+pub async fn get(player_id: i32, pool: &State<PointercratePool>) -> Result<Dated<Tagged<FullPlayer>>> {
+    let mut connection = pool.read_connection().await?;
 
-    Ok(Tagged(
-        Player::by_id(player_id, &mut connection).await?.upgrade(&mut connection).await?,
-    ))
+    let player = Player::by_id(player_id, &mut connection).await?.upgrade(&mut connection).await?;
+    let last_modified = Player::last_modified(player_id, &mut connection).await?;
+
+    Ok(Dated(Tagged(player), last_modified))
+}
+
+#[rocket::get("/<player_id>/records")]
+pub async fn paginate_records(
+    player_id: i32, auth: Option<TokenAuth>, pool: &State<PointercratePool>, pagination: Query<RecordPagination>, count: CountRequested,
+) -> Result<Response2<Json<Vec<MinimalRecordPD>>>> {
+    let mut pagination = pagination.0;
+
+    // Make sure we return a 404 for unknown players instead of an empty page
+    DatabasePlayer::by_id(player_id, &mut *pool.read_connection().await?).await?;
+
+    pagination.player = Some(player_id);
+
+    record::scoped_pagination(&format!("/api/v1/players/{}/records/", player_id), pagination, count, auth, pool).await
+}
+
+#[rocket::get("/<player_id>/aliases")]
+pub async fn aliases(player_id: i32, pool: &State<PointercratePool>) -> Result<Json<Vec<Alias>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let player = DatabasePlayer::by_id(player_id, &mut connection).await?;
+
+    Ok(Json(player.aliases(&mut connection).await?))
+}
+
+#[rocket::post("/<player_id>/aliases", data = "<alias>")]
+pub async fn post_alias(player_id: i32, mut auth: TokenAuth, alias: Json<PostAlias>) -> Result<Response2<Json<Alias>>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    let player = DatabasePlayer::by_id(player_id, &mut auth.connection).await?;
+    let alias = player.add_alias(alias.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    let location = format!("/api/v1/players/{}/aliases/{}/", player_id, alias.id);
+
+    Ok(Response2::json(alias).status(Status::Created).with_header("Location", location))
+}
+
+#[rocket::delete("/<player_id>/aliases/<alias_id>")]
+pub async fn delete_alias(player_id: i32, alias_id: i32, mut auth: TokenAuth) -> Result<Status> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    DatabasePlayer::by_id(player_id, &mut auth.connection)
+        .await?
+        .remove_alias(alias_id, &mut auth.connection)
+        .await?;
+
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
+
+/// The given player's progress through all packs
+#[rocket::get("/<player_id>/packs")]
+pub async fn packs(player_id: i32, pool: &State<PointercratePool>) -> Result<Json<Vec<PackProgress>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let player = DatabasePlayer::by_id(player_id, &mut connection).await?;
+
+    Ok(Json(Pack::progress_of(&player, &mut connection).await?))
+}
+
+/// The given player's approvals, verifications and first victories in chronological order
+#[rocket::get("/<player_id>/timeline")]
+pub async fn timeline(player_id: i32, pool: &State<PointercratePool>) -> Result<Json<Vec<TimelineEvent>>> {
+    let mut connection = pool.read_connection().await?;
+
+    let player = DatabasePlayer::by_id(player_id, &mut connection).await?;
+
+    Ok(Json(player.timeline(&mut connection).await?))
 }
 
 #[rocket::patch("/<player_id>", data = "<patch>")]
 pub async fn patch(
-    player_id: i32, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchPlayer>,
+    player_id: i32, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchPlayer>, pool: &State<PointercratePool>,
+    events: &State<ListEvents>,
 ) -> Result<Tagged<FullPlayer>> {
-    let player = Player::by_id(player_id, &mut auth.connection)
+    // Players that have a verified claim on their player object can change their own nationality,
+    // everything else is up to the list team
+    if patch.name.is_some() || patch.banned.is_some() {
+        auth.require_permission(LIST_HELPER)?;
+    } else if !auth.has_permission(LIST_HELPER) {
+        match PlayerClaim::verified_claim_on(player_id, &mut auth.connection).await? {
+            Some(claim) if claim.user_id == auth.user.inner().id => (),
+            _ => auth.require_permission(LIST_HELPER)?,
+        }
+    }
+
+    let mut attempt = 1;
+
+    let (player, was_banned) = loop {
+        // Since the precondition is checked again, a retry fails if the player itself was modified
+        // concurrently
+        let result = async {
+            let last_modified = Player::last_modified(player_id, &mut auth.connection).await?;
+            let player = Player::by_id(player_id, &mut auth.connection)
+                .await?
+                .upgrade(&mut auth.connection)
+                .await?
+                .require_match_at(precondition.clone(), last_modified)?;
+            let was_banned = player.player.base.banned;
+
+            Ok::<_, DemonlistError>((
+                player
+                    .apply_patch(patch.0.clone(), auth.user.inner().id, &mut auth.connection)
+                    .await?,
+                was_banned,
+            ))
+        }
+        .await;
+
+        // The commit is part of the retried operation, as that is where serialization failures
+        // usually surface
+        match result {
+            Ok(result) =>
+                match auth.commit_in_place(pool).await {
+                    Ok(()) => break result,
+                    Err(UserError::Core(CoreError::ConcurrentModification)) if attempt < PATCH_ATTEMPTS => (),
+                    Err(err) => return Err(err.into()),
+                },
+            Err(DemonlistError::Core(CoreError::ConcurrentModification)) if attempt < PATCH_ATTEMPTS => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        attempt += 1;
+
+        auth.restart_transaction(pool).await?;
+    };
+
+    if player.player.base.banned && !was_banned {
+        events.publish(ListEvent::PlayerBanned {
+            player: player.player.base.clone(),
+        });
+    } else {
+        events.publish(ListEvent::PlayerModified { player_id });
+    }
+
+    Ok(Tagged(player))
+}
+
+#[rocket::post("/<player_id>/merge/<other_id>")]
+pub async fn merge(player_id: i32, other_id: i32, mut auth: TokenAuth, events: &State<ListEvents>) -> Result<Tagged<FullPlayer>> {
+    auth.require_permission(LIST_ADMINISTRATOR)?;
+
+    let mut player = Player::by_id(player_id, &mut auth.connection)
         .await?
         .upgrade(&mut auth.connection)
+        .await?;
+    let other = DatabasePlayer::by_id(other_id, &mut auth.connection).await?;
+
+    player.merge(other, &mut auth.connection).await?;
+
+    score::refresh_player_scores(&mut auth.connection).await?;
+
+    // Reload the player to pick up the transferred creator, verifier and publisher entries
+    let player = Player::by_id(player_id, &mut auth.connection)
         .await?
-        .require_match(precondition)?
-        .apply_patch(patch.0, &mut auth.connection)
+        .upgrade(&mut auth.connection)
         .await?;
 
     auth.commit().await?;
 
+    events.publish(ListEvent::PlayerModified { player_id });
+
     Ok(Tagged(player))
 }
 
@@ -125,14 +332,22 @@ pub async fn put_claim(player_id: i32, mut auth: TokenAuth) -> Result<Response2<
 }
 
 #[rocket::patch("/<player_id>/claims/<user_id>", data = "<data>")]
-pub async fn patch_claim(player_id: i32, user_id: i32, mut auth: TokenAuth, data: Json<PatchVerified>) -> Result<Json<PlayerClaim>> {
+pub async fn patch_claim(
+    player_id: i32, user_id: i32, mut auth: TokenAuth, data: Json<PatchVerified>, events: &State<ListEvents>,
+) -> Result<Json<PlayerClaim>> {
     auth.require_permission(MODERATOR)?;
 
     let claim = PlayerClaim::get(user_id, player_id, &mut auth.connection).await?;
+    let was_verified = claim.verified;
     let claim = claim.set_verified(data.verified, &mut auth.connection).await?;
+    let player = DatabasePlayer::by_id(player_id, &mut auth.connection).await?;
 
     auth.commit().await?;
 
+    if claim.verified && !was_verified {
+        events.publish(ListEvent::ClaimVerified { user_id, player });
+    }
+
     Ok(Json(claim))
 }
 
@@ -222,9 +437,16 @@ pub async fn geolocate_nationality(
     }
 
     let nationality = Nationality::by_country_code_or_name(&data.country_code, &mut auth.connection).await?;
+    let mut nations = vec![nationality.iso_country_code.clone()];
+
+    if let Some(ref previous) = player.nationality {
+        nations.push(previous.iso_country_code.clone());
+    }
 
     player.set_nationality(nationality, &mut auth.connection).await?;
 
+    score::refresh_nation_scores(&nations, &mut auth.connection).await?;
+
     if ["US", "CA", "GB", "AU"].map(ToString::to_string).contains(&data.country_code) {
         if let Some(region) = data.region_iso_code {
             player.set_subdivision(region, &mut auth.connection).await?;
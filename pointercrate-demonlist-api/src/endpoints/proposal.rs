@@ -0,0 +1,67 @@
+use crate::{
+    endpoints::demon::check_video,
+    events::{ListEvent, ListEvents},
+};
+use pointercrate_core_api::{error::Result, response::Response2};
+use pointercrate_demonlist::{
+    demon::PostDemon,
+    proposal::{DemonProposal, ProposalVote},
+    LIST_HELPER, LIST_MODERATOR,
+};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{http::Status, serde::json::Json, State};
+
+/// All demon proposals, optionally filtered by status (`open`, `approved` or `rejected`)
+#[rocket::get("/?<status>")]
+pub async fn proposals(mut auth: TokenAuth, status: Option<String>) -> Result<Json<Vec<DemonProposal>>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    let status = status.map(|status| status.to_uppercase());
+
+    Ok(Json(DemonProposal::all(status.as_deref(), &mut auth.connection).await?))
+}
+
+#[rocket::get("/<proposal_id>")]
+pub async fn get(mut auth: TokenAuth, proposal_id: i32) -> Result<Json<DemonProposal>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    Ok(Json(DemonProposal::by_id(proposal_id, &mut auth.connection).await?))
+}
+
+#[rocket::post("/", data = "<data>")]
+pub async fn propose(mut auth: TokenAuth, data: Json<PostDemon>) -> Result<Response2<Json<DemonProposal>>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    if let Some(video) = data.video() {
+        check_video(video).await?;
+    }
+
+    let proposal = DemonProposal::create(data.0, auth.user.inner().id, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    let proposal_id = proposal.id;
+
+    Ok(Response2::json(proposal)
+        .status(Status::Created)
+        .with_header("Location", format!("/api/v1/demon_proposals/{}/", proposal_id)))
+}
+
+/// Votes for or against the given proposal. If this vote approves the proposal, the proposed demon
+/// is added to the list right away.
+#[rocket::put("/<proposal_id>/vote", data = "<vote>")]
+pub async fn vote(
+    mut auth: TokenAuth, proposal_id: i32, vote: Json<ProposalVote>, events: &State<ListEvents>,
+) -> Result<Json<DemonProposal>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    let (proposal, demon) = DemonProposal::vote(proposal_id, auth.user.inner().id, vote.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    if let Some(demon) = demon {
+        events.publish(ListEvent::DemonAdded { demon: demon.demon.base });
+    }
+
+    Ok(Json(proposal))
+}
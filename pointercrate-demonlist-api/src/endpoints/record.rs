@@ -1,36 +1,60 @@
-use crate::ratelimits::DemonlistRatelimits;
+use crate::{
+    captcha::{self, CaptchaResponse},
+    embed::OEmbed,
+    endpoints::PATCH_ATTEMPTS,
+    events::{ListEvent, ListEvents},
+    ratelimits::DemonlistRatelimits,
+    youtube,
+};
 use log::{debug, error, warn};
-use pointercrate_core::{audit::AuditLogEntry, error::CoreError, pool::PointercratePool};
+use pointercrate_core::{audit::AuditLogEntry, error::CoreError, etag::Taggable, pool::PointercratePool};
 use pointercrate_core_api::{
     error::Result,
-    etag::{Precondition, TaggableExt, Tagged},
+    etag::{Dated, Precondition, TaggableExt, Tagged},
     pagination_response,
-    query::Query,
-    response::Response2,
+    query::{CountRequested, Query, ResponseFormat},
+    response::{Response2, Tabular},
+    stream::{batched, JsonStream, BATCH_SIZE},
 };
 use pointercrate_demonlist::{
+    demon::ListSection,
     error::DemonlistError,
     record::{
         audit::RecordModificationData,
         note::{NewNote, Note, PatchNote},
-        FullRecord, MinimalRecordPD, PatchRecord, RecordPagination, RecordStatus, Submission,
+        BulkStatusChange, BulkStatusResult, FullRecord, MinimalRecordPD, PatchRecord, RecordPagination, RecordStatus, ReviewLock,
+        StatusTransition, Submission, SubmissionReport, VideoRevalidation,
     },
+    submission_guard,
     submitter::Submitter,
-    LIST_ADMINISTRATOR, LIST_HELPER, LIST_MODERATOR,
+    video, LIST_ADMINISTRATOR, LIST_HELPER, LIST_MODERATOR,
 };
+use pointercrate_user::error::UserError;
 use pointercrate_user_api::auth::TokenAuth;
-use rocket::{http::Status, serde::json::Json, tokio, State};
+use rocket::{
+    futures::stream::{BoxStream, StreamExt},
+    http::Status,
+    serde::json::Json,
+    tokio, State,
+};
+use serde::Serialize;
 use sqlx::{pool::PoolConnection, Postgres};
-use std::net::IpAddr;
+use std::{net::IpAddr, sync::Arc};
 
 #[rocket::get("/")]
-pub async fn paginate(mut auth: TokenAuth, query: Query<RecordPagination>) -> Result<Response2<Json<Vec<MinimalRecordPD>>>> {
+pub async fn paginate(
+    mut auth: TokenAuth, query: Query<RecordPagination>, count: CountRequested, format: ResponseFormat,
+) -> Result<Response2<Tabular<Vec<MinimalRecordPD>>>> {
     let mut pagination = query.0;
 
     if pagination.submitter.is_some() {
         auth.require_permission(LIST_MODERATOR)?;
     }
 
+    if pagination.include_deleted {
+        auth.require_permission(LIST_ADMINISTRATOR)?;
+    }
+
     if !auth.has_permission(LIST_HELPER) {
         if pagination.status.is_some() && pagination.status != Some(RecordStatus::Approved) {
             return Err(CoreError::Unauthorized.into())
@@ -40,20 +64,36 @@ pub async fn paginate(mut auth: TokenAuth, query: Query<RecordPagination>) -> Re
     }
 
     let mut records = pagination.page(&mut auth.connection).await?;
+    let total = if count.0 {
+        Some(pagination.count(&mut auth.connection).await?)
+    } else {
+        None
+    };
 
     let (max_id, min_id) = FullRecord::extremal_record_ids(&mut auth.connection).await?;
 
-    pagination_response!("/api/v1/records/", records, pagination, min_id, max_id, before_id, after_id, id)
+    pagination_response!(
+        total = total,
+        "/api/v1/records/",
+        records,
+        pagination,
+        min_id,
+        max_id,
+        before_id,
+        after_id,
+        id
+    )
+    .map(|response| response.format(format))
 }
 
 #[rocket::get("/", rank = 1)]
 pub async fn unauthed_pagination(
-    pool: &State<PointercratePool>, query: Query<RecordPagination>,
-) -> Result<Response2<Json<Vec<MinimalRecordPD>>>> {
-    let mut connection = pool.connection().await?;
+    pool: &State<PointercratePool>, query: Query<RecordPagination>, count: CountRequested, format: ResponseFormat,
+) -> Result<Response2<Tabular<Vec<MinimalRecordPD>>>> {
+    let mut connection = pool.read_connection().await?;
     let mut pagination = query.0;
 
-    if pagination.submitter.is_some() {
+    if pagination.submitter.is_some() || pagination.include_deleted {
         return Err(CoreError::Unauthorized.into())
     }
 
@@ -64,22 +104,143 @@ pub async fn unauthed_pagination(
     pagination.status = Some(RecordStatus::Approved);
 
     let mut records = pagination.page(&mut connection).await?;
+    let total = if count.0 {
+        Some(pagination.count(&mut connection).await?)
+    } else {
+        None
+    };
+
+    let (max_id, min_id) = FullRecord::extremal_record_ids(&mut connection).await?;
+
+    pagination_response!(
+        total = total,
+        "/api/v1/records/",
+        records,
+        pagination,
+        min_id,
+        max_id,
+        before_id,
+        after_id,
+        id
+    )
+    .map(|response| response.format(format))
+}
+
+/// All approved records matching the given filters, in ascending order of their ids. Pagination
+/// parameters other than `after` are ignored.
+#[rocket::get("/export")]
+pub async fn export(
+    pool: &State<PointercratePool>, query: Query<RecordPagination>,
+) -> Result<JsonStream<BoxStream<'static, std::result::Result<MinimalRecordPD, DemonlistError>>>> {
+    let mut filters = query.0;
+
+    if filters.submitter.is_some() || filters.include_deleted {
+        return Err(CoreError::Unauthorized.into())
+    }
+
+    if filters.status.is_some() && filters.status != Some(RecordStatus::Approved) {
+        return Err(CoreError::Unauthorized.into())
+    }
+
+    filters.status = Some(RecordStatus::Approved);
+
+    let pool = pool.inner().clone();
+
+    Ok(JsonStream(
+        batched(
+            move |after| {
+                let mut pagination = filters.clone();
+                let pool = pool.clone();
+
+                // Having an 'after' value makes sure we always get records in ascending order
+                pagination.after_id = after.or(pagination.after_id).or(Some(0));
+                pagination.before_id = None;
+                pagination.limit = Some(BATCH_SIZE);
+
+                async move { pagination.page(&mut *pool.read_connection().await?).await }
+            },
+            |record| record.id,
+        )
+        .boxed(),
+    ))
+}
+
+/// Retrieves a page of records for endpoints that are scoped to a single demon or player
+///
+/// Applies the same permission checks as the global records endpoint, with `endpoint` being the
+/// path used for generating the pagination links.
+pub(crate) async fn scoped_pagination(
+    endpoint: &str, mut pagination: RecordPagination, count: CountRequested, auth: Option<TokenAuth>, pool: &PointercratePool,
+) -> Result<Response2<Json<Vec<MinimalRecordPD>>>> {
+    let is_team_member = auth.as_ref().map(|auth| auth.has_permission(LIST_HELPER)).unwrap_or(false);
+
+    if pagination.submitter.is_some() && !auth.as_ref().map(|auth| auth.has_permission(LIST_MODERATOR)).unwrap_or(false) {
+        return Err(CoreError::Unauthorized.into())
+    }
+
+    if pagination.include_deleted && !auth.as_ref().map(|auth| auth.has_permission(LIST_ADMINISTRATOR)).unwrap_or(false) {
+        return Err(CoreError::Unauthorized.into())
+    }
+
+    if !is_team_member {
+        if pagination.status.is_some() && pagination.status != Some(RecordStatus::Approved) {
+            return Err(CoreError::Unauthorized.into())
+        }
+
+        pagination.status = Some(RecordStatus::Approved);
+    }
+
+    let mut connection = match auth {
+        Some(auth) => auth.connection,
+        None => pool.transaction().await?,
+    };
+
+    let mut records = pagination.page(&mut connection).await?;
+    let total = if count.0 {
+        Some(pagination.count(&mut connection).await?)
+    } else {
+        None
+    };
 
     let (max_id, min_id) = FullRecord::extremal_record_ids(&mut connection).await?;
 
-    pagination_response!("/api/v1/records/", records, pagination, min_id, max_id, before_id, after_id, id)
+    pagination_response!(
+        total = total,
+        endpoint,
+        records,
+        pagination,
+        min_id,
+        max_id,
+        before_id,
+        after_id,
+        id
+    )
+}
+
+#[derive(rocket::Responder)]
+pub enum SubmissionResponse {
+    /// The newly created record, and, if it still needs to be reviewed, its position in the queue
+    Created(Response2<Json<serde_json::Value>>),
+    Verified(Json<SubmissionReport>),
 }
 
-#[rocket::post("/", data = "<submission>")]
+/// Submits a record. If `verify_only` is set, the submission is only validated, and a report of
+/// what submitting it would result in is returned instead of the newly created record.
+///
+/// Alongside the newly created record, the response contains the number of submissions ahead of it
+/// in the queue and an estimate of how long reviewing it will take (`null` for records that skipped
+/// the queue).
+#[rocket::post("/?<verify_only>", data = "<submission>")]
 pub async fn submit(
-    ip: IpAddr, auth: Option<TokenAuth>, submission: Json<Submission>, pool: &State<PointercratePool>,
-    ratelimits: &State<DemonlistRatelimits>,
-) -> Result<Tagged<FullRecord>> {
+    ip: IpAddr, auth: Option<TokenAuth>, captcha: CaptchaResponse, submission: Json<Submission>, verify_only: Option<bool>,
+    pool: &State<PointercratePool>, ratelimits: &State<DemonlistRatelimits>, events: &State<ListEvents>,
+) -> Result<SubmissionResponse> {
     let submission = submission.0;
     let is_team_member = match auth {
         Some(ref auth) => auth.has_permission(LIST_HELPER),
         None => false,
     };
+    let user_id = auth.as_ref().map(|auth| auth.user.inner().id);
 
     if submission.status != RecordStatus::Submitted || submission.video.is_none() {
         match auth {
@@ -88,12 +249,34 @@ pub async fn submit(
         }
     }
 
+    // Check ratelimits before doing any work, so that neither validation (via `verify_only`) nor
+    // submissions failing validation can be used to probe the list without limits
+    if !is_team_member {
+        // Also check the local ratelimit first since that one expires earlier
+        if let Some(user_id) = user_id {
+            ratelimits.record_submission_user(user_id)?;
+        }
+        ratelimits.record_submission(ip)?;
+        ratelimits.record_submission_global()?;
+    }
+
+    // Logged in users already had to prove that they are human when registering
+    if auth.is_none() {
+        captcha::verify(captcha, ip).await?;
+    }
+
+    // Talking to YouTube might take a while, so do it before we start holding locks. Malformed videos
+    // are reported by the actual validation later on
+    if let Some(Ok(video)) = submission.video.as_deref().map(video::validate) {
+        youtube::check_availability(&video).await?;
+    }
+
     let mut connection = match auth {
         Some(auth) => auth.connection,
         None => pool.transaction().await?,
     };
 
-    let submitter = match Submitter::by_ip(ip, &mut connection).await? {
+    let mut submitter = match Submitter::by_ip(ip, &mut connection).await? {
         Some(submitter) => submitter,
         None => {
             ratelimits.new_submitters()?;
@@ -102,42 +285,63 @@ pub async fn submit(
         },
     };
 
-    let validated = submission.validate(submitter, &mut connection).await?;
-
     if !is_team_member {
-        // Check ratelimits before any change is made to the database so that the transaction rollback is
-        // easier.
+        if let Err(err) = submission_guard::check(&mut submitter, &submission, &mut connection).await {
+            // The guard might have flagged the submitter, which needs to persist
+            connection.commit().await.map_err(DemonlistError::from)?;
 
-        // Also check the local ratelimit first since that one expires earlier
-        ratelimits.record_submission(ip)?;
-        ratelimits.record_submission_global()?;
+            return Err(err)
+        }
+    }
+
+    let validated = submission.validate(submitter, &mut connection).await?;
+
+    // Dropping the transaction without committing it undoes any changes validation made (e.g.
+    // creating the submitter or player)
+    if verify_only.unwrap_or(false) {
+        return Ok(SubmissionResponse::Verified(Json(validated.report(&mut connection).await?)))
     }
 
     let record = validated.create(&mut connection).await?;
+    let queue_position = match record.status {
+        RecordStatus::Submitted => Some(record.queue_position(&mut connection).await?),
+        _ => None,
+    };
 
     connection.commit().await.map_err(DemonlistError::from)?;
 
+    // Records added directly by the list team skip the submission stage
+    notify_status_change(RecordStatus::Submitted, &record, events);
+
     // FIXME: This is fucking stupid
     if record.status == RecordStatus::Submitted {
         if let Some(ref video) = record.video {
             tokio::spawn(validate(
-                record.id,
+                record.clone(),
                 video.to_string(),
-                webhook_embed(&record),
+                events.inner().clone(),
                 pool.connection().await?,
             ));
         }
     }
 
-    Ok(Tagged(record))
+    let etag = record.etag_string();
+
+    Ok(SubmissionResponse::Created(
+        Response2::json(serde_json::json!({"data": record, "queue": queue_position})).with_header("etag", etag),
+    ))
 }
 
 #[rocket::get("/<record_id>")]
-pub async fn get(record_id: i32, auth: Option<TokenAuth>, pool: &State<PointercratePool>) -> Result<Tagged<FullRecord>> {
+pub async fn get(record_id: i32, auth: Option<TokenAuth>, pool: &State<PointercratePool>) -> Result<Dated<Tagged<FullRecord>>> {
     let is_helper = match auth {
         Some(ref auth) => auth.has_permission(LIST_HELPER),
         _ => false,
     };
+    let is_moderator = match auth {
+        Some(ref auth) => auth.has_permission(LIST_MODERATOR),
+        _ => false,
+    };
 
     let mut connection = match auth {
         Some(auth) => auth.connection,
@@ -146,6 +350,10 @@ pub async fn get(record_id: i32, auth: Option<TokenAuth>, pool: &State<Pointercr
 
     let mut record = FullRecord::by_id(record_id, &mut connection).await?;
 
+    if !is_moderator {
+        record.raw_footage = None;
+    }
+
     if !is_helper {
         record.notes.clear();
 
@@ -154,12 +362,31 @@ pub async fn get(record_id: i32, auth: Option<TokenAuth>, pool: &State<Pointercr
         }
     }
 
-    Ok(Tagged(record))
+    let last_modified = FullRecord::last_modified(record_id, &mut connection).await?;
+
+    Ok(Dated(Tagged(record), last_modified))
+}
+
+/// Describes the given record for link unfurling (see [`crate::embed`]). Only available for
+/// approved records.
+#[rocket::get("/<record_id>/oembed")]
+pub async fn oembed(record_id: i32, pool: &State<PointercratePool>) -> Result<Json<OEmbed>> {
+    let record = FullRecord::by_id(record_id, &mut *pool.read_connection().await?).await?;
+
+    if record.status != RecordStatus::Approved {
+        return Err(DemonlistError::RecordNotFound { record_id }.into())
+    }
+
+    Ok(Json(OEmbed::of(&record)))
 }
 
+/// The changelog of a single record, available to list moderators for resolving disputes about
+/// when a record was approved or edited
+///
+/// Records that were deleted in the meantime still have their changelog available.
 #[rocket::get("/<record_id>/audit")]
 pub async fn audit(record_id: i32, mut auth: TokenAuth) -> Result<Json<Vec<AuditLogEntry<RecordModificationData>>>> {
-    auth.require_permission(LIST_ADMINISTRATOR)?;
+    auth.require_permission(LIST_MODERATOR)?;
 
     let log = pointercrate_demonlist::record::audit::audit_log_for_record(record_id, &mut auth.connection).await?;
 
@@ -172,28 +399,255 @@ pub async fn audit(record_id: i32, mut auth: TokenAuth) -> Result<Json<Vec<Audit
 
 #[rocket::patch("/<record_id>", data = "<patch>")]
 pub async fn patch(
-    record_id: i32, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchRecord>,
+    record_id: i32, mut auth: TokenAuth, precondition: Precondition, patch: Json<PatchRecord>, pool: &State<PointercratePool>,
+    events: &State<ListEvents>,
+) -> Result<Tagged<FullRecord>> {
+    let is_moderator = auth.has_permission(LIST_MODERATOR);
+    let mut attempt = 1;
+
+    let (mut record, old_status) = loop {
+        // Since the precondition is checked again, a retry fails if the record itself was modified
+        // concurrently
+        let result = async {
+            let record = FullRecord::by_id(record_id, &mut auth.connection).await?;
+
+            let required = if record.demon.section(&mut auth.connection).await? == ListSection::Legacy {
+                LIST_MODERATOR
+            } else {
+                LIST_HELPER
+            };
+
+            auth.permissions.require_permission(auth.user.inner().permissions, required)?;
+
+            if patch.status().is_some() {
+                ReviewLock::require_unlocked(record_id, auth.user.inner().id, &mut auth.connection).await?;
+            }
+
+            let last_modified = FullRecord::last_modified(record_id, &mut auth.connection).await?;
+            let old_status = record.status;
+            let record = record
+                .require_match_at(precondition.clone(), last_modified)?
+                .apply_patch(patch.0.clone(), &mut auth.connection)
+                .await?;
+
+            Ok::<_, DemonlistError>((record, old_status))
+        }
+        .await;
+
+        // The commit is part of the retried operation, as that is where serialization failures
+        // usually surface
+        match result {
+            Ok(result) =>
+                match auth.commit_in_place(pool).await {
+                    Ok(()) => break result,
+                    Err(UserError::Core(CoreError::ConcurrentModification)) if attempt < PATCH_ATTEMPTS => (),
+                    Err(err) => return Err(err.into()),
+                },
+            Err(DemonlistError::Core(CoreError::ConcurrentModification)) if attempt < PATCH_ATTEMPTS => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        attempt += 1;
+
+        auth.restart_transaction(pool).await?;
+    };
+
+    if record.status == old_status {
+        events.publish(ListEvent::RecordModified { record_id });
+    } else {
+        notify_status_change(old_status, &record, events);
+    }
+
+    if !is_moderator {
+        record.raw_footage = None;
+    }
+
+    Ok(Tagged(record))
+}
+
+#[rocket::put("/<record_id>/status", data = "<transition>")]
+pub async fn transition_status(
+    record_id: i32, mut auth: TokenAuth, precondition: Precondition, transition: Json<StatusTransition>, events: &State<ListEvents>,
 ) -> Result<Tagged<FullRecord>> {
     let record = FullRecord::by_id(record_id, &mut auth.connection).await?;
 
-    if record.demon.position > pointercrate_demonlist::config::extended_list_size() {
+    if record.demon.section(&mut auth.connection).await? == ListSection::Legacy {
         auth.require_permission(LIST_MODERATOR)?;
     } else {
         auth.require_permission(LIST_HELPER)?;
     }
 
-    let record = record
-        .require_match(precondition)?
-        .apply_patch(patch.0, &mut auth.connection)
+    ReviewLock::require_unlocked(record_id, auth.user.inner().id, &mut auth.connection).await?;
+
+    let last_modified = FullRecord::last_modified(record_id, &mut auth.connection).await?;
+    let old_status = record.status;
+    let is_moderator = auth.has_permission(LIST_MODERATOR);
+    let mut record = record
+        .require_match_at(precondition, last_modified)?
+        .transition_to(transition.0.status, &mut auth.connection)
         .await?;
 
     auth.commit().await?;
 
+    notify_status_change(old_status, &record, events);
+
+    if !is_moderator {
+        record.raw_footage = None;
+    }
+
     Ok(Tagged(record))
 }
 
-#[rocket::delete("/<record_id>")]
-pub async fn delete(record_id: i32, mut auth: TokenAuth, precondition: Precondition) -> Result<Status> {
+#[rocket::patch("/bulk", data = "<changes>")]
+pub async fn bulk_transition_status(
+    mut auth: TokenAuth, changes: Json<Vec<BulkStatusChange>>, events: &State<ListEvents>,
+) -> Result<Json<Vec<BulkStatusResult>>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    let is_moderator = auth.has_permission(LIST_MODERATOR);
+    let member_id = auth.user.inner().id;
+    let record_ids: Vec<i32> = changes.iter().map(|change| change.id).collect();
+    let locks = ReviewLock::of_records(&record_ids, &mut auth.connection).await?;
+
+    let mut results = FullRecord::bulk_transition(changes.0, &mut auth.connection, |record, section| {
+        if section == ListSection::Legacy && !is_moderator {
+            return Err(CoreError::MissingPermissions { required: LIST_MODERATOR }.into())
+        }
+
+        match locks.get(&record.id) {
+            Some(lock) => lock.require_held_by(member_id),
+            None => Ok(()),
+        }
+    })
+    .await?;
+
+    auth.commit().await?;
+
+    for result in &results {
+        if let (Some(previous_status), Some(record)) = (result.previous_status, &result.record) {
+            notify_status_change(previous_status, record, events);
+        }
+    }
+
+    if !is_moderator {
+        for record in results.iter_mut().filter_map(|result| result.record.as_mut()) {
+            record.raw_footage = None;
+        }
+    }
+
+    Ok(Json(results))
+}
+
+#[derive(Serialize)]
+pub struct QueuedRecord {
+    #[serde(flatten)]
+    record: MinimalRecordPD,
+
+    /// The claim on this record, if someone is currently reviewing it
+    claim: Option<ReviewLock>,
+}
+
+/// The submissions waiting for review, oldest first, together with who is currently reviewing them
+///
+/// Accepts the same filters as the records pagination, although the status is always `SUBMITTED`.
+#[rocket::get("/queue")]
+pub async fn queue(mut auth: TokenAuth, query: Query<RecordPagination>) -> Result<Json<Vec<QueuedRecord>>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    let mut pagination = query.0;
+
+    pagination.status = Some(RecordStatus::Submitted);
+    // Having an 'after' value makes sure we always get records in ascending order
+    pagination.after_id = pagination.after_id.or(Some(0));
+    pagination.before_id = None;
+
+    let mut records = pagination.page(&mut auth.connection).await?;
+
+    // The pagination fetches one additional record to determine whether there is a next page
+    records.truncate(pagination.limit.unwrap_or(50) as usize);
+
+    let record_ids: Vec<i32> = records.iter().map(|record| record.id).collect();
+    let mut locks = ReviewLock::of_records(&record_ids, &mut auth.connection).await?;
+
+    Ok(Json(
+        records
+            .into_iter()
+            .map(|record| {
+                QueuedRecord {
+                    claim: locks.remove(&record.id),
+                    record,
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Claims a submitted record for review, so that nobody else can change its status while the claim
+/// lasts. Claiming a record again extends the claim.
+#[rocket::post("/<record_id>/claim")]
+pub async fn claim(record_id: i32, mut auth: TokenAuth) -> Result<Json<ReviewLock>> {
+    let record = FullRecord::by_id(record_id, &mut auth.connection).await?;
+
+    if record.demon.section(&mut auth.connection).await? == ListSection::Legacy {
+        auth.require_permission(LIST_MODERATOR)?;
+    } else {
+        auth.require_permission(LIST_HELPER)?;
+    }
+
+    if record.status != RecordStatus::Submitted {
+        return Err(DemonlistError::RecordNotClaimable {
+            record_id,
+            status: record.status,
+        }
+        .into())
+    }
+
+    let lock = ReviewLock::claim(record_id, auth.user.inner().id, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    Ok(Json(lock))
+}
+
+/// Releases the claim on a record. List administrators can release anyone's claim, everyone else
+/// only their own.
+#[rocket::delete("/<record_id>/claim")]
+pub async fn release_claim(record_id: i32, mut auth: TokenAuth) -> Result<Status> {
+    auth.require_permission(LIST_HELPER)?;
+
+    if auth.has_permission(LIST_ADMINISTRATOR) {
+        ReviewLock::break_lock(record_id, &mut auth.connection).await?;
+    } else {
+        ReviewLock::release(record_id, auth.user.inner().id, &mut auth.connection).await?;
+    }
+
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
+
+fn notify_status_change(old_status: RecordStatus, record: &FullRecord, events: &ListEvents) {
+    if old_status == record.status {
+        return
+    }
+
+    match record.status {
+        RecordStatus::Approved => events.publish(ListEvent::record_approved(record)),
+        RecordStatus::Rejected => events.publish(ListEvent::record_rejected(record)),
+        // The record no longer counts towards the list
+        _ if old_status == RecordStatus::Approved => events.publish(ListEvent::RecordModified { record_id: record.id }),
+        _ => (),
+    }
+}
+
+/// Deletes a record. The mandatory `reason` is kept alongside the deleted record, which can be
+/// restored via [`restore`]
+#[rocket::delete("/<record_id>?<reason>")]
+pub async fn delete(
+    record_id: i32, reason: Option<&str>, mut auth: TokenAuth, precondition: Precondition, events: &State<ListEvents>,
+) -> Result<Status> {
+    let reason = reason.ok_or(DemonlistError::DeletionReasonRequired)?;
+
     let record = FullRecord::by_id(record_id, &mut auth.connection).await?;
 
     if record.status == RecordStatus::Submitted && !record.was_modified(&mut auth.connection).await? {
@@ -202,14 +656,80 @@ pub async fn delete(record_id: i32, mut auth: TokenAuth, precondition: Precondit
         auth.require_permission(LIST_MODERATOR)?;
     }
 
-    precondition.require_etag_match(&record)?;
+    precondition.require_match_or_unmodified(&record, FullRecord::last_modified(record_id, &mut auth.connection).await?)?;
+
+    let deleted_by = auth.user.inner().id;
 
-    record.delete(&mut auth.connection).await?;
+    record.delete(reason, Some(deleted_by), &mut auth.connection).await?;
     auth.commit().await?;
 
+    events.publish(ListEvent::RecordModified { record_id });
+
     Ok(Status::NoContent)
 }
 
+#[rocket::post("/<record_id>/restore")]
+pub async fn restore(record_id: i32, mut auth: TokenAuth, events: &State<ListEvents>) -> Result<Tagged<FullRecord>> {
+    auth.require_permission(LIST_ADMINISTRATOR)?;
+
+    let record = FullRecord::restore(record_id, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    events.publish(ListEvent::RecordModified { record_id });
+
+    Ok(Tagged(record))
+}
+
+#[rocket::get("/redundant")]
+pub async fn redundant_submissions(mut auth: TokenAuth) -> Result<Json<Vec<MinimalRecordPD>>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    Ok(Json(FullRecord::redundant_submissions(&mut auth.connection).await?))
+}
+
+#[rocket::delete("/redundant")]
+pub async fn clean_redundant_submissions(mut auth: TokenAuth) -> Result<Json<Vec<MinimalRecordPD>>> {
+    auth.require_permission(LIST_MODERATOR)?;
+
+    let cleaned = FullRecord::clean_redundant_submissions(&mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    Ok(Json(cleaned))
+}
+
+#[rocket::post("/revalidate_videos?<dry_run>")]
+pub async fn revalidate_videos(dry_run: bool, mut auth: TokenAuth) -> Result<Json<VideoRevalidation>> {
+    auth.require_permission(LIST_ADMINISTRATOR)?;
+
+    let summary = FullRecord::revalidate_stored_videos(dry_run, &mut auth.connection).await?;
+
+    if !dry_run {
+        auth.commit().await?;
+    }
+
+    Ok(Json(summary))
+}
+
+#[rocket::get("/<record_id>/notes")]
+pub async fn notes(record_id: i32, mut auth: TokenAuth) -> Result<Json<Vec<Note>>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    // Going through the record makes sure we return a 404 for non-existing records instead of an empty
+    // list
+    let record = FullRecord::by_id(record_id, &mut auth.connection).await?;
+
+    Ok(Json(record.notes))
+}
+
+#[rocket::get("/<record_id>/notes/<note_id>")]
+pub async fn note(record_id: i32, note_id: i32, mut auth: TokenAuth) -> Result<Tagged<Note>> {
+    auth.require_permission(LIST_HELPER)?;
+
+    Ok(Tagged(Note::by_id(record_id, note_id, &mut auth.connection).await?))
+}
+
 #[rocket::post("/<record_id>/notes", data = "<data>")]
 pub async fn add_note(record_id: i32, mut auth: TokenAuth, data: Json<NewNote>) -> Result<Response2<Tagged<Note>>> {
     auth.require_permission(LIST_HELPER)?;
@@ -263,7 +783,11 @@ pub async fn delete_note(record_id: i32, note_id: i32, mut auth: TokenAuth) -> R
     Ok(Status::NoContent)
 }
 
-async fn validate(record_id: i32, video: String, body: serde_json::Value, mut connection: PoolConnection<Postgres>) {
+/// Checks whether the video of a new submission is reachable, publishing the submission event if it
+/// is and deleting the submission otherwise
+async fn validate(record: FullRecord, video: String, events: ListEvents, mut connection: PoolConnection<Postgres>) {
+    let record_id = record.id;
+
     debug!("Verifying that submission {} with video {} actually is valid", record_id, video);
 
     match reqwest::get(&video).await {
@@ -271,13 +795,13 @@ async fn validate(record_id: i32, video: String, body: serde_json::Value, mut co
             let status = response.status().as_u16();
 
             if status >= 200 && status < 400 {
-                debug!("GET request yielded some sort of successful response, executing webhook");
+                debug!("GET request yielded some sort of successful response, publishing submission");
 
-                execute_webhook(body).await;
+                events.publish(ListEvent::RecordSubmitted { record: Arc::new(record) });
             } else {
                 warn!("Server response to 'GET {}' was {:?}, deleting submission!", video, response);
 
-                match FullRecord::delete_by_id(record_id, &mut connection).await {
+                match FullRecord::delete_by_id(record_id, "Video unreachable", None, &mut connection).await {
                     Ok(_) => (),
                     Err(error) => error!("INTERNAL SERVER ERROR: Failure to delete record - {:?}!", error),
                 }
@@ -289,61 +813,10 @@ async fn validate(record_id: i32, video: String, body: serde_json::Value, mut co
                 error
             );
 
-            match FullRecord::delete_by_id(record_id, &mut connection).await {
+            match FullRecord::delete_by_id(record_id, "Video unreachable", None, &mut connection).await {
                 Ok(_) => (),
                 Err(error) => error!("INTERNAL SERVER ERROR: Failure to delete record - {:?}!", error),
             }
         },
     }
 }
-
-async fn execute_webhook(body: serde_json::Value) {
-    if let Some(ref webhook_url) = crate::config::submission_webhook() {
-        match reqwest::Client::new()
-            .post(webhook_url)
-            .header("Content-Type", "application/json")
-            .body(body.to_string())
-            .send()
-            .await
-        {
-            Err(error) => error!("INTERNAL SERVER ERROR: Failure to execute discord webhook: {:?}", error),
-            Ok(_) => debug!("Successfully executed discord webhook"),
-        }
-    } else {
-        warn!("Trying to execute webhook, though no link was configured!");
-    }
-}
-
-fn webhook_embed(record: &FullRecord) -> serde_json::Value {
-    let mut payload = serde_json::json!({
-        "content": format!("**New record submitted! ID: {}**", record.id),
-        "embeds": [
-            {
-                "type": "rich",
-                "title": format!("{}% on {}", record.progress, record.demon.name),
-                "description": format!("{} just got {}% on {}! Go add their record!", record.player.name, record.progress, record.demon.name),
-                "footer": {
-                    "text": format!("This record has been submitted by submitter #{}", record.submitter.map(|s|s.id).unwrap_or(1))
-                },
-                "author": {
-                    "name": format!("{} (ID: {})", record.player.name, record.player.id),
-                    "url": record.video
-                },
-                "thumbnail": {
-                    "url": "https://cdn.discordapp.com/avatars/277391246035648512/b03c85d94dc02084c413a7fdbe2cea79.webp?size=1024"
-                },
-            }
-        ]
-    });
-
-    if let Some(ref video) = record.video {
-        payload["embeds"][0]["fields"] = serde_json::json! {
-            [{
-                "name": "Video Proof:",
-                "value": video
-            }]
-        };
-    }
-
-    payload
-}
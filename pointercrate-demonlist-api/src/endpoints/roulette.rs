@@ -0,0 +1,33 @@
+use pointercrate_core::{error::CoreError, pool::PointercratePool};
+use pointercrate_core_api::{error::Result, query::Query};
+use pointercrate_demonlist::{
+    error::DemonlistError,
+    player::claim::PlayerClaim,
+    roulette::{Roulette, RouletteOptions},
+};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{serde::json::Json, State};
+
+/// Generates a random sequence of demons for the roulette game
+///
+/// With `exclude_beaten=true`, demons beaten by the player the authenticated user has a verified
+/// claim on are left out.
+#[rocket::get("/")]
+pub async fn roulette(auth: Option<TokenAuth>, options: Query<RouletteOptions>, pool: &State<PointercratePool>) -> Result<Json<Roulette>> {
+    let options = options.0;
+
+    if !options.exclude_beaten {
+        let mut connection = pool.read_connection().await?;
+
+        return Ok(Json(Roulette::generate(&options, None, &mut connection).await?))
+    }
+
+    let mut auth = auth.ok_or(CoreError::Unauthorized)?;
+
+    let player = match PlayerClaim::by_user(auth.user.inner().id, &mut auth.connection).await? {
+        Some(claim) if claim.verified => claim.player,
+        _ => return Err(DemonlistError::ClaimUnverified.into()),
+    };
+
+    Ok(Json(Roulette::generate(&options, Some(&player), &mut auth.connection).await?))
+}
@@ -0,0 +1,70 @@
+use pointercrate_core::{error::CoreError, pool::PointercratePool};
+use pointercrate_core_api::error::Result;
+use pointercrate_demonlist::{demon::MinimalDemon, player::DatabasePlayer, search, LIST_HELPER};
+use pointercrate_user::{User, MODERATOR};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{serde::json::Json, State};
+use serde::Serialize;
+
+/// A single search hit, tagged with the kind of object it is
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchResult {
+    Demon(MinimalDemon),
+    Player(DatabasePlayer),
+    User(User),
+}
+
+/// Searches demons, players and users by name
+///
+/// At most `limit` (default 10) results of each kind are returned, demons first, then players,
+/// then users. Banned players are only included for list helpers, and users only for moderators.
+#[rocket::get("/?<q>&<limit>")]
+pub async fn search(
+    q: &str, limit: Option<u8>, auth: Option<TokenAuth>, pool: &State<PointercratePool>,
+) -> Result<Json<Vec<SearchResult>>> {
+    let limit = limit.unwrap_or(10);
+
+    if !(1..=100).contains(&limit) {
+        return Err(CoreError::InvalidPaginationLimit.into())
+    }
+
+    let term = q.trim();
+
+    if term.is_empty() {
+        return Ok(Json(Vec::new()))
+    }
+
+    let limit = i64::from(limit);
+    let (include_banned, include_users) = match auth {
+        Some(ref auth) => (auth.has_permission(LIST_HELPER), auth.has_permission(MODERATOR)),
+        None => (false, false),
+    };
+
+    let mut connection = pool.read_connection().await?;
+    let mut results = Vec::new();
+
+    results.extend(
+        search::search_demons(term, limit, &mut connection)
+            .await?
+            .into_iter()
+            .map(SearchResult::Demon),
+    );
+    results.extend(
+        search::search_players(term, limit, include_banned, &mut connection)
+            .await?
+            .into_iter()
+            .map(SearchResult::Player),
+    );
+
+    if include_users {
+        results.extend(
+            User::search(term, limit, &mut connection)
+                .await?
+                .into_iter()
+                .map(SearchResult::User),
+        );
+    }
+
+    Ok(Json(results))
+}
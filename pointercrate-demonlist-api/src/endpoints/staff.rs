@@ -0,0 +1,135 @@
+//! Endpoints for managing the list team
+//!
+//! Promoting and demoting members of the list team is just assigning permissions, which is also
+//! possible via `/api/v1/users/<user_id>/permissions`. These endpoints however only ever touch the
+//! list permissions, and refuse to leave the list without any list administrator.
+
+use log::info;
+use pointercrate_core::{error::CoreError, permission::Permission, pool::PointercratePool};
+use pointercrate_core_api::{error::Result, etag::Tagged};
+use pointercrate_demonlist::{error::DemonlistError, LIST_ADMINISTRATOR, LIST_HELPER, LIST_MODERATOR};
+use pointercrate_user::{error::UserError, PatchUser, User};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The list team, with every member only listed under their highest role
+#[derive(Serialize)]
+pub struct Staff {
+    administrators: Vec<User>,
+    moderators: Vec<User>,
+    helpers: Vec<User>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StaffRole {
+    Helper,
+    Moderator,
+    Administrator,
+}
+
+impl StaffRole {
+    fn permission(self) -> Permission {
+        match self {
+            StaffRole::Helper => LIST_HELPER,
+            StaffRole::Moderator => LIST_MODERATOR,
+            StaffRole::Administrator => LIST_ADMINISTRATOR,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RoleAssignment {
+    role: StaffRole,
+}
+
+#[rocket::get("/")]
+pub async fn staff(pool: &State<PointercratePool>) -> Result<Json<Staff>> {
+    let mut connection = pool.read_connection().await?;
+
+    let administrators = User::by_permission(LIST_ADMINISTRATOR, &mut connection).await?;
+    let moderators = User::by_permission(LIST_MODERATOR, &mut connection)
+        .await?
+        .into_iter()
+        .filter(|user| !user.has_permission(LIST_ADMINISTRATOR))
+        .collect();
+    let helpers = User::by_permission(LIST_HELPER, &mut connection)
+        .await?
+        .into_iter()
+        .filter(|user| !user.has_permission(LIST_ADMINISTRATOR) && !user.has_permission(LIST_MODERATOR))
+        .collect();
+
+    Ok(Json(Staff {
+        administrators,
+        moderators,
+        helpers,
+    }))
+}
+
+/// Makes the given user a member of the list team with exactly the given role, promoting or
+/// demoting them if they already are part of the team
+#[rocket::put("/<user_id>", data = "<assignment>")]
+pub async fn put_staff(auth: TokenAuth, user_id: i32, assignment: Json<RoleAssignment>) -> Result<Tagged<User>> {
+    Ok(Tagged(assign_role(auth, user_id, Some(assignment.role)).await?))
+}
+
+/// Removes the given user from the list team
+#[rocket::delete("/<user_id>")]
+pub async fn delete_staff(auth: TokenAuth, user_id: i32) -> Result<Tagged<User>> {
+    Ok(Tagged(assign_role(auth, user_id, None).await?))
+}
+
+async fn assign_role(mut auth: TokenAuth, user_id: i32, role: Option<StaffRole>) -> Result<User> {
+    let assignable = auth.assignable_permissions();
+
+    if assignable.is_empty() {
+        return Err(CoreError::Forbidden.into())
+    }
+
+    if user_id == auth.user.inner().id {
+        return Err(UserError::PatchSelf.into())
+    }
+
+    let user = User::by_id(user_id, &mut auth.connection).await?;
+
+    let list_permissions = LIST_HELPER.bit() | LIST_MODERATOR.bit() | LIST_ADMINISTRATOR.bit();
+    let permissions = (user.permissions & !list_permissions) | role.map(|role| role.permission().bit()).unwrap_or(0x0);
+
+    let non_assignable: HashSet<Permission> = auth
+        .permissions
+        .bits_to_permissions(user.permissions ^ permissions)
+        .into_iter()
+        .filter(|permission| !assignable.contains(permission))
+        .collect();
+
+    if !non_assignable.is_empty() {
+        return Err(UserError::PermissionNotAssignable { non_assignable }.into())
+    }
+
+    if user.has_permission(LIST_ADMINISTRATOR) && permissions & LIST_ADMINISTRATOR.bit() == 0 {
+        let administrators = User::by_permission(LIST_ADMINISTRATOR, &mut auth.connection).await?;
+
+        if administrators.len() <= 1 {
+            return Err(DemonlistError::LastListAdministrator.into())
+        }
+    }
+
+    info!("Changing list team role of {} to {:?}", user, role);
+
+    let user = user
+        .apply_patch(
+            PatchUser {
+                display_name: None,
+                youtube_channel: None,
+                permissions: Some(permissions),
+            },
+            &mut auth.connection,
+        )
+        .await?;
+
+    auth.commit().await?;
+
+    Ok(user)
+}
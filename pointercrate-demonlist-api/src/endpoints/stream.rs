@@ -0,0 +1,34 @@
+use crate::events::ListEvents;
+use log::warn;
+use rocket::{
+    response::stream::{Event, EventStream},
+    tokio::{select, sync::broadcast::error::RecvError},
+    Shutdown, State,
+};
+
+/// Server-sent event stream of changes to the list, see [`crate::events::PublicListEvent`]
+#[rocket::get("/")]
+pub fn stream(events: &State<ListEvents>, mut shutdown: Shutdown) -> EventStream![] {
+    let mut receiver = events.subscribe();
+
+    EventStream! {
+        loop {
+            let event = select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Event stream subscriber lagged behind, skipping {} events", skipped);
+
+                        continue
+                    },
+                },
+                _ = &mut shutdown => break,
+            };
+
+            if let Some(event) = event.public() {
+                yield Event::json(&event);
+            }
+        }
+    }
+}
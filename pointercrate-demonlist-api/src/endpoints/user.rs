@@ -0,0 +1,64 @@
+use pointercrate_core::{error::CoreError, pool::PointercratePool};
+use pointercrate_core_api::{error::Result, etag::Tagged};
+use pointercrate_demonlist::{
+    error::DemonlistError,
+    export::DemonlistUserData,
+    player::claim::{ClaimBy, PlayerClaim},
+};
+use pointercrate_user::{ApiKey, Session, User, ADMINISTRATOR, MODERATOR};
+use pointercrate_user_api::auth::TokenAuth;
+use rocket::{serde::json::Json, State};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct UserDataExport {
+    user: User,
+    sessions: Vec<Session>,
+    api_keys: Vec<ApiKey>,
+
+    #[serde(flatten)]
+    demonlist: DemonlistUserData,
+}
+
+#[rocket::get("/<user_id>/export")]
+pub async fn export(user_id: i32, auth: TokenAuth, pool: &State<PointercratePool>) -> Result<Json<UserDataExport>> {
+    if auth.user.inner().id != user_id && !auth.has_permission(ADMINISTRATOR) {
+        return Err(CoreError::Forbidden.into())
+    }
+
+    Ok(Json(export_user_data(user_id, pool).await?))
+}
+
+/// Export of all data tied to the authenticated user, mounted at `/api/v1/auth/me/data`
+#[rocket::get("/me/data")]
+pub async fn my_data(auth: TokenAuth, pool: &State<PointercratePool>) -> Result<Json<UserDataExport>> {
+    Ok(Json(export_user_data(auth.user.inner().id, pool).await?))
+}
+
+async fn export_user_data(user_id: i32, pool: &PointercratePool) -> Result<UserDataExport> {
+    // Use a separate, read-only transaction so that all data is read from the same snapshot. The
+    // isolation level has to be set before the first query, which the authentication guard already
+    // ran on its own transaction.
+    let mut connection = pool.transaction().await?;
+
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut connection)
+        .await
+        .map_err(DemonlistError::from)?;
+
+    Ok(UserDataExport {
+        user: User::by_id(user_id, &mut connection).await?,
+        sessions: Session::of_user(user_id, &mut connection).await?,
+        api_keys: ApiKey::of_user(user_id, &mut connection).await?,
+        demonlist: pointercrate_demonlist::export::data_of_user(user_id, &mut connection).await?,
+    })
+}
+
+#[rocket::get("/<user_id>/claim")]
+pub async fn claim(user_id: i32, mut auth: TokenAuth) -> Result<Tagged<Option<ClaimBy>>> {
+    if auth.user.inner().id != user_id {
+        auth.require_permission(MODERATOR)?;
+    }
+
+    Ok(Tagged(PlayerClaim::by_user(user_id, &mut auth.connection).await?))
+}
@@ -0,0 +1,191 @@
+//! Module containing the internal event bus for changes to the list
+//!
+//! Endpoints publish a [`ListEvent`] for every change that something else might be interested in,
+//! and side effects (webhooks, cache invalidation, inbox notifications, the `/api/v1/stream/`
+//! endpoint) subscribe to the bus instead of being called from the endpoints directly.
+//!
+//! Events are only published after the transaction causing them has been committed. Nothing is
+//! persisted. Subscribers via [`ListEvents::subscribe`] that fall behind or disconnect simply miss
+//! all events published in the meantime, which is fine for caches and the stream endpoint.
+//! Subscribers that must see every event (webhooks, inbox notifications) use
+//! [`ListEvents::subscribe_lossless`] instead, which queues events for them without bound.
+
+use log::debug;
+use pointercrate_demonlist::{demon::MinimalDemon, player::DatabasePlayer, record::FullRecord};
+use rocket::tokio::sync::{
+    broadcast::{self, Receiver, Sender},
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// How many events a slow subscriber can fall behind before it starts missing events
+const CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum ListEvent {
+    DemonAdded {
+        demon: MinimalDemon,
+    },
+    DemonMoved {
+        demon: MinimalDemon,
+        from: i16,
+    },
+
+    /// A new submission was received, and its video was verified to be reachable
+    RecordSubmitted {
+        record: Arc<FullRecord>,
+    },
+    RecordApproved {
+        record: Arc<FullRecord>,
+    },
+
+    /// A record was rejected. It might have been approved before.
+    RecordRejected {
+        record: Arc<FullRecord>,
+    },
+    PlayerBanned {
+        player: DatabasePlayer,
+    },
+
+    /// A record was modified, deleted or restored in a way not covered by the events above (e.g.
+    /// its progress or video changed)
+    RecordModified {
+        record_id: i32,
+    },
+
+    /// A player was modified in a way not covered by the events above (e.g. renamed or merged)
+    PlayerModified {
+        player_id: i32,
+    },
+
+    /// A creator was added to or removed from a demon
+    CreatorsChanged {
+        demon_id: i32,
+    },
+
+    /// The video proof of an approved record was found to be no longer available
+    VideoDead {
+        record: Arc<FullRecord>,
+    },
+
+    /// A user's claim on a player was verified
+    ClaimVerified {
+        user_id: i32,
+        player: DatabasePlayer,
+    },
+}
+
+/// The form in which [`ListEvent`]s are sent to clients of the `/api/v1/stream/` endpoint
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PublicListEvent<'a> {
+    DemonAdded {
+        demon: &'a MinimalDemon,
+    },
+    DemonMoved {
+        demon: &'a MinimalDemon,
+        from: i16,
+    },
+    RecordApproved {
+        id: i32,
+        progress: i16,
+        video: &'a Option<String>,
+        player: &'a DatabasePlayer,
+        demon: &'a MinimalDemon,
+    },
+}
+
+impl ListEvent {
+    pub fn record_approved(record: &FullRecord) -> Self {
+        ListEvent::RecordApproved {
+            record: Arc::new(record.clone()),
+        }
+    }
+
+    pub fn record_rejected(record: &FullRecord) -> Self {
+        ListEvent::RecordRejected {
+            record: Arc::new(record.clone()),
+        }
+    }
+
+    /// Whether this event can change the state of the list (positions, scores, rankings)
+    pub fn changes_list(&self) -> bool {
+        !matches!(
+            self,
+            ListEvent::RecordSubmitted { .. } | ListEvent::VideoDead { .. } | ListEvent::ClaimVerified { .. }
+        )
+    }
+
+    /// The form in which this event is shown to the public, if it is public at all
+    pub fn public(&self) -> Option<PublicListEvent> {
+        match self {
+            ListEvent::DemonAdded { demon } => Some(PublicListEvent::DemonAdded { demon }),
+            ListEvent::DemonMoved { demon, from } => Some(PublicListEvent::DemonMoved { demon, from: *from }),
+            ListEvent::RecordApproved { record } =>
+                Some(PublicListEvent::RecordApproved {
+                    id: record.id,
+                    progress: record.progress,
+                    video: &record.video,
+                    player: &record.player,
+                    demon: &record.demon,
+                }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ListEvents {
+    broadcast: Sender<ListEvent>,
+    lossless: Arc<Mutex<Vec<UnboundedSender<ListEvent>>>>,
+}
+
+impl ListEvents {
+    pub fn new() -> Self {
+        ListEvents {
+            broadcast: broadcast::channel(CAPACITY).0,
+            lossless: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn publish(&self, event: ListEvent) {
+        // Sending fails if the receiving end was dropped, in which case we can forget about the
+        // subscriber
+        self.lossless
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+
+        // Sending only fails if nobody is currently subscribed, in which case the event can simply be
+        // dropped
+        if let Ok(subscribers) = self.broadcast.send(event) {
+            debug!("Published list event to {} subscribers", subscribers);
+        }
+    }
+
+    /// Subscribes to the bus, missing events if the subscriber falls behind by more than
+    /// [`CAPACITY`] events
+    pub fn subscribe(&self) -> Receiver<ListEvent> {
+        self.broadcast.subscribe()
+    }
+
+    /// Closes the bus for all subscribers via [`ListEvents::subscribe_lossless`]. They still
+    /// receive all events published so far, after which their receivers report the end of the
+    /// stream.
+    ///
+    /// Meant for shutting down, as events published afterwards are not delivered to them anymore.
+    pub fn close(&self) {
+        self.lossless.lock().unwrap().clear();
+    }
+
+    /// Subscribes to the bus without ever missing an event, no matter how far the subscriber
+    /// falls behind
+    pub fn subscribe_lossless(&self) -> UnboundedReceiver<ListEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        self.lossless.lock().unwrap().push(sender);
+
+        receiver
+    }
+}
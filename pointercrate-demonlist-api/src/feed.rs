@@ -0,0 +1,65 @@
+//! Module for rendering recent list changes as an Atom feed (RFC 4287), to allow following the list
+//! in feed readers and RSS bots
+
+use crate::config;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use pointercrate_demonlist::demon::audit::ListChange;
+
+/// How many changes are included in the feed
+pub const FEED_SIZE: i64 = 50;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn timestamp(time: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_utc(time, Utc).to_rfc3339()
+}
+
+fn describe(change: &ListChange) -> String {
+    match change.from {
+        None => format!("{} was added at #{}", change.demon_name, change.to),
+        Some(from) if from > change.to => format!("{} was raised from #{} to #{}", change.demon_name, from, change.to),
+        Some(from) => format!("{} was lowered from #{} to #{}", change.demon_name, from, change.to),
+    }
+}
+
+/// Renders the given changes, which need to be ordered newest first, as an Atom feed
+pub fn render(changes: &[ListChange]) -> String {
+    let site = config::site_url();
+    let self_link = format!("{}/api/v2/demons/feed.xml", site);
+    let updated = changes
+        .first()
+        .map(|change| timestamp(change.time))
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+    let mut feed = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n<title>Demonlist \
+         changes</title>\n<id>{self_link}</id>\n<link rel=\"self\" href=\"{self_link}\"/>\n<link \
+         href=\"{site}/demonlist/\"/>\n<updated>{updated}</updated>\n<author><name>List Team</name></author>\n",
+        self_link = escape(&self_link),
+        site = escape(&site),
+        updated = updated
+    );
+
+    for change in changes {
+        let link = format!("{}/demonlist/permalink/{}", site, change.demon_id);
+        let description = escape(&describe(change));
+
+        feed.push_str(&format!(
+            "<entry>\n<id>{link}#{unix}</id>\n<title>{description}</title>\n<link \
+             href=\"{link}\"/>\n<updated>{updated}</updated>\n<summary>{description}</summary>\n</entry>\n",
+            link = escape(&link),
+            unix = change.time.timestamp(),
+            description = description,
+            updated = timestamp(change.time)
+        ));
+    }
+
+    feed.push_str("</feed>\n");
+    feed
+}
@@ -0,0 +1,51 @@
+//! Module for storing uploaded demon thumbnails
+//!
+//! Images are stored via an [`ImageStorage`] implementation. Currently, the only one is
+//! [`LocalStorage`], which writes images to a directory whose contents are expected to be served
+//! by the reverse proxy in front of pointercrate. Other backends (e.g. S3-compatible object
+//! storage) only need to implement [`ImageStorage`] and be managed in [`crate::setup`] instead.
+
+use crate::config;
+use log::info;
+use pointercrate_demonlist::demon::image::ImageFormat;
+use rocket::tokio::fs;
+use std::{io, path::PathBuf};
+
+#[rocket::async_trait]
+pub trait ImageStorage: Send + Sync {
+    /// Stores the given image under the given name, returning the URL under which it is
+    /// publicly available
+    async fn store(&self, name: &str, format: ImageFormat, data: &[u8]) -> io::Result<String>;
+}
+
+pub struct LocalStorage {
+    directory: PathBuf,
+    base_url: String,
+}
+
+impl LocalStorage {
+    /// Constructs a [`LocalStorage`] from the configuration, if a [`config::demon_image_directory`]
+    /// is set
+    pub fn from_config() -> Option<LocalStorage> {
+        config::demon_image_directory().map(|directory| {
+            LocalStorage {
+                directory: PathBuf::from(directory),
+                base_url: config::demon_image_url(),
+            }
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl ImageStorage for LocalStorage {
+    async fn store(&self, name: &str, format: ImageFormat, data: &[u8]) -> io::Result<String> {
+        let file_name = format!("{}.{}", name, format.extension());
+
+        fs::create_dir_all(&self.directory).await?;
+        fs::write(self.directory.join(&file_name), data).await?;
+
+        info!("Stored {} byte image {} in {}", data.len(), file_name, self.directory.display());
+
+        Ok(format!("{}/{}", self.base_url.trim_end_matches('/'), file_name))
+    }
+}
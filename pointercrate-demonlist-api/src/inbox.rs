@@ -0,0 +1,70 @@
+//! Module putting notifications about list events into the inbox of the affected users
+//!
+//! Users are notified when a record of their verified claimed player is approved or rejected, and
+//! when their claim on a player is verified. Since events are only published after the change was
+//! committed, a failure to create the notification does not undo the change; it is only logged.
+
+use crate::events::{ListEvent, ListEvents};
+use log::{error, info};
+use pointercrate_demonlist::{error::Result, player::claim::PlayerClaim};
+use pointercrate_user::{Notification, NotificationKind};
+use rocket::tokio;
+use sqlx::{Pool, Postgres};
+
+pub fn notify_on(events: &ListEvents, pool: Pool<Postgres>) {
+    let mut receiver = events.subscribe_lossless();
+
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            if let Err(err) = notify(&event, &pool).await {
+                error!(
+                    "INTERNAL SERVER ERROR: Failed to create inbox notification for {:?}: {:?}",
+                    event, err
+                );
+            }
+        }
+
+        info!("List event bus closed, no longer creating inbox notifications");
+    });
+}
+
+async fn notify(event: &ListEvent, pool: &Pool<Postgres>) -> Result<()> {
+    let mut connection = pool.acquire().await?;
+
+    let (user_id, kind, message) = match event {
+        ListEvent::RecordApproved { record } | ListEvent::RecordRejected { record } => {
+            let claim = match PlayerClaim::verified_claim_on(record.player.id, &mut connection).await? {
+                Some(claim) => claim,
+                None => return Ok(()),
+            };
+
+            let decision = match event {
+                ListEvent::RecordApproved { .. } => "approved",
+                _ => "rejected",
+            };
+
+            (
+                claim.user_id,
+                NotificationKind::RecordDecision,
+                format!(
+                    "Your {}% record on {} (ID: {}) has been {}",
+                    record.progress, record.demon.name, record.id, decision
+                ),
+            )
+        },
+        ListEvent::ClaimVerified { user_id, player } =>
+            (
+                *user_id,
+                NotificationKind::ClaimVerified,
+                format!("Your claim on player {} has been verified", player.name),
+            ),
+        _ => return Ok(()),
+    };
+
+    // The user crate's errors cannot be converted into ours, so report them here
+    if let Err(err) = Notification::create(user_id, kind, &message, &mut connection).await {
+        error!("INTERNAL SERVER ERROR: Failed to notify user {}: {:?}", user_id, err);
+    }
+
+    Ok(())
+}
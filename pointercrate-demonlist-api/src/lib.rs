@@ -1,22 +1,142 @@
-use crate::{endpoints::misc, ratelimits::DemonlistRatelimits};
+use crate::{
+    cache::ListCache,
+    endpoints::misc,
+    events::ListEvents,
+    images::{ImageStorage, LocalStorage},
+    ratelimits::DemonlistRatelimits,
+};
 use chrono::Duration;
-use pointercrate_core::pool::PointercratePool;
+use log::{error, info, warn};
+use pointercrate_core::{permission::PermissionsManager, pool::PointercratePool};
+use pointercrate_demonlist::{list_config::ListConfig, score::ScoreFormula};
 use pointercrate_integrate::gd::PgCache;
-use rocket::{Build, Rocket};
+use pointercrate_user_api::mail::Mailer;
+use rocket::{fairing::AdHoc, tokio, Build, Rocket};
 
+mod archive;
+pub(crate) mod cache;
+pub(crate) mod captcha;
 pub(crate) mod config;
+mod dead_links;
+mod digest;
+mod docs;
+pub(crate) mod embed;
 mod endpoints;
+pub(crate) mod events;
+pub(crate) mod feed;
+pub(crate) mod images;
+mod inbox;
 pub(crate) mod pages;
 pub(crate) mod ratelimits;
+pub mod webhook;
+pub(crate) mod youtube;
 
 pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
+    // Report misconfiguration right away, instead of on the first request that needs the value in
+    // question
+    if let Err(err) = pointercrate_core::config::load() {
+        panic!("Invalid configuration: {}", err)
+    }
+
+    if let Err(err) = pointercrate_demonlist::config::validate().and_then(|_| config::validate()) {
+        panic!("Invalid configuration: {}", err)
+    }
+
+    // Read the score formula right away, so that a misconfiguration does not only show once the first
+    // record is approved
+    ScoreFormula::configured();
+
+    let permissions = rocket.state::<PermissionsManager>().unwrap();
+    for role in pointercrate_demonlist::roles() {
+        permissions.register_role(role);
+    }
+
     let ratelimits = DemonlistRatelimits::new();
-    let dash_rs = PgCache::new(rocket.state::<PointercratePool>().unwrap().clone_inner(), Duration::minutes(30));
+    let dash_rs =
+        PgCache::new(rocket.state::<PointercratePool>().unwrap().clone_inner(), Duration::minutes(30)).with_mirror(config::gd_mirror());
+
+    let events = ListEvents::new();
+    let cache = ListCache::new();
+
+    cache.invalidate_on(&events);
+    let webhook_deliveries = webhook::notify_on(&events);
+    inbox::notify_on(&events, rocket.state::<PointercratePool>().unwrap().clone_inner());
+
+    dead_links::spawn(rocket.state::<PointercratePool>().unwrap().clone_inner(), events.clone());
+    archive::spawn(rocket.state::<PointercratePool>().unwrap().clone_inner());
+
+    // Pages do not load the list configuration themselves, so make sure they use the sizes stored in
+    // the database right from the start, and pick up changes made through other instances
+    let pool = rocket.state::<PointercratePool>().unwrap().clone_inner();
+    tokio::spawn(async move {
+        let interval = std::time::Duration::from_secs(config::list_config_refresh_interval());
+
+        loop {
+            let result = match pool.acquire().await {
+                Ok(mut connection) => ListConfig::load(&mut connection).await.map(|_| ()),
+                Err(err) => Err(err.into()),
+            };
+
+            if let Err(err) = result {
+                error!("Failed to load list configuration, keeping previous list sizes: {}", err);
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    tokio::spawn(dash_rs.clone().refresh_periodically(
+        reqwest::Client::new(),
+        std::time::Duration::from_secs(config::gd_refresh_interval()),
+    ));
+
+    // Uploading thumbnails is only possible if we have somewhere to put them
+    let rocket = match LocalStorage::from_config() {
+        Some(storage) =>
+            rocket
+                .manage::<Box<dyn ImageStorage>>(Box::new(storage))
+                .mount("/api/v2/demons/", rocket::routes![endpoints::demon::put_image]),
+        None => rocket,
+    };
 
-    rocket
+    pointercrate_core_api::docs::register_schemas(rocket, docs::schemas())
+        // The mailer is managed by the user API, which might be set up after us
+        .attach(AdHoc::on_liftoff("Notification digest", |rocket| {
+            Box::pin(async move {
+                match (rocket.state::<PointercratePool>(), rocket.state::<Mailer>()) {
+                    (Some(pool), Some(mailer)) => digest::spawn(pool.clone_inner(), mailer.clone()),
+                    _ => error!("Not sending notification digests, since the user API has not been set up"),
+                }
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Webhook delivery on shutdown", {
+            let events = events.clone();
+
+            move |rocket| {
+                let shutdown = rocket.shutdown();
+
+                Box::pin(async move {
+                    tokio::spawn(async move {
+                        shutdown.await;
+
+                        finish_webhooks(events, webhook_deliveries).await
+                    });
+                })
+            }
+        }))
         .manage(ratelimits)
         .manage(dash_rs)
-        .mount("/api/v1/list_information/", rocket::routes![misc::list_information])
+        .manage(events)
+        .manage(cache)
+        .mount("/api/v1/list_information/", rocket::routes![
+            misc::list_information,
+            misc::patch_list_information
+        ])
+        .mount("/api/v1/lists/", rocket::routes![
+            endpoints::list::lists,
+            endpoints::list::get,
+            endpoints::list::paginate_demons
+        ])
         .mount("/api/v1/submitters/", rocket::routes![
             endpoints::submitter::paginate,
             endpoints::submitter::get,
@@ -25,13 +145,26 @@ pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
         .mount("/api/v1/records/", rocket::routes![
             endpoints::record::add_note,
             endpoints::record::audit,
+            endpoints::record::claim,
+            endpoints::record::clean_redundant_submissions,
             endpoints::record::delete,
+            endpoints::record::restore,
             endpoints::record::delete_note,
+            endpoints::record::export,
             endpoints::record::get,
+            endpoints::record::oembed,
+            endpoints::record::note,
+            endpoints::record::notes,
             endpoints::record::paginate,
             endpoints::record::unauthed_pagination,
             endpoints::record::patch,
+            endpoints::record::transition_status,
+            endpoints::record::bulk_transition_status,
             endpoints::record::patch_note,
+            endpoints::record::queue,
+            endpoints::record::redundant_submissions,
+            endpoints::record::release_claim,
+            endpoints::record::revalidate_videos,
             endpoints::record::submit
         ])
         .mount("/api/v1/players/", rocket::routes![
@@ -39,7 +172,15 @@ pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
             endpoints::player::paginate,
             endpoints::player::unauthed_paginate,
             endpoints::player::patch,
+            endpoints::player::merge,
+            endpoints::player::paginate_records,
+            endpoints::player::packs,
+            endpoints::player::timeline,
+            endpoints::player::aliases,
+            endpoints::player::post_alias,
+            endpoints::player::delete_alias,
             endpoints::player::ranking,
+            endpoints::player::refresh_ranking,
             endpoints::player::put_claim,
             endpoints::player::patch_claim,
             endpoints::player::paginate_claims,
@@ -49,17 +190,54 @@ pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
         .mount("/api/v1/nationalities/", rocket::routes![
             endpoints::nationality::subdivisions,
             endpoints::nationality::ranking,
+            endpoints::nationality::national_ranking,
             endpoints::nationality::nation
         ])
+        .mount("/api/v1/packs/", rocket::routes![
+            endpoints::pack::packs,
+            endpoints::pack::get,
+            endpoints::pack::post,
+            endpoints::pack::patch,
+            endpoints::pack::delete
+        ])
+        .mount("/api/v1/demon_proposals/", rocket::routes![
+            endpoints::proposal::proposals,
+            endpoints::proposal::get,
+            endpoints::proposal::propose,
+            endpoints::proposal::vote
+        ])
+        .mount("/api/v1/roulette/", rocket::routes![endpoints::roulette::roulette])
+        .mount("/api/v1/search/", rocket::routes![endpoints::search::search])
+        .mount("/api/v1/staff/", rocket::routes![
+            endpoints::staff::staff,
+            endpoints::staff::put_staff,
+            endpoints::staff::delete_staff
+        ])
+        .mount("/api/v1/stream/", rocket::routes![endpoints::stream::stream])
+        .mount("/api/v1/users/", rocket::routes![endpoints::user::export, endpoints::user::claim])
+        .mount("/api/v1/auth/", rocket::routes![endpoints::user::my_data])
         .mount("/api/v2/demons/", rocket::routes![
             endpoints::demon::get,
             endpoints::demon::paginate,
             endpoints::demon::paginate_listed,
+            endpoints::demon::listed_at,
+            endpoints::demon::feed,
+            endpoints::demon::paginate_legacy,
+            endpoints::demon::changed_since,
             endpoints::demon::audit,
+            endpoints::demon::record_neighbors,
+            endpoints::demon::paginate_records,
+            endpoints::demon::first_victor,
+            endpoints::demon::stats,
             endpoints::demon::patch,
             endpoints::demon::post,
+            endpoints::demon::merge,
+            endpoints::demon::creators,
             endpoints::demon::post_creator,
-            endpoints::demon::delete_creator
+            endpoints::demon::delete_creator,
+            endpoints::demon::aliases,
+            endpoints::demon::post_alias,
+            endpoints::demon::delete_alias
         ])
         .mount("/demonlist/", rocket::routes![
             pages::overview,
@@ -68,6 +246,25 @@ pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
             pages::nation_stats_viewer,
             pages::demon_page,
             pages::demon_permalink,
+            pages::record_page,
             pages::heatmap_css
         ])
 }
+
+/// Delivers the webhooks for all events published before shutdown was triggered, waiting at most
+/// for [`config::shutdown_deadline`] seconds
+///
+/// Rocket only waits for its `shutdown.grace` period before stopping, so it should be configured to
+/// be at least as long as the deadline.
+async fn finish_webhooks(events: ListEvents, deliveries: webhook::Deliveries) {
+    info!("Shutting down, waiting for pending webhook deliveries");
+
+    events.close();
+
+    let deadline = std::time::Duration::from_secs(config::shutdown_deadline());
+
+    match tokio::time::timeout(deadline, deliveries.finished()).await {
+        Ok(()) => info!("All pending webhooks delivered"),
+        Err(_) => warn!("Giving up on pending webhook deliveries after {:?}", deadline),
+    }
+}
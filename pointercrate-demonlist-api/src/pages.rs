@@ -1,21 +1,25 @@
+use crate::{cache::ListCache, config};
 use rocket::{response::Redirect, State};
 
-use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 use pointercrate_core::{audit::AuditLogEntryType, pool::PointercratePool};
 use pointercrate_core_api::{
     error::Result,
-    response::{Page, Response2},
+    response::{Page, PageRenderer, RenderedPage, Response2},
 };
 use pointercrate_demonlist::{
-    demon::{audit::audit_log_for_demon, current_list, list_at, FullDemon, MinimalDemon},
+    demon::{audit::audit_log_for_demon, beginning_of_time, list_at, MinimalDemon},
     error::DemonlistError,
+    list::{DemonList, CLASSIC_LIST},
     nationality::Nationality,
+    record::{FullRecord, RecordStatus},
     LIST_ADMINISTRATOR, LIST_HELPER, LIST_MODERATOR,
 };
 use pointercrate_demonlist_pages::{
     components::{team::Team, time_machine::Tardis},
     demon_page::{DemonMovement, DemonPage},
     overview::OverviewPage,
+    record_page::RecordPage,
     statsviewer::{individual::IndividualStatsViewer, national::NationBasedStatsViewer},
 };
 use pointercrate_integrate::gd::{GDIntegrationResult, PgCache};
@@ -29,15 +33,10 @@ pub fn stats_viewer_redirect() -> Redirect {
 
 #[rocket::get("/?<timemachine>&<submitter>")]
 pub async fn overview(
-    pool: &State<PointercratePool>, timemachine: Option<bool>, submitter: Option<bool>, cookies: &CookieJar<'_>,
-) -> Result<Page<OverviewPage>> {
-    // should be const, but chrono aint const :(
-    let beginning_of_time: DateTime<FixedOffset> =
-        FixedOffset::east(0).from_utc_datetime(&NaiveDate::from_ymd(2017, 1, 4).and_hms(0, 0, 0));
-
-    let mut connection = pool.connection().await?;
-
-    let demonlist = current_list(&mut connection).await?;
+    pool: &State<PointercratePool>, cache: &State<ListCache>, renderer: PageRenderer<'_>, timemachine: Option<bool>,
+    submitter: Option<bool>, cookies: &CookieJar<'_>,
+) -> Result<RenderedPage> {
+    let beginning_of_time = beginning_of_time();
 
     let specified_when = cookies
         .get("when")
@@ -50,12 +49,27 @@ pub async fn overview(
         _ => None,
     };
 
+    // Pages showing the list at some point in the past depend on the cookie, so we do not cache them
+    let cache_key = format!("overview?timemachine={:?}&submitter={:?}", timemachine, submitter);
+    let version = cache.version();
+
+    if specified_when.is_none() {
+        if let Some(page) = cache.page(&cache_key) {
+            return Ok(page.max_age(config::page_max_age()))
+        }
+    }
+
+    // Whatever we read here ends up in the cache, so it must not come from a lagging replica
+    let mut connection = pool.connection().await?;
+
+    let demonlist = cache.demonlist(&mut connection).await?;
+
     let tardis = match specified_when {
         Some(destination) => Tardis::new(timemachine.unwrap_or(false)).activate(destination, list_at(&mut connection, destination).await?),
         _ => Tardis::new(timemachine.unwrap_or(false)),
     };
 
-    Ok(Page(OverviewPage {
+    let page = renderer.render(&OverviewPage {
         team: Team {
             admins: User::by_permission(LIST_ADMINISTRATOR, &mut connection).await?,
             moderators: User::by_permission(LIST_MODERATOR, &mut connection).await?,
@@ -64,12 +78,20 @@ pub async fn overview(
         demonlist,
         time_machine: tardis,
         submitter_initially_visible: submitter.unwrap_or(false),
-    }))
+    });
+
+    if specified_when.is_none() {
+        cache.insert_page(cache_key, version, page.clone());
+
+        Ok(page.max_age(config::page_max_age()))
+    } else {
+        Ok(page)
+    }
 }
 
 #[rocket::get("/permalink/<demon_id>")]
 pub async fn demon_permalink(demon_id: i32, pool: &State<PointercratePool>) -> Result<Redirect> {
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
 
     let position = MinimalDemon::by_id(demon_id, &mut connection).await?.position;
 
@@ -77,10 +99,20 @@ pub async fn demon_permalink(demon_id: i32, pool: &State<PointercratePool>) -> R
 }
 
 #[rocket::get("/<position>")]
-pub async fn demon_page(position: i16, pool: &State<PointercratePool>, gd: &State<PgCache>) -> Result<Page<DemonPage>> {
+pub async fn demon_page(
+    position: i16, pool: &State<PointercratePool>, gd: &State<PgCache>, cache: &State<ListCache>, renderer: PageRenderer<'_>,
+) -> Result<RenderedPage> {
+    let cache_key = format!("demon/{}", position);
+    let version = cache.version();
+
+    if let Some(page) = cache.page(&cache_key) {
+        return Ok(page.max_age(config::page_max_age()))
+    }
+
+    // Whatever we read here ends up in the cache, so it must not come from a lagging replica
     let mut connection = pool.connection().await?;
 
-    let full_demon = FullDemon::by_position(position, &mut connection).await?;
+    let full_demon = cache.demon(position, &mut connection).await?;
 
     let audit_log = audit_log_for_demon(full_demon.demon.base.id, &mut connection).await?;
 
@@ -119,30 +151,62 @@ pub async fn demon_page(position: i16, pool: &State<PointercratePool>, gd: &Stat
         });
     }
 
-    Ok(Page(DemonPage {
+    let integration = gd
+        .data_for_demon(
+            reqwest::Client::new(),
+            full_demon.demon.level_id,
+            full_demon.demon.base.name.clone(),
+            full_demon.demon.base.id,
+        )
+        .await
+        .unwrap_or(GDIntegrationResult::LevelDataNotFound);
+
+    // Level data that is not cached yet (or could not be looked up) becomes available later on, so
+    // pages missing it must not be cached
+    let integration_complete = matches!(
+        integration,
+        GDIntegrationResult::Success(..) | GDIntegrationResult::DemonNotFoundByName
+    );
+
+    let page = renderer.render(&DemonPage {
         team: Team {
             admins: User::by_permission(LIST_ADMINISTRATOR, &mut connection).await?,
             moderators: User::by_permission(LIST_MODERATOR, &mut connection).await?,
             helpers: User::by_permission(LIST_HELPER, &mut connection).await?,
         },
-        demonlist: current_list(&mut connection).await?,
+        demonlist: cache.demonlist(&mut connection).await?,
         movements: modifications,
-        integration: gd
-            .data_for_demon(
-                reqwest::Client::new(),
-                full_demon.demon.level_id,
-                full_demon.demon.base.name.clone(),
-                full_demon.demon.base.id,
-            )
-            .await
-            .unwrap_or(GDIntegrationResult::LevelDataNotFound),
+        integration,
+        list: DemonList::by_id(CLASSIC_LIST, &mut connection).await?,
         data: full_demon,
+    });
+
+    if !integration_complete {
+        return Ok(page)
+    }
+
+    cache.insert_page(cache_key, version, page.clone());
+
+    Ok(page.max_age(config::page_max_age()))
+}
+
+#[rocket::get("/records/<record_id>")]
+pub async fn record_page(record_id: i32, pool: &State<PointercratePool>) -> Result<Page<RecordPage>> {
+    let record = FullRecord::by_id(record_id, &mut *pool.read_connection().await?).await?;
+
+    if record.status != RecordStatus::Approved {
+        return Err(DemonlistError::RecordNotFound { record_id }.into())
+    }
+
+    Ok(Page(RecordPage {
+        record,
+        site_url: config::site_url(),
     }))
 }
 
 #[rocket::get("/statsviewer")]
 pub async fn stats_viewer(pool: &State<PointercratePool>) -> Result<Page<IndividualStatsViewer>> {
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
 
     Ok(Page(IndividualStatsViewer {
         nationalities_in_use: Nationality::used(&mut connection).await?,
@@ -181,7 +245,7 @@ macro_rules! heatmap_query {
 
 #[rocket::get("/statsviewer/heatmap.css")]
 pub async fn heatmap_css(pool: &State<PointercratePool>) -> Result<Response2<String>> {
-    let mut connection = pool.connection().await?;
+    let mut connection = pool.read_connection().await?;
     let mut css = heatmap_query!(
         connection,
         r#"SELECT LOWER(iso_country_code) as "code!", score as "score!" from nations_with_score order by score desc"#,
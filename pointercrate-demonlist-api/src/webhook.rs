@@ -0,0 +1,208 @@
+//! Module for notifying external services (usually discord channels) about record events via
+//! webhooks
+//!
+//! Payloads are discord-compatible embeds. Delivery happens in the background and is retried with
+//! exponential backoff, so a slow or unavailable webhook target never delays an API response. On
+//! shutdown, the event bus is closed and [`Deliveries::finished`] waits for all events still queued
+//! to be delivered.
+
+use crate::{
+    config,
+    events::{ListEvent, ListEvents},
+};
+use log::{debug, error, info, warn};
+use pointercrate_demonlist::record::FullRecord;
+use rocket::tokio::{self, sync::mpsc};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RecordEvent {
+    Submitted,
+    Approved,
+    Rejected,
+
+    /// The video proof of an approved record was found to be deleted or private
+    VideoDead,
+}
+
+impl RecordEvent {
+    /// The webhooks that should be notified about this event
+    fn targets(self) -> Vec<String> {
+        let mut targets = config::record_webhooks();
+
+        // The original submission webhook only ever received new submissions
+        if self == RecordEvent::Submitted {
+            targets.extend(config::submission_webhook());
+        }
+
+        targets
+    }
+}
+
+/// Notifies the configured webhooks about every record event published on the given bus
+///
+/// The returned [`Deliveries`] allow waiting for all deliveries to finish once the bus was closed.
+pub fn notify_on(events: &ListEvents) -> Deliveries {
+    let mut receiver = events.subscribe_lossless();
+
+    // Nothing is ever sent through this channel. Every delivery holds a sender until it finished, so
+    // the receiver only reports the end of the channel once all senders are gone.
+    let (in_flight, finished) = mpsc::channel::<()>(1);
+
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            let (event, record) = match event {
+                ListEvent::RecordSubmitted { record } => (RecordEvent::Submitted, record),
+                ListEvent::RecordApproved { record } => (RecordEvent::Approved, record),
+                ListEvent::RecordRejected { record } => (RecordEvent::Rejected, record),
+                ListEvent::VideoDead { record } => (RecordEvent::VideoDead, record),
+                _ => continue,
+            };
+
+            let payload = embed(event, &record);
+            let in_flight = in_flight.clone();
+
+            tokio::spawn(async move {
+                execute(event, payload).await;
+
+                drop(in_flight);
+            });
+        }
+
+        info!("List event bus closed, no longer executing webhooks");
+    });
+
+    Deliveries(finished)
+}
+
+/// Handle for waiting on the webhook deliveries started by [`notify_on`]
+pub struct Deliveries(mpsc::Receiver<()>);
+
+impl Deliveries {
+    /// Waits until the event bus has been closed (see [`ListEvents::close`]) and all webhooks for
+    /// the events published before have been delivered
+    pub async fn finished(mut self) {
+        while self.0.recv().await.is_some() {}
+
+        debug!("All pending webhooks delivered");
+    }
+}
+
+/// Delivers the given payload to all webhooks configured for the given event, retrying failed
+/// deliveries
+pub async fn execute(event: RecordEvent, payload: serde_json::Value) {
+    let targets = event.targets();
+
+    if targets.is_empty() {
+        warn!("Trying to execute webhook for {:?} event, though no link was configured!", event);
+    }
+
+    let client = reqwest::Client::new();
+
+    for target in targets {
+        deliver(&client, &target, &payload).await
+    }
+}
+
+async fn deliver(client: &reqwest::Client, target: &str, payload: &serde_json::Value) {
+    let max_attempts = config::webhook_max_attempts();
+    let mut backoff = Duration::from_secs(1);
+
+    for attempt in 1..=max_attempts {
+        let result = client
+            .post(target)
+            .header("Content-Type", "application/json")
+            .body(payload.to_string())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => {
+                debug!("Successfully executed webhook (attempt {})", attempt);
+
+                return
+            },
+            Err(error) if attempt < max_attempts => {
+                warn!(
+                    "Failure to execute webhook (attempt {} of {}): {:?}. Retrying in {:?}",
+                    attempt, max_attempts, error, backoff
+                );
+
+                tokio::time::sleep(backoff).await;
+
+                backoff *= 2;
+            },
+            Err(error) => error!("INTERNAL SERVER ERROR: Failure to execute webhook, giving up: {:?}", error),
+        }
+    }
+}
+
+pub fn embed(event: RecordEvent, record: &FullRecord) -> serde_json::Value {
+    let (content, description) = match event {
+        RecordEvent::Submitted =>
+            (
+                format!("**New record submitted! ID: {}**", record.id),
+                format!(
+                    "{} just got {}% on {}! Go add their record!",
+                    record.player.name, record.progress, record.demon.name
+                ),
+            ),
+        RecordEvent::Approved =>
+            (
+                format!("**Record approved! ID: {}**", record.id),
+                format!(
+                    "{}'s record of {}% on {} has been approved!",
+                    record.player.name, record.progress, record.demon.name
+                ),
+            ),
+        RecordEvent::Rejected =>
+            (
+                format!("**Record rejected! ID: {}**", record.id),
+                format!(
+                    "{}'s record of {}% on {} has been rejected.",
+                    record.player.name, record.progress, record.demon.name
+                ),
+            ),
+        RecordEvent::VideoDead =>
+            (
+                format!("**Dead video link! Record ID: {}**", record.id),
+                format!(
+                    "The video proof of {}'s record of {}% on {} is no longer available.",
+                    record.player.name, record.progress, record.demon.name
+                ),
+            ),
+    };
+
+    let mut payload = serde_json::json!({
+        "content": content,
+        "embeds": [
+            {
+                "type": "rich",
+                "title": format!("{}% on {}", record.progress, record.demon.name),
+                "description": description,
+                "footer": {
+                    "text": format!("This record has been submitted by submitter #{}", record.submitter.map(|s|s.id).unwrap_or(1))
+                },
+                "author": {
+                    "name": format!("{} (ID: {})", record.player.name, record.player.id),
+                    "url": record.video
+                },
+                "thumbnail": {
+                    "url": "https://cdn.discordapp.com/avatars/277391246035648512/b03c85d94dc02084c413a7fdbe2cea79.webp?size=1024"
+                },
+            }
+        ]
+    });
+
+    if let Some(ref video) = record.video {
+        payload["embeds"][0]["fields"] = serde_json::json! {
+            [{
+                "name": "Video Proof:",
+                "value": video
+            }]
+        };
+    }
+
+    payload
+}
@@ -0,0 +1,95 @@
+//! Module for checking the availability of YouTube videos via the YouTube Data API
+//!
+//! Checks only happen if an API key is configured. Problems with the API itself are logged, but
+//! otherwise ignored, so that a YouTube outage does not prevent people from submitting records.
+
+use crate::config;
+use log::{debug, error};
+use pointercrate_demonlist::error::DemonlistError;
+use serde::Deserialize;
+
+const WATCH_URL: &str = "https://www.youtube.com/watch?v=";
+
+#[derive(Deserialize)]
+struct VideoListResponse {
+    items: Vec<Video>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Video {
+    status: Status,
+    snippet: Snippet,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Status {
+    privacy_status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Snippet {
+    live_broadcast_content: String,
+}
+
+/// Verifies that the given (normalized) video exists, is not private and is not a livestream
+///
+/// Videos not hosted on YouTube are always considered available
+pub async fn check_availability(video: &str) -> Result<(), DemonlistError> {
+    let video_id = match video.strip_prefix(WATCH_URL) {
+        // Validated URLs might have a timestamp appended after the video id
+        Some(rest) => rest.split('&').next().unwrap_or(rest),
+        None => return Ok(()),
+    };
+
+    let api_key = match config::youtube_api_key() {
+        Some(api_key) => api_key,
+        None => return Ok(()),
+    };
+
+    let response = reqwest::Client::new()
+        .get("https://www.googleapis.com/youtube/v3/videos")
+        .query(&[("part", "status,snippet"), ("id", video_id), ("key", &api_key)])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    let data = match response {
+        Ok(response) => response.json::<VideoListResponse>().await,
+        Err(err) => Err(err),
+    };
+
+    let data = match data {
+        Ok(data) => data,
+        Err(err) => {
+            // The request URL contains our API key, so it must not end up in the logs
+            error!(
+                "Failed to check availability of video {} via YouTube Data API: {}",
+                video,
+                err.without_url()
+            );
+
+            return Ok(())
+        },
+    };
+
+    debug!("YouTube Data API returned {} items for video {}", data.items.len(), video);
+
+    match data.items.first() {
+        None =>
+            Err(DemonlistError::VideoUnavailable {
+                reason: "the video does not exist or was deleted",
+            }),
+        Some(video) if video.status.privacy_status == "private" =>
+            Err(DemonlistError::VideoUnavailable {
+                reason: "the video is private",
+            }),
+        Some(video) if video.snippet.live_broadcast_content != "none" =>
+            Err(DemonlistError::VideoUnavailable {
+                reason: "the video is a livestream",
+            }),
+        _ => Ok(()),
+    }
+}
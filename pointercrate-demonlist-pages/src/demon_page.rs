@@ -9,8 +9,8 @@ use chrono::NaiveDateTime;
 use maud::{html, Markup, PreEscaped, Render};
 use pointercrate_core_pages::{config as page_config, PageFragment, Script};
 use pointercrate_demonlist::{
-    config as list_config,
     demon::{Demon, FullDemon},
+    list::DemonList,
 };
 use pointercrate_integrate::gd::{DemonRating, GDIntegrationResult, LevelRating, Thunk};
 use url::Url;
@@ -25,6 +25,9 @@ pub struct DemonPage {
     pub team: Team,
     pub demonlist: Vec<Demon>,
     pub data: FullDemon,
+
+    /// The list the demon is on
+    pub list: DemonList,
     pub movements: Vec<DemonMovement>,
     pub integration: GDIntegrationResult,
 }
@@ -108,7 +111,7 @@ impl PageFragment for DemonPage {
                 <script>
                     window.list_length = {0};
                     window.extended_list_length = {1}
-                </script>", list_config::list_size(), list_config::extended_list_size()
+                </script>", self.list.list_size, self.list.extended_list_size
             )))
         }
     }
@@ -198,8 +201,8 @@ impl DemonPage {
         let position = self.data.demon.base.position;
         let name = &self.data.demon.base.name;
 
-        let score100 = self.data.demon.score(100);
-        let score_requirement = self.data.demon.score(self.data.demon.requirement);
+        let score100 = self.data.demon.score(&self.list, 100);
+        let score_requirement = self.data.demon.score(&self.list, self.data.demon.requirement);
 
         html! {
             section.panel.fade.js-scroll-anim data-anim = "fade" {
@@ -352,7 +355,7 @@ impl DemonPage {
                             }
                         }
                     }
-                    @if position <= list_config::extended_list_size() {
+                    @if position <= self.list.extended_list_size {
                         span {
                             b {
                                 "Demonlist score (100%): "
@@ -361,7 +364,7 @@ impl DemonPage {
                             (format!("{:.2}", score100))
                         }
                     }
-                    @if position <= list_config::list_size(){
+                    @if position <= self.list.list_size{
                         span {
                             b {
                                 "Demonlist score (" (self.data.demon.requirement) "%): "
@@ -380,18 +383,18 @@ impl DemonPage {
         let _name = &self.data.demon.base.name;
 
         html! {
-            @if !self.data.records.is_empty() || position <= list_config::extended_list_size() {
+            @if !self.data.records.is_empty() || position <= self.list.extended_list_size {
                 section.records.panel.fade.js-scroll-anim data-anim = "fade" {
                     div.underlined.pad {
                         h2 {
                             "Records"
                         }
-                        @if position <= list_config::list_size() {
+                        @if position <= self.list.list_size {
                             h3 {
                                 (self.data.demon.requirement) "% or better required to qualify"
                             }
                         }
-                        @else if position <= list_config::extended_list_size() {
+                        @else if position <= self.list.extended_list_size {
                             h3 {
                                 "100% required to qualify"
                             }
@@ -409,7 +412,7 @@ impl DemonPage {
                     }
                     @if self.data.records.is_empty() {
                         h3 {
-                            @if position > list_config::extended_list_size() {
+                            @if position > self.list.extended_list_size {
                                 "No records!"
                             }
                             @else {
@@ -481,7 +484,7 @@ fn host(video: &str) -> &str {
     }
 }
 
-fn embed(video: &str) -> Option<String> {
+pub(crate) fn embed(video: &str) -> Option<String> {
     // Video URLs need to be wellformed once we get here!
     let url = Url::parse(video).unwrap();
 
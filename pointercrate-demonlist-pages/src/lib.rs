@@ -6,6 +6,7 @@ pub mod account;
 pub mod components;
 pub mod demon_page;
 pub mod overview;
+pub mod record_page;
 pub mod statsviewer;
 
 struct ListSection {
@@ -0,0 +1,82 @@
+use crate::demon_page::embed;
+use maud::{html, Markup};
+use pointercrate_core_pages::{PageFragment, Script};
+use pointercrate_demonlist::{record::FullRecord, video};
+
+/// Page showing a single approved record, mainly meant as a link target that unfurls nicely when
+/// pasted into chat applications
+pub struct RecordPage {
+    pub record: FullRecord,
+
+    /// The URL under which this site is publicly reachable, for generating the absolute URLs
+    /// OpenGraph and oEmbed require
+    pub site_url: String,
+}
+
+impl PageFragment for RecordPage {
+    fn title(&self) -> String {
+        format!(
+            "{} - {}% on {} - Geometry Dash Demonlist",
+            self.record.player.name, self.record.progress, self.record.demon.name
+        )
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "{} achieved {}% on {} (#{} on the Geometry Dash Demonlist)",
+            self.record.player.name, self.record.progress, self.record.demon.name, self.record.demon.position
+        )
+    }
+
+    fn additional_scripts(&self) -> Vec<Script> {
+        vec![]
+    }
+
+    fn additional_stylesheets(&self) -> Vec<String> {
+        vec!["/static/css/demonlist.v2.1.css".to_string()]
+    }
+
+    fn head_fragment(&self) -> Markup {
+        let page_url = format!("{}/demonlist/records/{}/", self.site_url, self.record.id);
+        let oembed_url = format!("{}/api/v1/records/{}/oembed/", self.site_url, self.record.id);
+
+        html! {
+            meta property="og:url" content=(page_url);
+            @if let Some(thumbnail) = self.record.video.as_deref().and_then(video::thumbnail) {
+                meta property="og:image" content=(thumbnail);
+                meta name="twitter:card" content="summary_large_image";
+            }
+            link rel="alternate" type="application/json+oembed" href=(oembed_url) title=(self.title());
+        }
+    }
+
+    fn body_fragment(&self) -> Markup {
+        html! {
+            div.flex.m-center.container {
+                main.left {
+                    section.panel.fade {
+                        h1.underlined.pad {
+                            (self.record.progress) "% on "
+                            a href = {"/demonlist/permalink/" (self.record.demon.id) "/"} {
+                                (self.record.demon.name)
+                            }
+                        }
+                        h3.pad {
+                            "by " (self.record.player.name)
+                        }
+                        @if let Some(ref video) = self.record.video {
+                            @if let Some(embedded_video) = embed(video) {
+                                iframe."ratio-16-9" style="width:90%; margin: 15px 5%" allowfullscreen="" src = (embedded_video) {"Video"}
+                            }
+                            @else {
+                                p.pad {
+                                    a.link href = (video) target = "_blank" {"Watch the video"}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,12 @@
-SELECT demons.id AS "demon_id!", demons.name AS "demon_name!: String", demons.position_ as "position!", demons.requirement as "requirement!", demons.level_id, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END, verifiers.id AS "verifier_id!", verifiers.name AS "verifier_name!: String", verifiers.banned AS "verifier_banned!", publishers.id AS "publisher_id!", publishers.name AS "publisher_name!: String", publishers.banned AS "publisher_banned!", demons.current_position as "current_position!"
+SELECT demons.id AS "demon_id!", demons.name AS "demon_name!: String", demons.position_ as "position!", demons.requirement as "requirement!", demons.level_id, (SELECT thumbnail FROM demons AS current WHERE current.id = demons.id) AS thumbnail, (SELECT requires_timestamp FROM demons AS current WHERE current.id = demons.id) AS "requires_timestamp!", COALESCE(demon_victors.victors, 0) AS "victors!", first_victors.id AS "first_victor_id?", first_victors.name::TEXT AS "first_victor_name?", first_victors.banned AS "first_victor_banned?", CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END, verifiers.id AS "verifier_id!", verifiers.name AS "verifier_name!: String", verifiers.banned AS "verifier_banned!", publishers.id AS "publisher_id!", publishers.name AS "publisher_name!: String", publishers.banned AS "publisher_banned!", demons.current_position as "current_position!"
 FROM list_at($1) AS demons
     INNER JOIN players as publishers
         ON demons.publisher = publishers.id
     INNER JOIN players AS verifiers
         ON demons.verifier = verifiers.id
+    LEFT OUTER JOIN demon_victors
+        ON demon_victors.demon = demons.id
+    LEFT OUTER JOIN players AS first_victors
+        ON first_victors.id = demon_victors.first_victor
+WHERE (SELECT list_id FROM demons AS current WHERE current.id = demons.id) = $2
 ORDER BY position_
\ No newline at end of file
@@ -1,7 +1,9 @@
-SELECT demons.id AS demon_id, demons.name AS "demon_name: String", demons.position, demons.requirement, demons.level_id, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video END,
+SELECT demons.id AS demon_id, demons.name AS "demon_name: String", demons.position, demons.requirement, demons.level_id, demons.thumbnail, demons.requires_timestamp, COALESCE(demon_victors.victors, 0) AS "victors!", first_victors.id AS "first_victor_id?", first_victors.name::TEXT AS "first_victor_name?", first_victors.banned AS "first_victor_banned?", CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video END,
        verifiers.id AS verifier_id, verifiers.name AS "verifier_name: String", verifiers.banned AS verifier_banned,
        publishers.id AS publisher_id, publishers.name AS "publisher_name: String", publishers.banned AS publisher_banned
 FROM demons
 INNER JOIN players AS verifiers ON verifiers.id=demons.verifier
 INNER JOIN players AS publishers ON publishers.id=demons.publisher
-WHERE demons.position=$1
\ No newline at end of file
+LEFT OUTER JOIN demon_victors ON demon_victors.demon=demons.id
+LEFT OUTER JOIN players AS first_victors ON first_victors.id=demon_victors.first_victor
+WHERE demons.position=$1 AND demons.list_id=$2
\ No newline at end of file
@@ -0,0 +1,12 @@
+SELECT demons.id AS "demon_id!", demons.name AS "demon_name!: String", demons.position as "position!", demons.requirement as "requirement!", demons.level_id, demons.thumbnail, demons.requires_timestamp AS "requires_timestamp!", COALESCE(demon_victors.victors, 0) AS "victors!", first_victors.id AS "first_victor_id?", first_victors.name::TEXT AS "first_victor_name?", first_victors.banned AS "first_victor_banned?", CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END, verifiers.id AS "verifier_id!", verifiers.name AS "verifier_name!: String", verifiers.banned AS "verifier_banned!", publishers.id AS "publisher_id!", publishers.name AS "publisher_name!: String", publishers.banned AS "publisher_banned!", GREATEST(demons.last_modified, demons.victors_modified) AS "last_modified!"
+FROM demons
+    INNER JOIN players as publishers
+        ON demons.publisher = publishers.id
+    INNER JOIN players AS verifiers
+        ON demons.verifier = verifiers.id
+    LEFT OUTER JOIN demon_victors
+        ON demon_victors.demon = demons.id
+    LEFT OUTER JOIN players AS first_victors
+        ON first_victors.id = demon_victors.first_victor
+WHERE demons.list_id = $2 AND GREATEST(demons.last_modified, demons.victors_modified) > $1
+ORDER BY GREATEST(demons.last_modified, demons.victors_modified), demons.id
@@ -1,9 +1,12 @@
-SELECT demons.id AS demon_id, demons.name::text AS demon_name, demons.position, demons.requirement, demons.level_id, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END,
+SELECT demons.id AS demon_id, demons.name::text AS demon_name, demons.position, demons.requirement, demons.level_id, demons.thumbnail, demons.requires_timestamp, COALESCE(demon_victors.victors, 0) AS victors, first_victors.id AS first_victor_id, first_victors.name::TEXT AS first_victor_name, first_victors.banned AS first_victor_banned, COALESCE(pending_records.pending, 0) AS pending, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END,
        verifiers.id AS verifier_id, verifiers.name::text AS verifier_name, verifiers.banned AS verifier_banned,
        publishers.id AS publisher_id, publishers.name::text AS publisher_name, publishers.banned AS publisher_banned
 FROM demons
 INNER JOIN players AS verifiers ON verifiers.id=demons.verifier
 INNER JOIN players AS publishers ON publishers.id=demons.publisher
+LEFT OUTER JOIN demon_victors ON demon_victors.demon=demons.id
+LEFT OUTER JOIN players AS first_victors ON first_victors.id=demon_victors.first_victor
+LEFT OUTER JOIN (SELECT demon, COUNT(*) AS pending FROM records WHERE status_ = 'SUBMITTED' GROUP BY demon) AS pending_records ON pending_records.demon=demons.id
 WHERE (demons.position < $1 OR $1 IS NULL)
   AND (demons.position > $2 OR $2 IS NULL)
   AND (demons.name::CITEXT = $3 OR $3 IS NULL)
@@ -15,6 +18,8 @@ WHERE (demons.position < $1 OR $1 IS NULL)
   AND (publishers.id = $9 OR $9 IS NULL)
   AND (publishers.name::CITEXT = $10 OR $10 IS NULL)
   AND (STRPOS(demons.name, $11::CITEXT) > 0 OR $11 is NULL)
+  AND demons.list_id = $13
+  AND (demons.level_id = $14 OR $14 IS NULL)
   AND demons.position IS NOT NULL
 ORDER BY demons.position {}
 LIMIT $12
\ No newline at end of file
@@ -0,0 +1,13 @@
+SELECT demons.id AS demon_id, demons.name::text AS demon_name, demons.position, demons.legacy_position, demons.requirement, demons.level_id, demons.thumbnail, demons.requires_timestamp, COALESCE(demon_victors.victors, 0) AS victors, first_victors.id AS first_victor_id, first_victors.name::TEXT AS first_victor_name, first_victors.banned AS first_victor_banned, CASE WHEN verifiers.link_banned THEN NULL ElSE demons.video::text END,
+       verifiers.id AS verifier_id, verifiers.name::text AS verifier_name, verifiers.banned AS verifier_banned,
+       publishers.id AS publisher_id, publishers.name::text AS publisher_name, publishers.banned AS publisher_banned
+FROM demons
+INNER JOIN players AS verifiers ON verifiers.id=demons.verifier
+INNER JOIN players AS publishers ON publishers.id=demons.publisher
+LEFT OUTER JOIN demon_victors ON demon_victors.demon=demons.id
+LEFT OUTER JOIN players AS first_victors ON first_victors.id=demon_victors.first_victor
+WHERE (demons.legacy_position < $1 OR $1 IS NULL)
+  AND (demons.legacy_position > $2 OR $2 IS NULL)
+  AND demons.legacy_position IS NOT NULL
+ORDER BY demons.legacy_position {}
+LIMIT $3
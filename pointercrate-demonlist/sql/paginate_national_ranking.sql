@@ -0,0 +1,14 @@
+SELECT id, name, rank, score, index, nation, iso_country_code
+FROM (
+    SELECT id, name::TEXT, score, nation::TEXT, iso_country_code::TEXT, subdivision,
+           RANK() OVER (ORDER BY score DESC) AS rank,
+           ROW_NUMBER() OVER (ORDER BY score DESC, id) AS index
+    FROM ranked_players
+    WHERE iso_country_code = $1
+) AS national_ranking
+WHERE (index < $2 OR $2 IS NULL)
+  AND (index > $3 OR $3 IS NULL)
+  AND (STRPOS(name, $4::CITEXT) > 0 OR $4 is NULL)
+  AND (subdivision = $5 OR $5 IS NULL)
+ORDER BY index {}
+LIMIT $6
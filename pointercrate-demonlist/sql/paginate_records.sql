@@ -1,7 +1,7 @@
 SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, status_::text AS status,
        players.id AS player_id, players.name::text AS player_name, players.banned AS player_banned,
-       demons.id AS demon_id, demons.name::text AS demon_name, demons.position
-FROM records
+       demons.id AS demon_id, demons.name::text AS demon_name, demons.position, records.deleted
+FROM {} AS records
 INNER JOIN players ON records.player = players.id
 INNER JOIN demons ON records.demon = demons.id
 WHERE (records.id < $1 OR $1 IS NULL)
@@ -18,5 +18,9 @@ WHERE (records.id < $1 OR $1 IS NULL)
   AND (records.video = $12 OR (records.video IS NULL AND $13) OR ($12 IS NULL AND NOT $13))
   AND (players.id = $14 OR $14 IS NULL)
   AND (records.submitter = $15 OR $15 IS NULL)
+  AND (progress <= $17 OR $17 IS NULL)
+  AND (progress >= $18 OR $18 IS NULL)
+  AND (position <= $19 OR $19 IS NULL)
+  AND (position >= $20 OR $20 IS NULL)
 ORDER BY id {}
 LIMIT $16
\ No newline at end of file
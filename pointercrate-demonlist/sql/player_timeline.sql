@@ -0,0 +1,40 @@
+-- record_modifications stores the values a record had _before_ each change, so the last change of a currently approved
+-- record's status is its approval. Records approved right away have no such change, they were approved when added.
+WITH approvals AS (
+    SELECT records.id AS record_id, records.demon, records.progress, COALESCE(
+        (SELECT MAX(time) FROM record_modifications WHERE record_modifications.id = records.id AND record_modifications.status_ IS NOT NULL),
+        record_additions.time
+    ) AS time
+    FROM records
+        LEFT OUTER JOIN record_additions
+            ON record_additions.id = records.id
+    WHERE records.player = $1 AND records.status_ = 'APPROVED'
+),
+events AS (
+    SELECT time, 'APPROVAL' AS kind, demon, record_id, progress
+    FROM approvals
+    UNION ALL
+    SELECT approvals.time, 'FIRST_VICTOR', approvals.demon, approvals.record_id, approvals.progress
+    FROM approvals
+        INNER JOIN demon_victors
+            ON demon_victors.demon = approvals.demon
+    WHERE demon_victors.first_victor = $1 AND approvals.progress = 100
+    UNION ALL
+    SELECT demon_additions.time, 'VERIFICATION', demons.id, NULL, 100
+    FROM demons
+        LEFT OUTER JOIN demon_additions
+            ON demon_additions.id = demons.id
+    WHERE demons.verifier = $1
+)
+-- demon_modifications also stores old values, so the position a demon had at some point in time is the one stored in the
+-- first modification afterwards (or its current position). For events of unknown time, this is the earliest known position.
+SELECT events.time AS "time?", events.kind AS "kind!", demons.id AS "demon_id!", demons.name::TEXT AS "demon_name!", demons.position AS "current_position!",
+       events.record_id AS "record_id?", events.progress AS "progress!", COALESCE(
+           (SELECT position FROM demon_modifications WHERE demon_modifications.id = demons.id AND demon_modifications.position IS NOT NULL
+                AND (demon_modifications.time > events.time OR events.time IS NULL) ORDER BY demon_modifications.time LIMIT 1),
+           demons.position
+       ) AS "position!"
+FROM events
+    INNER JOIN demons
+        ON demons.id = events.demon
+ORDER BY events.time ASC NULLS FIRST, events.kind, demons.id
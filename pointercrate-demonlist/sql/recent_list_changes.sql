@@ -0,0 +1,35 @@
+-- demon_modifications stores the values a demon had _before_ each change, so the position a demon was moved to is the
+-- position stored in the next modification (or its current position)
+WITH moves AS (
+    SELECT demon_modifications.time, demon_modifications.id, demon_modifications.position AS from_position,
+           COALESCE(LEAD(demon_modifications.position) OVER (PARTITION BY demon_modifications.id ORDER BY demon_modifications.time), demons.position) AS to_position
+    FROM demon_modifications
+        INNER JOIN demons
+            ON demons.id = demon_modifications.id
+    WHERE demon_modifications.position IS NOT NULL
+      AND demons.list_id = $2
+),
+-- Moving (or adding) a single demon shifts every demon in between by one position. All of these changes happen in the
+-- same transaction, and thus have the same time. The demon that was actually moved is the one that moved the furthest.
+largest_moves AS (
+    SELECT *, MAX(ABS(to_position - from_position)) OVER (PARTITION BY time) AS largest_shift
+    FROM moves
+)
+SELECT largest_moves.time AS "time!", demons.id AS "demon_id!", demons.name::TEXT AS "demon_name!", largest_moves.from_position AS "from_position?", largest_moves.to_position AS "to_position!"
+FROM largest_moves
+    INNER JOIN demons
+        ON demons.id = largest_moves.id
+WHERE ABS(to_position - from_position) = largest_shift
+  AND to_position <> from_position
+  AND NOT EXISTS (SELECT 1 FROM demon_additions WHERE demon_additions.time = largest_moves.time)
+UNION ALL
+SELECT demon_additions.time, demons.id, demons.name::TEXT, NULL, COALESCE(
+    (SELECT position FROM demon_modifications WHERE demon_modifications.id = demons.id AND position IS NOT NULL ORDER BY time LIMIT 1),
+    demons.position
+)
+FROM demon_additions
+    INNER JOIN demons
+        ON demons.id = demon_additions.id
+WHERE demons.list_id = $2
+ORDER BY 1 DESC
+LIMIT $1
@@ -1,7 +1,9 @@
-SELECT progress, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, status_::text AS "status!: String" ,
+SELECT progress, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END, status_::text AS "status!: String", video_status,
+       CASE WHEN players.link_banned THEN NULL ELSE records.archive_url END AS archive_url, raw_footage,
        players.id AS player_id, players.name AS "player_name: String", players.banned AS player_banned,
        demons.id AS demon_id, demons.name AS "demon_name: String", demons.position,
-       submitters.submitter_id AS submitter_id, submitters.banned AS submitter_banned
+       submitters.submitter_id AS submitter_id, submitters.banned AS submitter_banned,
+       submitters.flagged AS submitter_flagged
 FROM records
 INNER JOIN players ON records.player = players.id
 INNER JOIN demons ON records.demon = demons.id
@@ -0,0 +1,143 @@
+//! Module for alternate names of players and demons
+//!
+//! Submitters do not always spell names the way they are stored (e.g. they use a transliteration
+//! or make a common typo), which used to result in duplicate players being created. Looking up a
+//! player or demon by name (see [`DatabasePlayer::by_name`] and [`MinimalDemon::by_name`]) falls
+//! back to their aliases if nothing with the given name exists. Aliases of players are unique among
+//! all player aliases, and aliases of demons among all demon aliases.
+
+use crate::{
+    demon::MinimalDemon,
+    error::{DemonlistError, Result},
+    player::DatabasePlayer,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize, Hash, PartialEq, Eq, Clone)]
+pub struct Alias {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostAlias {
+    pub name: String,
+}
+
+fn validate_alias(name: &str) -> Result<&str> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(DemonlistError::AliasEmpty)
+    }
+
+    Ok(name)
+}
+
+impl DatabasePlayer {
+    pub async fn aliases(&self, connection: &mut PgConnection) -> Result<Vec<Alias>> {
+        Ok(sqlx::query_as!(
+            Alias,
+            r#"SELECT id, name::TEXT AS "name!" FROM aliases WHERE player = $1 ORDER BY id"#,
+            self.id
+        )
+        .fetch_all(connection)
+        .await?)
+    }
+
+    pub async fn add_alias(&self, alias: PostAlias, connection: &mut PgConnection) -> Result<Alias> {
+        let name = validate_alias(&alias.name)?;
+
+        // Aliases are only consulted if no player has the given name, so such an alias would never be used
+        match DatabasePlayer::by_name(name, &mut *connection).await {
+            Ok(player) => return Err(DemonlistError::AliasTaken { name: player.name }),
+            Err(DemonlistError::PlayerNotFoundName { .. }) => (),
+            Err(err) => return Err(err),
+        }
+
+        info!("Adding alias '{}' to player {}", name, self);
+
+        let id = sqlx::query!(
+            "INSERT INTO aliases (name, player) VALUES ($1::TEXT, $2) RETURNING id",
+            name,
+            self.id
+        )
+        .fetch_one(connection)
+        .await?
+        .id;
+
+        Ok(Alias {
+            id,
+            name: name.to_string(),
+        })
+    }
+
+    pub async fn remove_alias(&self, alias_id: i32, connection: &mut PgConnection) -> Result<()> {
+        let deleted = sqlx::query!("DELETE FROM aliases WHERE id = $1 AND player = $2", alias_id, self.id)
+            .execute(connection)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(DemonlistError::AliasNotFound { alias_id })
+        }
+
+        info!("Removed alias {} from player {}", alias_id, self);
+
+        Ok(())
+    }
+}
+
+impl MinimalDemon {
+    pub async fn aliases(&self, connection: &mut PgConnection) -> Result<Vec<Alias>> {
+        Ok(sqlx::query_as!(
+            Alias,
+            r#"SELECT id, name::TEXT AS "name!" FROM aliases WHERE demon = $1 ORDER BY id"#,
+            self.id
+        )
+        .fetch_all(connection)
+        .await?)
+    }
+
+    pub async fn add_alias(&self, alias: PostAlias, connection: &mut PgConnection) -> Result<Alias> {
+        let name = validate_alias(&alias.name)?;
+
+        match MinimalDemon::by_name(name, &mut *connection).await {
+            Err(DemonlistError::DemonNotFoundName { .. }) => (),
+            Err(DemonlistError::DemonNameNotUnique { .. }) => return Err(DemonlistError::AliasTaken { name: name.to_string() }),
+            Ok(demon) => return Err(DemonlistError::AliasTaken { name: demon.name }),
+            Err(err) => return Err(err),
+        }
+
+        info!("Adding alias '{}' to demon {}", name, self);
+
+        let id = sqlx::query!(
+            "INSERT INTO aliases (name, demon) VALUES ($1::TEXT, $2) RETURNING id",
+            name,
+            self.id
+        )
+        .fetch_one(connection)
+        .await?
+        .id;
+
+        Ok(Alias {
+            id,
+            name: name.to_string(),
+        })
+    }
+
+    pub async fn remove_alias(&self, alias_id: i32, connection: &mut PgConnection) -> Result<()> {
+        let deleted = sqlx::query!("DELETE FROM aliases WHERE id = $1 AND demon = $2", alias_id, self.id)
+            .execute(connection)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(DemonlistError::AliasNotFound { alias_id })
+        }
+
+        info!("Removed alias {} from demon {}", alias_id, self);
+
+        Ok(())
+    }
+}
@@ -1,9 +1,169 @@
-use pointercrate_core::util::from_env_or_default;
+use lazy_static::lazy_static;
+use log::error;
+use pointercrate_core::{
+    config::{check, ConfigError},
+    util::from_env_or_default,
+};
 
+/// Checks the configuration of pointercrate-demonlist, so that malformed values are reported at
+/// startup
+pub fn validate() -> Result<(), ConfigError> {
+    check::<i16>("LIST_SIZE")?;
+    check::<i16>("EXTENDED_LIST_SIZE")?;
+    check::<i16>("SCORE_CUTOFF")?;
+    check::<f64>("SCORE_PROGRESS_DIVISOR")?;
+    check::<i16>("RAW_FOOTAGE_THRESHOLD")?;
+    check::<i64>("MAX_SUBMISSIONS_PER_HOUR")?;
+    check::<f64>("MAX_REJECTION_RATIO")?;
+    check::<i64>("REJECTION_RATIO_MIN_SUBMISSIONS")?;
+    check::<i32>("REVIEW_LOCK_MINUTES")?;
+    check::<i64>("PROPOSAL_VOTES_REQUIRED")?;
+
+    if let Some(rules) = pointercrate_core::config::var("PROGRESSIVE_REQUIREMENTS") {
+        parse_progressive_requirements(&rules).map_err(|reason| {
+            ConfigError::Invalid {
+                key: "PROGRESSIVE_REQUIREMENTS",
+                reason,
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+/// The size of the main list, as last loaded from the database (see [`crate::list_config`]), or
+/// [`env_list_size`] if it is not set there
 pub fn list_size() -> i16 {
-    from_env_or_default("LIST_SIZE", 50)
+    crate::list_config::cached_list_size().unwrap_or_else(env_list_size)
 }
 
+/// The size of the extended list, as last loaded from the database (see [`crate::list_config`]),
+/// or [`env_extended_list_size`] if it is not set there
 pub fn extended_list_size() -> i16 {
+    crate::list_config::cached_extended_list_size().unwrap_or_else(env_extended_list_size)
+}
+
+pub fn env_list_size() -> i16 {
+    from_env_or_default("LIST_SIZE", 50)
+}
+
+pub fn env_extended_list_size() -> i16 {
     from_env_or_default("EXTENDED_LIST_SIZE", 100)
 }
+
+pub fn score_cutoff() -> i16 {
+    from_env_or_default("SCORE_CUTOFF", 150)
+}
+
+pub fn score_progress_divisor() -> f64 {
+    from_env_or_default("SCORE_PROGRESS_DIVISOR", 10f64)
+}
+
+/// The size of the main section of the list with the given slug, configured via `<SLUG>_LIST_SIZE`
+pub fn list_size_of(slug: &str) -> i16 {
+    from_env_or_default(&format!("{}_LIST_SIZE", slug.to_uppercase()), list_size())
+}
+
+/// The size of the extended section of the list with the given slug, configured via
+/// `<SLUG>_EXTENDED_LIST_SIZE`
+pub fn extended_list_size_of(slug: &str) -> i16 {
+    from_env_or_default(&format!("{}_EXTENDED_LIST_SIZE", slug.to_uppercase()), extended_list_size())
+}
+
+/// Submissions for demons at or above this position need to provide a link to raw footage
+pub fn raw_footage_threshold() -> i16 {
+    from_env_or_default("RAW_FOOTAGE_THRESHOLD", 75)
+}
+
+/// The maximal number of records a single submitter can submit per hour
+pub fn max_submissions_per_hour() -> i64 {
+    from_env_or_default("MAX_SUBMISSIONS_PER_HOUR", 10)
+}
+
+/// Submitters with a higher ratio of rejected submissions get flagged by the submission guard
+pub fn max_rejection_ratio() -> f64 {
+    from_env_or_default("MAX_REJECTION_RATIO", 0.8f64)
+}
+
+/// The number of decided (approved or rejected) submissions a submitter needs before their
+/// rejection ratio is taken into account
+pub fn rejection_ratio_min_submissions() -> i64 {
+    from_env_or_default("REJECTION_RATIO_MIN_SUBMISSIONS", 10)
+}
+
+/// The number of minutes a claim on a submission (see [`ReviewLock`](crate::record::ReviewLock))
+/// lasts before other members can review it again
+pub fn review_lock_minutes() -> i32 {
+    from_env_or_default("REVIEW_LOCK_MINUTES", 15)
+}
+
+/// The number of list moderators that need to approve (or reject) a
+/// [`DemonProposal`](crate::proposal::DemonProposal) before it is executed (or discarded)
+pub fn proposal_votes_required() -> i64 {
+    from_env_or_default("PROPOSAL_VOTES_REQUIRED", 1)
+}
+
+lazy_static! {
+    static ref PROGRESSIVE_REQUIREMENTS: Vec<(i16, i16)> = match pointercrate_core::config::var("PROGRESSIVE_REQUIREMENTS") {
+        Some(rules) =>
+            parse_progressive_requirements(&rules).unwrap_or_else(|reason| {
+                error!("Ignoring invalid configuration key PROGRESSIVE_REQUIREMENTS: {}", reason);
+
+                Vec::new()
+            }),
+        None => Vec::new(),
+    };
+}
+
+/// Position dependent minimal record progress, configured via `PROGRESSIVE_REQUIREMENTS` as a comma
+/// separated list of `<position>:<progress>` rules.
+///
+/// A rule means that submissions for demons at or above the given position need to have at least
+/// the given progress, regardless of the demon's own (lower) requirement. For example,
+/// `10:100,25:80` only accepts completions for the top 10 and at least 80% for positions 11 to 25.
+/// Rules are sorted by position.
+///
+/// The rules are only parsed once. Malformed rules are reported by [`validate`].
+pub fn progressive_requirements() -> &'static [(i16, i16)] {
+    &PROGRESSIVE_REQUIREMENTS
+}
+
+fn parse_progressive_requirements(rules: &str) -> Result<Vec<(i16, i16)>, String> {
+    let mut rules = rules
+        .split(',')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(|rule| {
+            let parsed = rule
+                .split_once(':')
+                .and_then(|(position, progress)| Some((position.trim().parse().ok()?, progress.trim().parse().ok()?)));
+
+            match parsed {
+                Some((position, progress)) if position > 0 && (0..=100).contains(&progress) => Ok((position, progress)),
+                _ => Err(format!("invalid rule '{}', expected '<position>:<progress>'", rule.trim())),
+            }
+        })
+        .collect::<Result<Vec<(i16, i16)>, String>>()?;
+
+    rules.sort_unstable();
+
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_progressive_requirements;
+
+    #[test]
+    fn test_progressive_requirements_are_sorted() {
+        assert_eq!(parse_progressive_requirements("25:80, 10:100,").unwrap(), vec![(10, 100), (25, 80)]);
+        assert_eq!(parse_progressive_requirements("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_progressive_requirements() {
+        assert!(parse_progressive_requirements("10").is_err());
+        assert!(parse_progressive_requirements("10:101").is_err());
+        assert!(parse_progressive_requirements("0:100").is_err());
+        assert!(parse_progressive_requirements("10:100,a:50").is_err());
+    }
+}
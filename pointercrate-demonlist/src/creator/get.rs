@@ -6,6 +6,7 @@ use crate::{
 };
 use futures::stream::StreamExt;
 use sqlx::PgConnection;
+use std::collections::HashMap;
 
 impl Creator {
     pub async fn get(demon: &MinimalDemon, player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Creator> {
@@ -33,18 +34,27 @@ impl Creator {
 }
 
 pub async fn creators_of(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<DatabasePlayer>> {
+    Ok(creators_of_all(&[demon.id], connection)
+        .await?
+        .remove(&demon.id)
+        .unwrap_or_default())
+}
+
+/// The creators of each of the given demons, in a single query. Demons without creators are not
+/// contained in the returned map.
+pub async fn creators_of_all(demon_ids: &[i32], connection: &mut PgConnection) -> Result<HashMap<i32, Vec<DatabasePlayer>>> {
     let mut stream = sqlx::query!(
-        r#"SELECT players.id, players.name AS "name: String", players.banned FROM players INNER JOIN creators ON players.id = creators.creator WHERE 
-         creators.demon = $1"#,
-        demon.id
+        r#"SELECT creators.demon, players.id, players.name AS "name: String", players.banned FROM players INNER JOIN creators ON players.id = 
+         creators.creator WHERE creators.demon = ANY($1)"#,
+        demon_ids
     )
     .fetch(connection);
-    let mut players = Vec::new();
+    let mut players: HashMap<i32, Vec<DatabasePlayer>> = HashMap::new();
 
     while let Some(row) = stream.next().await {
         let row = row?;
 
-        players.push(DatabasePlayer {
+        players.entry(row.demon).or_default().push(DatabasePlayer {
             id: row.id,
             name: row.name,
             banned: row.banned,
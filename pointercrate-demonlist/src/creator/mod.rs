@@ -1,5 +1,5 @@
 // pub use self::post::PostCreator;
-pub use self::get::{created_by, creators_of};
+pub use self::get::{created_by, creators_of, creators_of_all};
 use derive_more::Display;
 pub use post::PostCreator;
 
@@ -1,5 +1,6 @@
-use crate::error::Result;
+use crate::{error::Result, list::CLASSIC_LIST};
 
+use chrono::NaiveDateTime;
 use futures::StreamExt;
 use pointercrate_core::audit::{AuditLogEntry, AuditLogEntryType, NamedId};
 use serde::Serialize;
@@ -103,3 +104,38 @@ pub async fn audit_log_for_demon(demon_id: i32, connection: &mut PgConnection) -
 
     Ok(entries)
 }
+
+/// A demon being added to or moved on the list
+#[derive(Debug, Serialize)]
+pub struct ListChange {
+    pub time: NaiveDateTime,
+    pub demon_id: i32,
+    pub demon_name: String,
+
+    /// The position the demon was moved from, or `None` if it was newly added
+    pub from: Option<i16>,
+    pub to: i16,
+}
+
+/// Gets the most recent additions and moves of demons on the main list, newest first
+///
+/// Demons that only shifted by one position because another demon was added or moved past them are
+/// not included.
+pub async fn recent_list_changes(limit: i64, connection: &mut PgConnection) -> Result<Vec<ListChange>> {
+    let mut stream = sqlx::query_file!("sql/recent_list_changes.sql", limit, CLASSIC_LIST).fetch(connection);
+    let mut changes = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        changes.push(ListChange {
+            time: row.time,
+            demon_id: row.demon_id,
+            demon_name: row.demon_name,
+            from: row.from_position,
+            to: row.to_position,
+        })
+    }
+
+    Ok(changes)
+}
@@ -1,33 +1,42 @@
 use crate::{
-    creator::creators_of,
-    demon::{Demon, FullDemon, MinimalDemon, TimeShiftedDemon},
+    creator::creators_of_all,
+    demon::{Demon, FullDemon, MinimalDemon, ModifiedDemon, TimeShiftedDemon},
     error::{DemonlistError, Result},
+    list::CLASSIC_LIST,
     player::DatabasePlayer,
-    record::approved_records_on,
+    record::approved_records_on_all,
 };
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
 use futures::StreamExt;
+use serde::Deserialize;
 use sqlx::{Error, PgConnection};
 
 impl MinimalDemon {
     pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<MinimalDemon> {
-        let row = sqlx::query!(r#"SELECT id, name as "name: String", position FROM demons WHERE id = $1"#, id)
+        let result = sqlx::query!(r#"SELECT id, name as "name: String", position FROM demons WHERE id = $1"#, id)
             .fetch_one(connection)
-            .await?;
+            .await;
 
-        Ok(MinimalDemon {
-            id,
-            position: row.position,
-            name: row.name,
-        })
+        match result {
+            Ok(row) =>
+                Ok(MinimalDemon {
+                    id,
+                    position: row.position,
+                    name: row.name,
+                }),
+            Err(Error::RowNotFound) => Err(DemonlistError::DemonNotFound { demon_id: id }),
+            Err(err) => Err(err.into()),
+        }
     }
 
+    /// Gets the demon with the given name, or, if no such demon exists, the demon that has the
+    /// given name as an [alias](crate::alias)
     pub async fn by_name(name: &str, connection: &mut PgConnection) -> Result<MinimalDemon> {
         let mut stream = sqlx::query!(
             r#"SELECT id, name as "name: String", position FROM demons WHERE name = cast($1::text as citext)"#, // FIXME(sqlx) once CITEXT is supported
             name.to_string()
         )
-        .fetch(connection);
+        .fetch(&mut *connection);
 
         let mut demon = None;
         let mut further_demons = Vec::new();
@@ -48,13 +57,33 @@ impl MinimalDemon {
             }
         }
 
+        drop(stream);
+
         if further_demons.is_empty() {
             match demon {
                 Some(demon) => Ok(demon),
-                None =>
-                    Err(DemonlistError::DemonNotFoundName {
-                        demon_name: name.to_string(),
-                    }),
+                None => {
+                    let alias = sqlx::query!(
+                        r#"SELECT demons.id, demons.name as "name: String", demons.position FROM aliases INNER JOIN demons ON demons.id =
+                         aliases.demon WHERE aliases.name = cast($1::text as citext)"#,
+                        name.to_string()
+                    )
+                    .fetch_optional(connection)
+                    .await?;
+
+                    match alias {
+                        Some(row) =>
+                            Ok(MinimalDemon {
+                                id: row.id,
+                                position: row.position,
+                                name: row.name,
+                            }),
+                        None =>
+                            Err(DemonlistError::DemonNotFoundName {
+                                demon_name: name.to_string(),
+                            }),
+                    }
+                },
             }
         } else {
             further_demons.extend(demon);
@@ -72,19 +101,31 @@ impl FullDemon {
     pub async fn by_position(position: i16, connection: &mut PgConnection) -> Result<FullDemon> {
         Demon::by_position(position, connection).await?.upgrade(connection).await
     }
+
+    /// Loads the creators and approved records of all given demons at once
+    async fn assemble(demons: Vec<Demon>, connection: &mut PgConnection) -> Result<Vec<FullDemon>> {
+        let ids: Vec<i32> = demons.iter().map(|demon| demon.base.id).collect();
+        let mut creators = creators_of_all(&ids, connection).await?;
+        let mut records = approved_records_on_all(&ids, connection).await?;
+
+        Ok(demons
+            .into_iter()
+            .map(|demon| {
+                FullDemon {
+                    creators: creators.remove(&demon.base.id).unwrap_or_default(),
+                    records: records.remove(&demon.base.id).unwrap_or_default(),
+                    demon,
+                }
+            })
+            .collect())
+    }
 }
 
 // FIXME: optimally, we want to only have one of these
 impl Demon {
     async fn upgrade(self, connection: &mut PgConnection) -> Result<FullDemon> {
-        let creators = creators_of(&self.base, connection).await?;
-        let records = approved_records_on(&self.base, connection).await?;
-
-        Ok(FullDemon {
-            demon: self,
-            creators,
-            records,
-        })
+        // assemble returns exactly one demon per demon passed in
+        Ok(FullDemon::assemble(vec![self], connection).await?.remove(0))
     }
 
     pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<Demon> {
@@ -101,7 +142,7 @@ impl Demon {
     }
 
     pub async fn by_position(position: i16, connection: &mut PgConnection) -> Result<Demon> {
-        sqlx::query_file_as!(FetchedDemon, "sql/demon_by_position.sql", position)
+        sqlx::query_file_as!(FetchedDemon, "sql/demon_by_position.sql", position, CLASSIC_LIST)
             .fetch_one(connection)
             .await
             .map(Into::into)
@@ -162,6 +203,21 @@ struct FetchedDemon {
     verifier_name: String,
     verifier_banned: bool,
     level_id: Option<i64>,
+    thumbnail: Option<String>,
+    requires_timestamp: bool,
+    victors: i64,
+    first_victor_id: Option<i32>,
+    first_victor_name: Option<String>,
+    first_victor_banned: Option<bool>,
+}
+
+/// Assembles a [`Demon`]'s first victor from the (nullable) columns selected from the
+/// `demon_victors` view
+pub(crate) fn first_victor_from_columns(id: Option<i32>, name: Option<String>, banned: Option<bool>) -> Option<DatabasePlayer> {
+    match (id, name, banned) {
+        (Some(id), Some(name), Some(banned)) => Some(DatabasePlayer { id, name, banned }),
+        _ => None,
+    }
 }
 
 impl Into<Demon> for FetchedDemon {
@@ -185,12 +241,17 @@ impl Into<Demon> for FetchedDemon {
                 banned: self.verifier_banned,
             },
             level_id: self.level_id.map(|id| id as u64),
+            thumbnail: self.thumbnail,
+            requires_timestamp: self.requires_timestamp,
+            victors: self.victors,
+            first_victor: first_victor_from_columns(self.first_victor_id, self.first_victor_name, self.first_victor_banned),
+            pending: None,
         }
     }
 }
 
 pub async fn current_list(connection: &mut PgConnection) -> Result<Vec<Demon>> {
-    Ok(sqlx::query_file_as!(FetchedDemon, "sql/all_demons.sql")
+    Ok(sqlx::query_file_as!(FetchedDemon, "sql/all_demons.sql", CLASSIC_LIST)
         .fetch_all(connection)
         .await?
         .into_iter()
@@ -198,8 +259,14 @@ pub async fn current_list(connection: &mut PgConnection) -> Result<Vec<Demon>> {
         .collect())
 }
 
+/// The earliest point in time the list's history is known for
+pub fn beginning_of_time() -> DateTime<FixedOffset> {
+    FixedOffset::east(0).from_utc_datetime(&NaiveDate::from_ymd(2017, 1, 4).and_hms(0, 0, 0))
+}
+
+/// Reconstructs the main list as it was at the given point in time
 pub async fn list_at(connection: &mut PgConnection, at: DateTime<FixedOffset>) -> Result<Vec<TimeShiftedDemon>> {
-    let mut stream = sqlx::query_file!("sql/all_demons_at.sql", at.naive_utc()).fetch(connection);
+    let mut stream = sqlx::query_file!("sql/all_demons_at.sql", at.naive_utc(), CLASSIC_LIST).fetch(connection);
     let mut demons = Vec::new();
 
     while let Some(row) = stream.next().await {
@@ -225,6 +292,11 @@ pub async fn list_at(connection: &mut PgConnection, at: DateTime<FixedOffset>) -
                     banned: row.verifier_banned,
                 },
                 level_id: row.level_id.map(|i| i as u64),
+                thumbnail: row.thumbnail,
+                requires_timestamp: row.requires_timestamp,
+                victors: row.victors,
+                first_victor: first_victor_from_columns(row.first_victor_id, row.first_victor_name, row.first_victor_banned),
+                pending: None,
             },
             position_now: row.current_position,
         })
@@ -232,3 +304,66 @@ pub async fn list_at(connection: &mut PgConnection, at: DateTime<FixedOffset>) -
 
     Ok(demons)
 }
+
+/// Query parameters for the incremental sync endpoint
+#[derive(Debug, Deserialize)]
+pub struct DemonsChangedSince {
+    /// Point in time, in UTC
+    pub since: NaiveDateTime,
+}
+
+/// Gets all demons on the main list whose state changed after the given point in time, in the order
+/// they were changed
+pub async fn changed_since(since: NaiveDateTime, connection: &mut PgConnection) -> Result<Vec<ModifiedDemon>> {
+    let mut stream = sqlx::query_file!("sql/demons_changed_since.sql", since, CLASSIC_LIST).fetch(connection);
+    let mut demons = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        demons.push(ModifiedDemon {
+            demon: Demon {
+                base: MinimalDemon {
+                    id: row.demon_id,
+                    position: row.position,
+                    name: row.demon_name,
+                },
+                requirement: row.requirement,
+                video: row.video,
+                publisher: DatabasePlayer {
+                    id: row.publisher_id,
+                    name: row.publisher_name,
+                    banned: row.publisher_banned,
+                },
+                verifier: DatabasePlayer {
+                    id: row.verifier_id,
+                    name: row.verifier_name,
+                    banned: row.verifier_banned,
+                },
+                level_id: row.level_id.map(|i| i as u64),
+                thumbnail: row.thumbnail,
+                requires_timestamp: row.requires_timestamp,
+                victors: row.victors,
+                first_victor: first_victor_from_columns(row.first_victor_id, row.first_victor_name, row.first_victor_banned),
+                pending: None,
+            },
+            last_modified: row.last_modified,
+        })
+    }
+
+    Ok(demons)
+}
+
+impl Demon {
+    /// Gets the point in time (in UTC) the demon with the given id was last modified
+    pub async fn last_modified(id: i32, connection: &mut PgConnection) -> Result<NaiveDateTime> {
+        match sqlx::query!("SELECT last_modified FROM demons WHERE id = $1", id)
+            .fetch_one(connection)
+            .await
+        {
+            Ok(row) => Ok(row.last_modified),
+            Err(Error::RowNotFound) => Err(DemonlistError::DemonNotFound { demon_id: id }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
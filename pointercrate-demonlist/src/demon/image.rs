@@ -0,0 +1,77 @@
+//! Module containing validation of demon thumbnails
+//!
+//! Thumbnails can either be set to an arbitrary (http/https) URL, or be uploaded as images, in
+//! which case only PNG, JPEG and WebP files are accepted. The format of uploaded images is
+//! determined by their content, not by whatever content type the client claims.
+
+use crate::error::Result;
+use pointercrate_core::error::CoreError;
+use url::Url;
+
+const SCHEMES: [&str; 2] = ["http", "https"];
+const SUPPORTED_FORMATS: &str = "image/png' or 'image/jpeg' or 'image/webp";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    /// Determines the format of the given image data based on its magic bytes
+    pub fn detect(data: &[u8]) -> Result<ImageFormat> {
+        match data {
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Ok(ImageFormat::Png),
+            [0xFF, 0xD8, 0xFF, ..] => Ok(ImageFormat::Jpeg),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Ok(ImageFormat::Webp),
+            _ =>
+                Err(CoreError::UnsupportedMediaType {
+                    expected: SUPPORTED_FORMATS,
+                }
+                .into()),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// Validates a thumbnail URL
+///
+/// Apart from absolute http(s) URLs, paths relative to the site root (such as the URLs of images
+/// uploaded to pointercrate itself) are accepted.
+pub fn validate_url(url: &str) -> Result<String> {
+    if url.starts_with('/') && !url.starts_with("//") {
+        return Ok(url.to_string())
+    }
+
+    let url = Url::parse(url).map_err(|_| {
+        CoreError::InvalidUrlFormat {
+            expected: "https://{host}/{path}",
+        }
+    })?;
+
+    if !SCHEMES.contains(&url.scheme()) {
+        return Err(CoreError::InvalidUrlScheme.into())
+    }
+
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(CoreError::UrlAuthenticated.into())
+    }
+
+    Ok(url.to_string())
+}
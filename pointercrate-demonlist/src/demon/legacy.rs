@@ -0,0 +1,174 @@
+//! Module for the legacy list, consisting of all demons beyond the extended section of the classic
+//! list
+//!
+//! Legacy demons are numbered in the order they dropped off the extended list, with the most recent
+//! one having the highest legacy position. These positions are frozen: Changes to the main list
+//! never affect them, unless a legacy demon is moved back onto the extended list, in which case it
+//! loses its legacy position.
+
+use crate::{
+    demon::{get::first_victor_from_columns, Demon, MinimalDemon},
+    error::Result,
+    list::{DemonList, CLASSIC_LIST},
+    player::DatabasePlayer,
+};
+use futures::stream::StreamExt;
+use log::info;
+use pointercrate_core::{error::CoreError, util::non_nullable};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Row};
+
+/// A [`Demon`] on the legacy list
+#[derive(Debug, Serialize)]
+pub struct LegacyDemon {
+    #[serde(flatten)]
+    pub demon: Demon,
+
+    pub legacy_position: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LegacyPagination {
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "before")]
+    pub before_legacy_position: Option<i32>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "after")]
+    pub after_legacy_position: Option<i32>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub limit: Option<u8>,
+}
+
+impl LegacyPagination {
+    pub async fn page(&self, connection: &mut PgConnection) -> Result<Vec<LegacyDemon>> {
+        if let Some(limit) = self.limit {
+            if limit < 1 || limit > 100 {
+                Err(CoreError::InvalidPaginationLimit)?
+            }
+        }
+
+        if let (Some(after), Some(before)) = (self.before_legacy_position, self.after_legacy_position) {
+            if after < before {
+                Err(CoreError::AfterSmallerBefore)?
+            }
+        }
+
+        let order = if self.after_legacy_position.is_none() && self.before_legacy_position.is_some() {
+            "DESC"
+        } else {
+            "ASC"
+        };
+
+        let query = format!(include_str!("../../sql/paginate_legacy_demons.sql"), order);
+
+        let mut stream = sqlx::query(&query)
+            .bind(self.before_legacy_position)
+            .bind(self.after_legacy_position)
+            .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .fetch(connection);
+
+        let mut demons = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            demons.push(LegacyDemon {
+                demon: Demon {
+                    base: MinimalDemon {
+                        id: row.get("demon_id"),
+                        name: row.get("demon_name"),
+                        position: row.get("position"),
+                    },
+                    requirement: row.get("requirement"),
+                    video: row.get("video"),
+                    publisher: DatabasePlayer {
+                        id: row.get("publisher_id"),
+                        name: row.get("publisher_name"),
+                        banned: row.get("publisher_banned"),
+                    },
+                    verifier: DatabasePlayer {
+                        id: row.get("verifier_id"),
+                        name: row.get("verifier_name"),
+                        banned: row.get("verifier_banned"),
+                    },
+                    level_id: row.get::<Option<i64>, _>("level_id").map(|id| id as u64),
+                    thumbnail: row.get("thumbnail"),
+                    requires_timestamp: row.get("requires_timestamp"),
+                    victors: row.get("victors"),
+                    first_victor: first_victor_from_columns(
+                        row.get("first_victor_id"),
+                        row.get("first_victor_name"),
+                        row.get("first_victor_banned"),
+                    ),
+                    pending: None,
+                },
+                legacy_position: row.get("legacy_position"),
+            })
+        }
+
+        Ok(demons)
+    }
+}
+
+/// Gets the maximal and minimal legacy position currently in use
+///
+/// The returned tuple is of the form (max, min). If the legacy list is empty, both are 0.
+pub async fn extremal_legacy_positions(connection: &mut PgConnection) -> Result<(i32, i32)> {
+    let row = sqlx::query!(
+        r#"SELECT COALESCE(MAX(legacy_position), 0) AS "max!: i32", COALESCE(MIN(legacy_position), 0) AS "min!: i32" FROM demons"#
+    )
+    .fetch_one(connection)
+    .await?;
+
+    Ok((row.max, row.min))
+}
+
+/// Brings legacy positions in line with the current state of the list
+///
+/// Demons that were moved back onto the extended list lose their legacy position, and demons that
+/// dropped off it are appended to the legacy list (in order of their current position, so that
+/// the highest one becomes the newest legacy demon). Existing legacy positions are never changed.
+///
+/// Must be called after every change to demon positions, inside the same transaction
+pub async fn freeze_legacy_positions(connection: &mut PgConnection) -> Result<()> {
+    let extended_list_size = DemonList::by_id(CLASSIC_LIST, &mut *connection).await?.extended_list_size;
+
+    freeze_legacy_positions_at(extended_list_size, connection).await
+}
+
+/// Like [`freeze_legacy_positions`], but for the given extended list size instead of the cached one
+///
+/// Needed when the extended list size changes, as the new size is only cached once the change has
+/// been committed (see [`ListConfig::cache`](crate::list_config::ListConfig::cache))
+pub async fn freeze_legacy_positions_at(extended_list_size: i16, connection: &mut PgConnection) -> Result<()> {
+    let cleared = sqlx::query!(
+        "UPDATE demons SET legacy_position = NULL WHERE position <= $1 AND list_id = $2 AND legacy_position IS NOT NULL",
+        extended_list_size,
+        CLASSIC_LIST
+    )
+    .execute(&mut *connection)
+    .await?
+    .rows_affected();
+
+    let frozen = sqlx::query!(
+        "UPDATE demons SET legacy_position = numbered.legacy_position FROM (SELECT id, (SELECT COALESCE(MAX(legacy_position), 0) FROM \
+         demons) + ROW_NUMBER() OVER (ORDER BY position DESC) AS legacy_position FROM demons WHERE position > $1 AND list_id = $2 AND \
+         legacy_position IS NULL) AS numbered WHERE demons.id = numbered.id",
+        extended_list_size,
+        CLASSIC_LIST
+    )
+    .execute(connection)
+    .await?
+    .rows_affected();
+
+    if cleared != 0 || frozen != 0 {
+        info!(
+            "Legacy list changed: {} demons moved back onto the extended list, {} demons dropped off it",
+            cleared, frozen
+        );
+    }
+
+    Ok(())
+}
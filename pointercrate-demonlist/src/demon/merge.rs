@@ -0,0 +1,113 @@
+use crate::{
+    demon::{legacy, MinimalDemon},
+    error::{DemonlistError, Result},
+    record::FullRecord,
+    score,
+};
+use log::info;
+use sqlx::PgConnection;
+
+impl MinimalDemon {
+    /// Merges the given demon into `self`, deleting `duplicate`
+    ///
+    /// All records (including deleted ones), creators, pack memberships, aliases and modification
+    /// history of `duplicate` are transferred to `self`. Conflicting records are resolved the same
+    /// way as when changing the demon of a single record. Demons below `duplicate` move up by one
+    /// position.
+    ///
+    /// Must be called inside a transaction
+    pub async fn merge(&self, duplicate: MinimalDemon, connection: &mut PgConnection) -> Result<()> {
+        if self.id == duplicate.id {
+            return Err(DemonlistError::SelfMerge)
+        }
+
+        info!("Merging demon {} into demon {}", duplicate, self);
+
+        let list_id = duplicate.list_id(&mut *connection).await?;
+
+        for row in sqlx::query!("SELECT id FROM records WHERE demon = $1", duplicate.id)
+            .fetch_all(&mut *connection)
+            .await?
+        {
+            // Previous iterations might have deleted this record while resolving conflicts
+            let mut record = match FullRecord::by_id(row.id, &mut *connection).await {
+                Err(DemonlistError::RecordNotFound { .. }) => continue,
+                result => result?,
+            };
+
+            info!("Moving record {} over to demon {}", record, self);
+
+            record.move_to_demon(self.clone(), &mut *connection).await?;
+        }
+
+        let archived = sqlx::query!("UPDATE deleted_records SET demon = $1 WHERE demon = $2", self.id, duplicate.id)
+            .execute(&mut *connection)
+            .await?;
+
+        info!(
+            "Transferred {} deleted records from {} to {}",
+            archived.rows_affected(),
+            duplicate,
+            self
+        );
+
+        // Delete duplicate creator entries before transferring the rest
+        sqlx::query!(
+            "DELETE FROM creators AS c1 WHERE c1.demon = $2 AND EXISTS (SELECT 1 FROM creators AS c2 WHERE c2.creator = c1.creator AND \
+             c2.demon = $1)",
+            self.id,
+            duplicate.id
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        let creators = sqlx::query!("UPDATE creators SET demon = $1 WHERE demon = $2", self.id, duplicate.id)
+            .execute(&mut *connection)
+            .await?;
+
+        info!(
+            "Transferred {} creator entries from {} to {}",
+            creators.rows_affected(),
+            duplicate,
+            self
+        );
+
+        // Packs containing both demons keep only one of them
+        sqlx::query!(
+            "UPDATE pack_demons SET demon = $1 WHERE demon = $2 AND NOT EXISTS (SELECT 1 FROM pack_demons AS other WHERE other.pack = \
+             pack_demons.pack AND other.demon = $1)",
+            self.id,
+            duplicate.id
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        sqlx::query!("UPDATE aliases SET demon = $1 WHERE demon = $2", self.id, duplicate.id)
+            .execute(&mut *connection)
+            .await?;
+
+        sqlx::query!("UPDATE demon_modifications SET id = $1 WHERE id = $2", self.id, duplicate.id)
+            .execute(&mut *connection)
+            .await?;
+
+        sqlx::query!("DELETE FROM demons WHERE id = $1", duplicate.id)
+            .execute(&mut *connection)
+            .await?;
+
+        // Close the gap left by the duplicate
+        sqlx::query!(
+            "UPDATE demons SET position = position - 1, last_modified = (NOW() AT TIME ZONE 'utc') WHERE position > $1 AND list_id = $2",
+            duplicate.position,
+            list_id
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        legacy::freeze_legacy_positions(&mut *connection).await?;
+        score::refresh_player_scores(connection).await?;
+
+        info!("Successfully merged demon {} into {}", duplicate, self);
+
+        Ok(())
+    }
+}
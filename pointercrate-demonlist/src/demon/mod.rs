@@ -1,18 +1,22 @@
 pub use self::{
-    get::{current_list, list_at, published_by, verified_by},
+    get::{beginning_of_time, changed_since, current_list, list_at, published_by, verified_by, DemonsChangedSince},
+    legacy::{LegacyDemon, LegacyPagination},
     paginate::{DemonIdPagination, DemonPositionPagination},
     patch::PatchDemon,
     post::PostDemon,
 };
 use crate::{
     error::{DemonlistError, Result},
+    list::{DemonList, CLASSIC_LIST},
     player::DatabasePlayer,
     record::MinimalRecordP,
+    score::ScoreFormula,
 };
+use chrono::NaiveDateTime;
 use derive_more::Display;
 use log::info;
-use pointercrate_core::{error::CoreError, etag::Taggable};
-use serde::Serialize;
+use pointercrate_core::etag::Taggable;
+use serde::{Deserialize, Serialize};
 use sqlx::PgConnection;
 use std::{
     collections::hash_map::DefaultHasher,
@@ -22,17 +26,39 @@ use std::{
 #[macro_use]
 mod get;
 pub mod audit;
+pub mod image;
+pub mod legacy;
+mod merge;
 mod paginate;
 mod patch;
 mod post;
 
+/// A [`Demon`] as it was placed at some point in the past
+///
+/// The position of `current_demon` is the demon's position at that point in time
+#[derive(Debug, Serialize)]
 pub struct TimeShiftedDemon {
+    #[serde(flatten)]
     pub current_demon: Demon,
+
+    /// The position the demon is at nowadays
+    #[serde(rename = "current_position")]
     pub position_now: i16,
 }
 
+/// A [`Demon`] together with the time its state was last changed
+#[derive(Debug, Serialize)]
+pub struct ModifiedDemon {
+    #[serde(flatten)]
+    pub demon: Demon,
+
+    /// The point in time (in UTC) this demon was last patched, had its position changed due to
+    /// some other demon being moved or added, or had its victors change
+    pub last_modified: NaiveDateTime,
+}
+
 /// Struct modelling a demon. These objects are returned from the paginating `/demons/` endpoint
-#[derive(Debug, Serialize, Hash, Display, Eq, PartialEq)]
+#[derive(Debug, Serialize, Hash, Display, Eq, PartialEq, Clone)]
 #[display(fmt = "{}", base)]
 pub struct Demon {
     #[serde(flatten)]
@@ -42,6 +68,10 @@ pub struct Demon {
     /// accepted
     pub requirement: i16,
 
+    /// Whether submissions for this [`Demon`] need to link to the timestamp of the completion in
+    /// their video
+    pub requires_timestamp: bool,
+
     pub video: Option<String>,
 
     /// This [`Demon`]'s publisher
@@ -55,6 +85,20 @@ pub struct Demon {
     /// This is automatically queried based on the level name, but can be manually overridden by a
     /// list mod.
     pub level_id: Option<u64>,
+
+    /// URL of a thumbnail image for this [`Demon`], if one was set
+    pub thumbnail: Option<String>,
+
+    /// The number of players with an approved 100% record on this [`Demon`]
+    pub victors: i64,
+
+    /// The player whose approved 100% record on this [`Demon`] was submitted first
+    pub first_victor: Option<DatabasePlayer>,
+
+    /// The number of submissions for this [`Demon`] that are waiting for review. Only ever
+    /// included for members of the list team.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending: Option<i64>,
 }
 
 /// Absolutely minimal representation of a demon to be sent when a demon is part of another object
@@ -75,11 +119,25 @@ pub struct MinimalDemon {
     pub name: String,
 }
 
+/// The sections a list is split into based on position (see [`DemonList::section_of`])
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSection {
+    /// The top [`DemonList::list_size`] demons
+    Main,
+
+    /// The demons up to [`DemonList::extended_list_size`], only 100% records are accepted here
+    Extended,
+
+    /// Everything below the extended list. No submissions are accepted here.
+    Legacy,
+}
+
 /// Struct modelling the "full" version of a demon.
 ///
 /// In addition to containing publisher/verifier information it also contains a list of the demon's
 /// creators and a list of accepted records
-#[derive(Debug, Serialize, Display, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Display, PartialEq, Eq, Hash, Clone)]
 #[display(fmt = "{}", demon)]
 pub struct FullDemon {
     #[serde(flatten)]
@@ -91,12 +149,26 @@ pub struct FullDemon {
 impl Taggable for FullDemon {
     fn patch_part(&self) -> u64 {
         let mut hasher = DefaultHasher::new();
-        self.demon.hash(&mut hasher);
+        self.demon.base.hash(&mut hasher);
+        self.demon.requirement.hash(&mut hasher);
+        self.demon.requires_timestamp.hash(&mut hasher);
+        self.demon.video.hash(&mut hasher);
+        self.demon.publisher.hash(&mut hasher);
+        self.demon.verifier.hash(&mut hasher);
+        self.demon.level_id.hash(&mut hasher);
+        self.demon.thumbnail.hash(&mut hasher);
+        // victors, first victor and pending submissions are derived from the demon's records and
+        // cannot be patched -> no hash
         hasher.finish()
     }
 }
 
 impl MinimalDemon {
+    /// The section of its list this demon is currently in
+    pub async fn section(&self, connection: &mut PgConnection) -> Result<ListSection> {
+        Ok(DemonList::of(self, connection).await?.section_of(self.position))
+    }
+
     /// Queries the record requirement for this demon from the database without collecting any of
     /// the other data
     pub async fn requirement(&self, connection: &mut PgConnection) -> Result<i16> {
@@ -105,6 +177,33 @@ impl MinimalDemon {
             .await?
             .requirement)
     }
+
+    /// The minimal progress submissions for this demon need to have, taking into account both the
+    /// given per-demon requirement and the position dependent requirements from
+    /// [`config::progressive_requirements`](crate::config::progressive_requirements)
+    pub fn effective_requirement(&self, requirement: i16) -> i16 {
+        crate::config::progressive_requirements()
+            .iter()
+            .find(|&&(position, _)| self.position <= position)
+            .map(|&(_, progress)| progress.max(requirement))
+            .unwrap_or(requirement)
+    }
+
+    /// Queries whether submissions for this demon need a timestamped video from the database
+    pub async fn requires_timestamp(&self, connection: &mut PgConnection) -> Result<bool> {
+        Ok(sqlx::query!("SELECT requires_timestamp FROM demons WHERE id = $1", self.id)
+            .fetch_one(connection)
+            .await?
+            .requires_timestamp)
+    }
+
+    /// Queries the id of the list this demon belongs to
+    pub async fn list_id(&self, connection: &mut PgConnection) -> Result<i32> {
+        Ok(sqlx::query!("SELECT list_id FROM demons WHERE id = $1", self.id)
+            .fetch_one(connection)
+            .await?
+            .list_id)
+    }
 }
 
 impl FullDemon {
@@ -173,8 +272,11 @@ impl Demon {
         Ok(())
     }
 
-    pub async fn validate_position(position: i16, connection: &mut PgConnection) -> Result<()> {
-        let maximal_position = Demon::max_position(connection).await?;
+    /// Validates that a new demon can be added to the given list at the given position
+    ///
+    /// Besides any position already taken, new demons can also be appended to the end of the list
+    pub async fn validate_position(position: i16, list_id: i32, connection: &mut PgConnection) -> Result<()> {
+        let maximal_position = Demon::max_position(list_id, connection).await? + 1;
 
         if position > maximal_position || position < 1 {
             return Err(DemonlistError::InvalidPosition { maximal: maximal_position })
@@ -185,35 +287,45 @@ impl Demon {
 
     /// Increments the position of all demons with positions equal to or greater than the given one,
     /// by one.
-    async fn shift_down(starting_at: i16, connection: &mut PgConnection) -> Result<()> {
-        info!("Shifting down all demons, starting at {}", starting_at);
+    async fn shift_down(starting_at: i16, list_id: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Shifting down all demons of list {}, starting at {}", list_id, starting_at);
 
-        sqlx::query!("UPDATE demons SET position = position + 1 WHERE position >= $1", starting_at)
-            .execute(connection)
-            .await?;
+        sqlx::query!(
+            "UPDATE demons SET position = position + 1, last_modified = (NOW() AT TIME ZONE 'utc') WHERE position >= $1 AND list_id = $2",
+            starting_at,
+            list_id
+        )
+        .execute(connection)
+        .await?;
 
         Ok(())
     }
 
     /// Decrements the position of all demons with positions equal to or smaller than the given one,
     /// by one.
-    async fn shift_up(until: i16, connection: &mut PgConnection) -> Result<()> {
-        info!("Shifting up all demons until {}", until);
+    async fn shift_up(until: i16, list_id: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Shifting up all demons of list {} until {}", list_id, until);
 
-        sqlx::query!("UPDATE demons SET position = position - 1 WHERE position <= $1", until)
-            .execute(connection)
-            .await?;
+        sqlx::query!(
+            "UPDATE demons SET position = position - 1, last_modified = (NOW() AT TIME ZONE 'utc') WHERE position <= $1 AND list_id = $2",
+            until,
+            list_id
+        )
+        .execute(connection)
+        .await?;
 
         Ok(())
     }
 
-    /// Gets the current max position a demon has
-    pub async fn max_position(connection: &mut PgConnection) -> Result<i16> {
-        sqlx::query!("SELECT MAX(position) as max_position FROM demons")
-            .fetch_one(connection)
-            .await?
-            .max_position
-            .ok_or(CoreError::NotFound.into())
+    /// Gets the current max position a demon on the given list has, or 0 if the list is empty
+    pub async fn max_position(list_id: i32, connection: &mut PgConnection) -> Result<i16> {
+        Ok(sqlx::query!(
+            r#"SELECT COALESCE(MAX(position), 0)::SMALLINT AS "max_position!" FROM demons WHERE list_id = $1"#,
+            list_id
+        )
+        .fetch_one(connection)
+        .await?
+        .max_position)
     }
 
     /// Gets the maximal and minimal submitter id currently in use
@@ -226,31 +338,16 @@ impl Demon {
         Ok((row.max_id, row.min_id))
     }
 
-    pub fn score(&self, progress: i16) -> f64 {
-        let position = self.base.position;
-
-        let beaten_score = if 125 < position && position <= 150 {
-            150f64 * f64::exp((1f64 - f64::from(position)) * (1f64 / 30f64).ln() / (-149f64))
-        } else if 50 < position && position <= 125 {
-            let a = 2.333f64;
-            let b = 1.884f64;
-            60f64 * (a.powf((51 - position) as f64 * ((30f64.ln()) / 99f64))) + b
-        } else if 20 < position && position <= 50 {
-            let c = 1.01327f64;
-            let d = 26.489f64;
-            -100f64 * (c.powf(position as f64 - d)) + 200f64
-        } else if 0 < position && position <= 20 {
-            let e = 1.168f64;
-            let f = 100.39f64;
-            (250f64 - f) * (e.powf(1f64 - position as f64) as f64) + f
-        } else {
-            0f64
-        };
-
-        if progress != 100 {
-            (beaten_score * (5f64.powf((progress - self.requirement) as f64 / (100f64 - self.requirement as f64)))) / 10f64
-        } else {
-            beaten_score
+    /// The score a record with the given progress on this demon awards, according to the configured
+    /// [`ScoreFormula`]. `list` needs to be the list this demon is on.
+    ///
+    /// Only demons on the classic list award points, so this is always 0 for demons on any other
+    /// list.
+    pub fn score(&self, list: &DemonList, progress: i16) -> f64 {
+        if list.id != CLASSIC_LIST {
+            return 0f64
         }
+
+        ScoreFormula::configured().record_score(self.base.position, self.requirement, progress)
     }
 }
@@ -1,12 +1,13 @@
 use crate::{
-    demon::{Demon, MinimalDemon},
+    demon::{get::first_victor_from_columns, Demon, MinimalDemon},
     error::Result,
+    list::CLASSIC_LIST,
     player::DatabasePlayer,
 };
 use futures::stream::StreamExt;
 use pointercrate_core::{error::CoreError, util::non_nullable};
 use serde::{Deserialize, Serialize};
-use sqlx::{PgConnection, Row};
+use sqlx::{postgres::PgArguments, query::Query, PgConnection, Postgres, Row};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DemonIdPagination {
@@ -47,6 +48,20 @@ pub struct DemonIdPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "requirement__lt")]
     requirement_lt: Option<i16>,
+
+    /// The id of the demon's level in Geometry Dash
+    #[serde(default, deserialize_with = "non_nullable")]
+    level_id: Option<i64>,
+
+    /// The list whose demons are paginated. Set by list-scoped endpoints, defaults to the classic
+    /// list
+    #[serde(skip)]
+    pub list_id: Option<i32>,
+
+    /// Whether to include the number of pending submissions for each demon. Only set for members of
+    /// the list team.
+    #[serde(skip)]
+    pub include_pending: bool,
 }
 
 impl DemonIdPagination {
@@ -71,20 +86,8 @@ impl DemonIdPagination {
 
         let query = format!(include_str!("../../sql/paginate_demons_by_id.sql"), order);
 
-        // FIXME(sqlx) once CITEXT is supported
-        let mut stream = sqlx::query(&query)
-            .bind(self.before_id)
-            .bind(self.after_id)
-            .bind(self.name.as_ref().map(|s| s.as_str()))
-            .bind(self.requirement)
-            .bind(self.requirement_lt)
-            .bind(self.requirement_gt)
-            .bind(self.verifier_id)
-            .bind(self.verifier_name.as_ref().map(|s| s.as_str()))
-            .bind(self.publisher_id)
-            .bind(self.publisher_name.as_ref().map(|s| s.as_str()))
-            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
-            .bind(self.limit.unwrap_or(50) as i32 + 1)
+        let mut stream = self
+            .bind(&query, self.before_id, self.after_id, Some(self.limit.unwrap_or(50) as i32 + 1))
             .fetch(connection);
 
         let mut demons = Vec::new();
@@ -113,11 +116,51 @@ impl DemonIdPagination {
                     banned: row.get("verifier_banned"),
                 },
                 level_id: row.get::<Option<i64>, _>("level_id").map(|id| id as u64),
+                thumbnail: row.get("thumbnail"),
+                requires_timestamp: row.get("requires_timestamp"),
+                victors: row.get("victors"),
+                first_victor: first_victor_from_columns(
+                    row.get("first_victor_id"),
+                    row.get("first_victor_name"),
+                    row.get("first_victor_banned"),
+                ),
+                pending: if self.include_pending { Some(row.get("pending")) } else { None },
             })
         }
 
         Ok(demons)
     }
+
+    /// Counts the demons matching the filters in here, regardless of `before`, `after` and `limit`
+    pub async fn count(&self, connection: &mut PgConnection) -> Result<i64> {
+        let query = format!(include_str!("../../sql/paginate_demons_by_id.sql"), "ASC");
+        let query = format!("SELECT COUNT(*) FROM ({}) AS matching", query);
+
+        Ok(self.bind(&query, None, None, None).fetch_one(connection).await?.get(0))
+    }
+
+    /// Binds the filters in here to the given query (based on `paginate_demons_by_id.sql`). A
+    /// limit of `None` means no limit.
+    fn bind<'q>(
+        &'q self, query: &'q str, before_id: Option<i32>, after_id: Option<i32>, limit: Option<i32>,
+    ) -> Query<'q, Postgres, PgArguments> {
+        // FIXME(sqlx) once CITEXT is supported
+        sqlx::query(query)
+            .bind(before_id)
+            .bind(after_id)
+            .bind(self.name.as_ref().map(|s| s.as_str()))
+            .bind(self.requirement)
+            .bind(self.requirement_lt)
+            .bind(self.requirement_gt)
+            .bind(self.verifier_id)
+            .bind(self.verifier_name.as_ref().map(|s| s.as_str()))
+            .bind(self.publisher_id)
+            .bind(self.publisher_name.as_ref().map(|s| s.as_str()))
+            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(limit)
+            .bind(self.list_id.unwrap_or(CLASSIC_LIST))
+            .bind(self.level_id)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -159,6 +202,20 @@ pub struct DemonPositionPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "requirement__lt")]
     requirement_lt: Option<i16>,
+
+    /// The id of the demon's level in Geometry Dash
+    #[serde(default, deserialize_with = "non_nullable")]
+    level_id: Option<i64>,
+
+    /// The list whose demons are paginated. Set by list-scoped endpoints, defaults to the classic
+    /// list
+    #[serde(skip)]
+    pub list_id: Option<i32>,
+
+    /// Whether to include the number of pending submissions for each demon. Only set for members of
+    /// the list team.
+    #[serde(skip)]
+    pub include_pending: bool,
 }
 
 impl DemonPositionPagination {
@@ -197,6 +254,8 @@ impl DemonPositionPagination {
             .bind(self.publisher_name.as_ref().map(|s| s.as_str()))
             .bind(self.name_contains.as_ref().map(|s| s.as_str()))
             .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .bind(self.list_id.unwrap_or(CLASSIC_LIST))
+            .bind(self.level_id)
             .fetch(connection);
 
         let mut demons = Vec::new();
@@ -225,6 +284,15 @@ impl DemonPositionPagination {
                     banned: row.get("verifier_banned"),
                 },
                 level_id: row.get::<Option<i64>, _>("level_id").map(|id| id as u64),
+                thumbnail: row.get("thumbnail"),
+                requires_timestamp: row.get("requires_timestamp"),
+                victors: row.get("victors"),
+                first_victor: first_victor_from_columns(
+                    row.get("first_victor_id"),
+                    row.get("first_victor_name"),
+                    row.get("first_victor_banned"),
+                ),
+                pending: if self.include_pending { Some(row.get("pending")) } else { None },
             })
         }
 
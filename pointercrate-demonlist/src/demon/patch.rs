@@ -1,14 +1,19 @@
 use crate::{
-    demon::{Demon, FullDemon, MinimalDemon},
+    demon::{image, legacy, Demon, FullDemon, MinimalDemon},
     error::{DemonlistError, Result},
     player::DatabasePlayer,
+    record::{FullRecord, RecordStatus},
+    score,
 };
 use log::{debug, info, warn};
-use pointercrate_core::util::{non_nullable, nullable};
+use pointercrate_core::{
+    audit::PatchLog,
+    util::{non_nullable, nullable},
+};
 use serde::Deserialize;
 use sqlx::PgConnection;
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct PatchDemon {
     #[serde(default, deserialize_with = "non_nullable")]
     pub name: Option<String>,
@@ -22,11 +27,20 @@ pub struct PatchDemon {
     #[serde(default, deserialize_with = "non_nullable")]
     pub requirement: Option<i16>,
 
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub requires_timestamp: Option<bool>,
+
+    #[serde(default, deserialize_with = "nullable")]
+    pub thumbnail: Option<Option<String>>,
+
     #[serde(default, deserialize_with = "non_nullable")]
     pub verifier: Option<String>,
 
     #[serde(default, deserialize_with = "non_nullable")]
     pub publisher: Option<String>,
+
+    #[serde(default, deserialize_with = "nullable")]
+    pub level_id: Option<Option<i64>>,
 }
 
 impl FullDemon {
@@ -51,6 +65,20 @@ impl Demon {
     pub async fn apply_patch(mut self, patch: PatchDemon, connection: &mut PgConnection) -> Result<Self> {
         // duplicate names are OK nowadays
 
+        let log = PatchLog::start("demon", self.base.id, &self);
+        let original = self.clone();
+
+        // Players whose records might get deleted by a raised requirement need to be determined
+        // before the change
+        let mut affected_players = match patch.requirement {
+            Some(requirement) if requirement != self.requirement => {
+                let list_id = self.base.list_id(&mut *connection).await?;
+
+                score::players_between(list_id, self.base.position, self.base.position, &mut *connection).await?
+            },
+            _ => Vec::new(),
+        };
+
         if let Some(position) = patch.position {
             self.base.mv(position, connection).await?;
         }
@@ -66,6 +94,20 @@ impl Demon {
             }
         }
 
+        if let Some(thumbnail) = patch.thumbnail {
+            match thumbnail {
+                None => self.remove_thumbnail(connection).await?,
+                Some(thumbnail) => self.set_thumbnail(thumbnail, connection).await?,
+            }
+        }
+
+        if let Some(level_id) = patch.level_id {
+            match level_id {
+                None => self.remove_level_id(connection).await?,
+                Some(level_id) => self.set_level_id(level_id, connection).await?,
+            }
+        }
+
         if let Some(verifier) = patch.verifier {
             let player = DatabasePlayer::by_name_or_create(verifier.as_ref(), connection).await?;
 
@@ -79,7 +121,46 @@ impl Demon {
         }
 
         if let Some(requirement) = patch.requirement {
-            self.set_requirement(requirement, connection).await?;
+            self.set_requirement(requirement, &mut *connection).await?;
+        }
+
+        if let Some(requires_timestamp) = patch.requires_timestamp {
+            self.set_requires_timestamp(requires_timestamp, &mut *connection).await?;
+        }
+
+        if self == original {
+            return Ok(self)
+        }
+
+        sqlx::query!(
+            "UPDATE demons SET last_modified = (NOW() AT TIME ZONE 'utc') WHERE id = $1",
+            self.base.id
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        log.finish(&self, &mut *connection).await?;
+
+        if self.base.position != original.base.position {
+            // Every demon between the old and the new position moved, changing the scores of everyone
+            // who completed any of them
+            let list_id = self.base.list_id(&mut *connection).await?;
+            let from = self.base.position.min(original.base.position);
+            let to = self.base.position.max(original.base.position);
+
+            affected_players.extend(score::players_between(list_id, from, to, &mut *connection).await?);
+        }
+
+        if self.verifier.id != original.verifier.id {
+            affected_players.push(original.verifier.id);
+            affected_players.push(self.verifier.id);
+        }
+
+        if !affected_players.is_empty() {
+            affected_players.sort_unstable();
+            affected_players.dedup();
+
+            score::refresh_scores_of(&affected_players, connection).await?;
         }
 
         Ok(self)
@@ -109,15 +190,46 @@ impl Demon {
         Ok(())
     }
 
+    /// Sets whether future submissions for this demon need to link to the timestamp of the
+    /// completion. Existing records are not affected.
+    pub async fn set_requires_timestamp(&mut self, requires_timestamp: bool, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!(
+            "UPDATE demons SET requires_timestamp = $1 WHERE id = $2",
+            requires_timestamp,
+            self.base.id
+        )
+        .execute(connection)
+        .await?;
+
+        self.requires_timestamp = requires_timestamp;
+
+        Ok(())
+    }
+
     pub async fn set_requirement(&mut self, requirement: i16, connection: &mut PgConnection) -> Result<()> {
         if requirement < 0 || requirement > 100 {
             return Err(DemonlistError::InvalidRequirement)
         }
 
-        // Delete associated notes
-        sqlx::query!("DELETE FROM records WHERE demon = $1 AND progress < $2", self.base.id, requirement)
-            .execute(&mut *connection)
-            .await?;
+        // Delete all records that no longer meet the requirement. Rejected records are kept, so that
+        // nobody can resubmit them (see also `reject_records_below`)
+        let below_requirement = sqlx::query!(
+            "SELECT id FROM records WHERE demon = $1 AND progress < $2 AND status_ <> 'REJECTED'",
+            self.base.id,
+            requirement
+        )
+        .fetch_all(&mut *connection)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<Vec<_>>();
+
+        FullRecord::delete_many(
+            &below_requirement,
+            &format!("Progress below the demon's new requirement of {}%", requirement),
+            &mut *connection,
+        )
+        .await?;
 
         sqlx::query!("UPDATE demons SET requirement = $1 WHERE id = $2", requirement, self.base.id)
             .execute(connection)
@@ -128,6 +240,41 @@ impl Demon {
         Ok(())
     }
 
+    /// Rejects all approved records on this demon whose progress is below the given requirement,
+    /// returning how many records were rejected
+    ///
+    /// Meant to be called before raising this demon's requirement, which would otherwise delete
+    /// these records. Must be called inside a transaction.
+    pub async fn reject_records_below(&self, requirement: i16, connection: &mut PgConnection) -> Result<u64> {
+        let mut rejected = 0;
+
+        for row in sqlx::query!(
+            "SELECT id FROM records WHERE demon = $1 AND status_ = 'APPROVED' AND progress < $2",
+            self.base.id,
+            requirement
+        )
+        .fetch_all(&mut *connection)
+        .await?
+        {
+            let mut record = FullRecord::by_id(row.id, &mut *connection).await?;
+
+            info!(
+                "Rejecting record {} since it does not meet the new requirement of {}%",
+                record, requirement
+            );
+
+            let log = PatchLog::start("record", record.id, &record);
+
+            record.set_status(RecordStatus::Rejected, &mut *connection).await?;
+
+            log.finish(&record, &mut *connection).await?;
+
+            rejected += 1;
+        }
+
+        Ok(rejected)
+    }
+
     pub async fn set_video(&mut self, video: String, connection: &mut PgConnection) -> Result<()> {
         let video = crate::video::validate(&video)?;
 
@@ -149,6 +296,74 @@ impl Demon {
 
         Ok(())
     }
+
+    /// Sets the id of this demon's level in Geometry Dash. Level ids are unique among all demons of
+    /// the same list.
+    pub async fn set_level_id(&mut self, level_id: i64, connection: &mut PgConnection) -> Result<()> {
+        if level_id <= 0 {
+            return Err(DemonlistError::InvalidLevelId)
+        }
+
+        let holder = sqlx::query!(
+            r#"SELECT id, name AS "name: String", position FROM demons WHERE level_id = $1 AND id <> $2 AND list_id = (SELECT list_id FROM
+             demons WHERE id = $2)"#,
+            level_id,
+            self.base.id
+        )
+        .fetch_optional(&mut *connection)
+        .await?;
+
+        if let Some(row) = holder {
+            return Err(DemonlistError::LevelIdTaken {
+                level_id,
+                demon: MinimalDemon {
+                    id: row.id,
+                    name: row.name,
+                    position: row.position,
+                },
+            })
+        }
+
+        sqlx::query!("UPDATE demons SET level_id = $1 WHERE id = $2", level_id, self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.level_id = Some(level_id as u64);
+
+        Ok(())
+    }
+
+    pub async fn remove_level_id(&mut self, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!("UPDATE demons SET level_id = NULL WHERE id = $1", self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.level_id = None;
+
+        Ok(())
+    }
+
+    pub async fn set_thumbnail(&mut self, thumbnail: String, connection: &mut PgConnection) -> Result<()> {
+        let thumbnail = image::validate_url(&thumbnail)?;
+
+        sqlx::query!("UPDATE demons SET thumbnail = $1 WHERE id = $2", thumbnail, self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.thumbnail = Some(thumbnail);
+
+        Ok(())
+    }
+
+    pub async fn remove_thumbnail(&mut self, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!("UPDATE demons SET thumbnail = NULL WHERE id = $1", self.base.id)
+            .execute(connection)
+            .await?;
+
+        self.thumbnail = None;
+
+        Ok(())
+    }
 }
 
 impl MinimalDemon {
@@ -167,9 +382,10 @@ impl MinimalDemon {
     /// Moves this demon to the specified position
     ///
     /// Validates that `to` is `> 0` and less than or equal to the currently highest position on the
-    /// list (to preven "holes")
+    /// list (to preven "holes"). Only demons on the same list as this demon are shifted.
     pub async fn mv(&mut self, to: i16, connection: &mut PgConnection) -> Result<()> {
-        let maximal_position = Demon::max_position(connection).await?;
+        let list_id = self.list_id(connection).await?;
+        let maximal_position = Demon::max_position(list_id, connection).await?;
 
         if to > maximal_position || to < 1 {
             return Err(DemonlistError::InvalidPosition { maximal: maximal_position })
@@ -195,9 +411,11 @@ impl MinimalDemon {
             );
 
             sqlx::query!(
-                "UPDATE demons SET position = position - 1 WHERE position > $1 AND position <= $2",
+                "UPDATE demons SET position = position - 1, last_modified = (NOW() AT TIME ZONE 'utc') WHERE position > $1 AND position \
+                 <= $2 AND list_id = $3",
                 self.position,
-                to
+                to,
+                list_id
             )
             .execute(&mut *connection)
             .await?;
@@ -208,9 +426,11 @@ impl MinimalDemon {
             );
 
             sqlx::query!(
-                "UPDATE demons SET position = position + 1 WHERE position >= $1 AND position < $2",
+                "UPDATE demons SET position = position + 1, last_modified = (NOW() AT TIME ZONE 'utc') WHERE position >= $1 AND position \
+                 < $2 AND list_id = $3",
                 to,
-                self.position
+                self.position,
+                list_id
             )
             .execute(&mut *connection)
             .await?;
@@ -219,9 +439,11 @@ impl MinimalDemon {
         debug!("Performing actual move to position {}", to);
 
         sqlx::query!("UPDATE demons SET position = $2 WHERE id = $1", self.id, to)
-            .execute(connection)
+            .execute(&mut *connection)
             .await?;
 
+        legacy::freeze_legacy_positions(connection).await?;
+
         info!("Moved demon {} from {} to {} successfully!", self, self.position, to);
 
         self.position = to;
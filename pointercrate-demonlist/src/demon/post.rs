@@ -1,8 +1,10 @@
 use crate::{
     creator::Creator,
-    demon::{Demon, FullDemon, MinimalDemon},
+    demon::{legacy, Demon, FullDemon, MinimalDemon},
     error::Result,
+    list::{DemonList, CLASSIC_LIST},
     player::DatabasePlayer,
+    score,
 };
 use log::info;
 use serde::Deserialize;
@@ -10,13 +12,29 @@ use sqlx::PgConnection;
 
 #[derive(Deserialize, Debug)]
 pub struct PostDemon {
-    name: String,
-    position: i16,
-    requirement: i16,
-    verifier: String,
-    publisher: String,
-    creators: Vec<String>,
-    video: Option<String>,
+    pub(crate) name: String,
+    pub(crate) position: i16,
+    pub(crate) requirement: i16,
+    pub(crate) verifier: String,
+    pub(crate) publisher: String,
+    pub(crate) creators: Vec<String>,
+    pub(crate) video: Option<String>,
+
+    /// The id of the demon's level in Geometry Dash. If not given, it is filled in automatically
+    /// once the level is found on the Geometry Dash servers.
+    #[serde(default)]
+    pub(crate) level_id: Option<i64>,
+
+    /// The slug of the list to add the demon to. Defaults to the classic list
+    #[serde(default)]
+    pub(crate) list: Option<String>,
+}
+
+impl PostDemon {
+    /// The verification video, as given (i.e. not yet normalized)
+    pub fn video(&self) -> Option<&str> {
+        self.video.as_deref()
+    }
 }
 
 impl FullDemon {
@@ -31,28 +49,36 @@ impl FullDemon {
             None => None,
         };
 
-        Demon::validate_position(data.position, connection).await?;
+        let list_id = match data.list {
+            Some(ref slug) => DemonList::by_slug(slug, connection).await?.id,
+            None => CLASSIC_LIST,
+        };
+
+        Demon::validate_position(data.position, list_id, connection).await?;
 
         let publisher = DatabasePlayer::by_name_or_create(data.publisher.as_ref(), connection).await?;
         let verifier = DatabasePlayer::by_name_or_create(data.verifier.as_ref(), connection).await?;
 
-        Demon::shift_down(data.position, connection).await?;
+        Demon::shift_down(data.position, list_id, connection).await?;
 
         let id_of_inserted = sqlx::query!(
-            "INSERT INTO demons (name, position, requirement, video, verifier, publisher) VALUES ($1::text,$2,$3,$4::text,$5,$6) \
-             RETURNING id",
+            "INSERT INTO demons (name, position, requirement, video, verifier, publisher, list_id) VALUES \
+             ($1::text,$2,$3,$4::text,$5,$6,$7) RETURNING id",
             data.name.to_string(),
             data.position,
             data.requirement,
             video.as_ref(),
             verifier.id,
-            publisher.id
+            publisher.id,
+            list_id
         )
         .fetch_one(&mut *connection)
         .await?
         .id;
 
-        let demon = Demon {
+        legacy::freeze_legacy_positions(connection).await?;
+
+        let mut demon = Demon {
             base: MinimalDemon {
                 id: id_of_inserted,
                 position: data.position,
@@ -63,17 +89,35 @@ impl FullDemon {
             publisher,
             verifier,
             level_id: None,
+            thumbnail: None,
+            requires_timestamp: false,
+            victors: 0,
+            first_victor: None,
+            pending: None,
         };
 
+        if let Some(level_id) = data.level_id {
+            demon.set_level_id(level_id, connection).await?;
+        }
+
         let mut creators = Vec::new();
 
         for creator in data.creators {
             let player = DatabasePlayer::by_name_or_create(creator.as_ref(), &mut *connection).await?;
+
+            // The same player might be listed twice (possibly under different capitalization), which
+            // shouldn't fail the whole request
+            if creators.contains(&player) {
+                continue
+            }
+
             Creator::insert(&demon.base, &player, connection).await?;
 
             creators.push(player);
         }
 
+        score::refresh_player_scores(connection).await?;
+
         Ok(FullDemon {
             demon,
             creators,
@@ -1,8 +1,10 @@
-use crate::{demon::MinimalDemon, record::RecordStatus};
+use crate::{demon::MinimalDemon, player::DatabasePlayer, record::RecordStatus};
+use chrono::NaiveDateTime;
 use derive_more::Display;
 
 use pointercrate_core::error::{CoreError, PointercrateError};
 use serde::Serialize;
+use std::time::Duration;
 
 pub type Result<T> = std::result::Result<T, DemonlistError>;
 
@@ -21,6 +23,20 @@ pub enum DemonlistError {
     #[display(fmt = "You are banned from submitting records to the demonlist!")]
     BannedFromSubmissions,
 
+    /// `403 FORBIDDEN` error returned if a submitter flagged by the submission guard tries to
+    /// submit a record
+    ///
+    /// Error Code `40308`
+    #[display(fmt = "Your submissions have been flagged as suspicious and are on hold until a list moderator reviews them")]
+    SubmitterFlagged,
+
+    /// `403 FORBIDDEN` error returned if CAPTCHA verification is enabled and an anonymous
+    /// submission does not come with a valid CAPTCHA response
+    ///
+    /// Error Code `40311`
+    #[display(fmt = "CAPTCHA verification failed, please try again")]
+    CaptchaFailed,
+
     #[display(fmt = "You claim on this player is unverified")]
     ClaimUnverified,
 
@@ -57,9 +73,21 @@ pub enum DemonlistError {
     #[display(fmt = "No demon at position {} found", demon_position)]
     DemonNotFoundPosition { demon_position: i16 },
 
+    #[display(fmt = "No list with slug {} found", slug)]
+    ListNotFound { slug: String },
+
+    #[display(fmt = "No alias with id {} found", alias_id)]
+    AliasNotFound { alias_id: i32 },
+
+    #[display(fmt = "No pack with id {} found", pack_id)]
+    PackNotFound { pack_id: i32 },
+
     #[display(fmt = "No record with id {} found", record_id)]
     RecordNotFound { record_id: i32 },
 
+    #[display(fmt = "No demon proposal with id {} found", proposal_id)]
+    ProposalNotFound { proposal_id: i32 },
+
     #[display(fmt = "No claim by user {} on player {} found", member_id, player_id)]
     ClaimNotFound { member_id: i32, player_id: i32 },
 
@@ -110,7 +138,8 @@ pub enum DemonlistError {
     /// Error Code `42215`
     #[display(fmt = "Record progress must lie between {} and 100%!", requirement)]
     InvalidProgress {
-        /// The [`Demon`]'s record requirement
+        /// The [`Demon`]'s record requirement, raised to any position dependent requirement that
+        /// applies to the submission
         requirement: i16,
     },
     /// `422 UNPROCESSABLE ENTITY` variant
@@ -146,7 +175,10 @@ pub enum DemonlistError {
     /// `422 UNPROCESSABLE ENTITY` variant
     ///
     /// Error Code `42224`
-    #[display(fmt = "The given video host is not supported. Supported are 'youtube', 'vimeo', 'everyplay', 'twitch' and 'bilibili'")]
+    #[display(
+        fmt = "The given video host is not supported. Supported are 'youtube', 'vimeo', 'everyplay', 'twitch', 'bilibili' and 'google \
+               drive'"
+    )]
     UnsupportedVideoHost,
 
     /// `422 UNPROCESSABLE ENTITY` variant
@@ -163,6 +195,153 @@ pub enum DemonlistError {
 
     #[display(fmt = "This player already have a verified claim associated with them")]
     AlreadyClaimed,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if attempted to change a record's status in a
+    /// way not permitted by [`RecordStatus::can_transition_to`]
+    ///
+    /// Error Code `42231`
+    #[display(fmt = "A {} record cannot be moved to {}", from, to)]
+    InvalidStateTransition { from: RecordStatus, to: RecordStatus },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if attempted to delete a record without giving
+    /// a reason
+    ///
+    /// Error Code `42232`
+    #[display(fmt = "Deleting a record requires a reason")]
+    DeletionReasonRequired,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if the YouTube Data API reports a submitted
+    /// video as deleted, private or a livestream
+    ///
+    /// Error Code `42233`
+    #[display(fmt = "The given video is unavailable: {}", reason)]
+    VideoUnavailable { reason: &'static str },
+
+    /// `422 UNPROCESSABLE ENTITY` variant
+    ///
+    /// Error Code `42234`
+    #[display(fmt = "Submissions for demons in the top {} need to include raw footage", threshold)]
+    RawFootageRequired { threshold: i16 },
+
+    /// `422 UNPROCESSABLE ENTITY` variant
+    ///
+    /// Error Code `42235`
+    #[display(fmt = "Raw footage has to be uploaded to either YouTube or Google Drive")]
+    UnsupportedRawFootageHost,
+
+    /// `429 TOO MANY REQUESTS` variant
+    ///
+    /// Error Code `42901`
+    #[display(fmt = "You have submitted too many records recently. Try again in {:.2?}", retry_after)]
+    SubmissionFlood { retry_after: Duration },
+
+    /// `409 CONFLICT` variant returned if a change to the list team would leave the list without
+    /// any list administrator
+    ///
+    /// Error Code `40910`
+    #[display(fmt = "Cannot remove the last list administrator from the list team")]
+    LastListAdministrator,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a submission for a demon with
+    /// [`crate::demon::Demon::requires_timestamp`] set does not link to a point in the video
+    ///
+    /// Error Code `42237`
+    #[display(
+        fmt = "Submissions for this demon need to link to the point in the video where the completion starts (e.g. using '?t=' on YouTube)"
+    )]
+    TimestampRequired,
+
+    /// `409 CONFLICT` variant returned if a pack is created or renamed to have the same name as an
+    /// existing pack
+    ///
+    /// Error Code `40911`
+    #[display(fmt = "A pack with this name already exists")]
+    PackNameNotUnique,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if the number of demons required to complete a
+    /// pack is not between 1 and the number of demons in the pack
+    ///
+    /// Error Code `42238`
+    #[display(
+        fmt = "The number of demons required to complete a pack must be between 1 and {} (the number of demons in the pack)",
+        demons
+    )]
+    InvalidPackRequirement { demons: usize },
+
+    /// `409 CONFLICT` variant returned if an alias would be ambiguous, because the name already
+    /// refers to some other player or demon
+    ///
+    /// Error Code `40912`
+    #[display(fmt = "This name already refers to '{}'", name)]
+    AliasTaken { name: String },
+
+    /// `409 CONFLICT` variant returned if a demon's level id is set to the level id of another
+    /// demon on the same list
+    ///
+    /// Error Code `40913`
+    #[display(fmt = "The level id {} already belongs to the demon '{}'", level_id, demon.name)]
+    LevelIdTaken { level_id: i64, demon: MinimalDemon },
+
+    /// `409 CONFLICT` variant returned if someone tries to change the status of a record another
+    /// member has claimed for review
+    ///
+    /// Error Code `40914`
+    #[display(fmt = "This record is currently being reviewed by someone else (until {})", expires)]
+    RecordLocked {
+        record_id: i32,
+        member_id: i32,
+        expires: NaiveDateTime,
+    },
+
+    /// `422 UNPROCESSABLE ENTITY` variant
+    ///
+    /// Error Code `42239`
+    #[display(fmt = "Aliases must not be empty")]
+    AliasEmpty,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a submission is for a player that does not
+    /// exist yet, but players with similar names do. Submitting again with `create_player` set
+    /// creates the player anyway.
+    ///
+    /// Error Code `42240`
+    #[display(fmt = "No player with the given name exists, but some with similar names do. Did you misspell the name?")]
+    SimilarPlayersExist { players: Vec<DatabasePlayer> },
+
+    /// `422 UNPROCESSABLE ENTITY` variant
+    ///
+    /// Error Code `42241`
+    #[display(fmt = "Level ids must be positive")]
+    InvalidLevelId,
+
+    /// `422 UNPROCESSABLE ENTITY` variant
+    ///
+    /// Error Code `42242`
+    #[display(
+        fmt = "The main list must contain at least one demon and cannot be larger than the extended list (got {} and {})",
+        list_size,
+        extended_list_size
+    )]
+    InvalidListSize { list_size: i16, extended_list_size: i16 },
+
+    /// `409 CONFLICT` variant returned if someone votes on a demon proposal that has already been
+    /// approved or rejected
+    ///
+    /// Error Code `40915`
+    #[display(fmt = "This proposal has already been {}", status)]
+    ProposalClosed { proposal_id: i32, status: String },
+
+    /// `409 CONFLICT` variant returned if someone tries to claim a record for review that is not
+    /// waiting for review
+    ///
+    /// Error Code `40916`
+    #[display(fmt = "Only submitted records can be claimed for review, but this record is {}", status)]
+    RecordNotClaimable { record_id: i32, status: RecordStatus },
+
+    /// `422 UNPROCESSABLE ENTITY` variant
+    ///
+    /// Error Code `42236`
+    #[display(fmt = "Cannot merge an object with itself")]
+    SelfMerge,
 }
 
 impl std::error::Error for DemonlistError {}
@@ -184,6 +363,8 @@ impl PointercrateError for DemonlistError {
             BannedFromSubmissions => 40304,
             ClaimUnverified => 40306,
             VpsDetected => 40307,
+            SubmitterFlagged => 40308,
+            CaptchaFailed => 40311,
             NationalityNotFound { .. } => 40401,
             SubdivisionNotFound { .. } => 40401,
             PlayerNotFound { .. } => 40401,
@@ -191,11 +372,22 @@ impl PointercrateError for DemonlistError {
             DemonNotFound { .. } => 40401,
             DemonNotFoundName { .. } => 40401,
             DemonNotFoundPosition { .. } => 40401,
+            ListNotFound { .. } => 40401,
+            PackNotFound { .. } => 40401,
+            AliasNotFound { .. } => 40401,
             RecordNotFound { .. } => 40401,
+            ProposalNotFound { .. } => 40401,
             ClaimNotFound { .. } => 40401,
             DuplicateVideo { .. } => 40906,
             NoNationSet => 40907,
             ConflictingClaims { .. } => 40908,
+            LastListAdministrator => 40910,
+            PackNameNotUnique => 40911,
+            AliasTaken { .. } => 40912,
+            LevelIdTaken { .. } => 40913,
+            RecordLocked { .. } => 40914,
+            ProposalClosed { .. } => 40915,
+            RecordNotClaimable { .. } => 40916,
             InvalidProgress { .. } => 42215,
             SubmissionExists { .. } => 42217,
             PlayerBanned => 42218,
@@ -204,6 +396,27 @@ impl PointercrateError for DemonlistError {
             UnsupportedVideoHost => 42224,
             DemonNameNotUnique { .. } => 42228,
             AlreadyClaimed => 42230,
+            InvalidStateTransition { .. } => 42231,
+            DeletionReasonRequired => 42232,
+            VideoUnavailable { .. } => 42233,
+            RawFootageRequired { .. } => 42234,
+            UnsupportedRawFootageHost => 42235,
+            SelfMerge => 42236,
+            TimestampRequired => 42237,
+            InvalidPackRequirement { .. } => 42238,
+            AliasEmpty => 42239,
+            SimilarPlayersExist { .. } => 42240,
+            InvalidLevelId => 42241,
+            InvalidListSize { .. } => 42242,
+            SubmissionFlood { .. } => 42901,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            DemonlistError::Core(core) => core.retry_after(),
+            DemonlistError::SubmissionFlood { retry_after } => Some(*retry_after),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,119 @@
+//! Module for collecting all demonlist related data tied to some user account, used to answer data
+//! subject access requests
+
+use crate::{
+    demon::MinimalDemon,
+    error::Result,
+    player::claim::{ClaimBy, PlayerClaim},
+    record::{MinimalRecordD, RecordStatus},
+};
+use chrono::NaiveDateTime;
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// An audit log entry caused by some user, regardless of what kind of object it refers to
+#[derive(Debug, Serialize)]
+pub struct AttributedAuditLogEntry {
+    pub time: NaiveDateTime,
+    pub entry_id: i32,
+
+    /// The kind of object that was changed, e.g. `"record"` or `"demon"`
+    pub object: String,
+
+    /// The id of the object that was changed
+    pub id: i32,
+
+    /// Either `"addition"`, `"modification"` or `"deletion"`
+    pub r#type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DemonlistUserData {
+    pub claim: Option<ClaimBy>,
+
+    /// All records of the claimed player, regardless of status. Only present if the claim is
+    /// verified
+    pub records: Vec<MinimalRecordD>,
+
+    pub audit_log: Vec<AttributedAuditLogEntry>,
+}
+
+/// Collects all demonlist data tied to the user with the given id
+///
+/// To get a consistent snapshot, this should be called inside a `REPEATABLE READ` transaction
+pub async fn data_of_user(user_id: i32, connection: &mut PgConnection) -> Result<DemonlistUserData> {
+    let claim = PlayerClaim::by_user(user_id, &mut *connection).await?;
+
+    let records = match claim {
+        Some(ref claim) if claim.verified => records_of_player(claim.player.id, &mut *connection).await?,
+        _ => Vec::new(),
+    };
+
+    Ok(DemonlistUserData {
+        claim,
+        records,
+        audit_log: audit_log_entries_by(user_id, connection).await?,
+    })
+}
+
+async fn records_of_player(player_id: i32, connection: &mut PgConnection) -> Result<Vec<MinimalRecordD>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT records.id, progress, video::text, status_::text AS "status!: String", demons.id AS demon_id, demons.name AS "name: String",
+         demons.position FROM records INNER JOIN demons ON records.demon = demons.id WHERE records.player = $1 ORDER BY records.id"#,
+        player_id
+    )
+    .fetch(connection);
+
+    let mut records = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        records.push(MinimalRecordD {
+            id: row.id,
+            progress: row.progress,
+            video: row.video,
+            status: RecordStatus::from_sql(&row.status),
+            demon: MinimalDemon {
+                id: row.demon_id,
+                position: row.position,
+                name: row.name,
+            },
+        })
+    }
+
+    Ok(records)
+}
+
+async fn audit_log_entries_by(user_id: i32, connection: &mut PgConnection) -> Result<Vec<AttributedAuditLogEntry>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT time AS "time!", audit_id AS "audit_id!", id AS "id!", object AS "object!", kind AS "kind!" FROM (
+             SELECT time, audit_id, id, 'demon' AS object, 'addition' AS kind FROM demon_additions WHERE userid = $1
+             UNION ALL SELECT time, audit_id, id, 'demon', 'modification' FROM demon_modifications WHERE userid = $1
+             UNION ALL SELECT time, audit_id, id, 'record', 'addition' FROM record_additions WHERE userid = $1
+             UNION ALL SELECT time, audit_id, id, 'record', 'modification' FROM record_modifications WHERE userid = $1
+             UNION ALL SELECT time, audit_id, id, 'record', 'deletion' FROM record_deletions WHERE userid = $1
+             UNION ALL SELECT time, audit_id, id, 'record_note', 'addition' FROM record_notes_additions WHERE userid = $1
+             UNION ALL SELECT time, audit_id, id, 'record_note', 'modification' FROM record_notes_modifications WHERE userid = $1
+           ) AS entries ORDER BY time"#,
+        user_id
+    )
+    .fetch(connection);
+
+    let mut entries = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        entries.push(AttributedAuditLogEntry {
+            time: row.time,
+            entry_id: row.audit_id,
+            object: row.object,
+            id: row.id,
+            r#type: row.kind,
+        })
+    }
+
+    Ok(entries)
+}
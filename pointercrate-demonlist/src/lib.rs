@@ -1,16 +1,35 @@
-use pointercrate_core::permission::Permission;
+use pointercrate_core::permission::{Permission, Role};
 
 #[macro_use]
 pub mod demon;
+pub mod alias;
 pub mod config;
 pub mod creator;
 pub mod error;
+pub mod export;
+pub mod list;
+pub mod list_config;
 pub mod nationality;
+pub mod pack;
 pub mod player;
+pub mod proposal;
 pub mod record;
+pub mod roulette;
+pub mod score;
+pub mod search;
+pub mod submission_guard;
 pub mod submitter;
-mod video;
+pub mod video;
 
 pub const LIST_HELPER: Permission = Permission::new("List Helper", 0x2);
 pub const LIST_MODERATOR: Permission = Permission::new("List Moderator", 0x4);
 pub const LIST_ADMINISTRATOR: Permission = Permission::new("List Administrator", 0x8);
+
+/// The roles list staff is usually granted, to be registered with the
+/// [`PermissionsManager`](pointercrate_core::permission::PermissionsManager)
+pub fn roles() -> Vec<Role> {
+    vec![
+        Role::new("List Team", vec![LIST_HELPER, LIST_MODERATOR]),
+        Role::new("List Leadership", vec![LIST_HELPER, LIST_MODERATOR, LIST_ADMINISTRATOR]),
+    ]
+}
@@ -0,0 +1,104 @@
+//! Module for the independent lists hosted on a single pointercrate instance
+//!
+//! Every demon belongs to exactly one list, and positions are only unique within a list. Records
+//! belong to the list of their demon. All endpoints not explicitly scoped to a list operate on the
+//! classic demonlist, and player scores are computed from the classic list only.
+
+use crate::{
+    config,
+    demon::{ListSection, MinimalDemon},
+    error::{DemonlistError, Result},
+};
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::{Error, PgConnection};
+
+/// The id of the classic demonlist, which always exists
+pub const CLASSIC_LIST: i32 = 1;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+pub struct DemonList {
+    pub id: i32,
+    pub slug: String,
+    pub name: String,
+
+    /// The size of the main section of this list, configured via `<SLUG>_LIST_SIZE`. For the
+    /// classic list, this is always [`config::list_size`].
+    pub list_size: i16,
+
+    /// The size of the extended section of this list, configured via `<SLUG>_EXTENDED_LIST_SIZE`.
+    /// For the classic list, this is always [`config::extended_list_size`].
+    pub extended_list_size: i16,
+}
+
+impl DemonList {
+    fn new(id: i32, slug: String, name: String) -> Self {
+        let (list_size, extended_list_size) = if id == CLASSIC_LIST {
+            (config::list_size(), config::extended_list_size())
+        } else {
+            (config::list_size_of(&slug), config::extended_list_size_of(&slug))
+        };
+
+        DemonList {
+            id,
+            list_size,
+            extended_list_size,
+            slug,
+            name,
+        }
+    }
+
+    /// The list the given demon belongs to
+    pub async fn of(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<DemonList> {
+        let list_id = demon.list_id(&mut *connection).await?;
+
+        DemonList::by_id(list_id, connection).await
+    }
+
+    /// The section of this list a demon at the given position is in
+    pub fn section_of(&self, position: i16) -> ListSection {
+        if position <= self.list_size {
+            ListSection::Main
+        } else if position <= self.extended_list_size {
+            ListSection::Extended
+        } else {
+            ListSection::Legacy
+        }
+    }
+
+    pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<DemonList> {
+        let row = sqlx::query!("SELECT id, slug::TEXT AS \"slug!\", name FROM lists WHERE id = $1", id)
+            .fetch_one(connection)
+            .await?;
+
+        Ok(DemonList::new(row.id, row.slug, row.name))
+    }
+
+    pub async fn by_slug(slug: &str, connection: &mut PgConnection) -> Result<DemonList> {
+        let result = sqlx::query!(
+            "SELECT id, slug::TEXT AS \"slug!\", name FROM lists WHERE slug = $1::TEXT::CITEXT",
+            slug
+        )
+        .fetch_one(connection)
+        .await;
+
+        match result {
+            Ok(row) => Ok(DemonList::new(row.id, row.slug, row.name)),
+            Err(Error::RowNotFound) => Err(DemonlistError::ListNotFound { slug: slug.to_string() }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn all(connection: &mut PgConnection) -> Result<Vec<DemonList>> {
+        let mut stream = sqlx::query!("SELECT id, slug::TEXT AS \"slug!\", name FROM lists ORDER BY id").fetch(connection);
+        let mut lists = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            lists.push(DemonList::new(row.id, row.slug, row.name))
+        }
+
+        Ok(lists)
+    }
+}
@@ -0,0 +1,119 @@
+//! Module for list configuration that can be changed at runtime
+//!
+//! The sizes of the main and extended list are stored in the `list_config` table and cached in this
+//! process, so that the synchronous [`config::list_size`] and [`config::extended_list_size`] can
+//! return them. The cache is refreshed every time the configuration is loaded from the database
+//! (see [`ListConfig::load`]), which code that needs the live values (e.g. record submission) does
+//! before using them, and which also happens periodically. Changes are only cached once they have
+//! been committed. Values not set in the database fall back to the environment.
+
+use crate::{
+    config,
+    demon::legacy,
+    error::{DemonlistError, Result},
+};
+use log::info;
+use pointercrate_core::{audit::PatchLog, util::nullable};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::sync::atomic::{AtomicI16, Ordering};
+
+/// Marks a cached value as not set in the database
+const UNSET: i16 = -1;
+
+static LIST_SIZE: AtomicI16 = AtomicI16::new(UNSET);
+static EXTENDED_LIST_SIZE: AtomicI16 = AtomicI16::new(UNSET);
+
+/// The cached list size from the database, if one is set
+pub(crate) fn cached_list_size() -> Option<i16> {
+    Some(LIST_SIZE.load(Ordering::Relaxed)).filter(|&size| size != UNSET)
+}
+
+/// The cached extended list size from the database, if one is set
+pub(crate) fn cached_extended_list_size() -> Option<i16> {
+    Some(EXTENDED_LIST_SIZE.load(Ordering::Relaxed)).filter(|&size| size != UNSET)
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct ListConfig {
+    /// The size of the main list. `None` means it is taken from the `LIST_SIZE` environment
+    /// variable.
+    pub list_size: Option<i16>,
+
+    /// The size of the extended list. `None` means it is taken from the `EXTENDED_LIST_SIZE`
+    /// environment variable.
+    pub extended_list_size: Option<i16>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchListConfig {
+    #[serde(default, deserialize_with = "nullable")]
+    pub list_size: Option<Option<i16>>,
+
+    #[serde(default, deserialize_with = "nullable")]
+    pub extended_list_size: Option<Option<i16>>,
+}
+
+impl ListConfig {
+    /// Loads the list configuration from the database and updates the values cached in this process
+    pub async fn load(connection: &mut PgConnection) -> Result<ListConfig> {
+        let list_config = sqlx::query_as!(ListConfig, "SELECT list_size, extended_list_size FROM list_config")
+            .fetch_one(connection)
+            .await?;
+
+        list_config.cache();
+
+        Ok(list_config)
+    }
+
+    /// Makes the synchronous [`config::list_size`] and [`config::extended_list_size`] return the
+    /// sizes from this configuration
+    pub fn cache(&self) {
+        LIST_SIZE.store(self.list_size.unwrap_or(UNSET), Ordering::Relaxed);
+        EXTENDED_LIST_SIZE.store(self.extended_list_size.unwrap_or(UNSET), Ordering::Relaxed);
+    }
+
+    /// Must be run within a transaction!
+    ///
+    /// The new sizes are not cached, as the transaction might still fail to commit. Call
+    /// [`ListConfig::cache`] after committing. Legacy positions are brought in line with the new
+    /// extended list size as part of the change.
+    pub async fn apply_patch(mut self, patch: PatchListConfig, connection: &mut PgConnection) -> Result<ListConfig> {
+        let log = PatchLog::start("list_config", 0, &self);
+
+        if let Some(list_size) = patch.list_size {
+            self.list_size = list_size;
+        }
+
+        if let Some(extended_list_size) = patch.extended_list_size {
+            self.extended_list_size = extended_list_size;
+        }
+
+        let list_size = self.list_size.unwrap_or_else(config::env_list_size);
+        let extended_list_size = self.extended_list_size.unwrap_or_else(config::env_extended_list_size);
+
+        if list_size < 1 || list_size > extended_list_size {
+            return Err(DemonlistError::InvalidListSize {
+                list_size,
+                extended_list_size,
+            })
+        }
+
+        info!("Changing list sizes to {} (main) and {} (extended)", list_size, extended_list_size);
+
+        sqlx::query!(
+            "UPDATE list_config SET list_size = $1, extended_list_size = $2",
+            self.list_size,
+            self.extended_list_size
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        // Demons might have dropped off (or moved back onto) the extended list
+        legacy::freeze_legacy_positions_at(extended_list_size, &mut *connection).await?;
+
+        log.finish(&self, connection).await?;
+
+        Ok(self)
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     demon::MinimalDemon,
     error::{DemonlistError, Result},
+    list::{DemonList, CLASSIC_LIST},
     nationality::{BestRecord, MiniDemon, MiniDemonWithPlayers, Nationality, NationalityRecord, Subdivision},
 };
 use futures::stream::StreamExt;
@@ -103,12 +104,17 @@ impl Nationality {
     }
 }
 
+/// The demons in the main and extended section of the classic list that no player of the given
+/// nation has beaten yet
 pub async fn unbeaten_in(nation: &Nationality, connection: &mut PgConnection) -> Result<Vec<MinimalDemon>> {
+    let list = DemonList::by_id(CLASSIC_LIST, &mut *connection).await?;
+
     let mut stream = sqlx::query!(
-        r#"select name::text as "name!", id as "id!", position as "position!" from demons where position <= $1 except (select demons.name, demons.id, position from records inner join players on 
+        r#"select name::text as "name!", id as "id!", position as "position!" from demons where position <= $1 and list_id = $3 except (select demons.name, demons.id, position from records inner join players on 
          players.id=records.player inner join demons on demons.id=records.demon where status_='APPROVED' and nationality=$2 and progress=100 union select demons.name, demons.id, demons.position from demons inner join players on players.id=verifier where players.nationality=$2)"#,
-        crate::config::extended_list_size(),
-        nation.iso_country_code
+        list.extended_list_size,
+        nation.iso_country_code,
+        list.id
     )
     .fetch(connection);
 
@@ -1,13 +1,13 @@
 use crate::demon::MinimalDemon;
 use derive_more::Constructor;
-pub use paginate::{NationalityRankingPagination, RankedNation};
+pub use paginate::{NationalRankingPagination, NationalityRankingPagination, RankedNation};
 use pointercrate_core::etag::Taggable;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 mod get;
 mod paginate;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Hash, Constructor)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Hash, Constructor)]
 pub struct Nationality {
     #[serde(rename = "country_code")]
     pub iso_country_code: String,
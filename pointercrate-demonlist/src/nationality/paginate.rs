@@ -1,11 +1,12 @@
 use crate::{
     error::Result,
     nationality::{Continent, Nationality},
+    player::RankedPlayer,
 };
 use futures::StreamExt;
-use pointercrate_core::util::non_nullable;
+use pointercrate_core::{error::CoreError, util::non_nullable};
 use serde::{Deserialize, Serialize};
-use sqlx::PgConnection;
+use sqlx::{PgConnection, Row};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct NationalityRankingPagination {
@@ -53,3 +54,88 @@ impl NationalityRankingPagination {
         Ok(nations)
     }
 }
+
+/// Pagination over the players of a single nation, ranked by their score
+///
+/// Ranks are relative to the nation, meaning the best player of every nation has rank 1.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NationalRankingPagination {
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "before")]
+    pub before_index: Option<i64>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "after")]
+    pub after_index: Option<i64>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub limit: Option<u8>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    subdivision: Option<String>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    name_contains: Option<String>,
+}
+
+impl NationalRankingPagination {
+    pub async fn page(&self, nation: &Nationality, connection: &mut PgConnection) -> Result<Vec<RankedPlayer>> {
+        if let Some(limit) = self.limit {
+            if limit < 1 || limit > 100 {
+                Err(CoreError::InvalidPaginationLimit)?
+            }
+        }
+
+        let order = if self.before_index.is_some() && self.after_index.is_none() {
+            "DESC"
+        } else {
+            "ASC"
+        };
+
+        let query = format!(include_str!("../../sql/paginate_national_ranking.sql"), order);
+
+        let mut stream = sqlx::query(&query)
+            .bind(&nation.iso_country_code)
+            .bind(self.before_index)
+            .bind(self.after_index)
+            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(&self.subdivision)
+            .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .fetch(connection);
+
+        let mut players = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            players.push(RankedPlayer {
+                id: row.get("id"),
+                name: row.get("name"),
+                rank: row.get("rank"),
+                nationality: Some(Nationality {
+                    iso_country_code: row.get("iso_country_code"),
+                    nation: row.get("nation"),
+                    subdivision: None,
+                }),
+                score: row.get("score"),
+                index: row.get("index"),
+            })
+        }
+
+        Ok(players)
+    }
+}
+
+impl Nationality {
+    /// Gets the number of ranked players of this nation, which is the highest index generated by
+    /// [`NationalRankingPagination`]
+    pub async fn ranked_player_count(&self, connection: &mut PgConnection) -> Result<i64> {
+        Ok(sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM ranked_players WHERE iso_country_code = $1"#,
+            self.iso_country_code
+        )
+        .fetch_one(connection)
+        .await?
+        .count)
+    }
+}
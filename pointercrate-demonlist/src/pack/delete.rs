@@ -0,0 +1,13 @@
+use crate::{error::Result, pack::Pack};
+use log::info;
+use sqlx::PgConnection;
+
+impl Pack {
+    pub async fn delete(self, connection: &mut PgConnection) -> Result<()> {
+        info!("Deleting pack {}", self);
+
+        sqlx::query!("DELETE FROM packs WHERE id = $1", self.id).execute(connection).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    demon::MinimalDemon,
+    error::{DemonlistError, Result},
+    pack::Pack,
+    player::DatabasePlayer,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::{Error, PgConnection};
+
+/// How far some player progressed through a single pack
+#[derive(Debug, Serialize)]
+pub struct PackProgress {
+    pub id: i32,
+    pub name: String,
+    pub required: i16,
+
+    /// The total number of demons in the pack
+    pub demons: i64,
+
+    /// The number of demons in the pack the player beat
+    pub beaten: i64,
+    pub completed: bool,
+}
+
+impl Pack {
+    pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<Pack> {
+        let result = sqlx::query!(r#"SELECT id, name::TEXT AS "name!", required FROM packs WHERE id = $1"#, id)
+            .fetch_one(&mut *connection)
+            .await;
+
+        match result {
+            Ok(row) =>
+                Ok(Pack {
+                    id: row.id,
+                    name: row.name,
+                    required: row.required,
+                    demons: demons_in(row.id, connection).await?,
+                }),
+            Err(Error::RowNotFound) => Err(DemonlistError::PackNotFound { pack_id: id }),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn all(connection: &mut PgConnection) -> Result<Vec<Pack>> {
+        let rows = sqlx::query!(r#"SELECT id, name::TEXT AS "name!", required FROM packs ORDER BY id"#)
+            .fetch_all(&mut *connection)
+            .await?;
+
+        let mut packs = Vec::new();
+
+        for row in rows {
+            packs.push(Pack {
+                id: row.id,
+                name: row.name,
+                required: row.required,
+                demons: demons_in(row.id, &mut *connection).await?,
+            })
+        }
+
+        Ok(packs)
+    }
+
+    /// Computes the progress of the given player through every pack. Banned players have not beaten
+    /// any demons.
+    pub async fn progress_of(player: &DatabasePlayer, connection: &mut PgConnection) -> Result<Vec<PackProgress>> {
+        let mut stream = sqlx::query!(
+            r#"SELECT packs.id, packs.name::TEXT AS "name!", packs.required, COUNT(*) AS "demons!", COUNT(*) FILTER (WHERE NOT $2 AND
+             (demons.verifier = $1 OR EXISTS (SELECT FROM records WHERE records.demon = demons.id AND records.player = $1 AND records.status_ =
+             'APPROVED' AND records.progress = 100))) AS "beaten!" FROM packs INNER JOIN pack_demons ON pack_demons.pack = packs.id INNER JOIN demons ON
+             demons.id = pack_demons.demon GROUP BY packs.id ORDER BY packs.id"#,
+            player.id,
+            player.banned
+        )
+        .fetch(connection);
+
+        let mut progress = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            progress.push(PackProgress {
+                id: row.id,
+                completed: row.beaten >= row.required as i64,
+                name: row.name,
+                required: row.required,
+                demons: row.demons,
+                beaten: row.beaten,
+            })
+        }
+
+        Ok(progress)
+    }
+}
+
+async fn demons_in(pack_id: i32, connection: &mut PgConnection) -> Result<Vec<MinimalDemon>> {
+    query_many_demons!(
+        connection,
+        r#"SELECT demons.id, demons.name AS "name: String", demons.position FROM demons INNER JOIN pack_demons ON demons.id = 
+         pack_demons.demon WHERE pack_demons.pack = $1 ORDER BY demons.position"#,
+        pack_id
+    )
+}
@@ -0,0 +1,84 @@
+//! Module for packs, named groups of demons used for tracking a player's progression
+//!
+//! A player completes a pack once they beat at least [`Pack::required`] of its demons. Beating a
+//! demon means either having an approved 100% record on it, or having verified it. Pack completion
+//! is always computed on the fly, so it reflects record changes immediately.
+
+pub use self::{get::PackProgress, patch::PatchPack, post::PostPack};
+use crate::{
+    demon::MinimalDemon,
+    error::{DemonlistError, Result},
+};
+use derive_more::Display;
+use pointercrate_core::etag::Taggable;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+mod delete;
+mod get;
+mod patch;
+mod post;
+
+#[derive(Debug, Serialize, Hash, Display, PartialEq, Eq, Clone)]
+#[display(fmt = "{} (ID: {})", name, id)]
+pub struct Pack {
+    pub id: i32,
+    pub name: String,
+
+    /// The number of this pack's demons a player needs to beat to complete it
+    pub required: i16,
+
+    /// The demons in this pack, ordered by position
+    pub demons: Vec<MinimalDemon>,
+}
+
+impl Taggable for Pack {}
+
+/// Checks that a pack with the given demons can be completed by beating `required` of them
+fn validate_requirement(required: i16, demons: &[i32]) -> Result<()> {
+    if required < 1 || required as usize > demons.len() {
+        return Err(DemonlistError::InvalidPackRequirement { demons: demons.len() })
+    }
+
+    Ok(())
+}
+
+/// Checks that no pack other than the one with id `pack_id` is called `name`
+async fn validate_name(name: &str, pack_id: Option<i32>, connection: &mut PgConnection) -> Result<()> {
+    let taken = sqlx::query!(
+        r#"SELECT EXISTS (SELECT FROM packs WHERE name = $1::TEXT::CITEXT AND id IS DISTINCT FROM $2) AS "taken!: bool""#,
+        name,
+        pack_id
+    )
+    .fetch_one(connection)
+    .await?
+    .taken;
+
+    if taken {
+        return Err(DemonlistError::PackNameNotUnique)
+    }
+
+    Ok(())
+}
+
+/// Makes the given demons the demons of the pack with the given id, replacing all previous ones
+async fn set_demons(pack_id: i32, demons: &[i32], connection: &mut PgConnection) -> Result<()> {
+    // Makes sure all demons exist, so that we do not error out on the foreign key constraint
+    for demon_id in demons {
+        MinimalDemon::by_id(*demon_id, &mut *connection).await?;
+    }
+
+    sqlx::query!("DELETE FROM pack_demons WHERE pack = $1", pack_id)
+        .execute(&mut *connection)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO pack_demons (pack, demon) SELECT $1, demon FROM UNNEST($2::INTEGER[]) AS demon ON CONFLICT DO NOTHING",
+        pack_id,
+        demons
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use crate::{
+    error::Result,
+    pack::{set_demons, validate_name, validate_requirement, Pack},
+};
+use log::info;
+use pointercrate_core::{audit::PatchLog, util::non_nullable};
+use serde::Deserialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Deserialize)]
+pub struct PatchPack {
+    #[serde(default, deserialize_with = "non_nullable")]
+    name: Option<String>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    required: Option<i16>,
+
+    /// The ids of the demons that should make up the pack, replacing all current demons
+    #[serde(default, deserialize_with = "non_nullable")]
+    demons: Option<Vec<i32>>,
+}
+
+impl Pack {
+    /// Must be run within a transaction!
+    pub async fn apply_patch(self, patch: PatchPack, connection: &mut PgConnection) -> Result<Pack> {
+        info!("Patching pack {} with {:?}", self, patch);
+
+        let log = PatchLog::start("pack", self.id, &self);
+
+        let replace_demons = patch.demons.is_some();
+        let mut demons = match patch.demons {
+            Some(demons) => demons,
+            None => self.demons.iter().map(|demon| demon.id).collect(),
+        };
+
+        demons.sort_unstable();
+        demons.dedup();
+
+        validate_requirement(patch.required.unwrap_or(self.required), &demons)?;
+
+        if let Some(ref name) = patch.name {
+            validate_name(name, Some(self.id), &mut *connection).await?;
+
+            sqlx::query!("UPDATE packs SET name = $1::TEXT WHERE id = $2", name, self.id)
+                .execute(&mut *connection)
+                .await?;
+        }
+
+        if let Some(required) = patch.required {
+            sqlx::query!("UPDATE packs SET required = $1 WHERE id = $2", required, self.id)
+                .execute(&mut *connection)
+                .await?;
+        }
+
+        if replace_demons {
+            set_demons(self.id, &demons, &mut *connection).await?;
+        }
+
+        let pack = Pack::by_id(self.id, &mut *connection).await?;
+
+        log.finish(&pack, connection).await?;
+
+        Ok(pack)
+    }
+}
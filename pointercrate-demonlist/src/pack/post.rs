@@ -0,0 +1,49 @@
+use crate::{
+    error::Result,
+    pack::{set_demons, validate_name, validate_requirement, Pack},
+};
+use log::info;
+use serde::Deserialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Deserialize)]
+pub struct PostPack {
+    pub name: String,
+
+    /// The number of demons that need to be beaten to complete the pack. Defaults to all of them.
+    #[serde(default)]
+    pub required: Option<i16>,
+
+    /// The ids of the demons in the pack
+    pub demons: Vec<i32>,
+}
+
+impl Pack {
+    /// Must be run within a transaction!
+    pub async fn create_from(data: PostPack, connection: &mut PgConnection) -> Result<Pack> {
+        info!("Creating new pack from {:?}", data);
+
+        let mut demons = data.demons;
+
+        demons.sort_unstable();
+        demons.dedup();
+
+        let required = data.required.unwrap_or(demons.len() as i16);
+
+        validate_requirement(required, &demons)?;
+        validate_name(&data.name, None, &mut *connection).await?;
+
+        let id = sqlx::query!(
+            "INSERT INTO packs (name, required) VALUES ($1::TEXT, $2) RETURNING id",
+            data.name,
+            required
+        )
+        .fetch_one(&mut *connection)
+        .await?
+        .id;
+
+        set_demons(id, &demons, &mut *connection).await?;
+
+        Pack::by_id(id, connection).await
+    }
+}
@@ -2,13 +2,18 @@ use crate::{
     error::{DemonlistError, Result},
     player::{claim::PlayerClaim, DatabasePlayer},
 };
+use pointercrate_core::etag::Taggable;
+use serde::Serialize;
 use sqlx::PgConnection;
 
+#[derive(Debug, Serialize, Hash)]
 pub struct ClaimBy {
     pub player: DatabasePlayer,
     pub verified: bool,
 }
 
+impl Taggable for ClaimBy {}
+
 impl PlayerClaim {
     pub async fn verified_claim_on(player_id: i32, connection: &mut PgConnection) -> Result<Option<PlayerClaim>> {
         match sqlx::query!("SELECT member_id FROM player_claims WHERE player_id = $1 AND verified", player_id)
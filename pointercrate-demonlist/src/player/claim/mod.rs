@@ -1,3 +1,4 @@
+pub use get::ClaimBy;
 pub use paginate::{ListedClaim, PlayerClaimPagination};
 pub use patch::PatchVerified;
 use serde::Serialize;
@@ -0,0 +1,27 @@
+use crate::{error::Result, player::DatabasePlayer};
+use log::info;
+use sqlx::PgConnection;
+
+impl DatabasePlayer {
+    /// Deletes all players that are not referenced anywhere anymore (no records, deleted records,
+    /// demons, creator entries or claims), e.g. because all their records were merged into a
+    /// different player or deleted
+    ///
+    /// Banned players are kept, so that the ban still applies to future submissions under their
+    /// name. Returns the number of deleted players.
+    pub async fn prune_orphaned(connection: &mut PgConnection) -> Result<u64> {
+        let deleted = sqlx::query!(
+            "DELETE FROM players WHERE NOT banned AND NOT EXISTS (SELECT 1 FROM records WHERE records.player = players.id) AND NOT EXISTS \
+             (SELECT 1 FROM demons WHERE demons.verifier = players.id OR demons.publisher = players.id) AND NOT EXISTS (SELECT 1 FROM \
+             creators WHERE creators.creator = players.id) AND NOT EXISTS (SELECT 1 FROM player_claims WHERE player_claims.player_id = \
+             players.id) AND NOT EXISTS (SELECT 1 FROM deleted_records WHERE deleted_records.player = players.id)"
+        )
+        .execute(connection)
+        .await?
+        .rows_affected();
+
+        info!("Pruned {} orphaned players", deleted);
+
+        Ok(deleted)
+    }
+}
@@ -6,6 +6,7 @@ use crate::{
     player::{DatabasePlayer, FullPlayer, Player},
     record::approved_records_by,
 };
+use chrono::NaiveDateTime;
 use sqlx::{Error, PgConnection};
 
 // Required until https://github.com/launchbadge/sqlx/pull/108 is merged
@@ -79,11 +80,16 @@ impl Player {
 }
 
 impl DatabasePlayer {
+    /// Gets the player with the given name, or, if no such player exists, the player that has the
+    /// given name as an [alias](crate::alias)
     pub async fn by_name(name: &str, connection: &mut PgConnection) -> Result<DatabasePlayer> {
         let name = name.trim();
 
         let result = sqlx::query!(
-            "SELECT id, name::text, banned FROM players WHERE name = cast($1::text as citext)",
+            r#"SELECT id AS "id!", name AS "name!", banned AS "banned!" FROM (SELECT id, name::text, banned, 0 AS priority FROM players WHERE
+             name = cast($1::text as citext) UNION ALL SELECT players.id, players.name::text, players.banned, 1 AS priority FROM aliases
+             INNER JOIN players ON players.id = aliases.player WHERE aliases.name = cast($1::text as citext)) AS candidates ORDER BY
+             priority LIMIT 1"#,
             name.to_string()
         ) // FIXME(sqlx) once CITEXT is supported
         .fetch_one(connection)
@@ -93,7 +99,7 @@ impl DatabasePlayer {
             Ok(row) =>
                 Ok(DatabasePlayer {
                     id: row.id,
-                    name: row.name.unwrap(), // FIXME(sqlx) casted columns interpreted as nullable
+                    name: row.name,
                     banned: row.banned,
                 }),
             Err(Error::RowNotFound) =>
@@ -141,3 +147,17 @@ impl DatabasePlayer {
         }
     }
 }
+
+impl Player {
+    /// Gets the point in time (in UTC) the player with the given id was last modified
+    pub async fn last_modified(id: i32, connection: &mut PgConnection) -> Result<NaiveDateTime> {
+        match sqlx::query!("SELECT last_modified FROM players WHERE id = $1", id)
+            .fetch_one(connection)
+            .await
+        {
+            Ok(row) => Ok(row.last_modified),
+            Err(Error::RowNotFound) => Err(DemonlistError::PlayerNotFound { player_id: id }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 pub use self::{
     paginate::{PlayerPagination, RankingPagination},
-    patch::PatchPlayer,
+    patch::{BanStrategy, PatchPlayer},
+    timeline::{TimelineEvent, TimelineEventKind},
 };
 use crate::{demon::MinimalDemon, error::Result, nationality::Nationality, record::MinimalRecordD};
 use derive_more::Display;
@@ -13,9 +14,11 @@ use std::{
 };
 
 pub mod claim;
+mod delete;
 mod get;
 mod paginate;
 mod patch;
+mod timeline;
 
 #[derive(Debug, Hash, Eq, PartialEq, Serialize, Display, Clone)]
 #[display(fmt = "{} (ID: {})", name, id)]
@@ -36,7 +39,7 @@ pub struct FullPlayer {
     pub published: Vec<MinimalDemon>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Display)]
+#[derive(Debug, PartialEq, Serialize, Display, Clone)]
 #[display(fmt = "{} (ID: {}) at rank {} with score {}", name, id, rank, score)]
 pub struct RankedPlayer {
     pub id: i32,
@@ -66,12 +69,14 @@ impl Taggable for FullPlayer {
 }
 
 impl RankedPlayer {
-    /// Gets the highest index value generated by the `players_with_score` view
+    /// Gets the highest index value generated by the `ranked_players` view
     pub async fn max_index(connection: &mut PgConnection) -> Result<i64> {
-        Ok(sqlx::query!(r#"SELECT MAX(index) AS "max_index!: i64" FROM players_with_score"#)
-            .fetch_one(connection)
-            .await?
-            .max_index)
+        Ok(
+            sqlx::query!(r#"SELECT COALESCE(MAX(index), 0) AS "max_index!: i64" FROM ranked_players"#)
+                .fetch_one(connection)
+                .await?
+                .max_index,
+        )
     }
 }
 
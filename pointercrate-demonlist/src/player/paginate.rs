@@ -9,7 +9,11 @@ use pointercrate_core::{
     util::{non_nullable, nullable},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgConnection, Row};
+use sqlx::{
+    postgres::{PgArguments, PgConnection},
+    query::Query,
+    Postgres, Row,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerPagination {
@@ -53,16 +57,8 @@ impl PlayerPagination {
 
         let query = format!(include_str!("../../sql/paginate_players_by_id.sql"), order);
 
-        // FIXME(sqlx) once CITEXT is supported
-        let mut stream = sqlx::query(&query)
-            .bind(self.before_id)
-            .bind(self.after_id)
-            .bind(self.name.as_ref().map(|s| s.as_str()))
-            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
-            .bind(self.banned)
-            .bind(&self.nation)
-            .bind(self.nation == Some(None))
-            .bind(self.limit.unwrap_or(50) as i32 + 1)
+        let mut stream = self
+            .bind(&query, self.before_id, self.after_id, Some(self.limit.unwrap_or(50) as i32 + 1))
             .fetch(connection);
 
         let mut players = Vec::new();
@@ -92,9 +88,34 @@ impl PlayerPagination {
 
         Ok(players)
     }
+
+    /// Counts the players matching the filters in here, regardless of `before`, `after` and `limit`
+    pub async fn count(&self, connection: &mut PgConnection) -> Result<i64> {
+        let query = format!(include_str!("../../sql/paginate_players_by_id.sql"), "ASC");
+        let query = format!("SELECT COUNT(*) FROM ({}) AS matching", query);
+
+        Ok(self.bind(&query, None, None, None).fetch_one(connection).await?.get(0))
+    }
+
+    /// Binds the filters in here to the given query (based on `paginate_players_by_id.sql`). A
+    /// limit of `None` means no limit.
+    fn bind<'q>(
+        &'q self, query: &'q str, before_id: Option<i32>, after_id: Option<i32>, limit: Option<i32>,
+    ) -> Query<'q, Postgres, PgArguments> {
+        // FIXME(sqlx) once CITEXT is supported
+        sqlx::query(query)
+            .bind(before_id)
+            .bind(after_id)
+            .bind(self.name.as_ref().map(|s| s.as_str()))
+            .bind(self.name_contains.as_ref().map(|s| s.as_str()))
+            .bind(self.banned)
+            .bind(&self.nation)
+            .bind(self.nation == Some(None))
+            .bind(limit)
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RankingPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "before")]
@@ -3,13 +3,17 @@ use crate::{
     nationality::{Nationality, Subdivision},
     player::{claim::PlayerClaim, DatabasePlayer, FullPlayer, Player},
     record::{approved_records_by, FullRecord},
+    score,
 };
 use log::info;
-use pointercrate_core::util::{non_nullable, nullable};
-use serde::Deserialize;
+use pointercrate_core::{
+    audit::PatchLog,
+    util::{non_nullable, nullable},
+};
+use serde::{Deserialize, Serialize};
 use sqlx::PgConnection;
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct PatchPlayer {
     #[serde(default, deserialize_with = "non_nullable")]
     pub name: Option<String>,
@@ -17,6 +21,11 @@ pub struct PatchPlayer {
     #[serde(default, deserialize_with = "non_nullable")]
     pub banned: Option<bool>,
 
+    /// What to do with the player's records if this patch bans them. Defaults to
+    /// [`BanStrategy::RejectAll`]
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub ban_strategy: Option<BanStrategy>,
+
     #[serde(default, deserialize_with = "nullable")]
     pub nationality: Option<Option<String>>,
 
@@ -24,8 +33,51 @@ pub struct PatchPlayer {
     pub subdivision: Option<Option<String>>,
 }
 
+/// How the records of a player are dealt with when banning them
+///
+/// Pending submissions never survive a ban, as banned players cannot have records on the list.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BanStrategy {
+    /// Delete all pending submissions and reject all other records
+    RejectAll,
+
+    /// Delete all records, including approved and rejected ones. Deleted records are archived and
+    /// can be restored.
+    DeleteAll,
+
+    /// Only delete pending submissions, leaving approved and rejected records untouched (approved
+    /// records of banned players do not show up on the list)
+    KeepApproved,
+}
+
+impl Default for BanStrategy {
+    fn default() -> Self {
+        BanStrategy::RejectAll
+    }
+}
+
+/// The state of a player as recorded in the audit log, which for bans additionally contains the
+/// strategy the player's records were dealt with
+#[derive(Serialize)]
+struct AuditedPlayer<'a> {
+    #[serde(flatten)]
+    player: &'a Player,
+    ban_strategy: Option<BanStrategy>,
+}
+
 impl FullPlayer {
-    pub async fn apply_patch(mut self, patch: PatchPlayer, connection: &mut PgConnection) -> Result<Self> {
+    /// Applies the given patch, on behalf of the user with the given id
+    pub async fn apply_patch(mut self, patch: PatchPlayer, patched_by: i32, connection: &mut PgConnection) -> Result<Self> {
+        let log = PatchLog::start("player", self.player.base.id, &AuditedPlayer {
+            player: &self.player,
+            ban_strategy: None,
+        });
+        let mut ban_strategy = None;
+        let patch_changes_ban = patch.banned.is_some();
+        let patch_changes_name = patch.name.is_some();
+        let previous_nation = self.player.nationality.as_ref().map(|nation| nation.iso_country_code.clone());
+
         if let Some(nationality) = patch.nationality {
             match nationality {
                 Some(ident) =>
@@ -45,10 +97,16 @@ impl FullPlayer {
 
         if let Some(banned) = patch.banned {
             if banned && !self.player.base.banned {
-                self.player.base.ban(connection).await?;
+                let strategy = patch.ban_strategy.unwrap_or_default();
+
+                self.player.base.ban(strategy, patched_by, connection).await?;
 
                 // self.records only contains approved records!
-                self.records.clear();
+                if strategy != BanStrategy::KeepApproved {
+                    self.records.clear();
+                }
+
+                ban_strategy = Some(strategy);
             } else if !banned && self.player.base.banned {
                 self.player.base.unban(connection).await?;
             }
@@ -58,6 +116,31 @@ impl FullPlayer {
             self.set_name(name, connection).await?;
         }
 
+        log.finish(
+            &AuditedPlayer {
+                player: &self.player,
+                ban_strategy,
+            },
+            &mut *connection,
+        )
+        .await?;
+
+        // Merging (via renaming) can affect the scores of two players and their nations, banning only
+        // those of this player
+        if patch_changes_name {
+            score::refresh_player_scores(connection).await?;
+        } else if patch_changes_ban {
+            score::refresh_scores_of(&[self.player.base.id], &mut *connection).await?;
+        }
+
+        let current_nation = self.player.nationality.as_ref().map(|nation| nation.iso_country_code.clone());
+
+        if !patch_changes_name && previous_nation != current_nation {
+            let nations = previous_nation.into_iter().chain(current_nation).collect::<Vec<_>>();
+
+            score::refresh_nation_scores(&nations, connection).await?;
+        }
+
         Ok(self)
     }
 
@@ -71,10 +154,11 @@ impl FullPlayer {
             // If they are equal case insensitively, we're only doing a cosmetic rename, which won't
             // even require a merge
 
-            // try to see if a player with new name already exists
+            // try to see if a player with new name already exists. A player that merely has the new
+            // name as an alias is not merged with, as the alias will simply be shadowed by our new name
             match DatabasePlayer::by_name(name.as_ref(), &mut *connection).await {
-                Ok(existing) => self.merge(existing, &mut *connection).await?,
-                Err(DemonlistError::PlayerNotFoundName { .. }) => (),
+                Ok(existing) if existing.name.to_lowercase() == name.to_lowercase() => self.merge(existing, &mut *connection).await?,
+                Ok(_) | Err(DemonlistError::PlayerNotFoundName { .. }) => (),
                 Err(err) => return Err(err),
             }
         }
@@ -84,6 +168,15 @@ impl FullPlayer {
             name.to_string(),
             self.player.base.id
         )
+        .execute(&mut *connection)
+        .await?;
+
+        // An alias equal to our own name is pointless
+        sqlx::query!(
+            "DELETE FROM aliases WHERE player = $1 AND name = $2::text::citext",
+            self.player.base.id,
+            name.to_string()
+        )
         .execute(connection)
         .await?;
 
@@ -96,6 +189,10 @@ impl FullPlayer {
     ///
     /// Note that this method **does not** rename `Self`
     pub async fn merge(&mut self, with: DatabasePlayer, connection: &mut PgConnection) -> Result<()> {
+        if with.id == self.player.base.id {
+            return Err(DemonlistError::SelfMerge)
+        }
+
         info!("Merging player {} with player {}", self, with);
 
         let claim_on_self = PlayerClaim::verified_claim_on(self.player.base.id, &mut *connection).await?;
@@ -125,6 +222,15 @@ impl FullPlayer {
                 .await?;
             },
             (None, None) => {
+                // Users that (unverifiedly) claimed both players keep only their claim on `Self`
+                sqlx::query!(
+                    "DELETE FROM player_claims AS c1 WHERE c1.player_id = $2 AND EXISTS (SELECT 1 FROM player_claims AS c2 WHERE \
+                     c2.member_id = c1.member_id AND c2.player_id = $1)",
+                    self.player.base.id,
+                    with.id
+                )
+                .execute(&mut *connection)
+                .await?;
                 sqlx::query!(
                     "UPDATE player_claims SET player_id = $1 WHERE player_id = $2",
                     self.player.base.id,
@@ -202,11 +308,36 @@ impl FullPlayer {
 
         info!("Moved {} records from {} to {}", updated.rows_affected(), with, self);
 
+        // Deleted records move along as well, so that they can still be restored
+        let updated = sqlx::query!(
+            "UPDATE deleted_records SET player = $1 WHERE player = $2",
+            self.player.base.id,
+            with.id
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        info!("Moved {} deleted records from {} to {}", updated.rows_affected(), with, self);
+
+        // Transfer aliases, and keep the second player's name around as an alias so that future
+        // submissions using it end up at the merged player
+        sqlx::query!("UPDATE aliases SET player = $1 WHERE player = $2", self.player.base.id, with.id)
+            .execute(&mut *connection)
+            .await?;
+
         // Delete the second player
         sqlx::query!("DELETE FROM players WHERE id = $1", with.id)
-            .execute(connection)
+            .execute(&mut *connection)
             .await?;
 
+        sqlx::query!(
+            "INSERT INTO aliases (name, player) VALUES ($1::TEXT, $2) ON CONFLICT (name) WHERE player IS NOT NULL DO NOTHING",
+            with.name,
+            self.player.base.id
+        )
+        .execute(connection)
+        .await?;
+
         Ok(())
     }
 }
@@ -305,27 +436,44 @@ impl DatabasePlayer {
         Ok(())
     }
 
-    pub async fn ban(&mut self, connection: &mut PgConnection) -> Result<()> {
-        // Delete all submissions for this player
-        let deleted = sqlx::query!(
-            "DELETE FROM records WHERE player = $1 AND (status_ = 'SUBMITTED' OR status_ = 'UNDER_CONSIDERATION')",
-            self.id
-        )
-        .execute(&mut *connection)
-        .await?;
+    /// Bans this player, dealing with their records according to the given strategy. Deleted
+    /// records are attributed to the given user.
+    ///
+    /// Must be run within a transaction!
+    pub async fn ban(&mut self, strategy: BanStrategy, banned_by: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Banning player {}, dealing with their records via {:?}", self, strategy);
+
+        if strategy == BanStrategy::DeleteAll {
+            let deleted = FullRecord::delete_all_of(self.id, "Player was banned", Some(banned_by), &mut *connection).await?;
+
+            info!("Deleted {} records while banning {}", deleted, self);
+        } else {
+            // Delete all submissions for this player
+            let submissions = sqlx::query!(
+                "SELECT id FROM records WHERE player = $1 AND (status_ = 'SUBMITTED' OR status_ = 'UNDER_CONSIDERATION')",
+                self.id
+            )
+            .fetch_all(&mut *connection)
+            .await?
+            .into_iter()
+            .map(|row| row.id)
+            .collect::<Vec<_>>();
 
-        info!("Deleted {} submissions while banning {}", deleted.rows_affected(), self);
+            let deleted = FullRecord::delete_many(&submissions, "Player was banned", &mut *connection).await?;
 
-        // We can simply reject all accepted records here! All submitted records were deleted above, and we
-        // don't have to worry about conflicts with existing rejected record when setting status to
-        // 'rejected' since rejected records are globally unique!
+            info!("Deleted {} submissions while banning {}", deleted, self);
+        }
 
-        // Now, reject all previously accepted records
-        let updated = sqlx::query!("UPDATE records SET status_ = 'REJECTED' WHERE player = $1", self.id)
-            .execute(&mut *connection)
-            .await?;
+        if strategy == BanStrategy::RejectAll {
+            // We can simply reject all accepted records here! All submitted records were deleted above, and
+            // we don't have to worry about conflicts with existing rejected record when setting status to
+            // 'rejected' since rejected records are globally unique!
+            let updated = sqlx::query!("UPDATE records SET status_ = 'REJECTED' WHERE player = $1", self.id)
+                .execute(&mut *connection)
+                .await?;
 
-        info!("Rejected {} records while banning {}", updated.rows_affected(), self);
+            info!("Rejected {} records while banning {}", updated.rows_affected(), self);
+        }
 
         // Actually ban the player
         sqlx::query!("UPDATE players SET banned = true WHERE id = $1", self.id)
@@ -0,0 +1,81 @@
+//! Module for reconstructing a player's achievements in chronological order
+//!
+//! The timeline is computed from the record and demon history tables. Achievements that predate
+//! the history tables have no known time, and are put at the start of the timeline.
+
+use crate::{demon::MinimalDemon, error::Result, player::DatabasePlayer};
+use chrono::NaiveDateTime;
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize, Eq, PartialEq, Clone, Copy, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    /// A record of the player was approved
+    Approval,
+
+    /// A demon verified by the player was added to the list
+    Verification,
+
+    /// The player was the first to beat a demon. Always accompanied by an [`Approval`] at the same
+    /// time
+    ///
+    /// [`Approval`]: TimelineEventKind::Approval
+    FirstVictor,
+}
+
+impl TimelineEventKind {
+    fn from_sql(sql: &str) -> Self {
+        match sql {
+            "APPROVAL" => TimelineEventKind::Approval,
+            "VERIFICATION" => TimelineEventKind::Verification,
+            "FIRST_VICTOR" => TimelineEventKind::FirstVictor,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineEvent {
+    /// When this event happened, if known
+    pub time: Option<NaiveDateTime>,
+    pub kind: TimelineEventKind,
+
+    /// The demon this event is about, with its current position
+    pub demon: MinimalDemon,
+
+    /// The position the demon had at the time of this event
+    pub position: i16,
+
+    /// The record this event is about. `None` for verifications
+    pub record_id: Option<i32>,
+    pub progress: i16,
+}
+
+impl DatabasePlayer {
+    /// Gets this player's approvals, verifications and first victories, oldest first
+    pub async fn timeline(&self, connection: &mut PgConnection) -> Result<Vec<TimelineEvent>> {
+        let mut stream = sqlx::query_file!("sql/player_timeline.sql", self.id).fetch(connection);
+        let mut events = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            events.push(TimelineEvent {
+                time: row.time,
+                kind: TimelineEventKind::from_sql(&row.kind),
+                demon: MinimalDemon {
+                    id: row.demon_id,
+                    position: row.current_position,
+                    name: row.demon_name,
+                },
+                position: row.position,
+                record_id: row.record_id,
+                progress: row.progress,
+            })
+        }
+
+        Ok(events)
+    }
+}
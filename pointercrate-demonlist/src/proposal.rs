@@ -0,0 +1,212 @@
+//! Module for proposals of new demon placements
+//!
+//! List helpers cannot add demons to the list themselves, but they can prepare a placement as a
+//! [`DemonProposal`]. List moderators then vote on it. Once [`config::proposal_votes_required`] of
+//! them approved, the placement is executed as part of the transaction recording the deciding
+//! vote, so a proposal is never marked as approved without its demon having been added. The same
+//! number of rejecting votes discards the proposal.
+
+use crate::{
+    config,
+    demon::{Demon, FullDemon, PostDemon},
+    error::{DemonlistError, Result},
+    list::{DemonList, CLASSIC_LIST},
+};
+use chrono::NaiveDateTime;
+use log::info;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize)]
+pub struct DemonProposal {
+    pub id: i32,
+    pub name: String,
+    pub position: i16,
+    pub requirement: i16,
+    pub verifier: String,
+    pub publisher: String,
+    pub creators: Vec<String>,
+    pub video: Option<String>,
+    pub level_id: Option<i64>,
+    pub list_id: i32,
+
+    /// The id of the member who created this proposal, if their account still exists
+    pub proposed_by: Option<i32>,
+    pub created: NaiveDateTime,
+
+    /// One of `OPEN`, `APPROVED` or `REJECTED`
+    pub status: String,
+
+    /// The id of the demon added when this proposal was approved
+    pub demon: Option<i32>,
+    pub approvals: i64,
+    pub rejections: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProposalVote {
+    pub approve: bool,
+}
+
+impl DemonProposal {
+    /// Validates the given placement the same way [`FullDemon::create_from`] would, and stores it
+    /// as a new proposal
+    pub async fn create(data: PostDemon, proposed_by: i32, connection: &mut PgConnection) -> Result<DemonProposal> {
+        Demon::validate_requirement(data.requirement)?;
+
+        let video = match data.video {
+            Some(ref video) => Some(crate::video::validate(video)?),
+            None => None,
+        };
+
+        let list_id = match data.list {
+            Some(ref slug) => DemonList::by_slug(slug, connection).await?.id,
+            None => CLASSIC_LIST,
+        };
+
+        Demon::validate_position(data.position, list_id, connection).await?;
+
+        let id = sqlx::query!(
+            "INSERT INTO demon_proposals (name, position, requirement, verifier, publisher, creators, video, level_id, list_id, \
+             proposed_by) VALUES ($1::TEXT, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id",
+            data.name,
+            data.position,
+            data.requirement,
+            data.verifier,
+            data.publisher,
+            &data.creators,
+            video,
+            data.level_id,
+            list_id,
+            proposed_by
+        )
+        .fetch_one(&mut *connection)
+        .await?
+        .id;
+
+        info!(
+            "Member {} proposed placing '{}' at position {} (proposal {})",
+            proposed_by, data.name, data.position, id
+        );
+
+        DemonProposal::by_id(id, connection).await
+    }
+
+    pub async fn by_id(proposal_id: i32, connection: &mut PgConnection) -> Result<DemonProposal> {
+        sqlx::query_as!(
+            DemonProposal,
+            r#"SELECT id, name::TEXT AS "name!", position, requirement, verifier, publisher, creators, video, level_id, list_id, proposed_by,
+             created, status, demon, (SELECT COUNT(*) FROM demon_proposal_votes WHERE proposal = id AND approve) AS "approvals!",
+             (SELECT COUNT(*) FROM demon_proposal_votes WHERE proposal = id AND NOT approve) AS "rejections!" FROM demon_proposals WHERE
+             id = $1"#,
+            proposal_id
+        )
+        .fetch_optional(connection)
+        .await?
+        .ok_or(DemonlistError::ProposalNotFound { proposal_id })
+    }
+
+    /// All proposals with the given status (or all proposals, if no status is given), oldest first
+    pub async fn all(status: Option<&str>, connection: &mut PgConnection) -> Result<Vec<DemonProposal>> {
+        Ok(sqlx::query_as!(
+            DemonProposal,
+            r#"SELECT id, name::TEXT AS "name!", position, requirement, verifier, publisher, creators, video, level_id, list_id, proposed_by,
+             created, status, demon, (SELECT COUNT(*) FROM demon_proposal_votes WHERE proposal = id AND approve) AS "approvals!",
+             (SELECT COUNT(*) FROM demon_proposal_votes WHERE proposal = id AND NOT approve) AS "rejections!" FROM demon_proposals WHERE
+             (status = $1 OR $1 IS NULL) ORDER BY id"#,
+            status
+        )
+        .fetch_all(connection)
+        .await?)
+    }
+
+    /// Records the given member's vote on the given proposal, replacing any earlier vote of theirs
+    ///
+    /// If this vote decides the proposal, it is closed, and if it was approved, the proposed demon
+    /// is added and returned as well. Must be run within a transaction!
+    pub async fn vote(
+        proposal_id: i32, member_id: i32, vote: ProposalVote, connection: &mut PgConnection,
+    ) -> Result<(DemonProposal, Option<FullDemon>)> {
+        // Lock the proposal, so that two deciding votes cannot both execute it
+        let status = sqlx::query!("SELECT status FROM demon_proposals WHERE id = $1 FOR UPDATE", proposal_id)
+            .fetch_optional(&mut *connection)
+            .await?
+            .ok_or(DemonlistError::ProposalNotFound { proposal_id })?
+            .status;
+
+        if status != "OPEN" {
+            return Err(DemonlistError::ProposalClosed {
+                proposal_id,
+                status: status.to_lowercase(),
+            })
+        }
+
+        info!(
+            "Member {} votes {} proposal {}",
+            member_id,
+            if vote.approve { "for" } else { "against" },
+            proposal_id
+        );
+
+        sqlx::query!(
+            "INSERT INTO demon_proposal_votes (proposal, member, approve) VALUES ($1, $2, $3) ON CONFLICT (proposal, member) DO UPDATE \
+             SET approve = EXCLUDED.approve",
+            proposal_id,
+            member_id,
+            vote.approve
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        let proposal = DemonProposal::by_id(proposal_id, connection).await?;
+        let required = config::proposal_votes_required();
+
+        if proposal.approvals >= required {
+            let demon = proposal.execute(connection).await?;
+
+            sqlx::query!(
+                "UPDATE demon_proposals SET status = 'APPROVED', demon = $2 WHERE id = $1",
+                proposal_id,
+                demon.demon.base.id
+            )
+            .execute(&mut *connection)
+            .await?;
+
+            Ok((DemonProposal::by_id(proposal_id, connection).await?, Some(demon)))
+        } else if proposal.rejections >= required {
+            info!("Proposal {} has been rejected", proposal_id);
+
+            sqlx::query!("UPDATE demon_proposals SET status = 'REJECTED' WHERE id = $1", proposal_id)
+                .execute(&mut *connection)
+                .await?;
+
+            Ok((DemonProposal::by_id(proposal_id, connection).await?, None))
+        } else {
+            Ok((proposal, None))
+        }
+    }
+
+    /// Adds the proposed demon to its list. The position is validated again, since the list might
+    /// have changed since the proposal was made.
+    async fn execute(&self, connection: &mut PgConnection) -> Result<FullDemon> {
+        info!("Executing proposal {}", self.id);
+
+        let list = DemonList::by_id(self.list_id, connection).await?;
+
+        FullDemon::create_from(
+            PostDemon {
+                name: self.name.clone(),
+                position: self.position,
+                requirement: self.requirement,
+                verifier: self.verifier.clone(),
+                publisher: self.publisher.clone(),
+                creators: self.creators.clone(),
+                video: self.video.clone(),
+                level_id: self.level_id,
+                list: Some(list.slug),
+            },
+            connection,
+        )
+        .await
+    }
+}
@@ -0,0 +1,56 @@
+//! Module for keeping track of which record videos have been archived in the Wayback Machine
+//!
+//! The actual archiving happens outside of this crate, these functions only decide which records to
+//! archive next and store the results.
+
+use crate::{error::Result, record::FullRecord};
+use log::info;
+use sqlx::PgConnection;
+
+/// An approved record whose video has not been archived yet
+#[derive(Debug)]
+pub struct VideoArchival {
+    pub record_id: i32,
+    pub video: String,
+}
+
+impl FullRecord {
+    /// Gets up to `limit` approved records whose videos have not been archived yet, records for
+    /// which archiving was never attempted first
+    ///
+    /// Records of link banned players are skipped, as their videos are never shown anyway.
+    pub async fn videos_to_archive(limit: i64, connection: &mut PgConnection) -> Result<Vec<VideoArchival>> {
+        Ok(sqlx::query!(
+            r#"SELECT records.id, records.video::TEXT AS "video!" FROM records INNER JOIN players ON players.id = records.player WHERE
+             records.status_ = 'APPROVED' AND records.video IS NOT NULL AND records.archive_url IS NULL AND NOT players.link_banned
+             ORDER BY records.archive_attempted_at ASC NULLS FIRST, records.id LIMIT $1"#,
+            limit
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|row| {
+            VideoArchival {
+                record_id: row.id,
+                video: row.video,
+            }
+        })
+        .collect())
+    }
+
+    /// Stores the result of archiving the video of the record with the given id. `None` means
+    /// archiving failed and should be retried later.
+    pub async fn set_archive_url(record_id: i32, archive_url: Option<&str>, connection: &mut PgConnection) -> Result<()> {
+        info!("Setting archive url of record {} to {:?}", record_id, archive_url);
+
+        sqlx::query!(
+            "UPDATE records SET archive_url = $1, archive_attempted_at = (NOW() AT TIME ZONE 'utc') WHERE id = $2",
+            archive_url,
+            record_id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! Module for moderating many records at once
+//!
+//! All status changes of a batch happen inside the same transaction, but each of them is wrapped in
+//! its own savepoint, so that a single failing change only rolls back itself instead of the whole
+//! batch.
+
+use crate::{
+    demon::ListSection,
+    error::{DemonlistError, Result},
+    record::{FullRecord, RecordStatus},
+    score,
+};
+use log::{info, warn};
+use pointercrate_core::{audit::PatchLog, error::CoreError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+/// How many status changes a single batch may contain at most
+const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct BulkStatusChange {
+    pub id: i32,
+    pub status: RecordStatus,
+}
+
+/// The outcome of a single [`BulkStatusChange`]. Either `previous_status` and `record`, or `error`
+/// is set.
+#[derive(Debug, Serialize)]
+pub struct BulkStatusResult {
+    pub id: i32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_status: Option<RecordStatus>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<FullRecord>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<DemonlistError>,
+}
+
+impl FullRecord {
+    /// Applies all the given status changes in order, reporting the outcome of each of them
+    ///
+    /// Before a record is changed, it is passed to `authorize`, together with the section of its
+    /// list its demon is in. If that returns an error, the change is skipped and the error reported
+    /// as its outcome.
+    ///
+    /// Fails with [`CoreError::PayloadTooLarge`] if more than [`MAX_BATCH_SIZE`] changes are given.
+    ///
+    /// Must be called inside a transaction
+    pub async fn bulk_transition<F>(
+        changes: Vec<BulkStatusChange>, connection: &mut PgConnection, authorize: F,
+    ) -> Result<Vec<BulkStatusResult>>
+    where
+        F: Fn(&FullRecord, ListSection) -> Result<()>,
+    {
+        if changes.len() > MAX_BATCH_SIZE {
+            return Err(CoreError::PayloadTooLarge.into())
+        }
+
+        info!("Applying {} status changes in bulk", changes.len());
+
+        let mut results = Vec::with_capacity(changes.len());
+
+        for change in changes {
+            sqlx::query!("SAVEPOINT bulk_status_change").execute(&mut *connection).await?;
+
+            let result = FullRecord::bulk_transition_one(&change, &mut *connection, &authorize).await;
+
+            match result {
+                Ok((previous_status, record)) => {
+                    sqlx::query!("RELEASE SAVEPOINT bulk_status_change")
+                        .execute(&mut *connection)
+                        .await?;
+
+                    results.push(BulkStatusResult {
+                        id: change.id,
+                        previous_status: Some(previous_status),
+                        record: Some(record),
+                        error: None,
+                    })
+                },
+                Err(error) => {
+                    warn!("Bulk status change of record {} to {} failed: {}", change.id, change.status, error);
+
+                    // Rolling back to a savepoint keeps it around, so it needs to be released
+                    // afterwards as well
+                    sqlx::query!("ROLLBACK TO SAVEPOINT bulk_status_change")
+                        .execute(&mut *connection)
+                        .await?;
+                    sqlx::query!("RELEASE SAVEPOINT bulk_status_change")
+                        .execute(&mut *connection)
+                        .await?;
+
+                    results.push(BulkStatusResult {
+                        id: change.id,
+                        previous_status: None,
+                        record: None,
+                        error: Some(error),
+                    })
+                },
+            }
+        }
+
+        // Refreshing once for the whole batch instead of after every single change
+        let players = results
+            .iter()
+            .filter_map(|result| result.record.as_ref())
+            .map(|record| record.player.id)
+            .collect::<Vec<_>>();
+
+        score::refresh_scores_of(&players, connection).await?;
+
+        Ok(results)
+    }
+
+    async fn bulk_transition_one<F>(
+        change: &BulkStatusChange, connection: &mut PgConnection, authorize: &F,
+    ) -> Result<(RecordStatus, FullRecord)>
+    where
+        F: Fn(&FullRecord, ListSection) -> Result<()>,
+    {
+        let mut record = FullRecord::by_id(change.id, &mut *connection).await?;
+        let section = record.demon.section(&mut *connection).await?;
+
+        authorize(&record, section)?;
+
+        let log = PatchLog::start("record", record.id, &record);
+        let previous_status = record.status;
+
+        record.set_status(change.status, &mut *connection).await?;
+
+        log.finish(&record, connection).await?;
+
+        Ok((previous_status, record))
+    }
+}
@@ -1,22 +1,151 @@
-use crate::{error::Result, record::FullRecord};
+//! Module for deleting and restoring records
+//!
+//! Deleting a record moves it, together with its notes, into the `deleted_records` table, from
+//! where it can be restored via [`FullRecord::restore`].
+
+use crate::{
+    error::{DemonlistError, Result},
+    record::FullRecord,
+    score,
+};
 use log::info;
+use pointercrate_core::pool::create_active_user_table;
 use sqlx::PgConnection;
 
 impl FullRecord {
-    pub async fn delete(self, connection: &mut PgConnection) -> Result<()> {
-        info!("Deleting record {}", self);
+    pub async fn delete(self, reason: &str, deleted_by: Option<i32>, connection: &mut PgConnection) -> Result<()> {
+        info!("Deleting record {} (reason: {})", self, reason);
 
-        FullRecord::delete_by_id(self.id, connection).await
+        FullRecord::delete_by_id(self.id, reason, deleted_by, connection).await
     }
 
     /// `FullRecord::delete` should be preferred
-    pub async fn delete_by_id(record_id: i32, connection: &mut PgConnection) -> Result<()> {
-        // Associated notes get deleted due to the ON DELETE CASCADE on record_notes.record
+    pub async fn delete_by_id(record_id: i32, reason: &str, deleted_by: Option<i32>, connection: &mut PgConnection) -> Result<()> {
+        if reason.trim().is_empty() {
+            return Err(DemonlistError::DeletionReasonRequired)
+        }
+
+        // Associated notes get deleted due to the ON DELETE CASCADE on record_notes.record, but since all
+        // parts of this statement see the same snapshot, we can still archive them. Columns are matched
+        // by name, since columns added to `records` later on end up at different positions in both
+        // tables.
+        let deleted = sqlx::query!(
+            r#"WITH deleted AS (DELETE FROM records WHERE id = $1 RETURNING *) INSERT INTO deleted_records SELECT
+             (jsonb_populate_record(NULL::deleted_records, to_jsonb(deleted) || jsonb_build_object('notes', COALESCE((SELECT
+             jsonb_agg(to_jsonb(record_notes)) FROM record_notes WHERE record_notes.record = deleted.id), '[]'), 'reason', $2::TEXT,
+             'deleted_by', $3::INTEGER, 'deleted_at', NOW() AT TIME ZONE 'utc'))).* FROM deleted RETURNING player AS "player!""#,
+            record_id,
+            reason.trim(),
+            deleted_by
+        )
+        .fetch_optional(&mut *connection)
+        .await?;
+
+        match deleted {
+            Some(deleted) => score::refresh_scores_of(&[deleted.player], connection).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Deletes all records of the given player in a single statement, returning how many records
+    /// were deleted. See [`FullRecord::delete_by_id`].
+    ///
+    /// Does not refresh the player's score, as this is only used when banning players.
+    pub async fn delete_all_of(player_id: i32, reason: &str, deleted_by: Option<i32>, connection: &mut PgConnection) -> Result<u64> {
+        if reason.trim().is_empty() {
+            return Err(DemonlistError::DeletionReasonRequired)
+        }
+
+        let deleted = sqlx::query!(
+            r#"WITH deleted AS (DELETE FROM records WHERE player = $1 RETURNING *) INSERT INTO deleted_records SELECT
+             (jsonb_populate_record(NULL::deleted_records, to_jsonb(deleted) || jsonb_build_object('notes', COALESCE((SELECT
+             jsonb_agg(to_jsonb(record_notes)) FROM record_notes WHERE record_notes.record = deleted.id), '[]'), 'reason', $2::TEXT,
+             'deleted_by', $3::INTEGER, 'deleted_at', NOW() AT TIME ZONE 'utc'))).* FROM deleted"#,
+            player_id,
+            reason.trim(),
+            deleted_by
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(deleted.rows_affected())
+    }
+
+    /// Deletes all the given records in a single statement, returning how many records were
+    /// deleted. See [`FullRecord::delete_by_id`].
+    ///
+    /// Used wherever records are deleted as a side effect of some other change (e.g. approving a
+    /// record deletes the submissions it supersedes), so that such records can still be restored.
+    /// The deletion is attributed to the user the connection is audited for. Does not refresh any
+    /// scores.
+    pub(crate) async fn delete_many(record_ids: &[i32], reason: &str, connection: &mut PgConnection) -> Result<u64> {
+        if record_ids.is_empty() {
+            return Ok(0)
+        }
+
+        create_active_user_table(&mut *connection).await?;
 
-        sqlx::query!("DELETE FROM records WHERE id = $1", record_id)
-            .execute(connection)
+        let deleted = sqlx::query!(
+            r#"WITH deleted AS (DELETE FROM records WHERE id = ANY($1::INTEGER[]) RETURNING *) INSERT INTO deleted_records SELECT
+             (jsonb_populate_record(NULL::deleted_records, to_jsonb(deleted) || jsonb_build_object('notes', COALESCE((SELECT
+             jsonb_agg(to_jsonb(record_notes)) FROM record_notes WHERE record_notes.record = deleted.id), '[]'), 'reason', $2::TEXT,
+             'deleted_by', (SELECT NULLIF(id, 0) FROM active_user LIMIT 1), 'deleted_at', NOW() AT TIME ZONE 'utc'))).* FROM deleted"#,
+            record_ids,
+            reason
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(deleted.rows_affected())
+    }
+
+    /// Moves a deleted record (and its notes) back into the `records` table
+    ///
+    /// Fails if the record's video has been used by a different record in the meantime.
+    ///
+    /// Must be called inside a transaction
+    pub async fn restore(record_id: i32, connection: &mut PgConnection) -> Result<FullRecord> {
+        let deleted = sqlx::query!("SELECT video::text, player FROM deleted_records WHERE id = $1", record_id)
+            .fetch_optional(&mut *connection)
+            .await?
+            .ok_or(DemonlistError::RecordNotFound { record_id })?;
+
+        if let Some(video) = deleted.video {
+            let existing = sqlx::query!(
+                "SELECT id FROM records WHERE video_without_timestamp(video::TEXT) = video_without_timestamp($1::TEXT)",
+                video
+            )
+            .fetch_optional(&mut *connection)
             .await?;
 
-        Ok(())
+            if let Some(existing) = existing {
+                return Err(DemonlistError::DuplicateVideo { id: existing.id })
+            }
+        }
+
+        info!("Restoring deleted record {}", record_id);
+
+        // The additional columns of deleted_records (notes, reason, ...) are simply ignored by
+        // jsonb_populate_record
+        sqlx::query!(
+            "INSERT INTO records SELECT (jsonb_populate_record(NULL::records, to_jsonb(deleted_records))).* FROM deleted_records WHERE id \
+             = $1",
+            record_id
+        )
+        .execute(&mut *connection)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO record_notes SELECT (jsonb_populate_recordset(NULL::record_notes, notes)).* FROM deleted_records WHERE id = $1",
+            record_id
+        )
+        .execute(&mut *connection)
+        .await?;
+        sqlx::query!("DELETE FROM deleted_records WHERE id = $1", record_id)
+            .execute(&mut *connection)
+            .await?;
+
+        score::refresh_scores_of(&[deleted.player], &mut *connection).await?;
+
+        FullRecord::by_id(record_id, connection).await
     }
 }
@@ -3,17 +3,24 @@ use crate::{
     error::{DemonlistError, Result},
     nationality::Nationality,
     player::DatabasePlayer,
-    record::{note::notes_on, FullRecord, MinimalRecordD, MinimalRecordP, RecordStatus},
+    record::{note::notes_on, FullRecord, MinimalRecordD, MinimalRecordP, RecordStatus, VideoStatus},
     submitter::Submitter,
 };
+use chrono::NaiveDateTime;
 use futures::stream::StreamExt;
+use pointercrate_core::etag::Taggable;
+use serde::Serialize;
 use sqlx::{Error, PgConnection};
+use std::collections::HashMap;
 
 // Required until https://github.com/launchbadge/sqlx/pull/108 is merged
 struct FetchedRecord {
     progress: i16,
     video: Option<String>,
     status: String,
+    video_status: String,
+    archive_url: Option<String>,
+    raw_footage: Option<String>,
     player_id: i32,
     player_name: String,
     player_banned: bool,
@@ -22,6 +29,7 @@ struct FetchedRecord {
     position: i16,
     submitter_id: i32,
     submitter_banned: bool,
+    submitter_flagged: bool,
 }
 
 impl FullRecord {
@@ -37,6 +45,9 @@ impl FullRecord {
                     progress: row.progress,
                     video: row.video,
                     status: RecordStatus::from_sql(&row.status),
+                    video_status: VideoStatus::from_sql(&row.video_status),
+                    archive_url: row.archive_url,
+                    raw_footage: row.raw_footage,
                     player: DatabasePlayer {
                         id: row.player_id,
                         name: row.player_name,
@@ -50,6 +61,7 @@ impl FullRecord {
                     submitter: Some(Submitter {
                         id: row.submitter_id,
                         banned: row.submitter_banned,
+                        flagged: row.submitter_flagged,
                     }),
                     notes: notes_on(id, connection).await?,
                 }),
@@ -91,8 +103,18 @@ pub async fn approved_records_by(player: &DatabasePlayer, connection: &mut PgCon
 }
 
 pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Vec<MinimalRecordP>> {
+    Ok(approved_records_on_all(&[demon.id], connection)
+        .await?
+        .remove(&demon.id)
+        .unwrap_or_default())
+}
+
+/// The approved records on each of the given demons, in a single query. Demons without approved
+/// records are not contained in the returned map. Records of banned players are left out.
+pub async fn approved_records_on_all(demon_ids: &[i32], connection: &mut PgConnection) -> Result<HashMap<i32, Vec<MinimalRecordP>>> {
     struct Fetched {
         id: i32,
+        demon: i32,
         progress: i16,
         video: Option<String>,
         player_id: i32,
@@ -104,19 +126,19 @@ pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnec
 
     let mut stream = sqlx::query_as!(
         Fetched,
-        r#"SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE video::text END, players.id AS player_id, 
+        r#"SELECT records.id, records.demon, progress, CASE WHEN players.link_banned THEN NULL ELSE video::text END, players.id AS player_id, 
          players.name AS "name: String", players.banned, nation::TEXT, iso_country_code::TEXT FROM records INNER JOIN players ON records.player = players.id LEFT OUTER JOIN nationalities ON nationality = iso_country_code WHERE status_ = 'APPROVED' AND 
-         records.demon = $1 ORDER BY progress DESC, id ASC"#,
-        demon.id
+         records.demon = ANY($1) AND NOT players.banned ORDER BY progress DESC, id ASC"#,
+        demon_ids
     )
     .fetch(connection);
 
-    let mut records = Vec::new();
+    let mut records: HashMap<i32, Vec<MinimalRecordP>> = HashMap::new();
 
     while let Some(row) = stream.next().await {
         let row = row?;
 
-        records.push(MinimalRecordP {
+        records.entry(row.demon).or_default().push(MinimalRecordP {
             id: row.id,
             progress: row.progress,
             video: row.video,
@@ -140,3 +162,125 @@ pub async fn approved_records_on(demon: &MinimalDemon, connection: &mut PgConnec
 
     Ok(records)
 }
+
+/// The approved records directly above and below some progress value on a demon's leaderboard
+#[derive(Debug, Serialize, Hash)]
+pub struct RecordNeighbors {
+    /// The record with the lowest progress strictly greater than the requested one
+    pub above: Option<MinimalRecordP>,
+
+    /// The record with the highest progress strictly less than the requested one
+    pub below: Option<MinimalRecordP>,
+}
+
+impl Taggable for RecordNeighbors {}
+
+struct FetchedRecordP {
+    id: i32,
+    progress: i16,
+    video: Option<String>,
+    player_id: i32,
+    name: String,
+    banned: bool,
+    nation: Option<String>,
+    iso_country_code: Option<String>,
+}
+
+impl From<FetchedRecordP> for MinimalRecordP {
+    fn from(row: FetchedRecordP) -> Self {
+        MinimalRecordP {
+            id: row.id,
+            progress: row.progress,
+            video: row.video,
+            status: RecordStatus::Approved,
+            player: DatabasePlayer {
+                id: row.player_id,
+                name: row.name,
+                banned: row.banned,
+            },
+            nationality: match (row.nation, row.iso_country_code) {
+                (Some(nation), Some(code)) =>
+                    Some(Nationality {
+                        iso_country_code: code,
+                        nation,
+                        subdivision: None,
+                    }),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Gets the approved records adjacent to the given progress on the given demon's leaderboard
+///
+/// Records with equal progress are ordered by their submission time, meaning that the neighbor
+/// above is the most recently submitted record with the next higher progress, and the neighbor
+/// below is the earliest submitted record with the next lower progress. Records predating the audit
+/// log have no submission time and are considered older than all others.
+pub async fn record_neighbors(demon: &MinimalDemon, progress: i16, connection: &mut PgConnection) -> Result<RecordNeighbors> {
+    let above = sqlx::query_as!(
+        FetchedRecordP,
+        r#"SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE video::text END, players.id AS player_id, 
+         players.name AS "name: String", players.banned, nation::TEXT, iso_country_code::TEXT FROM records INNER JOIN players ON 
+         records.player = players.id LEFT OUTER JOIN nationalities ON nationality = iso_country_code LEFT OUTER JOIN record_additions ON 
+         record_additions.id = records.id WHERE status_ = 'APPROVED' AND records.demon = $1 AND progress > $2 ORDER BY progress ASC, 
+         record_additions.time DESC NULLS LAST, records.id DESC LIMIT 1"#,
+        demon.id,
+        progress
+    )
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    let below = sqlx::query_as!(
+        FetchedRecordP,
+        r#"SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE video::text END, players.id AS player_id, 
+         players.name AS "name: String", players.banned, nation::TEXT, iso_country_code::TEXT FROM records INNER JOIN players ON 
+         records.player = players.id LEFT OUTER JOIN nationalities ON nationality = iso_country_code LEFT OUTER JOIN record_additions ON 
+         record_additions.id = records.id WHERE status_ = 'APPROVED' AND records.demon = $1 AND progress < $2 ORDER BY progress DESC, 
+         record_additions.time ASC NULLS FIRST, records.id ASC LIMIT 1"#,
+        demon.id,
+        progress
+    )
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(RecordNeighbors {
+        above: above.map(Into::into),
+        below: below.map(Into::into),
+    })
+}
+
+/// Gets the first victor of the given demon, that is the earliest submitted approved 100% record on
+/// it by a player that is not banned
+///
+/// Records predating the audit log have no submission time and are considered older than all
+/// others.
+pub async fn first_victor(demon: &MinimalDemon, connection: &mut PgConnection) -> Result<Option<MinimalRecordP>> {
+    let victor = sqlx::query_as!(
+        FetchedRecordP,
+        r#"SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE video::text END, players.id AS player_id, 
+         players.name AS "name: String", players.banned, nation::TEXT, iso_country_code::TEXT FROM records INNER JOIN players ON 
+         records.player = players.id LEFT OUTER JOIN nationalities ON nationality = iso_country_code LEFT OUTER JOIN record_additions ON 
+         record_additions.id = records.id WHERE status_ = 'APPROVED' AND records.demon = $1 AND progress = 100 AND NOT players.banned ORDER BY 
+         record_additions.time ASC NULLS FIRST, records.id ASC LIMIT 1"#,
+        demon.id
+    )
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(victor.map(Into::into))
+}
+
+impl FullRecord {
+    /// Gets the point in time (in UTC) the record with the given id was last modified
+    pub async fn last_modified(id: i32, connection: &mut PgConnection) -> Result<NaiveDateTime> {
+        match sqlx::query!("SELECT last_modified FROM records WHERE id = $1", id)
+            .fetch_one(connection)
+            .await
+        {
+            Ok(row) => Ok(row.last_modified),
+            Err(Error::RowNotFound) => Err(DemonlistError::RecordNotFound { record_id: id }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
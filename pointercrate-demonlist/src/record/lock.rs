@@ -0,0 +1,134 @@
+//! Module for claiming submissions for review
+//!
+//! To prevent two members from reviewing the same submission at the same time, a member can claim a
+//! record. Until the claim expires (after [`config::review_lock_minutes`] minutes) or is released,
+//! nobody else can change the record's status. Claiming a record again extends the claim. Only
+//! submitted records can be claimed, and list administrators can release anyone's claim.
+
+use crate::{
+    config,
+    error::{DemonlistError, Result},
+};
+use chrono::NaiveDateTime;
+use log::info;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReviewLock {
+    pub record: i32,
+
+    /// The id of the member who claimed the record
+    pub member: i32,
+    pub expires: NaiveDateTime,
+}
+
+impl ReviewLock {
+    /// Claims the given record for the given member, failing if someone else holds an unexpired
+    /// claim on it
+    pub async fn claim(record_id: i32, member_id: i32, connection: &mut PgConnection) -> Result<ReviewLock> {
+        sqlx::query!("DELETE FROM record_review_locks WHERE expires < (NOW() AT TIME ZONE 'utc')")
+            .execute(&mut *connection)
+            .await?;
+
+        let claimed = sqlx::query_as!(
+            ReviewLock,
+            "INSERT INTO record_review_locks (record, member, expires) VALUES ($1, $2, (NOW() AT TIME ZONE 'utc') + make_interval(mins => \
+             $3)) ON CONFLICT (record) DO UPDATE SET expires = EXCLUDED.expires WHERE record_review_locks.member = EXCLUDED.member \
+             RETURNING record, member, expires",
+            record_id,
+            member_id,
+            config::review_lock_minutes()
+        )
+        .fetch_optional(&mut *connection)
+        .await?;
+
+        match claimed {
+            Some(lock) => {
+                info!(
+                    "Member {} claimed record {} for review until {}",
+                    member_id, record_id, lock.expires
+                );
+
+                Ok(lock)
+            },
+            None => {
+                // Expired claims were deleted above, so someone else holds a valid claim
+                let lock = sqlx::query_as!(
+                    ReviewLock,
+                    "SELECT record, member, expires FROM record_review_locks WHERE record = $1",
+                    record_id
+                )
+                .fetch_one(connection)
+                .await?;
+
+                Err(DemonlistError::RecordLocked {
+                    record_id,
+                    member_id: lock.member,
+                    expires: lock.expires,
+                })
+            },
+        }
+    }
+
+    /// Releases the given member's claim on the given record, if they hold one
+    pub async fn release(record_id: i32, member_id: i32, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM record_review_locks WHERE record = $1 AND member = $2",
+            record_id,
+            member_id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Releases any claim on the given record, regardless of who holds it
+    pub async fn break_lock(record_id: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Breaking review claim on record {}", record_id);
+
+        sqlx::query!("DELETE FROM record_review_locks WHERE record = $1", record_id)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The unexpired claims on the given records, by record id
+    pub async fn of_records(record_ids: &[i32], connection: &mut PgConnection) -> Result<HashMap<i32, ReviewLock>> {
+        Ok(sqlx::query_as!(
+            ReviewLock,
+            "SELECT record, member, expires FROM record_review_locks WHERE record = ANY($1) AND expires > (NOW() AT TIME ZONE 'utc')",
+            record_ids
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|lock| (lock.record, lock))
+        .collect())
+    }
+
+    /// Fails with [`DemonlistError::RecordLocked`] if a member other than the given one holds an
+    /// unexpired claim on the given record
+    pub async fn require_unlocked(record_id: i32, member_id: i32, connection: &mut PgConnection) -> Result<()> {
+        match ReviewLock::of_records(&[record_id], connection).await?.remove(&record_id) {
+            Some(lock) => lock.require_held_by(member_id),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails with [`DemonlistError::RecordLocked`] if this claim is not held by the given member
+    pub fn require_held_by(&self, member_id: i32) -> Result<()> {
+        if self.member != member_id {
+            return Err(DemonlistError::RecordLocked {
+                record_id: self.record,
+                member_id: self.member,
+                expires: self.expires,
+            })
+        }
+
+        Ok(())
+    }
+}
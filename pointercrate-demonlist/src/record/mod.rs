@@ -20,12 +20,21 @@
 //! * 'under consideration' means essentially the same as 'submitted', only that all further
 //!   submissions for this (demon, player) tuple are disallowed. Note that this does not mean that
 //!   the 'under consideration' status makes. A record under consideration IS NOT UNIQUE!
+//!
+//! Not every status change is allowed, see [`RecordStatus::can_transition_to`].
 
 pub use self::{
-    get::{approved_records_by, approved_records_on},
+    archive::VideoArchival,
+    bulk::{BulkStatusChange, BulkStatusResult},
+    get::{approved_records_by, approved_records_on, approved_records_on_all, first_victor, record_neighbors, RecordNeighbors},
+    lock::ReviewLock,
     paginate::RecordPagination,
-    patch::PatchRecord,
-    post::Submission,
+    patch::{PatchRecord, StatusTransition},
+    post::{Submission, SubmissionReport, SupersededRecord},
+    queue::{average_review_time, QueuePosition},
+    revalidate::{ChangedVideo, CollidingVideo, FailedVideo, VideoRevalidation},
+    stats::{DemonRecordStatistics, WeeklySubmissions},
+    video_status::{VideoCheck, VideoStatus},
 };
 use crate::{
     demon::MinimalDemon, error::Result, nationality::Nationality, player::DatabasePlayer, record::note::Note, submitter::Submitter,
@@ -40,13 +49,21 @@ use std::{
     hash::{Hash, Hasher},
 };
 
+mod archive;
 pub mod audit;
+mod bulk;
 mod delete;
 mod get;
+mod lock;
 pub mod note;
 mod paginate;
 mod patch;
 mod post;
+mod queue;
+mod redundant;
+mod revalidate;
+mod stats;
+mod video_status;
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub enum RecordStatus {
@@ -67,7 +84,21 @@ impl RecordStatus {
         .to_owned()
     }
 
-    fn from_sql(sql: &str) -> Self {
+    /// Whether a record with this status is allowed to have its status changed to `to`
+    ///
+    /// 'submitted' and 'under consideration' records can be moved into any status. Once a record
+    /// has been decided on however, it can only be moved between 'approved' and 'rejected'.
+    /// Keeping a record's current status is always allowed.
+    pub fn can_transition_to(self, to: RecordStatus) -> bool {
+        match (self, to) {
+            (from, to) if from == to => true,
+            (RecordStatus::Submitted, _) | (RecordStatus::UnderConsideration, _) => true,
+            (RecordStatus::Approved, RecordStatus::Rejected) | (RecordStatus::Rejected, RecordStatus::Approved) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn from_sql(sql: &str) -> Self {
         match sql {
             "SUBMITTED" => RecordStatus::Submitted,
             "APPROVED" => RecordStatus::Approved,
@@ -125,19 +156,50 @@ impl<'de> Deserialize<'de> for RecordStatus {
     }
 }
 
-#[derive(Debug, Serialize, Display, Hash)]
+#[derive(Debug, Serialize, Display, Clone)]
 #[display(fmt = "{} {}% on {} (ID: {})", player, progress, demon, id)]
 pub struct FullRecord {
     pub id: i32,
     pub progress: i16,
     pub video: Option<String>,
     pub status: RecordStatus,
+
+    /// Whether this record's video was still reachable when it was last checked
+    pub video_status: VideoStatus,
+
+    /// Link to a snapshot of this record's video in the Wayback Machine, if it has been archived
+    pub archive_url: Option<String>,
+
+    /// Link to the unedited footage of the completion. Only visible to list moderators, endpoints
+    /// need to clear it for everyone else
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_footage: Option<String>,
+
     pub player: DatabasePlayer,
     pub demon: MinimalDemon,
     pub submitter: Option<Submitter>,
     pub notes: Vec<Note>,
 }
 
+impl Taggable for MinimalRecordP {}
+
+// The archive url is set by a background job (see `pointercrate-demonlist-api`'s archive module),
+// which should not invalidate the ETags of moderators currently editing the record -> no hash
+impl Hash for FullRecord {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.progress.hash(state);
+        self.video.hash(state);
+        self.status.hash(state);
+        self.video_status.hash(state);
+        self.raw_footage.hash(state);
+        self.player.hash(state);
+        self.demon.hash(state);
+        self.submitter.hash(state);
+        self.notes.hash(state);
+    }
+}
+
 impl Taggable for FullRecord {
     fn patch_part(&self) -> u64 {
         let mut hasher = DefaultHasher::new();
@@ -162,6 +224,11 @@ pub struct MinimalRecordPD {
     pub status: RecordStatus,
     pub demon: MinimalDemon,
     pub player: DatabasePlayer,
+
+    /// Whether this record has been deleted. Deleted records only show up when explicitly
+    /// requested.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
 }
 
 #[derive(Debug, Hash, Serialize, Display, PartialEq, Eq)]
@@ -174,7 +241,7 @@ pub struct MinimalRecordD {
     pub demon: MinimalDemon,
 }
 
-#[derive(Debug, Hash, Serialize, Display, PartialEq, Eq)]
+#[derive(Debug, Hash, Serialize, Display, PartialEq, Eq, Clone)]
 #[display(fmt = "{} - {}% (ID: {})", player, progress, id)]
 pub struct MinimalRecordP {
     pub id: i32,
@@ -64,7 +64,7 @@ pub async fn notes_on(record_id: i32, connection: &mut PgConnection) -> Result<V
     let partials = sqlx::query_as!(
         PartialNote,
         r#"SELECT id, record, content, members.name AS "author?: String", EXISTS(SELECT 1 FROM record_notes_modifications WHERE record IS NOT NULL AND 
-         id = $1) AS "transferred!: bool"  FROM record_notes NATURAL JOIN record_notes_additions LEFT OUTER JOIN members on members.member_id = 
+         record_notes_modifications.id = record_notes.id) AS "transferred!: bool" FROM record_notes NATURAL JOIN record_notes_additions LEFT OUTER JOIN members on members.member_id = 
          record_notes_additions.userid WHERE record = $1"#,
         record_id
     )
@@ -11,7 +11,7 @@ use std::{
     hash::{Hash, Hasher},
 };
 
-#[derive(Serialize, Debug, Hash)]
+#[derive(Serialize, Debug, Hash, Clone)]
 pub struct Note {
     pub id: i32,
 
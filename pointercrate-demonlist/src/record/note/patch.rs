@@ -2,7 +2,7 @@ use crate::{
     error::{DemonlistError, Result},
     record::note::Note,
 };
-use pointercrate_core::util::non_nullable;
+use pointercrate_core::{audit::PatchLog, util::non_nullable};
 use serde::Deserialize;
 use sqlx::PgConnection;
 
@@ -14,18 +14,22 @@ pub struct PatchNote {
 
 impl Note {
     pub async fn apply_patch(mut self, patch: PatchNote, connection: &mut PgConnection) -> Result<Note> {
+        let log = PatchLog::start("record_note", self.id, &self);
+
         if let Some(content) = patch.content {
             if content.trim().is_empty() {
                 return Err(DemonlistError::NoteEmpty)
             }
 
             sqlx::query!("UPDATE record_notes SET content = $1 WHERE id = $2", content, self.id)
-                .execute(connection)
+                .execute(&mut *connection)
                 .await?;
 
             self.content = content;
         }
 
+        log.finish(&self, connection).await?;
+
         Ok(self)
     }
 }
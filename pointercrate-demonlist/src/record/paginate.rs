@@ -10,9 +10,18 @@ use pointercrate_core::{
     util::{non_nullable, nullable},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgRow, PgConnection, Row};
+use sqlx::{
+    postgres::{PgArguments, PgRow},
+    query::Query,
+    PgConnection, Postgres, Row,
+};
 
+/// Pagination data for records
+///
+/// Unknown query parameters are rejected, so that typos in filter names (e.g. `progres__gt`)
+/// produce an error instead of silently returning unfiltered data.
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct RecordPagination {
     #[serde(default, deserialize_with = "non_nullable")]
     #[serde(rename = "before")]
@@ -35,6 +44,14 @@ pub struct RecordPagination {
     #[serde(rename = "progress__gt")]
     progress_gt: Option<i16>,
 
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "progress__lte")]
+    progress_lte: Option<i16>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "progress__gte")]
+    progress_gte: Option<i16>,
+
     demon_position: Option<i16>,
 
     #[serde(default, deserialize_with = "non_nullable")]
@@ -45,6 +62,14 @@ pub struct RecordPagination {
     #[serde(rename = "demon_position__gt")]
     demon_position_gt: Option<i16>,
 
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "demon_position__lte")]
+    demon_position_lte: Option<i16>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    #[serde(rename = "demon_position__gte")]
+    demon_position_gte: Option<i16>,
+
     #[serde(default, deserialize_with = "non_nullable")]
     pub status: Option<RecordStatus>,
 
@@ -55,15 +80,23 @@ pub struct RecordPagination {
     demon: Option<String>,
 
     #[serde(default, deserialize_with = "non_nullable")]
-    demon_id: Option<i32>,
+    pub demon_id: Option<i32>,
 
     #[serde(default, deserialize_with = "nullable")]
     video: Option<Option<String>>,
 
     #[serde(default, deserialize_with = "non_nullable")]
     pub submitter: Option<i32>,
+
+    /// Whether deleted records should be included in the result
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_deleted: bool,
 }
 
+const LIVE_RECORDS: &str = "(SELECT id, progress, video, status_, player, demon, submitter, FALSE AS deleted FROM records)";
+const ALL_RECORDS: &str = "(SELECT id, progress, video, status_, player, demon, submitter, FALSE AS deleted FROM records UNION ALL SELECT \
+                           id, progress, video, status_, player, demon, submitter, TRUE FROM deleted_records)";
+
 impl RecordPagination {
     /// Retries the page of records matching the pagination data in here
     ///
@@ -94,25 +127,10 @@ impl RecordPagination {
             "ASC"
         };
 
-        let query = format!(include_str!("../../sql/paginate_records.sql"), order);
+        let query = format!(include_str!("../../sql/paginate_records.sql"), self.source(), order);
 
-        let mut stream = sqlx::query(&query)
-            .bind(self.before_id)
-            .bind(self.after_id)
-            .bind(self.progress)
-            .bind(self.progress_lt)
-            .bind(self.progress_gt)
-            .bind(self.demon_position)
-            .bind(self.demon_position_lt)
-            .bind(self.demon_position_gt)
-            .bind(self.status.map(|s| s.to_sql()))
-            .bind(self.demon.as_ref().map(|s| s.as_str()))
-            .bind(self.demon_id)
-            .bind(&self.video)
-            .bind(self.video == Some(None))
-            .bind(self.player)
-            .bind(self.submitter)
-            .bind(limit + 1)
+        let mut stream = self
+            .bind(&query, self.before_id, self.after_id, Some(limit + 1))
             .fetch(&mut *connection);
 
         let mut records = Vec::new();
@@ -125,6 +143,7 @@ impl RecordPagination {
                 progress: row.try_get("progress")?,
                 video: row.try_get("video")?,
                 status: RecordStatus::from_sql(&row.try_get::<String, _>("status")?),
+                deleted: row.try_get("deleted")?,
                 player: DatabasePlayer {
                     id: row.try_get("player_id")?,
                     name: row.try_get("player_name")?,
@@ -140,4 +159,52 @@ impl RecordPagination {
 
         Ok(records)
     }
+
+    /// Counts the records matching the filters in here, regardless of `before`, `after` and `limit`
+    pub async fn count(&self, connection: &mut PgConnection) -> Result<i64> {
+        let query = format!(include_str!("../../sql/paginate_records.sql"), self.source(), "ASC");
+        let query = format!("SELECT COUNT(*) FROM ({}) AS matching", query);
+
+        Ok(self
+            .bind(&query, None, None, None)
+            .fetch_one(connection)
+            .await?
+            .try_get::<i64, _>(0)?)
+    }
+
+    fn source(&self) -> &'static str {
+        if self.include_deleted {
+            ALL_RECORDS
+        } else {
+            LIVE_RECORDS
+        }
+    }
+
+    /// Binds the filters in here to the given query (based on `paginate_records.sql`). A limit of
+    /// `None` means no limit.
+    fn bind<'q>(
+        &'q self, query: &'q str, before_id: Option<i32>, after_id: Option<i32>, limit: Option<i32>,
+    ) -> Query<'q, Postgres, PgArguments> {
+        sqlx::query(query)
+            .bind(before_id)
+            .bind(after_id)
+            .bind(self.progress)
+            .bind(self.progress_lt)
+            .bind(self.progress_gt)
+            .bind(self.demon_position)
+            .bind(self.demon_position_lt)
+            .bind(self.demon_position_gt)
+            .bind(self.status.map(|s| s.to_sql()))
+            .bind(self.demon.as_ref().map(|s| s.as_str()))
+            .bind(self.demon_id)
+            .bind(&self.video)
+            .bind(self.video == Some(None))
+            .bind(self.player)
+            .bind(self.submitter)
+            .bind(limit)
+            .bind(self.progress_lte)
+            .bind(self.progress_gte)
+            .bind(self.demon_position_lte)
+            .bind(self.demon_position_gte)
+    }
 }
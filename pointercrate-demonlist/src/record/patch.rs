@@ -2,17 +2,19 @@ use crate::{
     demon::MinimalDemon,
     error::{DemonlistError, Result},
     player::DatabasePlayer,
-    record::{FullRecord, RecordStatus},
+    record::{FullRecord, RecordStatus, VideoStatus},
+    score,
 };
 use log::{info, warn};
 use pointercrate_core::{
+    audit::PatchLog,
     error::CoreError,
     util::{non_nullable, nullable},
 };
 use serde::Deserialize;
 use sqlx::PgConnection;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct PatchRecord {
     #[serde(default, deserialize_with = "non_nullable")]
     progress: Option<i16>,
@@ -33,11 +35,27 @@ pub struct PatchRecord {
     demon_id: Option<i32>,
 }
 
+impl PatchRecord {
+    /// The status this patch changes the record to, if any
+    pub fn status(&self) -> Option<RecordStatus> {
+        self.status
+    }
+}
+
+/// Request body of the dedicated status transition endpoint
+#[derive(Debug, Deserialize)]
+pub struct StatusTransition {
+    pub status: RecordStatus,
+}
+
 impl FullRecord {
     /// Must be called inside a transaction
     pub async fn apply_patch(mut self, data: PatchRecord, connection: &mut PgConnection) -> Result<Self> {
         info!("Applying patch {:?} for record {}", data, self);
 
+        let log = PatchLog::start("record", self.id, &self);
+        let previous_player = self.player.id;
+
         if let Some(progress) = data.progress {
             self.set_progress(progress, connection).await?;
         }
@@ -68,6 +86,28 @@ impl FullRecord {
             _ => (),
         }
 
+        log.finish(&self, &mut *connection).await?;
+
+        score::refresh_scores_of(&[previous_player, self.player.id], connection).await?;
+
+        Ok(self)
+    }
+
+    /// Moves this record into the given status, deleting all (player, demon)-records made redundant
+    /// by this
+    ///
+    /// Must be called inside a transaction
+    pub async fn transition_to(mut self, status: RecordStatus, connection: &mut PgConnection) -> Result<Self> {
+        info!("Moving record {} from status {} to {}", self, self.status, status);
+
+        let log = PatchLog::start("record", self.id, &self);
+
+        self.set_status(status, connection).await?;
+
+        log.finish(&self, &mut *connection).await?;
+
+        score::refresh_scores_of(&[self.player.id], connection).await?;
+
         Ok(self)
     }
 
@@ -94,9 +134,15 @@ impl FullRecord {
                 .execute(&mut *connection)
                 .await?;
 
-                let records_deleted = sqlx::query!("DELETE FROM records WHERE player = $1 AND demon = $2", player, demon)
-                    .execute(connection)
-                    .await?;
+                let superseded = sqlx::query!("SELECT id FROM records WHERE player = $1 AND demon = $2", player, demon)
+                    .fetch_all(&mut *connection)
+                    .await?
+                    .into_iter()
+                    .map(|row| row.id)
+                    .collect::<Vec<_>>();
+
+                let records_deleted =
+                    FullRecord::delete_many(&superseded, &format!("Superseded by rejected record {}", self.id), connection).await?;
 
                 info!(
                     "Turning {} into a ({}, {})-record caused the transfer of {} notes and the deletion of {} records!",
@@ -104,7 +150,7 @@ impl FullRecord {
                     player,
                     demon,
                     notes_transferred.rows_affected(),
-                    records_deleted.rows_affected()
+                    records_deleted
                 );
             },
             RecordStatus::Approved => {
@@ -133,18 +179,19 @@ impl FullRecord {
                 .await?;
 
                 if let Some(row) = row {
-                    sqlx::query!("DELETE FROM records WHERE id = $1", row.id)
-                        .execute(&mut *connection)
-                        .await?;
-                    sqlx::query("UPDATE records SET video = $1::TEXT, progress = $2 WHERE id = $3")
-                        .bind(&row.video)
-                        .bind(row.progress)
-                        .bind(self.id)
-                        .execute(&mut *connection)
-                        .await?;
+                    FullRecord::delete_many(&[row.id], &format!("Merged into record {}", self.id), &mut *connection).await?;
+                    sqlx::query(
+                        "UPDATE records SET video = $1::TEXT, progress = $2, archive_url = NULL, archive_attempted_at = NULL WHERE id = $3",
+                    )
+                    .bind(&row.video)
+                    .bind(row.progress)
+                    .bind(self.id)
+                    .execute(&mut *connection)
+                    .await?;
 
                     self.progress = row.progress;
                     self.video = row.video;
+                    self.archive_url = None;
                 }
 
                 let notes_transferred = sqlx::query!(
@@ -158,14 +205,20 @@ impl FullRecord {
                 .execute(&mut *connection)
                 .await?;
 
-                let records_deleted = sqlx::query!(
-                    "DELETE FROM records WHERE demon = $1 AND player = $2 AND (status_ = 'REJECTED' OR progress <= $3)",
+                let superseded = sqlx::query!(
+                    "SELECT id FROM records WHERE demon = $1 AND player = $2 AND (status_ = 'REJECTED' OR progress <= $3)",
                     demon,
                     player,
                     self.progress
                 )
-                .execute(connection)
-                .await?;
+                .fetch_all(&mut *connection)
+                .await?
+                .into_iter()
+                .map(|row| row.id)
+                .collect::<Vec<_>>();
+
+                let records_deleted =
+                    FullRecord::delete_many(&superseded, &format!("Superseded by record {}", self.id), connection).await?;
 
                 info!(
                     "Turning {} into a ({}, {})-record caused the transfer of {} notes and the deletion of {} records!",
@@ -173,7 +226,7 @@ impl FullRecord {
                     player,
                     demon,
                     notes_transferred.rows_affected(),
-                    records_deleted.rows_affected()
+                    records_deleted
                 );
             },
             // Nothing needed to be done here!
@@ -184,11 +237,15 @@ impl FullRecord {
     }
 
     pub async fn delete_video(&mut self, connection: &mut PgConnection) -> Result<()> {
-        sqlx::query!("UPDATE records SET video = NULL WHERE id = $1", self.id)
-            .execute(connection)
-            .await?;
+        sqlx::query!(
+            "UPDATE records SET video = NULL, archive_url = NULL, archive_attempted_at = NULL WHERE id = $1",
+            self.id
+        )
+        .execute(connection)
+        .await?;
 
         self.video = None;
+        self.archive_url = None;
 
         Ok(())
     }
@@ -200,18 +257,31 @@ impl FullRecord {
             return Ok(())
         }
 
-        if let Some(row) = sqlx::query!(r#"SELECT id FROM records WHERE video = $1"#, video.to_string())
-            .fetch_optional(&mut *connection)
-            .await?
+        // Only the timestamp might have changed, in which case this record is not a duplicate of
+        // itself
+        if let Some(row) = sqlx::query!(
+            "SELECT id FROM records WHERE video_without_timestamp(video::TEXT) = video_without_timestamp($1) AND id <> $2",
+            video.to_string(),
+            self.id
+        )
+        .fetch_optional(&mut *connection)
+        .await?
         {
             return Err(DemonlistError::DuplicateVideo { id: row.id })
         }
 
-        sqlx::query!("UPDATE records SET video = $1::text WHERE id = $2", video, self.id)
-            .execute(connection)
-            .await?;
+        sqlx::query!(
+            "UPDATE records SET video = $1::text, video_status = 'UNCHECKED', video_checked_at = NULL, archive_url = NULL, \
+             archive_attempted_at = NULL WHERE id = $2",
+            video,
+            self.id
+        )
+        .execute(connection)
+        .await?;
 
         self.video = Some(video);
+        self.video_status = VideoStatus::Unchecked;
+        self.archive_url = None;
 
         Ok(())
     }
@@ -223,7 +293,12 @@ impl FullRecord {
             return Err(DemonlistError::InvalidProgress { requirement })
         }
 
-        self.ensure_invariants(self.player.id, self.demon.id, connection).await?;
+        self.move_to_demon(demon, connection).await
+    }
+
+    /// Changes the demon of this record without checking the new demon's record requirement
+    pub(crate) async fn move_to_demon(&mut self, demon: MinimalDemon, connection: &mut PgConnection) -> Result<()> {
+        self.ensure_invariants(self.player.id, demon.id, connection).await?;
 
         sqlx::query!("UPDATE records SET demon = $1 WHERE id = $2", demon.id, self.id)
             .execute(connection)
@@ -257,7 +332,17 @@ impl FullRecord {
     }
 
     /// Updates this record's status
+    ///
+    /// Fails with [`DemonlistError::InvalidStateTransition`] if the current status cannot be
+    /// changed into the given one
     pub async fn set_status(&mut self, status: RecordStatus, connection: &mut PgConnection) -> Result<()> {
+        if !self.status.can_transition_to(status) {
+            return Err(DemonlistError::InvalidStateTransition {
+                from: self.status,
+                to: status,
+            })
+        }
+
         // To uphold the invariants outlined in the module documentation, we need to do some preparations.
         // What preparation has to be done, depends on what the current and new status are.
         match (self.status, status) {
@@ -274,14 +359,19 @@ impl FullRecord {
                 .execute(&mut *connection)
                 .await?;
 
-                sqlx::query!(
-                    "DELETE FROM records WHERE id <> $1 AND player = $2 AND demon = $3",
+                let superseded = sqlx::query!(
+                    "SELECT id FROM records WHERE id <> $1 AND player = $2 AND demon = $3",
                     self.id,
                     self.player.id,
                     self.demon.id
                 )
-                .execute(&mut *connection)
-                .await?;
+                .fetch_all(&mut *connection)
+                .await?
+                .into_iter()
+                .map(|row| row.id)
+                .collect::<Vec<_>>();
+
+                FullRecord::delete_many(&superseded, &format!("Superseded by rejected record {}", self.id), &mut *connection).await?;
             },
 
             // Nothing needed here, approved records are unique while submitted and records under consideration are not
@@ -307,15 +397,20 @@ impl FullRecord {
                 .execute(&mut *connection)
                 .await?;
 
-                sqlx::query!(
-                    "DELETE FROM records WHERE id <> $1 AND records.player = $2 AND records.demon = $3 AND progress <= $4",
+                let superseded = sqlx::query!(
+                    "SELECT id FROM records WHERE id <> $1 AND records.player = $2 AND records.demon = $3 AND progress <= $4",
                     self.id,
                     self.player.id,
                     self.demon.id,
                     self.progress
                 )
-                .execute(&mut *connection)
-                .await?;
+                .fetch_all(&mut *connection)
+                .await?
+                .into_iter()
+                .map(|row| row.id)
+                .collect::<Vec<_>>();
+
+                FullRecord::delete_many(&superseded, &format!("Superseded by record {}", self.id), &mut *connection).await?;
             },
 
             // the other cases just convert back and forth between 'submitted' and 'under consideration', which doesn't change anything
@@ -361,20 +456,22 @@ impl FullRecord {
             .execute(&mut *connection)
             .await?;
 
-            let deleted = sqlx::query!(
-                "DELETE FROM records WHERE player = $1 AND demon = $2 AND status_='SUBMITTED'",
+            let superseded = sqlx::query!(
+                "SELECT id FROM records WHERE player = $1 AND demon = $2 AND status_='SUBMITTED'",
                 self.player.id,
                 self.demon.id
             )
-            .execute(&mut *connection)
-            .await?;
+            .fetch_all(&mut *connection)
+            .await?
+            .into_iter()
+            .map(|row| row.id)
+            .collect::<Vec<_>>();
+
+            let deleted = FullRecord::delete_many(&superseded, &format!("Superseded by record {}", self.id), &mut *connection).await?;
 
             info!(
                 "Changing progress of record {} from {} to {} caused the deletion of {} submissions",
-                self,
-                self.progress,
-                progress,
-                deleted.rows_affected()
+                self, self.progress, progress, deleted
             );
         }
 
@@ -1,13 +1,15 @@
 use crate::{
-    demon::MinimalDemon,
+    demon::{ListSection, MinimalDemon},
     error::{DemonlistError, Result},
+    list_config::ListConfig,
     player::DatabasePlayer,
-    record::{note::Note, FullRecord, RecordStatus},
+    record::{note::Note, FullRecord, RecordStatus, VideoStatus},
+    score, search,
     submitter::Submitter,
 };
 use derive_more::Display;
 use log::{debug, info};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::{PgConnection, Row};
 
 #[derive(Deserialize, Debug, Display)]
@@ -24,16 +26,63 @@ pub struct Submission {
     /// An initial, submitter provided note for the demon.
     #[serde(default)]
     pub note: Option<String>,
+
+    /// Link to the unedited footage of the completion, required for submissions of demons at or
+    /// above [`crate::config::raw_footage_threshold`]
+    #[serde(default)]
+    pub raw_footage: Option<String>,
+
+    /// Whether a new player should be created if no player with the given name exists, even though
+    /// players with similar names exist (see [`DemonlistError::SimilarPlayersExist`])
+    #[serde(default)]
+    pub create_player: bool,
 }
 
+/// The maximal number of similarly named players suggested if the submitted player does not exist
+const MAX_PLAYER_SUGGESTIONS: i64 = 5;
+
 pub struct ValidatedSubmission {
     progress: i16,
     video: Option<String>,
+    raw_footage: Option<String>,
     status: RecordStatus,
     player: DatabasePlayer,
     demon: MinimalDemon,
     submitter: Submitter,
     note: Option<String>,
+
+    /// Whether [`ValidatedSubmission::player`] was created while validating this submission
+    new_player: bool,
+}
+
+/// Report of what submitting a [`Submission`] would result in, generated by validating it without
+/// actually creating a record
+#[derive(Serialize, Debug)]
+pub struct SubmissionReport {
+    /// The video the record would be stored with, after normalization
+    pub video: Option<String>,
+
+    /// The raw footage the record would be stored with, after normalization
+    pub raw_footage: Option<String>,
+
+    /// The player the record would belong to. [`None`] if no player with the given name exists yet,
+    /// in which case a new one would be created.
+    pub player: Option<DatabasePlayer>,
+    pub demon: MinimalDemon,
+
+    /// The section of the list the demon is currently in
+    pub section: ListSection,
+
+    /// The player's approved record on the demon, which would be replaced by this submission once
+    /// it is approved
+    pub supersedes: Option<SupersededRecord>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SupersededRecord {
+    pub id: i32,
+    pub progress: i16,
+    pub video: Option<String>,
 }
 
 impl Submission {
@@ -51,8 +100,27 @@ impl Submission {
             None => None,
         };
 
+        let raw_footage = match self.raw_footage {
+            Some(ref raw_footage) => Some(crate::video::validate_raw_footage(raw_footage)?),
+            None => None,
+        };
+
         // Resolve player and demon name against the database
-        let player = DatabasePlayer::by_name_or_create(self.player.as_ref(), connection).await?;
+        let (player, new_player) = match DatabasePlayer::by_name(self.player.trim(), connection).await {
+            Err(DemonlistError::PlayerNotFoundName { .. }) => {
+                // Most likely, the submitter misspelled the name of an existing player
+                if !self.create_player {
+                    let players = search::similar_players(self.player.trim(), MAX_PLAYER_SUGGESTIONS, connection).await?;
+
+                    if !players.is_empty() {
+                        return Err(DemonlistError::SimilarPlayersExist { players })
+                    }
+                }
+
+                (DatabasePlayer::by_name_or_create(self.player.as_ref(), connection).await?, true)
+            },
+            player => (player?, false),
+        };
         // TODO: handle the ambiguous case
         let demon = MinimalDemon::by_id(self.demon, connection).await?;
 
@@ -61,18 +129,41 @@ impl Submission {
             return Err(DemonlistError::PlayerBanned)
         }
 
+        // The list might have been resized by another instance since we last looked
+        ListConfig::load(&mut *connection).await?;
+
+        let section = demon.section(&mut *connection).await?;
+
         // Cannot submit records for the legacy list (it is possible to directly add them for list mods)
-        if demon.position > crate::config::extended_list_size() && self.status == RecordStatus::Submitted {
+        if section == ListSection::Legacy && self.status == RecordStatus::Submitted {
             return Err(DemonlistError::SubmitLegacy)
         }
 
         // Can only submit 100% records for the extended list (it is possible to directly add them for list
         // mods)
-        if demon.position > crate::config::list_size() && self.progress != 100 && self.status == RecordStatus::Submitted {
+        if section != ListSection::Main && self.progress != 100 && self.status == RecordStatus::Submitted {
             return Err(DemonlistError::Non100Extended)
         }
 
-        let requirement = demon.requirement(&mut *connection).await?;
+        if demon.position <= crate::config::raw_footage_threshold() && raw_footage.is_none() && self.status == RecordStatus::Submitted {
+            return Err(DemonlistError::RawFootageRequired {
+                threshold: crate::config::raw_footage_threshold(),
+            })
+        }
+
+        if self.status == RecordStatus::Submitted && demon.requires_timestamp(&mut *connection).await? {
+            if video.as_deref().and_then(crate::video::timestamp).is_none() {
+                return Err(DemonlistError::TimestampRequired)
+            }
+        }
+
+        let mut requirement = demon.requirement(&mut *connection).await?;
+
+        // Submissions for demons high up on the list might need more progress than the demon's own
+        // requirement
+        if self.status == RecordStatus::Submitted {
+            requirement = demon.effective_requirement(requirement);
+        }
 
         // Check if the record meets the record requirement for this demon
         if self.progress > 100 || self.progress < requirement {
@@ -82,12 +173,15 @@ impl Submission {
         debug!("Submission is valid, checking for duplicates!");
 
         // Search for existing records. If a video exists, we also check if a record with
-        // exactly that video exists.
+        // that video exists, ignoring timestamps.
 
         if let Some(ref video) = video {
-            if let Some(row) = sqlx::query!(r#"SELECT id, status_::text as "status_!: String" FROM records WHERE video = $1"#, video.to_string())
-                .fetch_optional(&mut *connection) // FIXME(sqlx)
-                .await?
+            if let Some(row) = sqlx::query!(
+                r#"SELECT id, status_::text as "status_!: String" FROM records WHERE video_without_timestamp(video::TEXT) = video_without_timestamp($1)"#,
+                video.to_string()
+            )
+            .fetch_optional(&mut *connection) // FIXME(sqlx)
+            .await?
             {
                 return Err(DemonlistError::SubmissionExists {
                     existing: row.id,
@@ -116,26 +210,63 @@ impl Submission {
         Ok(ValidatedSubmission {
             progress: self.progress,
             video,
+            raw_footage,
             status: self.status,
             player,
             demon,
             submitter,
             note: self.note,
+            new_player,
         })
     }
 }
 
 impl ValidatedSubmission {
+    /// Generates a [`SubmissionReport`] for this submission
+    ///
+    /// Since validating a submission might have created a new player, the transaction this
+    /// submission was validated in should be rolled back afterwards.
+    pub async fn report(self, connection: &mut PgConnection) -> Result<SubmissionReport> {
+        // Validation made sure that there is no approved record with higher progress
+        let supersedes = sqlx::query!(
+            "SELECT records.id, progress, CASE WHEN players.link_banned THEN NULL ELSE records.video::text END AS video FROM records \
+             INNER JOIN players ON players.id = records.player WHERE demon = $1 AND player = $2 AND status_ = 'APPROVED'",
+            self.demon.id,
+            self.player.id
+        )
+        .fetch_optional(&mut *connection)
+        .await?
+        .map(|row| {
+            SupersededRecord {
+                id: row.id,
+                progress: row.progress,
+                video: row.video,
+            }
+        });
+
+        let section = self.demon.section(&mut *connection).await?;
+
+        Ok(SubmissionReport {
+            section,
+            video: self.video,
+            raw_footage: self.raw_footage,
+            player: if self.new_player { None } else { Some(self.player) },
+            demon: self.demon,
+            supersedes,
+        })
+    }
+
     pub async fn create(self, connection: &mut PgConnection) -> Result<FullRecord> {
         let id = sqlx::query(
-            "INSERT INTO records (progress, video, status_, player, submitter, demon) VALUES ($1, $2::TEXT, 'SUBMITTED', $3, $4,$5) \
-             RETURNING id",
+            "INSERT INTO records (progress, video, status_, player, submitter, demon, raw_footage) VALUES ($1, $2::TEXT, 'SUBMITTED', $3, \
+             $4,$5, $6) RETURNING id",
         )
         .bind(self.progress)
         .bind(&self.video)
         .bind(self.player.id)
         .bind(self.submitter.id)
         .bind(self.demon.id)
+        .bind(&self.raw_footage)
         .fetch_one(&mut *connection)
         .await?
         .get("id");
@@ -145,6 +276,9 @@ impl ValidatedSubmission {
             progress: self.progress,
             video: self.video,
             status: RecordStatus::Submitted,
+            video_status: VideoStatus::Unchecked,
+            archive_url: None,
+            raw_footage: self.raw_footage,
             player: self.player,
             demon: self.demon,
             submitter: Some(self.submitter),
@@ -155,6 +289,8 @@ impl ValidatedSubmission {
         // duplicate that code!
         if self.status != RecordStatus::Submitted {
             record.set_status(self.status, &mut *connection).await?;
+
+            score::refresh_scores_of(&[record.player.id], &mut *connection).await?;
         }
 
         if let Some(note) = self.note {
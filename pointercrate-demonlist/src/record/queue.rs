@@ -0,0 +1,60 @@
+//! Module for estimating how long submitted records wait for review
+//!
+//! The estimate is the average time between submission and approval of the
+//! [`REVIEW_TIME_SAMPLE_SIZE`] most recently approved records. Records are reviewed roughly in the
+//! order they were submitted, so the number of submissions ahead of a record is its position in the
+//! queue.
+
+use crate::{error::Result, record::FullRecord};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// The number of recent approvals the estimated review time is averaged over
+pub const REVIEW_TIME_SAMPLE_SIZE: i64 = 50;
+
+#[derive(Debug, Serialize)]
+pub struct QueuePosition {
+    /// The number of submitted records that were submitted before this one and have not been
+    /// reviewed yet
+    pub ahead: i64,
+
+    /// The average number of seconds recently approved records spent in the queue, or `None` if no
+    /// record has been approved since the audit log was introduced
+    pub estimated_review_time: Option<i64>,
+}
+
+impl FullRecord {
+    /// Determines this record's position in the queue of submitted records
+    pub async fn queue_position(&self, connection: &mut PgConnection) -> Result<QueuePosition> {
+        let ahead = sqlx::query!(
+            r#"SELECT COUNT(*) AS "ahead!" FROM records WHERE status_ = 'SUBMITTED' AND id < $1"#,
+            self.id
+        )
+        .fetch_one(&mut *connection)
+        .await?
+        .ahead;
+
+        Ok(QueuePosition {
+            ahead,
+            estimated_review_time: average_review_time(connection).await?,
+        })
+    }
+}
+
+/// Computes the average number of seconds between submission and approval of the most recently
+/// approved records
+///
+/// Since the audit log stores the previous values of modified columns, an approval is a
+/// modification whose old status was `SUBMITTED` of a record that is now approved.
+pub async fn average_review_time(connection: &mut PgConnection) -> Result<Option<i64>> {
+    Ok(sqlx::query!(
+        r#"SELECT AVG(EXTRACT(EPOCH FROM review_time))::BIGINT AS average FROM (SELECT record_modifications.time - record_additions.time AS
+         review_time FROM record_modifications INNER JOIN record_additions ON record_additions.id = record_modifications.id INNER JOIN
+         records ON records.id = record_modifications.id WHERE record_modifications.status_ = 'SUBMITTED' AND records.status_ = 'APPROVED'
+         ORDER BY record_modifications.time DESC LIMIT $1) AS recent_approvals"#,
+        REVIEW_TIME_SAMPLE_SIZE
+    )
+    .fetch_one(connection)
+    .await?
+    .average)
+}
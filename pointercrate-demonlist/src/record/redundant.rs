@@ -0,0 +1,90 @@
+//! Module for finding and cleaning up submissions that have been superseded by approved records
+//!
+//! Such submissions should never exist, since approving a record (or increasing the progress of an
+//! approved record) removes all submissions of lower progress for the same (player, demon) tuple.
+//! However, races between concurrent submissions and approvals from before these operations were
+//! transactional left some of them in the database.
+
+use crate::{
+    demon::MinimalDemon,
+    error::Result,
+    player::DatabasePlayer,
+    record::{FullRecord, MinimalRecordPD, RecordStatus},
+};
+use futures::StreamExt;
+use log::info;
+use sqlx::PgConnection;
+
+impl FullRecord {
+    /// Gets all 'submitted' records for which the same player already has an 'approved' record with
+    /// equal or higher progress on the same demon
+    pub async fn redundant_submissions(connection: &mut PgConnection) -> Result<Vec<MinimalRecordPD>> {
+        let mut stream = sqlx::query!(
+            r#"SELECT submissions.id, submissions.progress, submissions.video::TEXT, demons.id AS demon_id, demons.name AS "demon_name: String",
+             demons.position, players.id AS player_id, players.name AS "player_name: String", players.banned FROM records AS submissions
+             INNER JOIN demons ON submissions.demon = demons.id INNER JOIN players ON submissions.player = players.id WHERE
+             submissions.status_ = 'SUBMITTED' AND EXISTS (SELECT 1 FROM records AS approved WHERE approved.status_ = 'APPROVED' AND
+             approved.player = submissions.player AND approved.demon = submissions.demon AND approved.progress >= submissions.progress)
+             ORDER BY submissions.id"#
+        )
+        .fetch(connection);
+
+        let mut records = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            records.push(MinimalRecordPD {
+                id: row.id,
+                progress: row.progress,
+                video: row.video,
+                status: RecordStatus::Submitted,
+                deleted: false,
+                demon: MinimalDemon {
+                    id: row.demon_id,
+                    position: row.position,
+                    name: row.demon_name,
+                },
+                player: DatabasePlayer {
+                    id: row.player_id,
+                    name: row.player_name,
+                    banned: row.banned,
+                },
+            })
+        }
+
+        Ok(records)
+    }
+
+    /// Removes all submissions returned by [`FullRecord::redundant_submissions`], transferring
+    /// their notes to the approved record superseding them.
+    ///
+    /// The submissions are deleted instead of being moved to 'rejected', as a 'rejected' record has
+    /// to be globally unique, which it couldn't be while the approved record exists. This is the
+    /// same thing that happens to them when a record is approved the regular way.
+    ///
+    /// Must be called inside a transaction
+    pub async fn clean_redundant_submissions(connection: &mut PgConnection) -> Result<Vec<MinimalRecordPD>> {
+        let redundant = FullRecord::redundant_submissions(&mut *connection).await?;
+        let ids = redundant.iter().map(|record| record.id).collect::<Vec<_>>();
+
+        let notes_transferred = sqlx::query!(
+            "UPDATE record_notes SET record = approved.id FROM records AS submissions, records AS approved WHERE record_notes.record = \
+             submissions.id AND submissions.id = ANY($1) AND approved.status_ = 'APPROVED' AND approved.player = submissions.player AND \
+             approved.demon = submissions.demon",
+            &ids[..]
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        let records_deleted = FullRecord::delete_many(&ids, "Redundant submission", connection).await?;
+
+        info!(
+            "Cleaning up redundant submissions caused the transfer of {} notes and the deletion of {} records",
+            notes_transferred.rows_affected(),
+            records_deleted
+        );
+
+        Ok(redundant)
+    }
+}
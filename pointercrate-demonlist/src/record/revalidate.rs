@@ -0,0 +1,139 @@
+//! Module for re-running stored record videos through the current video validator
+//!
+//! Whenever [`crate::video::validate`] learns about new hosts or changes the canonical form of some
+//! URL, the videos already stored in the database are not touched. The functions in here are the
+//! migration tool for such changes.
+
+use crate::{
+    error::{DemonlistError, Result},
+    record::FullRecord,
+};
+use log::{info, warn};
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+/// A record whose stored video would be (or was) rewritten into a different canonical form
+#[derive(Debug, Serialize)]
+pub struct ChangedVideo {
+    pub record_id: i32,
+    pub old: String,
+    pub new: String,
+}
+
+/// A record whose stored video no longer passes validation
+#[derive(Debug, Serialize)]
+pub struct FailedVideo {
+    pub record_id: i32,
+    pub video: String,
+    pub error: DemonlistError,
+}
+
+/// A record whose video could not be rewritten because its canonical form is already used by
+/// another record
+#[derive(Debug, Serialize)]
+pub struct CollidingVideo {
+    pub record_id: i32,
+    pub old: String,
+    pub new: String,
+    pub existing: i32,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct VideoRevalidation {
+    pub dry_run: bool,
+    pub checked: usize,
+    pub changed: Vec<ChangedVideo>,
+    pub failed: Vec<FailedVideo>,
+    pub collisions: Vec<CollidingVideo>,
+}
+
+impl FullRecord {
+    /// Runs every stored record video through the current video validator
+    ///
+    /// Videos that fail validation are only reported, never touched. Videos whose canonical form
+    /// differs from the stored one are rewritten, unless `dry_run` is set. If the new canonical
+    /// form is already in use by a different record, the video is left untouched and the
+    /// collision is reported instead, as rewriting it would violate the uniqueness of record
+    /// videos.
+    ///
+    /// Must be called inside a transaction
+    pub async fn revalidate_stored_videos(dry_run: bool, connection: &mut PgConnection) -> Result<VideoRevalidation> {
+        info!("Re-validating all stored record videos (dry run: {})", dry_run);
+
+        let videos: Vec<(i32, String)> =
+            sqlx::query!(r#"SELECT id, video::TEXT as "video!: String" FROM records WHERE video IS NOT NULL ORDER BY id"#)
+                .fetch_all(&mut *connection)
+                .await?
+                .into_iter()
+                .map(|row| (row.id, row.video))
+                .collect();
+
+        // Keeps track of which video belongs to which record, including the changes we already decided
+        // to make during this run, so that collisions between two rewritten videos are also caught
+        let mut owners: HashMap<String, i32> = videos.iter().map(|(id, video)| (video.clone(), *id)).collect();
+        let mut summary = VideoRevalidation {
+            dry_run,
+            checked: videos.len(),
+            ..Default::default()
+        };
+
+        for (record_id, video) in videos {
+            let canonical = match crate::video::validate(&video) {
+                Ok(canonical) => canonical,
+                Err(error) => {
+                    summary.failed.push(FailedVideo { record_id, video, error });
+
+                    continue
+                },
+            };
+
+            if canonical == video {
+                continue
+            }
+
+            if let Some(&existing) = owners.get(&canonical) {
+                if existing != record_id {
+                    warn!(
+                        "Canonical form {} of video of record {} collides with video of record {}",
+                        canonical, record_id, existing
+                    );
+
+                    summary.collisions.push(CollidingVideo {
+                        record_id,
+                        old: video,
+                        new: canonical,
+                        existing,
+                    });
+
+                    continue
+                }
+            }
+
+            if !dry_run {
+                sqlx::query!("UPDATE records SET video = $1::text WHERE id = $2", canonical, record_id)
+                    .execute(&mut *connection)
+                    .await?;
+            }
+
+            owners.remove(&video);
+            owners.insert(canonical.clone(), record_id);
+
+            summary.changed.push(ChangedVideo {
+                record_id,
+                old: video,
+                new: canonical,
+            });
+        }
+
+        info!(
+            "Video re-validation checked {} videos: {} changed, {} failed, {} collisions",
+            summary.checked,
+            summary.changed.len(),
+            summary.failed.len(),
+            summary.collisions.len()
+        );
+
+        Ok(summary)
+    }
+}
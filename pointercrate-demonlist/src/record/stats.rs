@@ -0,0 +1,91 @@
+use crate::{
+    demon::MinimalDemon,
+    error::Result,
+    record::{first_victor, MinimalRecordP},
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+/// Aggregate statistics about the records on a single demon. Records of banned players are not
+/// counted.
+#[derive(Debug, Serialize)]
+pub struct DemonRecordStatistics {
+    /// Number of approved records
+    pub approved: i64,
+
+    /// Number of approved 100% records
+    pub completions: i64,
+
+    /// Number of records that were not approved, by status. Only ever included for members of the
+    /// list team.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub under_consideration: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<i64>,
+
+    /// Average progress of all approved records that are not completions, or `None` if there are no
+    /// such records
+    pub average_progress: Option<f64>,
+
+    /// Number of records submitted per week, in chronological order. Weeks without submissions are
+    /// omitted, as are records predating the audit log. Only counts approved records, unless
+    /// non-approved records are included.
+    pub weekly_submissions: Vec<WeeklySubmissions>,
+    pub first_victor: Option<MinimalRecordP>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeeklySubmissions {
+    /// The monday the week starts on
+    pub week: NaiveDate,
+    pub submissions: i64,
+}
+
+impl DemonRecordStatistics {
+    /// Computes the statistics of the given demon. The counts of non-approved records are only
+    /// included if `include_unapproved` is set.
+    pub async fn of(demon: &MinimalDemon, include_unapproved: bool, connection: &mut PgConnection) -> Result<DemonRecordStatistics> {
+        let counts = sqlx::query!(
+            r#"SELECT COUNT(*) FILTER (WHERE status_ = 'APPROVED') AS "approved!", COUNT(*) FILTER (WHERE status_ = 'APPROVED' AND progress = 100) 
+             AS "completions!", COUNT(*) FILTER (WHERE status_ = 'SUBMITTED') AS "submitted!", COUNT(*) FILTER (WHERE status_ = 
+             'UNDER_CONSIDERATION') AS "under_consideration!", COUNT(*) FILTER (WHERE status_ = 'REJECTED') AS "rejected!", AVG(progress) 
+             FILTER (WHERE status_ = 'APPROVED' AND progress < 100)::DOUBLE PRECISION AS average_progress FROM records INNER JOIN players ON 
+             players.id = records.player WHERE demon = $1 AND NOT players.banned"#,
+            demon.id
+        )
+        .fetch_one(&mut *connection)
+        .await?;
+
+        let weekly_submissions = sqlx::query!(
+            r#"SELECT date_trunc('week', record_additions.time)::DATE AS "week!", COUNT(*) AS "submissions!" FROM records INNER JOIN 
+             record_additions ON record_additions.id = records.id WHERE records.demon = $1 AND (records.status_ = 'APPROVED' OR $2) GROUP 
+             BY 1 ORDER BY 1"#,
+            demon.id,
+            include_unapproved
+        )
+        .fetch_all(&mut *connection)
+        .await?
+        .into_iter()
+        .map(|row| {
+            WeeklySubmissions {
+                week: row.week,
+                submissions: row.submissions,
+            }
+        })
+        .collect();
+
+        Ok(DemonRecordStatistics {
+            approved: counts.approved,
+            completions: counts.completions,
+            submitted: Some(counts.submitted).filter(|_| include_unapproved),
+            under_consideration: Some(counts.under_consideration).filter(|_| include_unapproved),
+            rejected: Some(counts.rejected).filter(|_| include_unapproved),
+            average_progress: counts.average_progress,
+            weekly_submissions,
+            first_victor: first_victor(demon, connection).await?,
+        })
+    }
+}
@@ -0,0 +1,88 @@
+//! Module for keeping track of whether the video proof of approved records is still reachable
+//!
+//! The actual checking happens outside of this crate, these functions only decide which records to
+//! check next and store the results.
+
+use crate::{error::Result, record::FullRecord};
+use log::info;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize, Eq, PartialEq, Clone, Copy, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoStatus {
+    /// The video has not been checked since it was submitted (or last changed)
+    Unchecked,
+
+    Alive,
+
+    /// The video has been deleted or made private
+    Dead,
+}
+
+impl VideoStatus {
+    fn to_sql(self) -> &'static str {
+        match self {
+            VideoStatus::Unchecked => "UNCHECKED",
+            VideoStatus::Alive => "ALIVE",
+            VideoStatus::Dead => "DEAD",
+        }
+    }
+
+    pub(crate) fn from_sql(sql: &str) -> Self {
+        match sql {
+            "UNCHECKED" => VideoStatus::Unchecked,
+            "ALIVE" => VideoStatus::Alive,
+            "DEAD" => VideoStatus::Dead,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// An approved record whose video is due for a check
+#[derive(Debug)]
+pub struct VideoCheck {
+    pub record_id: i32,
+    pub video: String,
+    pub status: VideoStatus,
+}
+
+impl FullRecord {
+    /// Gets up to `limit` approved records with videos, least recently checked first
+    pub async fn videos_to_check(limit: i64, connection: &mut PgConnection) -> Result<Vec<VideoCheck>> {
+        Ok(sqlx::query!(
+            r#"SELECT id, video::TEXT AS "video!", video_status FROM records WHERE status_ = 'APPROVED' AND video IS NOT NULL ORDER BY
+             video_checked_at ASC NULLS FIRST, id LIMIT $1"#,
+            limit
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|row| {
+            VideoCheck {
+                record_id: row.id,
+                video: row.video,
+                status: VideoStatus::from_sql(&row.video_status),
+            }
+        })
+        .collect())
+    }
+
+    /// Stores the result of checking the video of the record with the given id
+    ///
+    /// Does not change the record's `last_modified` timestamp, as the `records_last_modified`
+    /// trigger ignores the columns updated here
+    pub async fn set_video_status(record_id: i32, status: VideoStatus, connection: &mut PgConnection) -> Result<()> {
+        info!("Setting video status of record {} to {:?}", record_id, status);
+
+        sqlx::query!(
+            "UPDATE records SET video_status = $1, video_checked_at = (NOW() AT TIME ZONE 'utc') WHERE id = $2",
+            status.to_sql(),
+            record_id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
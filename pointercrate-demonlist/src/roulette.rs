@@ -0,0 +1,125 @@
+//! Module for the "demon roulette" game, in which players have to beat demons in a random order
+//!
+//! Sequences are generated from a seed, so the same seed always yields the same sequence as long as
+//! the list does not change. This allows players to share their roulette with others.
+
+use crate::{
+    demon::{ListSection, MinimalDemon},
+    error::Result,
+    list::{DemonList, CLASSIC_LIST},
+    player::DatabasePlayer,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+/// Randomly chosen seeds are kept below 2^53, so that JavaScript clients (which represent all
+/// numbers as doubles) can share them without losing precision
+const MAX_RANDOM_SEED: u64 = 1 << 53;
+
+#[derive(Debug, Deserialize)]
+pub struct RouletteOptions {
+    /// The seed to generate the sequence from. If not given, a random seed is chosen.
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// The section of the list to draw demons from. Defaults to both the main and the extended
+    /// list.
+    #[serde(default)]
+    pub section: Option<ListSection>,
+
+    /// Whether to leave out demons the requesting user's claimed player has already beaten
+    #[serde(default)]
+    pub exclude_beaten: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Roulette {
+    /// The seed this roulette was generated from
+    pub seed: u64,
+    pub demons: Vec<MinimalDemon>,
+}
+
+impl Roulette {
+    /// Generates a roulette from the given options, excluding all demons beaten by the given
+    /// player (by either having an approved 100% record or having verified it)
+    pub async fn generate(
+        options: &RouletteOptions, beaten_by: Option<&DatabasePlayer>, connection: &mut PgConnection,
+    ) -> Result<Roulette> {
+        let list = DemonList::by_id(CLASSIC_LIST, &mut *connection).await?;
+
+        let (lowest, highest) = match options.section {
+            Some(ListSection::Main) => (1, list.list_size),
+            Some(ListSection::Extended) => (list.list_size + 1, list.extended_list_size),
+            Some(ListSection::Legacy) => (list.extended_list_size + 1, i16::MAX),
+            None => (1, list.extended_list_size),
+        };
+
+        let mut stream = sqlx::query!(
+            r#"SELECT id, name AS "name: String", position FROM demons WHERE list_id = $1 AND position BETWEEN $2 AND $3 AND ($4::INTEGER IS
+             NULL OR (verifier <> $4 AND NOT EXISTS (SELECT FROM records WHERE records.demon = demons.id AND records.player = $4 AND status_ =
+             'APPROVED' AND progress = 100))) ORDER BY position"#,
+            list.id,
+            lowest,
+            highest,
+            beaten_by.map(|player| player.id)
+        )
+        .fetch(connection);
+
+        let mut demons = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            demons.push(MinimalDemon {
+                id: row.id,
+                name: row.name,
+                position: row.position,
+            })
+        }
+
+        let seed = options
+            .seed
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_nanos() as u64 % MAX_RANDOM_SEED);
+
+        shuffle(&mut demons, seed);
+
+        Ok(Roulette { seed, demons })
+    }
+}
+
+/// Shuffles the given slice using a Fisher-Yates shuffle driven by a splitmix64 generator
+///
+/// We implement this ourselves instead of relying on some library, as the sequence generated for a
+/// given seed must never change.
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+
+    for i in (1..items.len()).rev() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        items.swap(i, (z % (i as u64 + 1)) as usize);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::shuffle;
+
+    // If this test fails, previously shared roulettes no longer produce the same sequence
+    #[test]
+    fn test_shuffle_is_stable() {
+        let mut items: Vec<u32> = (0..10).collect();
+        shuffle(&mut items, 42);
+        assert_eq!(items, vec![0, 9, 5, 8, 6, 4, 7, 2, 1, 3]);
+
+        let mut items: Vec<u32> = (0..10).collect();
+        shuffle(&mut items, 0);
+        assert_eq!(items, vec![6, 3, 2, 9, 8, 1, 4, 7, 0, 5]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Module for computing the demonlist score of players
+//!
+//! A player's score is the sum of the scores of all their approved records, where each verified
+//! demon counts as a 100% record. A nation's score is computed the same way, from the best progress
+//! any of its players achieved on each demon. Only demons on the classic list award points.
+//!
+//! Scores are stored in the `player_scores` and `nation_scores` tables. Changes to single records
+//! only affect the scores of their players (and these players' nations), which are refreshed via
+//! [`refresh_scores_of`]. Changes moving demons affect everyone, and require a full refresh via
+//! [`refresh_player_scores`].
+
+use crate::{config, error::Result, list::CLASSIC_LIST};
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use log::info;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+/// The parameters of the score formula
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreFormula {
+    /// The lowest position for which a demon still awards points
+    pub cutoff: i16,
+
+    /// By how much the score of a record below 100% is divided after applying progress scaling
+    pub progress_divisor: f64,
+}
+
+lazy_static! {
+    static ref CONFIGURED_FORMULA: ScoreFormula = ScoreFormula::from_env();
+}
+
+impl ScoreFormula {
+    /// The score formula configured in the environment
+    ///
+    /// The environment is only read on the first call, which should happen at startup so that
+    /// configuration errors surface right away.
+    pub fn configured() -> &'static ScoreFormula {
+        &CONFIGURED_FORMULA
+    }
+
+    fn from_env() -> Self {
+        ScoreFormula {
+            cutoff: config::score_cutoff(),
+            progress_divisor: config::score_progress_divisor(),
+        }
+    }
+
+    /// Computes the score awarded for achieving `progress` on the demon at `position`, whose list
+    /// requirement is `requirement`
+    pub fn record_score(&self, position: i16, requirement: i16, progress: i16) -> f64 {
+        let beaten_score = if position > self.cutoff {
+            0f64
+        } else if 125 < position && position <= 150 {
+            150f64 * f64::exp((1f64 - f64::from(position)) * (1f64 / 30f64).ln() / (-149f64))
+        } else if 50 < position && position <= 125 {
+            let a = 2.333f64;
+            let b = 1.884f64;
+            60f64 * (a.powf((51 - position) as f64 * ((30f64.ln()) / 99f64))) + b
+        } else if 20 < position && position <= 50 {
+            let c = 1.01327f64;
+            let d = 26.489f64;
+            -100f64 * (c.powf(position as f64 - d)) + 200f64
+        } else if 0 < position && position <= 20 {
+            let e = 1.168f64;
+            let f = 100.39f64;
+            (250f64 - f) * (e.powf(1f64 - position as f64) as f64) + f
+        } else {
+            0f64
+        };
+
+        if progress != 100 {
+            (beaten_score * (5f64.powf((progress - requirement) as f64 / (100f64 - requirement as f64)))) / self.progress_divisor
+        } else {
+            beaten_score
+        }
+    }
+}
+
+/// Recomputes the score of every player and nation
+///
+/// Must be called inside a transaction
+pub async fn refresh_player_scores(connection: &mut PgConnection) -> Result<()> {
+    let player_scores = compute_player_scores(None, &mut *connection).await?;
+    let nation_scores = compute_nation_scores(None, &mut *connection).await?;
+
+    sqlx::query!("DELETE FROM player_scores").execute(&mut *connection).await?;
+    sqlx::query!("DELETE FROM nation_scores").execute(&mut *connection).await?;
+
+    store_scores(player_scores, nation_scores, connection).await
+}
+
+/// Recomputes the scores of the given players, and of the nations they belong to
+///
+/// Must be called inside a transaction
+pub async fn refresh_scores_of(players: &[i32], connection: &mut PgConnection) -> Result<()> {
+    let nations = sqlx::query!(
+        r#"SELECT DISTINCT nationality::TEXT AS "nationality!" FROM players WHERE id = ANY($1::INTEGER[]) AND nationality IS NOT NULL"#,
+        players
+    )
+    .fetch_all(&mut *connection)
+    .await?
+    .into_iter()
+    .map(|row| row.nationality)
+    .collect::<Vec<_>>();
+
+    let player_scores = compute_player_scores(Some(players), &mut *connection).await?;
+
+    // Players without any completions have no entry in `player_scores`, so make sure their old
+    // score goes away, too
+    sqlx::query!("DELETE FROM player_scores WHERE player = ANY($1::INTEGER[])", players)
+        .execute(&mut *connection)
+        .await?;
+
+    refresh_nation_scores(&nations, &mut *connection).await?;
+
+    store_scores(player_scores, HashMap::new(), connection).await
+}
+
+/// The players that have an approved record on, or the verification of, any demon on the given
+/// list between the two given positions (inclusive)
+///
+/// These are exactly the players whose scores change if these demons are moved, or have their
+/// requirement changed.
+pub async fn players_between(list_id: i32, from: i16, to: i16, connection: &mut PgConnection) -> Result<Vec<i32>> {
+    Ok(sqlx::query!(
+        r#"SELECT records.player AS "player!" FROM records INNER JOIN demons ON demons.id = records.demon WHERE records.status_ =
+         'APPROVED' AND demons.list_id = $1 AND demons.position BETWEEN $2 AND $3 UNION SELECT verifier FROM demons WHERE list_id = $1
+         AND position BETWEEN $2 AND $3"#,
+        list_id,
+        from,
+        to
+    )
+    .fetch_all(connection)
+    .await?
+    .into_iter()
+    .map(|row| row.player)
+    .collect())
+}
+
+/// Recomputes the scores of the given nations, identified by their country codes
+///
+/// Needs to be called for both the old and the new nation whenever a player's nationality changes.
+/// Must be called inside a transaction
+pub async fn refresh_nation_scores(nations: &[String], connection: &mut PgConnection) -> Result<()> {
+    let nation_scores = compute_nation_scores(Some(nations), &mut *connection).await?;
+
+    sqlx::query!("DELETE FROM nation_scores WHERE iso_country_code::TEXT = ANY($1::TEXT[])", nations)
+        .execute(&mut *connection)
+        .await?;
+
+    store_scores(HashMap::new(), nation_scores, connection).await
+}
+
+/// Computes the scores of the given players (or of all players, if `None` is given)
+async fn compute_player_scores(players: Option<&[i32]>, connection: &mut PgConnection) -> Result<HashMap<i32, f64>> {
+    let formula = ScoreFormula::configured();
+    let mut scores: HashMap<i32, f64> = HashMap::new();
+
+    // A verification counts as a 100% record. Should a player have both an approved record and the
+    // verification of some demon, only the better of the two counts.
+    let mut stream = sqlx::query!(
+        r#"SELECT completions.player AS "player!", demons.position, demons.requirement, MAX(completions.progress) AS "progress!: i16"
+         FROM (SELECT player, demon, progress FROM records WHERE status_ = 'APPROVED' UNION ALL SELECT verifier, id, 100::SMALLINT FROM demons)
+         AS completions INNER JOIN demons ON completions.demon = demons.id INNER JOIN players ON completions.player = players.id
+         WHERE NOT players.banned AND demons.list_id = $1 AND (completions.player = ANY($2::INTEGER[]) OR $2 IS NULL) GROUP BY
+         completions.player, demons.id, demons.position, demons.requirement"#,
+        CLASSIC_LIST,
+        players
+    )
+    .fetch(connection);
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        *scores.entry(row.player).or_default() += formula.record_score(row.position, row.requirement, row.progress);
+    }
+
+    Ok(scores)
+}
+
+/// Computes the scores of the given nations (or of all nations, if `None` is given)
+async fn compute_nation_scores(nations: Option<&[String]>, connection: &mut PgConnection) -> Result<HashMap<String, f64>> {
+    let formula = ScoreFormula::configured();
+    let mut scores: HashMap<String, f64> = HashMap::new();
+
+    let mut stream = sqlx::query!(
+        r#"SELECT players.nationality::TEXT AS "nationality!", demons.position, demons.requirement, MAX(completions.progress) AS
+         "progress!: i16" FROM (SELECT player, demon, progress FROM records WHERE status_ = 'APPROVED' UNION ALL SELECT verifier, id,
+         100::SMALLINT FROM demons) AS completions INNER JOIN demons ON completions.demon = demons.id INNER JOIN players ON
+         completions.player = players.id WHERE NOT players.banned AND demons.list_id = $1 AND players.nationality IS NOT NULL AND
+         (players.nationality::TEXT = ANY($2::TEXT[]) OR $2 IS NULL) GROUP BY players.nationality, demons.id, demons.position,
+         demons.requirement"#,
+        CLASSIC_LIST,
+        nations
+    )
+    .fetch(connection);
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        *scores.entry(row.nationality).or_default() += formula.record_score(row.position, row.requirement, row.progress);
+    }
+
+    Ok(scores)
+}
+
+/// Inserts the given scores. Old scores of the players and nations in question need to have been
+/// deleted beforehand.
+async fn store_scores(player_scores: HashMap<i32, f64>, nation_scores: HashMap<String, f64>, connection: &mut PgConnection) -> Result<()> {
+    let (players, scores): (Vec<i32>, Vec<f64>) = player_scores.into_iter().unzip();
+
+    sqlx::query!(
+        "INSERT INTO player_scores (player, score) SELECT * FROM UNNEST($1::INTEGER[], $2::DOUBLE PRECISION[])",
+        &players[..],
+        &scores[..]
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    let (nations, national_scores): (Vec<String>, Vec<f64>) = nation_scores.into_iter().unzip();
+
+    sqlx::query!(
+        "INSERT INTO nation_scores (iso_country_code, score) SELECT * FROM UNNEST($1::TEXT[], $2::DOUBLE PRECISION[])",
+        &nations[..],
+        &national_scores[..]
+    )
+    .execute(connection)
+    .await?;
+
+    info!("Refreshed the scores of {} players and {} nations", players.len(), nations.len());
+
+    Ok(())
+}
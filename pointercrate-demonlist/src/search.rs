@@ -0,0 +1,92 @@
+//! Module for looking up demons and players by (parts of) their name
+//!
+//! A name matches if it contains the search term (case-insensitively) or is similar enough to it
+//! according to postgres' trigram similarity. Results are ordered by similarity, best match first.
+
+use crate::{demon::MinimalDemon, error::Result, player::DatabasePlayer};
+use futures::StreamExt;
+use sqlx::PgConnection;
+
+pub async fn search_demons(term: &str, limit: i64, connection: &mut PgConnection) -> Result<Vec<MinimalDemon>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT id, name AS "name: String", position FROM demons WHERE STRPOS(name, $1::CITEXT) > 0 OR name::TEXT % $1 ORDER BY 
+         SIMILARITY(name::TEXT, $1) DESC, position LIMIT $2"#,
+        term,
+        limit
+    )
+    .fetch(connection);
+
+    let mut demons = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        demons.push(MinimalDemon {
+            id: row.id,
+            position: row.position,
+            name: row.name,
+        })
+    }
+
+    Ok(demons)
+}
+
+/// Searches for players by name. Banned players are only included if `include_banned` is set
+pub async fn search_players(term: &str, limit: i64, include_banned: bool, connection: &mut PgConnection) -> Result<Vec<DatabasePlayer>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT id, name AS "name: String", banned FROM players WHERE (STRPOS(name, $1::CITEXT) > 0 OR name::TEXT % $1) AND (NOT banned 
+         OR $3) ORDER BY SIMILARITY(name::TEXT, $1) DESC, id LIMIT $2"#,
+        term,
+        limit,
+        include_banned
+    )
+    .fetch(connection);
+
+    let mut players = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        players.push(DatabasePlayer {
+            id: row.id,
+            name: row.name,
+            banned: row.banned,
+        })
+    }
+
+    Ok(players)
+}
+
+/// The trigram similarity above which [`similar_players`] considers a name a likely typo of
+/// another. Much stricter than pg_trgm's default threshold of `0.3`, which matches almost every
+/// name.
+const TYPO_SIMILARITY: f32 = 0.6;
+
+/// Finds non-banned players whose name is similar to, but not the same as, the given name
+///
+/// Used to warn submitters about likely typos before creating a new player. Only trigram similarity
+/// is considered, as the given name is a full name, not a search term.
+pub async fn similar_players(name: &str, limit: i64, connection: &mut PgConnection) -> Result<Vec<DatabasePlayer>> {
+    let mut stream = sqlx::query!(
+        r#"SELECT id, name AS "name: String", banned FROM players WHERE name::TEXT % $1 AND SIMILARITY(name::TEXT, $1) > $3 AND NOT banned
+         ORDER BY SIMILARITY(name::TEXT, $1) DESC, id LIMIT $2"#,
+        name,
+        limit,
+        TYPO_SIMILARITY
+    )
+    .fetch(connection);
+
+    let mut players = Vec::new();
+
+    while let Some(row) = stream.next().await {
+        let row = row?;
+
+        players.push(DatabasePlayer {
+            id: row.id,
+            name: row.name,
+            banned: row.banned,
+        })
+    }
+
+    Ok(players)
+}
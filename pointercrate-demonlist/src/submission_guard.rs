@@ -0,0 +1,111 @@
+//! Module for detecting spam submissions before they are processed
+//!
+//! The guard looks at the past behavior of a submitter and rejects submissions from submitters
+//! that either submit too many records in a short time frame, reuse the videos of other players'
+//! records or have most of their submissions rejected. Submitters caught by the latter two checks
+//! are flagged and cannot submit records anymore until a list moderator unflags them (see
+//! [`PatchSubmitter`](crate::submitter::PatchSubmitter)). Once unflagged, only the records
+//! submitted afterwards count towards the ratio of rejected submissions.
+//!
+//! Since flagging a submitter happens inside the submission's transaction, callers need to commit
+//! the transaction even if the guard rejects the submission.
+
+use crate::{
+    config,
+    error::{DemonlistError, Result},
+    record::Submission,
+    submitter::Submitter,
+};
+use log::{info, warn};
+use sqlx::PgConnection;
+use std::time::Duration;
+
+/// Checks whether the given submitter is allowed to make the given submission
+pub async fn check(submitter: &mut Submitter, submission: &Submission, connection: &mut PgConnection) -> Result<()> {
+    if submitter.flagged {
+        return Err(DemonlistError::SubmitterFlagged)
+    }
+
+    check_frequency(submitter, connection).await?;
+
+    if let Some(ref video) = submission.video {
+        // Malformed videos are reported by the actual validation later on
+        if let Ok(video) = crate::video::validate(video) {
+            if video_reused(&video, &submission.player, connection).await? {
+                warn!(
+                    "Submitter {} submitted video {} for {}, which belongs to a record of a different player",
+                    submitter, video, submission.player
+                );
+
+                return flag(submitter, connection).await
+            }
+        }
+    }
+
+    if rejection_ratio_exceeded(submitter, connection).await? {
+        warn!("Submitter {} exceeded the maximal ratio of rejected submissions", submitter);
+
+        return flag(submitter, connection).await
+    }
+
+    Ok(())
+}
+
+async fn flag(submitter: &mut Submitter, connection: &mut PgConnection) -> Result<()> {
+    info!("Flagging submitter {}", submitter);
+
+    submitter.set_flagged(true, connection).await?;
+
+    Err(DemonlistError::SubmitterFlagged)
+}
+
+async fn check_frequency(submitter: &Submitter, connection: &mut PgConnection) -> Result<()> {
+    let row = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!", EXTRACT(EPOCH FROM MIN(record_additions.time) + INTERVAL '1 hour' - (NOW() AT TIME ZONE 'utc'))::FLOAT8
+         AS remaining FROM record_additions INNER JOIN records ON records.id = record_additions.id WHERE records.submitter = $1 AND
+         record_additions.time > (NOW() AT TIME ZONE 'utc') - INTERVAL '1 hour'"#,
+        submitter.id
+    )
+    .fetch_one(connection)
+    .await?;
+
+    if row.count >= config::max_submissions_per_hour() {
+        return Err(DemonlistError::SubmissionFlood {
+            retry_after: Duration::from_secs_f64(row.remaining.unwrap_or(0.0).max(0.0)),
+        })
+    }
+
+    Ok(())
+}
+
+/// Checks whether the given video is already used by a record (possibly deleted) of a player other
+/// than the one with the given name
+async fn video_reused(video: &str, player: &str, connection: &mut PgConnection) -> Result<bool> {
+    Ok(sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM (SELECT video, player FROM records UNION ALL SELECT video, player FROM deleted_records) AS videos
+         INNER JOIN players ON players.id = videos.player WHERE video_without_timestamp(videos.video::TEXT) = video_without_timestamp($1) AND players.name <> $2::TEXT::CITEXT) AS "reused!""#,
+        video,
+        player
+    )
+    .fetch_one(connection)
+    .await?
+    .reused)
+}
+
+async fn rejection_ratio_exceeded(submitter: &Submitter, connection: &mut PgConnection) -> Result<bool> {
+    let row = sqlx::query!(
+        r#"SELECT COUNT(*) FILTER (WHERE status_ = 'REJECTED') AS "rejected!", COUNT(*) FILTER (WHERE status_ IN ('APPROVED', 'REJECTED')) AS
+         "decided!" FROM records INNER JOIN submitters ON submitters.submitter_id = records.submitter LEFT OUTER JOIN record_additions ON
+         record_additions.id = records.id WHERE records.submitter = $1 AND (submitters.unflagged_at IS NULL OR record_additions.time >
+         submitters.unflagged_at)"#,
+        submitter.id
+    )
+    .fetch_one(connection)
+    .await?;
+
+    if row.decided < config::rejection_ratio_min_submissions() {
+        return Ok(false)
+    }
+
+    Ok(row.rejected as f64 / row.decided as f64 > config::max_rejection_ratio())
+}
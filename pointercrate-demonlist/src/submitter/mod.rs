@@ -13,10 +13,13 @@ mod patch;
 mod post;
 
 #[derive(Debug, Serialize, Hash, Display, Copy, Clone)]
-#[display(fmt = "{} (Banned: {})", id, banned)]
+#[display(fmt = "{} (Banned: {}, Flagged: {})", id, banned, flagged)]
 pub struct Submitter {
     pub id: i32,
     pub banned: bool,
+
+    /// Whether the [submission guard](crate::submission_guard) considers this submitter suspicious
+    pub flagged: bool,
 }
 
 impl Taggable for Submitter {}
@@ -17,6 +17,9 @@ pub struct SubmitterPagination {
 
     #[serde(default, deserialize_with = "non_nullable")]
     banned: Option<bool>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    flagged: Option<bool>,
 }
 
 impl SubmitterPagination {
@@ -34,17 +37,18 @@ impl SubmitterPagination {
         }
 
         let query = if self.before_id.is_some() && self.after_id.is_none() {
-            "SELECT submitter_id, banned FROM submitters WHERE (submitter_id < $1 OR $1 IS NULL) AND (submitter_id > $2 OR $2 IS NULL) AND \
-             (banned = $3 OR $3 IS NULL) ORDER BY submitter_id DESC LIMIT $4 "
+            "SELECT submitter_id, banned, flagged FROM submitters WHERE (submitter_id < $1 OR $1 IS NULL) AND (submitter_id > $2 OR $2 IS \
+             NULL) AND (banned = $3 OR $3 IS NULL) AND (flagged = $4 OR $4 IS NULL) ORDER BY submitter_id DESC LIMIT $5 "
         } else {
-            "SELECT submitter_id, banned FROM submitters WHERE (submitter_id < $1 OR $1 IS NULL) AND (submitter_id > $2 OR $2 IS NULL) AND \
-             (banned = $3 OR $3 IS NULL) ORDER BY submitter_id ASC LIMIT $4 "
+            "SELECT submitter_id, banned, flagged FROM submitters WHERE (submitter_id < $1 OR $1 IS NULL) AND (submitter_id > $2 OR $2 IS \
+             NULL) AND (banned = $3 OR $3 IS NULL) AND (flagged = $4 OR $4 IS NULL) ORDER BY submitter_id ASC LIMIT $5 "
         };
 
         let mut stream = sqlx::query(query)
             .bind(self.before_id)
             .bind(self.after_id)
             .bind(self.banned)
+            .bind(self.flagged)
             .bind(self.limit.unwrap_or(50) as i32 + 1)
             .fetch(connection);
 
@@ -56,6 +60,7 @@ impl SubmitterPagination {
             submitters.push(Submitter {
                 id: row.get("submitter_id"),
                 banned: row.get("banned"),
+                flagged: row.get("flagged"),
             })
         }
 
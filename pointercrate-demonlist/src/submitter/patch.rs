@@ -1,6 +1,6 @@
-use crate::{error::Result, submitter::Submitter};
+use crate::{error::Result, record::FullRecord, submitter::Submitter};
 use log::info;
-use pointercrate_core::util::non_nullable;
+use pointercrate_core::{audit::PatchLog, util::non_nullable};
 use serde::Deserialize;
 use sqlx::PgConnection;
 
@@ -8,25 +8,45 @@ use sqlx::PgConnection;
 pub struct PatchSubmitter {
     #[serde(default, deserialize_with = "non_nullable")]
     banned: Option<bool>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    flagged: Option<bool>,
+
+    /// Whether banning the submitter should also delete all their pending submissions. Has no
+    /// effect unless `banned` is set to `true`.
+    #[serde(default = "default_delete_submissions")]
+    delete_submissions: bool,
+}
+
+fn default_delete_submissions() -> bool {
+    true
 }
 
 impl Submitter {
-    pub async fn ban(&mut self, connection: &mut PgConnection) -> Result<()> {
+    /// Bans this submitter, optionally deleting all their pending submissions
+    pub async fn ban(&mut self, delete_submissions: bool, connection: &mut PgConnection) -> Result<()> {
         sqlx::query!("UPDATE submitters SET banned = true WHERE submitter_id = $1", self.id)
             .execute(&mut *connection)
             .await?;
 
-        let deleted = sqlx::query!("DELETE FROM records WHERE submitter = $1 AND status_ = 'SUBMITTED'", self.id)
-            .execute(connection)
-            .await?;
+        self.banned = true;
 
-        info!(
-            "Banning submitter {} caused deletion of {} submissions",
-            self,
-            deleted.rows_affected()
-        );
+        if !delete_submissions {
+            info!("Banned submitter {}, keeping their submissions", self);
 
-        self.banned = true;
+            return Ok(())
+        }
+
+        let submissions = sqlx::query!("SELECT id FROM records WHERE submitter = $1 AND status_ = 'SUBMITTED'", self.id)
+            .fetch_all(&mut *connection)
+            .await?
+            .into_iter()
+            .map(|row| row.id)
+            .collect::<Vec<_>>();
+
+        let deleted = FullRecord::delete_many(&submissions, "Submitter was banned", connection).await?;
+
+        info!("Banning submitter {} caused deletion of {} submissions", self, deleted);
 
         Ok(())
     }
@@ -41,15 +61,42 @@ impl Submitter {
         Ok(())
     }
 
+    /// Sets whether this submitter is flagged by the [submission guard](crate::submission_guard)
+    ///
+    /// Unflagging a submitter records the time it happened, so that the guard only judges them by
+    /// their submissions from then on
+    pub async fn set_flagged(&mut self, flagged: bool, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!(
+            "UPDATE submitters SET flagged = $1, unflagged_at = CASE WHEN flagged AND NOT $1 THEN (NOW() AT TIME ZONE 'utc') ELSE \
+             unflagged_at END WHERE submitter_id = $2",
+            flagged,
+            self.id
+        )
+        .execute(connection)
+        .await?;
+
+        self.flagged = flagged;
+
+        Ok(())
+    }
+
     pub async fn apply_patch(mut self, patch: PatchSubmitter, connection: &mut PgConnection) -> Result<Self> {
         info!("Patching submitter {} with {:?}", self, patch);
 
+        let log = PatchLog::start("submitter", self.id, &self);
+
         match patch.banned {
-            Some(true) => self.ban(connection).await?,
+            Some(true) => self.ban(patch.delete_submissions, connection).await?,
             Some(false) => self.unban(connection).await?,
             _ => (),
         }
 
+        if let Some(flagged) = patch.flagged {
+            self.set_flagged(flagged, connection).await?;
+        }
+
+        log.finish(&self, connection).await?;
+
         Ok(self)
     }
 }
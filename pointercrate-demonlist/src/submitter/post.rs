@@ -12,6 +12,10 @@ impl Submitter {
         .await?
         .submitter_id;
 
-        Ok(Submitter { id, banned: false })
+        Ok(Submitter {
+            id,
+            banned: false,
+            flagged: false,
+        })
     }
 }
@@ -10,10 +10,15 @@ const YOUTUBE_FORMAT: &str = "https://www.youtube.com/watch?v={video_id}' or \
 const TWITCH_FORMAT: &str = "https://www.twitch.tv/videos/{video_id}' or \
                              'https://twitch.tv/videos/{video_id}' or\
                              'https://www.twitch.tv/{channel_name}/v/{video_id}' or\
-                             'https://twitch.tv/{channel_name}/v/{video_id}";
+                             'https://twitch.tv/{channel_name}/v/{video_id}' or\
+                             'https://clips.twitch.tv/{clip_id}' or\
+                             'https://www.twitch.tv/{channel_name}/clip/{clip_id}";
 const EVERYPLAY_FORMAT: &str = "https://everyplay.com/videos/{video_id}' or'https://www.everyplay.com/videos/{video_id}";
-const VIMEO_FORMAT: &str = "https://vimeo.com/{video_id}' or'https://www.vimeo.com/{video_id}";
-const BILIBILI_FORMAT: &str = "'https://www.bilibili.com/video/{video_id}' or'https://bilibili.com/video/{video_id}";
+const VIMEO_FORMAT: &str =
+    "https://vimeo.com/{video_id}' or'https://www.vimeo.com/{video_id}' or 'https://player.vimeo.com/video/{video_id}";
+const BILIBILI_FORMAT: &str =
+    "'https://www.bilibili.com/video/{video_id}' or'https://bilibili.com/video/{video_id}' or 'https://m.bilibili.com/video/{video_id}";
+const GOOGLE_DRIVE_FORMAT: &str = "https://drive.google.com/file/d/{file_id}' or 'https://drive.google.com/open?id={file_id}";
 
 pub fn validate(url: &str) -> Result<String> {
     let url = Url::parse(url).map_err(|_| DemonlistError::MalformedVideoUrl)?;
@@ -35,8 +40,9 @@ pub fn validate(url: &str) -> Result<String> {
                         .find_map(|(key, value)| if key == "v" { Some(value) } else { None })
                     {
                         return Ok(format!(
-                            "https://www.youtube.com/watch?v={}",
-                            video_id.chars().take(11).collect::<String>()
+                            "https://www.youtube.com/watch?v={}{}",
+                            video_id.chars().take(11).collect::<String>(),
+                            youtube_timestamp(&url)
                         ))
                     }
                 }
@@ -48,8 +54,9 @@ pub fn validate(url: &str) -> Result<String> {
                     match &path_segments.collect::<Vec<_>>()[..] {
                         [video_id] =>
                             Ok(format!(
-                                "https://www.youtube.com/watch?v={}",
-                                video_id.chars().take(11).collect::<String>()
+                                "https://www.youtube.com/watch?v={}{}",
+                                video_id.chars().take(11).collect::<String>(),
+                                youtube_timestamp(&url)
                             )),
                         _ => Err(CoreError::InvalidUrlFormat { expected: YOUTUBE_FORMAT }.into()),
                     }
@@ -59,13 +66,19 @@ pub fn validate(url: &str) -> Result<String> {
             "www.twitch.tv" | "twitch.tv" =>
                 if let Some(path_segments) = url.path_segments() {
                     match &path_segments.collect::<Vec<_>>()[..] {
-                        ["videos", video_id] => Ok(format!("https://www.twitch.tv/videos/{}", video_id)),
-                        [_, "v", video_id] => Ok(format!("https://www.twitch.tv/videos/{}", video_id)),
+                        ["videos", video_id] | [_, "v", video_id] =>
+                            Ok(format!("https://www.twitch.tv/videos/{}{}", video_id, twitch_timestamp(&url))),
+                        [_, "clip", clip_id] => Ok(format!("https://clips.twitch.tv/{}", clip_id)),
                         _ => Err(CoreError::InvalidUrlFormat { expected: TWITCH_FORMAT }.into()),
                     }
                 } else {
                     Err(CoreError::InvalidUrlFormat { expected: TWITCH_FORMAT }.into())
                 },
+            "clips.twitch.tv" =>
+                match &path_segments(&url)[..] {
+                    [clip_id] => Ok(format!("https://clips.twitch.tv/{}", clip_id)),
+                    _ => Err(CoreError::InvalidUrlFormat { expected: TWITCH_FORMAT }.into()),
+                },
             "everyplay.com" | "www.everyplay.com" =>
                 if let Some(path_segments) = url.path_segments() {
                     match &path_segments.collect::<Vec<_>>()[..] {
@@ -82,7 +95,7 @@ pub fn validate(url: &str) -> Result<String> {
                     }
                     .into())
                 },
-            "www.bilibili.com" | "bilibili.com" =>
+            "www.bilibili.com" | "bilibili.com" | "m.bilibili.com" =>
                 if let Some(path_segments) = url.path_segments() {
                     match &path_segments.collect::<Vec<_>>()[..] {
                         ["video", video_id] => Ok(format!("https://www.bilibili.com/video/{}", video_id)),
@@ -91,10 +104,36 @@ pub fn validate(url: &str) -> Result<String> {
                 } else {
                     Err(CoreError::InvalidUrlFormat { expected: BILIBILI_FORMAT }.into())
                 },
+            "player.vimeo.com" =>
+                match &path_segments(&url)[..] {
+                    ["video", video_id] => Ok(format!("https://vimeo.com/{}{}", video_id, vimeo_timestamp(&url))),
+                    _ => Err(CoreError::InvalidUrlFormat { expected: VIMEO_FORMAT }.into()),
+                },
+            "drive.google.com" =>
+                match &path_segments(&url)[..] {
+                    ["file", "d", file_id, ..] => Ok(format!("https://drive.google.com/file/d/{}/view", file_id)),
+                    ["open"] | ["uc"] =>
+                        match url
+                            .query_pairs()
+                            .find_map(|(key, value)| if key == "id" { Some(value) } else { None })
+                        {
+                            Some(file_id) => Ok(format!("https://drive.google.com/file/d/{}/view", file_id)),
+                            None =>
+                                Err(CoreError::InvalidUrlFormat {
+                                    expected: GOOGLE_DRIVE_FORMAT,
+                                }
+                                .into()),
+                        },
+                    _ =>
+                        Err(CoreError::InvalidUrlFormat {
+                            expected: GOOGLE_DRIVE_FORMAT,
+                        }
+                        .into()),
+                },
             "vimeo.com" | "www.vimeo.com" =>
                 if let Some(path_segments) = url.path_segments() {
                     match &path_segments.collect::<Vec<_>>()[..] {
-                        [video_id] => Ok(format!("https://vimeo.com/{}", video_id)),
+                        [video_id] => Ok(format!("https://vimeo.com/{}{}", video_id, vimeo_timestamp(&url))),
                         _ => Err(CoreError::InvalidUrlFormat { expected: VIMEO_FORMAT }.into()),
                     }
                 } else {
@@ -106,3 +145,117 @@ pub fn validate(url: &str) -> Result<String> {
         Err(CoreError::UnprocessableEntity.into())
     }
 }
+
+/// The point in the video (in seconds) the given URL links to, if any
+///
+/// Understands the timestamp formats of YouTube (`t` or `start` query parameter, or `#t=`), Twitch
+/// (`t` query parameter) and Vimeo (`#t=`), given either as plain seconds or in the `1h2m3s`
+/// format. Since [`validate`] preserves timestamps in this format, this works on both raw and
+/// validated URLs.
+pub fn timestamp(url: &str) -> Option<u32> {
+    let url = Url::parse(url).ok()?;
+
+    url.query_pairs()
+        .find_map(|(key, value)| {
+            if key == "t" || key == "start" {
+                parse_timestamp(&value)
+            } else {
+                None
+            }
+        })
+        .or_else(|| {
+            url.fragment()
+                .and_then(|fragment| fragment.strip_prefix("t="))
+                .and_then(parse_timestamp)
+        })
+        .filter(|&seconds| seconds > 0)
+}
+
+/// Parses timestamps of the form `90`, `90s`, `1m30s` or `1h2m3s` into seconds
+fn parse_timestamp(value: &str) -> Option<u32> {
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds)
+    }
+
+    let mut seconds = 0u32;
+    let mut current: Option<u32> = None;
+
+    for c in value.chars() {
+        match c {
+            '0'..='9' => current = Some(current.unwrap_or(0).checked_mul(10)?.checked_add(c.to_digit(10)?)?),
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+
+                seconds = seconds.checked_add(current.take()?.checked_mul(unit)?)?;
+            },
+            _ => return None,
+        }
+    }
+
+    // Trailing digits without a unit
+    if current.is_some() || value.is_empty() {
+        return None
+    }
+
+    Some(seconds)
+}
+
+fn youtube_timestamp(url: &Url) -> String {
+    match timestamp(url.as_str()) {
+        Some(seconds) => format!("&t={}s", seconds),
+        None => String::new(),
+    }
+}
+
+fn twitch_timestamp(url: &Url) -> String {
+    match timestamp(url.as_str()) {
+        Some(seconds) => format!("?t={}h{}m{}s", seconds / 3600, seconds / 60 % 60, seconds % 60),
+        None => String::new(),
+    }
+}
+
+fn vimeo_timestamp(url: &Url) -> String {
+    match timestamp(url.as_str()) {
+        Some(seconds) => format!("#t={}s", seconds),
+        None => String::new(),
+    }
+}
+
+/// The URL of a preview image for the given (validated) video, if its host provides one
+///
+/// Currently only supports YouTube.
+pub fn thumbnail(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+
+    if url.domain()? != "www.youtube.com" {
+        return None
+    }
+
+    let video_id = url
+        .query_pairs()
+        .find_map(|(key, value)| if key == "v" { Some(value) } else { None })?;
+
+    Some(format!("https://i.ytimg.com/vi/{}/mqdefault.jpg", video_id))
+}
+
+/// Validates a link to raw footage, which has to be hosted on either YouTube or Google Drive
+pub fn validate_raw_footage(url: &str) -> Result<String> {
+    let url = validate(url)?;
+
+    if url.starts_with("https://www.youtube.com/") || url.starts_with("https://drive.google.com/") {
+        Ok(url)
+    } else {
+        Err(DemonlistError::UnsupportedRawFootageHost)
+    }
+}
+
+/// The non-empty path segments of the given URL, so that trailing slashes do not matter
+fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default()
+}
@@ -13,6 +13,7 @@ use dash_rs::{
 use futures::{FutureExt, StreamExt};
 use log::{error, info, trace};
 use reqwest::{header::CONTENT_TYPE, Client};
+use serde::Serialize;
 use sqlx::{Error, Pool, Postgres};
 use std::{
     borrow::Cow,
@@ -20,6 +21,10 @@ use std::{
     hash::{Hash, Hasher},
 };
 
+/// The base URL of the official Geometry Dash servers, which is what dash-rs generates request URLs
+/// for
+const BOOMLINGS: &str = "http://www.boomlings.com/database/";
+
 pub use dash_rs::{
     model::level::{DemonRating, LevelRating},
     Thunk,
@@ -34,6 +39,50 @@ pub enum GDIntegrationResult {
     LevelDataNotCached,
 }
 
+/// Summary of the cached Geometry Dash data of a demon, suitable for embedding into API responses
+#[derive(Debug, Serialize, Hash, Clone)]
+pub struct LevelMetadata {
+    pub level_id: u64,
+    pub length_in_seconds: Option<u32>,
+    pub object_count: Option<usize>,
+    pub song: Option<SongMetadata>,
+}
+
+#[derive(Debug, Serialize, Hash, Clone)]
+pub struct SongMetadata {
+    pub song_id: u64,
+    pub name: String,
+    pub artist: String,
+}
+
+impl GDIntegrationResult {
+    /// Extracts the [`LevelMetadata`] from a successful lookup
+    pub fn metadata(&self) -> Option<LevelMetadata> {
+        match self {
+            GDIntegrationResult::Success(level, level_data, song) => {
+                let objects = match level_data.level_data {
+                    Thunk::Processed(ref objects) => Some(objects),
+                    _ => None,
+                };
+
+                Some(LevelMetadata {
+                    level_id: level.level_id,
+                    length_in_seconds: objects.map(|objects| objects.length_in_seconds() as u32),
+                    object_count: objects.map(|objects| objects.objects.len()),
+                    song: song.as_ref().map(|song| {
+                        SongMetadata {
+                            song_id: song.song_id,
+                            name: song.name.to_string(),
+                            artist: song.artist.to_string(),
+                        }
+                    }),
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
 impl PgCache {
     pub async fn data_for_demon(
         &self, http_client: Client, level_id: Option<u64>, name: String, demon_id: i32,
@@ -129,6 +178,53 @@ impl PgCache {
         }
     }
 
+    /// Periodically goes through all demons and re-downloads their level data if it is missing or
+    /// expired
+    ///
+    /// Demons are processed one after another, to avoid flooding the Geometry Dash servers with
+    /// requests.
+    pub async fn refresh_periodically(self, http_client: Client, interval: std::time::Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let demons = match sqlx::query!(r#"SELECT id, name::TEXT AS "name!", level_id FROM demons"#)
+                .fetch_all(&self.pool)
+                .await
+            {
+                Ok(demons) => demons,
+                Err(err) => {
+                    error!("Error retrieving demons for refreshing level data: {:?}", err);
+
+                    continue
+                },
+            };
+
+            info!("Refreshing level data of {} demons", demons.len());
+
+            for demon in demons {
+                let level_id = match demon.level_id {
+                    None => {
+                        let _ = self.clone().find_demon(http_client.clone(), demon.name, demon.id).await;
+
+                        continue
+                    },
+                    Some(level_id) => level_id as u64,
+                };
+
+                match self.lookup_level(level_id).await {
+                    Ok(CacheEntry::Missing) | Ok(CacheEntry::Expired(..)) => {
+                        let _ = self.clone().find_demon(http_client.clone(), demon.name, demon.id).await;
+                    },
+                    Ok(CacheEntry::Live(..)) =>
+                        if let Ok(CacheEntry::Missing) | Ok(CacheEntry::Expired(..)) = self.lookup_level_data(level_id).await {
+                            let _ = self.clone().download_demon(http_client.clone(), level_id.into(), demon.id).await;
+                        },
+                    _ => (),
+                }
+            }
+        }
+    }
+
     async fn find_demon(self, http_client: Client, demon_name: String, demon: i32) -> Result<(), ()> {
         let request = LevelsRequest::default()
             .request_type(LevelRequestType::MostLiked)
@@ -139,7 +235,7 @@ impl PgCache {
         trace!("Trying to find demon {} via request {:?}", demon_name, request);
 
         let request_result = http_client
-            .post(&request.to_url())
+            .post(&self.url(request.to_url()))
             .body(request.to_string())
             .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
             .send()
@@ -200,7 +296,7 @@ impl PgCache {
         trace!("Downloading demon with id {}", request.level_id);
 
         let request_result = http_client
-            .post(&request.to_url())
+            .post(&self.url(request.to_url()))
             .body(request.to_string())
             .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
             .send()
@@ -218,10 +314,17 @@ impl PgCache {
                                     .await
                                     .map_err(|err| error!("Error storing demon '{}': {:?}", demon.name, err))?;
 
-                                sqlx::query!("UPDATE demons SET level_id = $1 WHERE id = $2", request.level_id as i64, demon_id)
-                                    .execute(&self.pool)
-                                    .await
-                                    .map_err(|err| error!("Error updating level_id: {:?}", err))?;
+                                // Level ids are unique within a list, so if some other demon on the same list was
+                                // already manually assigned this level, our guess was most likely wrong
+                                sqlx::query!(
+                                    "UPDATE demons SET level_id = $1 WHERE id = $2 AND NOT EXISTS (SELECT 1 FROM demons AS other WHERE \
+                                     other.level_id = $1 AND other.list_id = demons.list_id)",
+                                    request.level_id as i64,
+                                    demon_id
+                                )
+                                .execute(&self.pool)
+                                .await
+                                .map_err(|err| error!("Error updating level_id: {:?}", err))?;
 
                                 sqlx::query!("DELETE FROM download_lock WHERE level_id = $1", request.level_id as i64)
                                     .execute(&self.pool)
@@ -288,15 +391,35 @@ pub enum CacheEntry<T> {
 pub struct PgCache {
     pool: Pool<Postgres>,
     expire_after: Duration,
+
+    /// Base URL of a Geometry Dash server mirror to use instead of the official servers
+    mirror: Option<String>,
 }
 
 impl PgCache {
     pub fn new(pool: Pool<Postgres>, expire_after: Duration) -> Self {
-        PgCache { pool, expire_after }
+        PgCache {
+            pool,
+            expire_after,
+            mirror: None,
+        }
+    }
+
+    /// Makes all requests go to the server at the given base URL instead of the official servers
+    pub fn with_mirror(mut self, mirror: Option<String>) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    fn url(&self, url: String) -> String {
+        match self.mirror {
+            Some(ref mirror) => url.replacen(BOOMLINGS, mirror, 1),
+            None => url,
+        }
     }
 
     fn make_cache_entry<T>(&self, meta: CacheEntryMeta, t: T) -> CacheEntry<T> {
-        if DateTime::<Utc>::from_utc(meta.made, Utc) - Utc::now() < self.expire_after {
+        if Utc::now() - DateTime::<Utc>::from_utc(meta.made, Utc) < self.expire_after {
             CacheEntry::Live(t, meta)
         } else {
             CacheEntry::Expired(t, meta)
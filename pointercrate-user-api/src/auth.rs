@@ -1,25 +1,39 @@
+use crate::endpoints::auth::{add_access_token_cookie, add_session_cookies};
 use log::{debug, error, warn};
 use pointercrate_core::{
     error::{CoreError, PointercrateError},
     permission::{Permission, PermissionsManager},
-    pool::{audit_connection, PointercratePool},
+    pool::{audit_connection, impersonated_audit_connection, PointercratePool},
 };
-use pointercrate_user::{error::UserError, AuthenticatedUser};
+use pointercrate_core_api::context::RequestContext;
+use pointercrate_user::{error::UserError, log_impersonated_request, ApiKey, ApiKeyScope, AuthenticatedUser, Session};
 use rocket::{
     http::{Method, Status},
     request::{FromRequest, Outcome},
     Request, State,
 };
-use sqlx::{Postgres, Transaction};
+use sqlx::{PgConnection, Postgres, Transaction};
 use std::collections::HashSet;
 
+/// An authenticated request
+///
+/// Every authenticated request runs inside a single transaction, opened by the guard (see
+/// [`begin_transaction`]) and used for all of the request's database accesses. Handlers persist
+/// their changes by calling [`Auth::commit`] once all of them succeeded. If the handler returns
+/// early (e.g. via `?`), the transaction is dropped and thus rolled back, so requests never leave
+/// partial writes behind.
 pub struct Auth<const IsToken: bool> {
     pub user: AuthenticatedUser,
+
+    /// The transaction this request runs in
     pub connection: Transaction<'static, Postgres>,
     pub permissions: PermissionsManager,
 
     /* The secret, either token or password */
     pub(crate) secret: String,
+
+    /// The id of the administrator impersonating [`Auth::user`], if any
+    pub(crate) impersonated_by: Option<i32>,
 }
 
 impl<const IsToken: bool> Auth<IsToken> {
@@ -27,6 +41,36 @@ impl<const IsToken: bool> Auth<IsToken> {
         self.connection.commit().await.map_err(UserError::from)
     }
 
+    /// Commits this request's transaction, replacing it with a new one
+    ///
+    /// Unlike [`Auth::commit`], this does not consume the authorization, so that a commit failing
+    /// with [`CoreError::ConcurrentModification`] can be retried via
+    /// [`Auth::restart_transaction`]. Serialization failures are often only detected at commit
+    /// time.
+    pub async fn commit_in_place(&mut self, pool: &PointercratePool) -> Result<(), UserError> {
+        let connection = pool.transaction().await?;
+
+        std::mem::replace(&mut self.connection, connection)
+            .commit()
+            .await
+            .map_err(UserError::from)
+    }
+
+    /// Rolls back this request's transaction and begins a new, serializable one
+    ///
+    /// Used to retry requests whose transaction failed with
+    /// [`CoreError::ConcurrentModification`]
+    pub async fn restart_transaction(&mut self, pool: &PointercratePool) -> Result<(), UserError> {
+        let mut connection = pool.serializable_transaction().await?;
+
+        impersonated_audit_connection(&mut connection, self.user.inner().id, self.impersonated_by).await?;
+
+        std::mem::replace(&mut self.connection, connection)
+            .rollback()
+            .await
+            .map_err(UserError::from)
+    }
+
     pub fn require_permission(&self, permission: Permission) -> Result<(), UserError> {
         self.permissions.require_permission(self.user.inner().permissions, permission)?;
 
@@ -37,6 +81,17 @@ impl<const IsToken: bool> Auth<IsToken> {
         self.require_permission(permission).is_ok()
     }
 
+    /// Rejects requests made with an impersonation token
+    ///
+    /// Must be called by every endpoint that could give the impersonating administrator lasting
+    /// access to the impersonated account (sessions, API keys, 2FA, linked accounts, ...)
+    pub fn forbid_impersonation(&self) -> Result<(), UserError> {
+        match self.impersonated_by {
+            Some(_) => Err(UserError::NotAllowedWhileImpersonating),
+            None => Ok(()),
+        }
+    }
+
     pub fn assignable_permissions(&self) -> HashSet<Permission> {
         self.permissions.assignable_by_bits(self.user.inner().permissions)
     }
@@ -45,6 +100,31 @@ impl<const IsToken: bool> Auth<IsToken> {
 pub type BasicAuth = Auth<false>;
 pub type TokenAuth = Auth<true>;
 
+/// Checks whether the given request is covered by the scopes of the API key it was authenticated
+/// with
+fn scopes_allow(key: &ApiKey, request: &Request<'_>) -> bool {
+    if key.has_scope(ApiKeyScope::Write) {
+        return true
+    }
+
+    match request.method() {
+        Method::Get | Method::Head | Method::Options => key.has_scope(ApiKeyScope::Read),
+        Method::Post if request.uri().path().as_str().trim_end_matches('/') == "/api/v1/records" => key.has_scope(ApiKeyScope::Submit),
+        _ => false,
+    }
+}
+
+/// Begins the transaction the given request will run in
+///
+/// `PATCH` requests follow a read-modify-write pattern, so they run in serializable transactions
+/// to detect concurrent modifications (see [`Auth::restart_transaction`]).
+async fn begin_transaction(pool: &PointercratePool, request: &Request<'_>) -> Result<Transaction<'static, Postgres>, CoreError> {
+    match request.method() {
+        Method::Patch => pool.serializable_transaction().await,
+        _ => pool.transaction().await,
+    }
+}
+
 macro_rules! try_outcome {
     ($outcome:expr) => {
         match $outcome {
@@ -60,7 +140,10 @@ impl<'r> FromRequest<'r> for Auth<true> {
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         // No auth header set, forward to the request handler that doesnt require authorization
-        if request.headers().get_one("Authorization").is_none() && request.cookies().get("access_token").is_none() {
+        if request.headers().get_one("Authorization").is_none()
+            && request.cookies().get("access_token").is_none()
+            && request.cookies().get("refresh_token").is_none()
+        {
             return Outcome::Forward(())
         }
 
@@ -79,7 +162,7 @@ impl<'r> FromRequest<'r> for Auth<true> {
         };
 
         let mut connection = match pool {
-            Outcome::Success(pool) => try_outcome!(pool.transaction().await),
+            Outcome::Success(pool) => try_outcome!(begin_transaction(pool, request).await),
             Outcome::Failure(err) => {
                 error!("Could not retrieve database pool from shared state. Did you correctly configure rocket state?");
 
@@ -95,77 +178,181 @@ impl<'r> FromRequest<'r> for Auth<true> {
         };
 
         for authorization in request.headers().get("Authorization") {
-            if let &["Bearer", token] = &authorization.split(' ').collect::<Vec<_>>()[..] {
-                let user =
-                    try_outcome!(AuthenticatedUser::token_auth(token, None, &pointercrate_core::config::secret(), &mut connection).await);
+            if let &["Key", api_key] = &authorization.split(' ').collect::<Vec<_>>()[..] {
+                let (user, key) = try_outcome!(AuthenticatedUser::api_key_auth(api_key, &mut connection).await);
+
+                if !scopes_allow(&key, request) {
+                    warn!(
+                        "Request by {} not covered by scopes {:?} of API key {}",
+                        user.inner(),
+                        key.scopes,
+                        key.id
+                    );
+
+                    return Outcome::Failure((Status::Forbidden, UserError::InsufficientScope))
+                }
 
                 try_outcome!(audit_connection(&mut connection, user.inner().id).await);
+                RequestContext::record_user(request, user.inner().id);
+                RequestContext::record_permissions(request, user.inner().permissions);
 
                 return Outcome::Success(Auth {
                     user,
                     connection,
                     permissions: permission_manager,
-                    secret: token.to_string(),
+                    secret: api_key.to_string(),
+                    impersonated_by: None,
                 })
             }
-        }
-
-        // no matching auth header, lets try the cookie
-        if let Some(access_token) = request.cookies().get("access_token") {
-            let access_token = access_token.value();
-
-            if request.method() == Method::Get {
-                debug!("GET request, the cookie is enough");
 
-                let user = try_outcome!(
-                    AuthenticatedUser::token_auth(access_token, None, &pointercrate_core::config::secret(), &mut connection).await
+            if let &["Bearer", token] = &authorization.split(' ').collect::<Vec<_>>()[..] {
+                let (user, impersonated_by) = try_outcome!(
+                    AuthenticatedUser::token_auth(token, None, &pointercrate_core::config::signing_keys(), &mut connection).await
                 );
 
-                try_outcome!(audit_connection(&mut connection, user.inner().id).await);
+                try_outcome!(impersonated_audit_connection(&mut connection, user.inner().id, impersonated_by).await);
+                try_outcome!(log_impersonation(request, &user, impersonated_by).await);
+                RequestContext::record_user(request, user.inner().id);
 
                 return Outcome::Success(Auth {
                     user,
                     connection,
                     permissions: permission_manager,
-                    secret: access_token.to_string(),
+                    secret: token.to_string(),
+                    impersonated_by,
                 })
             }
+        }
+
+        // no matching auth header, lets try the cookies
+        let csrf_token = if request.method() == Method::Get {
+            debug!("GET request, the cookie is enough");
 
+            None
+        } else {
             debug!("Non-GET request, testing X-CSRF-TOKEN header");
             // if we're doing cookie based authorization, there needs to be a X-CSRF-TOKEN
             // header set, unless we're in GET requests, in which case everything is fine
             // :tm:
 
-            if let Some(csrf_token) = request.headers().get_one("X-CSRF-TOKEN") {
-                let user = try_outcome!(
-                    AuthenticatedUser::token_auth(
-                        access_token,
-                        Some(csrf_token),
-                        &pointercrate_core::config::secret(),
-                        &mut connection
-                    )
-                    .await
-                );
+            match request.headers().get_one("X-CSRF-TOKEN") {
+                Some(csrf_token) => Some(csrf_token),
+                None => {
+                    warn!("Cookie based authentication was used, but no CSRF-token was provided. This might be a CSRF attack!");
 
-                try_outcome!(audit_connection(&mut connection, user.inner().id).await);
+                    return Outcome::Failure((Status::Unauthorized, CoreError::Unauthorized.into()))
+                },
+            }
+        };
 
-                return Outcome::Success(Auth {
-                    user,
-                    connection,
-                    permissions: permission_manager,
-                    secret: access_token.to_string(),
-                })
-            } else {
-                warn!("Cookie based authentication was used, but no CSRF-token was provided. This might be a CSRF attack!");
+        let (user, impersonated_by, access_token) = try_outcome!(cookie_auth(request, csrf_token, &mut connection).await);
+
+        try_outcome!(impersonated_audit_connection(&mut connection, user.inner().id, impersonated_by).await);
+        try_outcome!(log_impersonation(request, &user, impersonated_by).await);
+        RequestContext::record_user(request, user.inner().id);
+
+        Outcome::Success(Auth {
+            user,
+            connection,
+            permissions: permission_manager,
+            secret: access_token,
+            impersonated_by,
+        })
+    }
+}
+
+/// Authenticates via the `access_token` cookie
+///
+/// If that cookie is missing or no longer valid (e.g. because the access token expired), the
+/// session is transparently renewed using the `refresh_token` cookie, replacing both cookies.
+/// Returns the authenticated user, the administrator impersonating them (if any) and the access
+/// token that was used.
+async fn cookie_auth(
+    request: &Request<'_>, csrf_token: Option<&str>, connection: &mut PgConnection,
+) -> Result<(AuthenticatedUser, Option<i32>, String), UserError> {
+    let signing_keys = pointercrate_core::config::signing_keys();
+    let cookies = request.cookies();
+
+    let error = match cookies.get("access_token") {
+        Some(access_token) =>
+            match AuthenticatedUser::token_auth(access_token.value(), csrf_token, &signing_keys, &mut *connection).await {
+                Ok((user, impersonated_by)) => return Ok((user, impersonated_by, access_token.value().to_string())),
+                Err(err) => err,
+            },
+        None => CoreError::Unauthorized.into(),
+    };
+
+    let refresh_token = match cookies.get("refresh_token") {
+        Some(refresh_token) => refresh_token.value().to_string(),
+        None => return Err(error),
+    };
+
+    debug!("Access token cookie missing or invalid, renewing session via refresh token cookie");
+
+    let pool = match request.guard::<&State<PointercratePool>>().await {
+        Outcome::Success(pool) => pool,
+        _ =>
+            return Err(CoreError::InternalServerError {
+                message: "PointercratePool not retrievable from rocket state".to_string(),
             }
-        }
+            .into()),
+    };
 
-        Outcome::Failure((Status::Unauthorized, CoreError::Unauthorized.into()))
+    // The renewal happens outside of the request's transaction, as the old refresh token stops
+    // working even if the request itself fails
+    let mut renewal = pool.transaction().await?;
+    let (user, session, refresh_token) = Session::refresh(&refresh_token, &mut renewal).await?;
+
+    renewal.commit().await?;
+
+    if let Some(csrf_token) = csrf_token {
+        user.validate_csrf_token(csrf_token, &signing_keys)?;
     }
+
+    // Without a new refresh token, a concurrent request already rotated the session and will set the
+    // cookie to the new token
+    let access_token = match refresh_token {
+        Some(refresh_token) => add_session_cookies(cookies, &user, &session, refresh_token),
+        None => add_access_token_cookie(cookies, &user, &session),
+    };
+
+    Ok((user, None, access_token))
+}
+
+/// Records a request made using an impersonation token
+///
+/// Happens outside of the request's transaction, so that the request is recorded even if it fails
+/// or never commits (e.g. because it only reads data).
+async fn log_impersonation(request: &Request<'_>, user: &AuthenticatedUser, impersonated_by: Option<i32>) -> Result<(), UserError> {
+    let administrator_id = match impersonated_by {
+        Some(administrator_id) => administrator_id,
+        None => return Ok(()),
+    };
+
+    let pool = match request.guard::<&State<PointercratePool>>().await {
+        Outcome::Success(pool) => pool,
+        _ =>
+            return Err(CoreError::InternalServerError {
+                message: "PointercratePool not retrievable from rocket state".to_string(),
+            }
+            .into()),
+    };
+
+    // Only the path, as query strings might contain sensitive data
+    let description = format!("{} {}", request.method(), request.uri().path());
+
+    log_impersonated_request(user.inner().id, administrator_id, &description, &mut *pool.connection().await?).await
 }
 
+/// Basic authentication that has not checked the user's two-factor authentication code yet
+///
+/// Only used by the website's login, which receives the code via the login form instead of the
+/// `X-TOTP-Code` header. The handler is responsible for calling
+/// [`AuthenticatedUser::verify_totp`] before doing anything else!
+pub(crate) struct PasswordAuth(pub(crate) BasicAuth);
+
 #[rocket::async_trait]
-impl<'r> FromRequest<'r> for Auth<false> {
+impl<'r> FromRequest<'r> for PasswordAuth {
     type Error = UserError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
@@ -189,7 +376,7 @@ impl<'r> FromRequest<'r> for Auth<false> {
         };
 
         let mut connection = match pool {
-            Outcome::Success(pool) => try_outcome!(pool.transaction().await),
+            Outcome::Success(pool) => try_outcome!(begin_transaction(pool, request).await),
             Outcome::Failure(err) => {
                 error!("Could not retrieve database pool from shared state. Did you correctly configure rocket state?");
 
@@ -219,13 +406,15 @@ impl<'r> FromRequest<'r> for Auth<false> {
                     let user = try_outcome!(AuthenticatedUser::basic_auth(*username, *password, &mut connection).await);
 
                     try_outcome!(audit_connection(&mut connection, user.inner().id).await);
+                    RequestContext::record_user(request, user.inner().id);
 
-                    return Outcome::Success(Auth {
+                    return Outcome::Success(PasswordAuth(Auth {
                         user,
                         connection,
                         permissions: permission_manager,
                         secret: password.to_string(),
-                    })
+                        impersonated_by: None,
+                    }))
                 }
             }
         }
@@ -233,3 +422,40 @@ impl<'r> FromRequest<'r> for Auth<false> {
         Outcome::Failure((Status::Unauthorized, CoreError::Unauthorized.into()))
     }
 }
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Auth<false> {
+    type Error = UserError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let PasswordAuth(mut auth) = match request.guard::<PasswordAuth>().await {
+            Outcome::Success(auth) => auth,
+            Outcome::Failure(failure) => return Outcome::Failure(failure),
+            Outcome::Forward(forward) => return Outcome::Forward(forward),
+        };
+
+        try_outcome!(
+            auth.user
+                .verify_totp(
+                    request.headers().get_one("X-TOTP-Code"),
+                    &pointercrate_core::config::secret(),
+                    &mut auth.connection
+                )
+                .await
+        );
+
+        Outcome::Success(auth)
+    }
+}
+
+/// The value of the request's `User-Agent` header, if it has one
+pub struct UserAgent(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(UserAgent(request.headers().get_one("User-Agent").map(ToString::to_string)))
+    }
+}
@@ -0,0 +1,46 @@
+use pointercrate_core::config::{check, ConfigError};
+
+/// Checks the configuration of pointercrate-user-api, so that malformed values are reported at
+/// startup
+pub fn validate() -> Result<(), ConfigError> {
+    check::<bool>("DISCORD_AUTO_REGISTER")
+}
+
+/// Host of the SMTP server used for sending emails. If unset, no emails are sent
+pub fn smtp_host() -> Option<String> {
+    pointercrate_core::config::var("SMTP_HOST")
+}
+
+pub fn smtp_username() -> Option<String> {
+    pointercrate_core::config::var("SMTP_USERNAME")
+}
+
+pub fn smtp_password() -> Option<String> {
+    pointercrate_core::config::var("SMTP_PASSWORD")
+}
+
+pub fn mail_from() -> String {
+    pointercrate_core::util::from_env_or_default("MAIL_FROM", "pointercrate <noreply@pointercrate.com>".to_string())
+}
+
+/// Client id of the Discord application used for logging in via Discord. Discord logins are only
+/// enabled if this, [`discord_client_secret`] and [`discord_redirect_uri`] are set
+pub fn discord_client_id() -> Option<String> {
+    pointercrate_core::config::var("DISCORD_CLIENT_ID")
+}
+
+pub fn discord_client_secret() -> Option<String> {
+    pointercrate_core::config::var("DISCORD_CLIENT_SECRET")
+}
+
+/// The URL Discord redirects to after authorization. Needs to point to the
+/// `/api/v1/auth/discord/callback` endpoint and be registered with the Discord application
+pub fn discord_redirect_uri() -> Option<String> {
+    pointercrate_core::config::var("DISCORD_REDIRECT_URI")
+}
+
+/// Whether logging in via a Discord account not linked to any account automatically creates a new
+/// account
+pub fn discord_auto_register() -> bool {
+    pointercrate_core::util::from_env_or_default("DISCORD_AUTO_REGISTER", false)
+}
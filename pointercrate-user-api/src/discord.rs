@@ -0,0 +1,109 @@
+//! Client for the parts of Discord's OAuth2 API needed for logging in via Discord
+
+use crate::config;
+use log::error;
+use pointercrate_core::error::CoreError;
+use pointercrate_user::error::{Result, UserError};
+use serde::Deserialize;
+
+const AUTHORIZE_URL: &str = "https://discord.com/api/oauth2/authorize";
+const TOKEN_URL: &str = "https://discord.com/api/oauth2/token";
+const CURRENT_USER_URL: &str = "https://discord.com/api/users/@me";
+
+pub struct DiscordOAuth {
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    client: reqwest::Client,
+}
+
+/// The parts of a Discord user object we care about
+#[derive(Debug, Deserialize)]
+pub struct DiscordUser {
+    pub id: String,
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+impl DiscordOAuth {
+    pub fn from_config() -> Option<Self> {
+        Some(DiscordOAuth {
+            client_id: config::discord_client_id()?,
+            client_secret: config::discord_client_secret()?,
+            redirect_uri: config::discord_redirect_uri()?,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// The URL to send users to for authorizing us to read their Discord account information
+    pub fn authorize_url(&self, state: &str) -> String {
+        format!(
+            "{}?{}",
+            AUTHORIZE_URL,
+            serde_urlencoded::to_string(&[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", "identify"),
+                ("state", state),
+                ("prompt", "none"),
+            ])
+            .unwrap()
+        )
+    }
+
+    /// Exchanges the authorization code Discord redirected the user back with for information
+    /// about their Discord account
+    pub async fn identify(&self, code: &str) -> Result<DiscordUser> {
+        let response = self
+            .client
+            .post(TOKEN_URL)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(internal_error)?;
+
+        // Discord responds with 400 if the code is invalid or expired
+        if response.status() == reqwest::StatusCode::BAD_REQUEST {
+            return Err(CoreError::Unauthorized.into())
+        }
+
+        let token: TokenResponse = response
+            .error_for_status()
+            .map_err(internal_error)?
+            .json()
+            .await
+            .map_err(internal_error)?;
+
+        self.client
+            .get(CURRENT_USER_URL)
+            .bearer_auth(token.access_token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(internal_error)?
+            .json()
+            .await
+            .map_err(internal_error)
+    }
+}
+
+fn internal_error(err: reqwest::Error) -> UserError {
+    // The error might contain the authorization code (or worse), so it must not end up in the response
+    error!("Communication with Discord failed: {}", err.without_url());
+
+    CoreError::InternalServerError {
+        message: "Communication with Discord failed".to_string(),
+    }
+    .into()
+}
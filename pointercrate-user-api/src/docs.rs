@@ -0,0 +1,65 @@
+//! Schemas of the models of the user API, for the OpenAPI document (see
+//! [`pointercrate_core_api::docs`])
+
+use rocket::serde::json::{json, Value};
+
+pub(crate) fn schemas() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "User",
+            json!({
+                "type": "object",
+                "required": ["id", "name", "permissions", "display_name", "youtube_channel"],
+                "properties": {
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "permissions": {"type": "integer", "description": "Bitmask of the user's permissions"},
+                    "display_name": {"type": "string", "nullable": true},
+                    "youtube_channel": {"type": "string", "format": "uri", "nullable": true}
+                }
+            }),
+        ),
+        (
+            "Registration",
+            json!({
+                "type": "object",
+                "required": ["name", "password"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "password": {"type": "string", "format": "password"},
+                    "email": {"type": "string", "format": "email", "nullable": true}
+                }
+            }),
+        ),
+        (
+            "PatchUser",
+            json!({
+                "type": "object",
+                "properties": {
+                    "display_name": {"type": "string", "nullable": true},
+                    "youtube_channel": {"type": "string", "format": "uri", "nullable": true},
+                    "permissions": {"type": "integer"}
+                }
+            }),
+        ),
+        (
+            "PatchMe",
+            json!({
+                "type": "object",
+                "properties": {
+                    "password": {"type": "string", "format": "password"},
+                    "display_name": {"type": "string", "nullable": true},
+                    "youtube_channel": {"type": "string", "format": "uri", "nullable": true},
+                    "email": {"type": "string", "format": "email"},
+                    "notifications": {
+                        "type": "object",
+                        "properties": {
+                            "record_decisions": {"type": "boolean"},
+                            "queue_size": {"type": "boolean"}
+                        }
+                    }
+                }
+            }),
+        ),
+    ]
+}
@@ -0,0 +1,62 @@
+use crate::auth::TokenAuth;
+use pointercrate_core::{
+    audit::{extremal_audit_log_ids, AuditLogPagination, GenericAuditLogEntry},
+    error::Result as CoreResult,
+    pool::PointercratePool,
+};
+use pointercrate_core_api::{
+    error::Result,
+    pagination_response,
+    query::Query,
+    response::Response2,
+    stream::{batched, JsonStream, BATCH_SIZE},
+};
+use pointercrate_user::ADMINISTRATOR;
+use rocket::{
+    futures::stream::{BoxStream, StreamExt},
+    serde::json::Json,
+    State,
+};
+
+#[rocket::get("/")]
+pub async fn paginate(mut auth: TokenAuth, data: Query<AuditLogPagination>) -> Result<Response2<Json<Vec<GenericAuditLogEntry>>>> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    let mut pagination = data.0;
+
+    let mut entries = pagination.page(&mut auth.connection).await?;
+
+    let (max_id, min_id) = extremal_audit_log_ids(&mut auth.connection).await?;
+
+    pagination_response!("/api/v1/auditlog/", entries, pagination, min_id, max_id, before_id, after_id, id)
+}
+
+/// All audit log entries matching the given filters, in ascending order. Pagination parameters
+/// other than `after` are ignored.
+#[rocket::get("/export")]
+pub async fn export(
+    auth: TokenAuth, data: Query<AuditLogPagination>, pool: &State<PointercratePool>,
+) -> Result<JsonStream<BoxStream<'static, CoreResult<GenericAuditLogEntry>>>> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    let filters = data.0;
+    let pool = pool.inner().clone();
+
+    Ok(JsonStream(
+        batched(
+            move |after| {
+                let mut pagination = filters.clone();
+                let pool = pool.clone();
+
+                // Having an 'after' value makes sure we always get entries in ascending order
+                pagination.after_id = after.or(pagination.after_id).or(Some(0));
+                pagination.before_id = None;
+                pagination.limit = Some(BATCH_SIZE);
+
+                async move { pagination.page(&mut *pool.read_connection().await?).await }
+            },
+            |entry| entry.id,
+        )
+        .boxed(),
+    ))
+}
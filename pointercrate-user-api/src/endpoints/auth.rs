@@ -1,24 +1,37 @@
 use crate::{
-    auth::{BasicAuth, TokenAuth},
+    auth::{BasicAuth, TokenAuth, UserAgent},
+    config as api_config,
+    discord::DiscordOAuth,
+    mail::{self, Mailer},
     ratelimits::UserRatelimits,
 };
-use pointercrate_core::{etag::Taggable, pool::PointercratePool};
+use log::info;
+use pointercrate_core::{error::CoreError, etag::Taggable, pool::PointercratePool};
 use pointercrate_core_api::{
     error::Result,
     etag::{Precondition, Tagged},
+    pagination_response,
+    query::Query,
     response::Response2,
 };
-use pointercrate_user::{error::UserError, AuthenticatedUser, PatchMe, Registration, User};
+use pointercrate_user::{
+    check_registration_limit, config, discord_oauth_state, discord_totp_challenge, error::UserError, verify_discord_oauth_state,
+    verify_discord_totp_challenge, AccessKind, ApiKey, AuthenticatedUser, NewApiKey, Notification, NotificationPagination,
+    NotificationPreferences, PatchMe, PatchNotification, Registration, Session, User,
+};
 use rocket::{
-    http::Status,
+    form::Form,
+    http::{Cookie, CookieJar, SameSite, Status},
+    response::Redirect,
     serde::json::{serde_json, Json},
-    State,
+    FromForm, State,
 };
+use serde::Deserialize;
 use std::net::IpAddr;
 
 #[rocket::post("/register", data = "<body>")]
 pub async fn register(
-    ip: IpAddr, body: Json<Registration>, ratelimits: &State<UserRatelimits>, pool: &State<PointercratePool>,
+    ip: IpAddr, body: Json<Registration>, ratelimits: &State<UserRatelimits>, pool: &State<PointercratePool>, mailer: &State<Mailer>,
 ) -> Result<Response2<Tagged<User>>> {
     let mut connection = pool.transaction().await.map_err(UserError::from)?;
 
@@ -27,56 +40,534 @@ pub async fn register(
     AuthenticatedUser::validate_password(&body.password)?;
     User::validate_name(&body.name)?;
 
-    ratelimits.registrations(ip)?;
+    if let Some(ref email) = body.email {
+        AuthenticatedUser::validate_email(email)?;
+    }
+
+    check_registration_limit(ip, &mut connection).await?;
 
+    let email = body.email.clone();
     let user = AuthenticatedUser::register(body.0, &mut connection).await?;
 
+    user.log_access(AccessKind::Registration, ip, &mut connection).await?;
+
     connection.commit().await.map_err(UserError::from)?;
 
+    if let Some(email) = email {
+        send_verification_mail(&user, &email, mailer);
+    }
+
     Ok(Response2::tagged(user.into_inner())
         .with_header("Location", "api/v1/auth/me")
         .status(Status::Created))
 }
 
+/// Logs in using basic authentication, starting a new session
+///
+/// The returned `token` is a short-lived access token, which can be renewed using the
+/// `refresh_token` via [`refresh`].
 #[rocket::post("/")]
 pub async fn login(
-    auth: std::result::Result<BasicAuth, UserError>, ip: IpAddr, ratelimits: &State<UserRatelimits>,
+    auth: std::result::Result<BasicAuth, UserError>, ip: IpAddr, user_agent: UserAgent, ratelimits: &State<UserRatelimits>,
 ) -> Result<Response2<Json<serde_json::Value>>> {
     ratelimits.login_attempts(ip)?;
-    let auth = auth?;
+    let mut auth = auth?;
+
+    auth.user.log_access(AccessKind::Login, ip, &mut auth.connection).await?;
+
+    let (session, refresh_token) = auth.user.start_session(ip, user_agent.0.as_deref(), &mut auth.connection).await?;
+
+    let response = session_response(&auth.user, &session, Some(refresh_token));
+
+    auth.connection.commit().await.map_err(UserError::from)?;
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct Refresh {
+    refresh_token: String,
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token
+///
+/// If the session was already refreshed using the same token moments ago (e.g. by a concurrent
+/// request), only a new access token is returned, and `refresh_token` is `null`.
+#[rocket::post("/refresh", data = "<body>")]
+pub async fn refresh(body: Json<Refresh>, pool: &State<PointercratePool>) -> Result<Response2<Json<serde_json::Value>>> {
+    let mut connection = pool.transaction().await.map_err(UserError::from)?;
+
+    let (user, session, refresh_token) = Session::refresh(&body.refresh_token, &mut connection).await?;
+
+    connection.commit().await.map_err(UserError::from)?;
+
+    Ok(session_response(&user, &session, refresh_token))
+}
+
+fn session_response(user: &AuthenticatedUser, session: &Session, refresh_token: Option<String>) -> Response2<Json<serde_json::Value>> {
+    Response2::json(serde_json::json! {
+        {
+            "data": user.inner(),
+            "token": user.generate_access_token(session, &pointercrate_core::config::signing_keys()),
+            "refresh_token": refresh_token,
+            "expires_in": config::access_token_lifetime(),
+            "session": session
+        }
+    })
+    .with_header("etag", user.inner().etag_string())
+}
+
+/// Name of the cookie binding a Discord OAuth2 flow to the browser that started it
+const DISCORD_STATE_COOKIE: &str = "discord_oauth_state";
+
+/// Starts logging in via Discord by redirecting to Discord's authorization page
+///
+/// If the request is authenticated, the Discord account is linked to the authenticated account
+/// instead. Impersonation tokens cannot link accounts, as that would permanently give the
+/// impersonating administrator access to the impersonated account.
+#[rocket::get("/discord")]
+pub fn discord_login(auth: Option<TokenAuth>, discord: &State<DiscordOAuth>, cookies: &CookieJar<'_>) -> Result<Redirect> {
+    if let Some(ref auth) = auth {
+        auth.forbid_impersonation()?;
+    }
+
+    let state = discord_oauth_state(auth.as_ref().map(|auth| &auth.user), &pointercrate_core::config::signing_keys());
+
+    // Needs to be 'Lax', as the callback is a cross-site navigation from discord
+    let mut cookie = Cookie::build(DISCORD_STATE_COOKIE, state.clone())
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .path("/api/v1/auth/discord");
+
+    if !cfg!(debug_assertions) {
+        cookie = cookie.secure(true)
+    }
+
+    cookies.add(cookie.finish());
+
+    Ok(Redirect::to(discord.authorize_url(&state)))
+}
+
+/// Name of the cookie holding the challenge a user with two-factor authentication enabled needs to
+/// redeem after authorizing via Discord
+pub(crate) const DISCORD_TOTP_COOKIE: &str = "discord_totp_challenge";
+
+/// The endpoint Discord redirects to after the user authorized us
+///
+/// Logs into the account linked to the Discord account (creating it if enabled via
+/// [`api_config::discord_auto_register`]), or links the Discord account if the flow was started
+/// by a logged in user. Users with two-factor authentication enabled are sent back to the login
+/// page to provide their code, which is then redeemed via [`discord_totp`].
+#[rocket::get("/discord/callback?<code>&<state>")]
+pub async fn discord_callback(
+    code: &str, state: &str, ip: IpAddr, user_agent: UserAgent, discord: &State<DiscordOAuth>, ratelimits: &State<UserRatelimits>,
+    cookies: &CookieJar<'_>, pool: &State<PointercratePool>,
+) -> Result<Redirect> {
+    match cookies.get(DISCORD_STATE_COOKIE) {
+        Some(cookie) if cookie.value() == state => (),
+        _ => return Err(CoreError::Unauthorized.into()),
+    }
+
+    cookies.remove(Cookie::build(DISCORD_STATE_COOKIE, "").path("/api/v1/auth/discord").finish());
+
+    let signing_keys = pointercrate_core::config::signing_keys();
+    let link = verify_discord_oauth_state(state, &signing_keys)?;
+    let discord_user = discord.identify(code).await?;
+
+    let discord_id: i64 = discord_user.id.parse().map_err(|_| {
+        CoreError::InternalServerError {
+            message: format!("Discord returned malformed user id '{}'", discord_user.id),
+        }
+    })?;
+
+    let mut connection = pool.transaction().await.map_err(UserError::from)?;
+
+    let (user, access) = match link {
+        Some(user_id) => {
+            // The user is already logged in, so there is no need to start a new session
+            AuthenticatedUser::by_id(user_id, &mut connection)
+                .await?
+                .link_discord(discord_id, &mut connection)
+                .await?;
+
+            connection.commit().await.map_err(UserError::from)?;
+
+            return Ok(Redirect::to(rocket::uri!(crate::pages::account_page)))
+        },
+        None =>
+            match AuthenticatedUser::by_discord_id(discord_id, &mut connection).await? {
+                Some(user) => (user, AccessKind::Login),
+                None if api_config::discord_auto_register() => {
+                    ratelimits.soft_registrations(ip)?;
+
+                    check_registration_limit(ip, &mut connection).await?;
+
+                    (
+                        AuthenticatedUser::register_discord(&discord_user.username, discord_id, &mut connection).await?,
+                        AccessKind::Registration,
+                    )
+                },
+                None => return Err(UserError::DiscordAccountNotLinked.into()),
+            },
+    };
+
+    if user.totp_enabled(&mut connection).await? {
+        info!(
+            "User {} authorized via Discord, but still needs to provide their TOTP code",
+            user.inner()
+        );
+
+        let mut cookie = Cookie::build(DISCORD_TOTP_COOKIE, discord_totp_challenge(&user, &signing_keys))
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .path("/");
+
+        if !cfg!(debug_assertions) {
+            cookie = cookie.secure(true)
+        }
+
+        cookies.add(cookie.finish());
+
+        return Ok(Redirect::to(rocket::uri!(crate::pages::login_page)))
+    }
+
+    user.log_access(access, ip, &mut connection).await?;
+
+    let (session, refresh_token) = user.start_session(ip, user_agent.0.as_deref(), &mut connection).await?;
+
+    connection.commit().await.map_err(UserError::from)?;
+
+    add_session_cookies(cookies, &user, &session, refresh_token);
+
+    Ok(Redirect::to(rocket::uri!(crate::pages::account_page)))
+}
+
+#[derive(FromForm)]
+pub struct DiscordTotp {
+    totp: String,
+}
+
+/// Finishes logging in via Discord for users with two-factor authentication enabled
+#[rocket::post("/discord/totp", data = "<form>")]
+pub async fn discord_totp(
+    form: Form<DiscordTotp>, ip: IpAddr, user_agent: UserAgent, ratelimits: &State<UserRatelimits>, cookies: &CookieJar<'_>,
+    pool: &State<PointercratePool>,
+) -> Result<Redirect> {
+    ratelimits.login_attempts(ip)?;
+
+    let challenge = cookies.get(DISCORD_TOTP_COOKIE).ok_or(CoreError::Unauthorized)?;
+    let user_id = verify_discord_totp_challenge(challenge.value(), &pointercrate_core::config::signing_keys())?;
+
+    let mut connection = pool.transaction().await.map_err(UserError::from)?;
+
+    let user = AuthenticatedUser::by_id(user_id, &mut connection).await?;
+
+    user.verify_totp(Some(&form.totp), &pointercrate_core::config::secret(), &mut connection)
+        .await?;
+
+    cookies.remove(Cookie::build(DISCORD_TOTP_COOKIE, "").path("/").finish());
+
+    user.log_access(AccessKind::Login, ip, &mut connection).await?;
+
+    let (session, refresh_token) = user.start_session(ip, user_agent.0.as_deref(), &mut connection).await?;
+
+    connection.commit().await.map_err(UserError::from)?;
+
+    add_session_cookies(cookies, &user, &session, refresh_token);
+
+    Ok(Redirect::to(rocket::uri!(crate::pages::account_page)))
+}
+
+/// Sets the cookies the website uses for authentication: a short-lived access token bound to the
+/// given session, and the session's refresh token. Returns the access token.
+pub(crate) fn add_session_cookies(cookies: &CookieJar<'_>, user: &AuthenticatedUser, session: &Session, refresh_token: String) -> String {
+    let access_token = add_access_token_cookie(cookies, user, session);
+
+    add_session_cookie(cookies, "refresh_token", refresh_token, config::refresh_token_lifetime());
+
+    access_token
+}
+
+/// Like [`add_session_cookies`], but leaves the refresh token cookie untouched
+pub(crate) fn add_access_token_cookie(cookies: &CookieJar<'_>, user: &AuthenticatedUser, session: &Session) -> String {
+    let access_token = user.generate_access_token(session, &pointercrate_core::config::signing_keys());
+
+    add_session_cookie(
+        cookies,
+        "access_token",
+        access_token.clone(),
+        config::access_token_lifetime() as i64,
+    );
+
+    access_token
+}
+
+fn add_session_cookie(cookies: &CookieJar<'_>, name: &'static str, value: String, lifetime: i64) {
+    let mut cookie = Cookie::build(name, value)
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .path("/")
+        .max_age(rocket::time::Duration::seconds(lifetime));
+
+    if !cfg!(debug_assertions) {
+        cookie = cookie.secure(true)
+    }
+
+    cookies.add(cookie.finish());
+}
+
+/// The authenticated user's active sessions, most recently used first
+#[rocket::get("/me/sessions/")]
+pub async fn sessions(mut auth: TokenAuth) -> Result<Json<Vec<Session>>> {
+    auth.forbid_impersonation()?;
+
+    Ok(Json(Session::of_user(auth.user.inner().id, &mut auth.connection).await?))
+}
+
+/// Revokes a single session of the authenticated user. To revoke all sessions at once (e.g. after
+/// a leaked password), use [`invalidate`].
+#[rocket::delete("/me/sessions/<session_id>")]
+pub async fn revoke_session(session_id: i32, mut auth: TokenAuth) -> Result<Status> {
+    auth.forbid_impersonation()?;
+
+    Session::revoke(auth.user.inner().id, session_id, &mut auth.connection).await?;
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
+
+/// Creates a new API key. Requires the account password, so that API keys cannot be used to mint
+/// further keys. The key itself is only ever returned in this response.
+#[rocket::post("/keys", data = "<body>")]
+pub async fn create_api_key(mut auth: BasicAuth, body: Json<NewApiKey>) -> Result<Response2<Json<serde_json::Value>>> {
+    auth.forbid_impersonation()?;
+
+    let (key, api_key) = auth.user.create_api_key(body.0, &mut auth.connection).await?;
+
+    auth.connection.commit().await.map_err(UserError::from)?;
 
     Ok(Response2::json(serde_json::json! {
         {
-            "data": auth.user.inner(),
-            "token": auth.user.generate_token(&pointercrate_core::config::secret())
+            "key": api_key,
+            "data": key
         }
     })
-    .with_header("etag", auth.user.inner().etag_string()))
+    .status(Status::Created))
+}
+
+#[rocket::get("/keys")]
+pub async fn api_keys(mut auth: TokenAuth) -> Result<Json<Vec<ApiKey>>> {
+    auth.forbid_impersonation()?;
+
+    Ok(Json(ApiKey::of_user(auth.user.inner().id, &mut auth.connection).await?))
+}
+
+#[rocket::delete("/keys/<key_id>")]
+pub async fn revoke_api_key(key_id: i32, mut auth: TokenAuth) -> Result<Status> {
+    auth.forbid_impersonation()?;
+
+    ApiKey::revoke(auth.user.inner().id, key_id, &mut auth.connection).await?;
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
 }
 
 #[rocket::post("/invalidate")]
 pub async fn invalidate(mut auth: BasicAuth) -> Result<Status> {
+    auth.forbid_impersonation()?;
+
     auth.user.invalidate_all_tokens(&auth.secret, &mut auth.connection).await?;
     auth.connection.commit().await.map_err(UserError::from)?;
 
     Ok(Status::NoContent)
 }
 
+/// Generates a new TOTP secret for the authenticated user. Two-factor authentication only takes
+/// effect once the secret has been confirmed via [`confirm_2fa`]
+#[rocket::post("/2fa/enroll")]
+pub async fn enroll_2fa(mut auth: BasicAuth) -> Result<Json<serde_json::Value>> {
+    auth.forbid_impersonation()?;
+
+    let enrollment = auth
+        .user
+        .enroll_totp(&pointercrate_core::config::secret(), &mut auth.connection)
+        .await?;
+
+    auth.commit().await?;
+
+    Ok(Json(serde_json::json! {
+        {
+            "secret": enrollment.secret,
+            "provisioning_uri": enrollment.provisioning_uri
+        }
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct TotpCode {
+    code: String,
+}
+
+#[rocket::post("/2fa/confirm", data = "<body>")]
+pub async fn confirm_2fa(mut auth: BasicAuth, body: Json<TotpCode>) -> Result<Status> {
+    auth.forbid_impersonation()?;
+
+    auth.user
+        .confirm_totp(&body.code, &pointercrate_core::config::secret(), &mut auth.connection)
+        .await?;
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
+
+#[rocket::delete("/2fa")]
+pub async fn disable_2fa(mut auth: BasicAuth) -> Result<Status> {
+    auth.forbid_impersonation()?;
+
+    auth.user.disable_totp(&mut auth.connection).await?;
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
+
+fn send_verification_mail(user: &AuthenticatedUser, email: &str, mailer: &Mailer) {
+    let token = user.generate_verification_token(email, &pointercrate_core::config::signing_keys());
+
+    mailer.send(mail::verification_mail(email, &user.inner().name, &token))
+}
+
+#[derive(Deserialize)]
+pub struct EmailVerification {
+    token: String,
+}
+
+#[rocket::post("/verify_email", data = "<body>")]
+pub async fn verify_email(body: Json<EmailVerification>, pool: &State<PointercratePool>) -> Result<Status> {
+    let mut connection = pool.transaction().await.map_err(UserError::from)?;
+
+    AuthenticatedUser::verify_email(&body.token, &pointercrate_core::config::signing_keys(), &mut connection).await?;
+
+    connection.commit().await.map_err(UserError::from)?;
+
+    Ok(Status::NoContent)
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    email: String,
+}
+
+/// Emails a password reset token to the given address, if it belongs to some account
+///
+/// Always responds with `202 ACCEPTED`, so that this endpoint cannot be used to find out which
+/// addresses are in use
+#[rocket::post("/reset_password", data = "<body>")]
+pub async fn reset_password(
+    ip: IpAddr, body: Json<PasswordResetRequest>, ratelimits: &State<UserRatelimits>, pool: &State<PointercratePool>,
+    mailer: &State<Mailer>,
+) -> Result<Status> {
+    ratelimits.password_resets(ip)?;
+
+    let mut connection = pool.connection().await.map_err(UserError::from)?;
+
+    if let Some(user) = AuthenticatedUser::by_verified_email(&body.email, &mut connection).await? {
+        let token = user.generate_reset_token(&pointercrate_core::config::signing_keys());
+
+        mailer.send(mail::reset_mail(&body.email, &user.inner().name, &token));
+    }
+
+    Ok(Status::Accepted)
+}
+
+#[derive(Deserialize)]
+pub struct PasswordReset {
+    token: String,
+    password: String,
+}
+
+#[rocket::post("/reset_password/confirm", data = "<body>")]
+pub async fn confirm_password_reset(body: Json<PasswordReset>, pool: &State<PointercratePool>) -> Result<Status> {
+    let body = body.0;
+    let mut connection = pool.transaction().await.map_err(UserError::from)?;
+
+    AuthenticatedUser::reset_password(
+        &body.token,
+        body.password,
+        &pointercrate_core::config::signing_keys(),
+        &mut connection,
+    )
+    .await?;
+
+    connection.commit().await.map_err(UserError::from)?;
+
+    Ok(Status::NoContent)
+}
+
 #[rocket::get("/me")]
 pub fn get_me(auth: TokenAuth) -> Tagged<User> {
     Tagged(auth.user.into_inner())
 }
 
+/// The authenticated user's notification preferences. They are changed via [`patch_me`].
+#[rocket::get("/me/notification_preferences")]
+pub async fn notification_preferences(mut auth: TokenAuth) -> Result<Json<NotificationPreferences>> {
+    Ok(Json(
+        NotificationPreferences::of_user(auth.user.inner().id, &mut auth.connection).await?,
+    ))
+}
+
+/// The authenticated user's in-app notifications
+#[rocket::get("/me/notifications/")]
+pub async fn notifications(mut auth: TokenAuth, data: Query<NotificationPagination>) -> Result<Response2<Json<Vec<Notification>>>> {
+    let mut pagination = data.0;
+    let user_id = auth.user.inner().id;
+
+    let mut notifications = pagination.page(user_id, &mut auth.connection).await?;
+
+    let (max_id, min_id) = Notification::extremal_ids(user_id, &mut auth.connection).await?;
+
+    pagination_response!(
+        "/api/v1/auth/me/notifications/",
+        notifications,
+        pagination,
+        min_id,
+        max_id,
+        before_id,
+        after_id,
+        id
+    )
+}
+
+/// Marks one of the authenticated user's notifications as read (or unread)
+#[rocket::patch("/me/notifications/<notification_id>", data = "<patch>")]
+pub async fn patch_notification(mut auth: TokenAuth, notification_id: i32, patch: Json<PatchNotification>) -> Result<Json<Notification>> {
+    let notification = Notification::by_id(notification_id, auth.user.inner().id, &mut auth.connection).await?;
+    let notification = notification.apply_patch(patch.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    Ok(Json(notification))
+}
+
 #[rocket::patch("/me", data = "<patch>")]
-pub async fn patch_me(mut auth: BasicAuth, patch: Json<PatchMe>, pred: Precondition) -> Result<std::result::Result<Tagged<User>, Status>> {
+pub async fn patch_me(
+    mut auth: BasicAuth, patch: Json<PatchMe>, pred: Precondition, mailer: &State<Mailer>,
+) -> Result<std::result::Result<Tagged<User>, Status>> {
+    auth.forbid_impersonation()?;
+
     pred.require_etag_match(auth.user.inner())?;
 
     let changes_password = patch.changes_password();
+    let new_email = patch.new_email().map(ToString::to_string);
 
     let updated_user = auth.user.apply_patch(patch.0, &mut auth.connection).await?;
 
     auth.connection.commit().await.map_err(UserError::from)?;
 
+    if let Some(email) = new_email {
+        send_verification_mail(&updated_user, &email, mailer);
+    }
+
     if changes_password {
         Ok(Err(Status::NotModified))
     } else {
@@ -84,11 +575,15 @@ pub async fn patch_me(mut auth: BasicAuth, patch: Json<PatchMe>, pred: Precondit
     }
 }
 
+/// Deletes the authenticated user's account by anonymizing it (see
+/// [`AuthenticatedUser::anonymize`])
 #[rocket::delete("/me")]
 pub async fn delete_me(mut auth: BasicAuth, pred: Precondition) -> Result<Status> {
+    auth.forbid_impersonation()?;
+
     pred.require_etag_match(auth.user.inner())?;
 
-    auth.user.delete(&mut auth.connection).await?;
+    auth.user.anonymize(&mut auth.connection).await?;
     auth.connection.commit().await.map_err(UserError::from)?;
 
     Ok(Status::NoContent)
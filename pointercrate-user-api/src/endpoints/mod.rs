@@ -1,2 +1,4 @@
+pub(crate) mod audit;
 pub(crate) mod auth;
+pub(crate) mod registration;
 pub(crate) mod user;
@@ -0,0 +1,38 @@
+//! Endpoints for managing the IP addresses exempt from the limit on registrations per day, e.g.
+//! the network of a school whose students all sign up at once
+
+use crate::auth::TokenAuth;
+use pointercrate_core_api::{error::Result, response::Response2};
+use pointercrate_user::{NewRegistrationLimitExemption, RegistrationLimitExemption, ADMINISTRATOR};
+use rocket::{http::Status, serde::json::Json};
+
+#[rocket::get("/")]
+pub async fn exemptions(mut auth: TokenAuth) -> Result<Json<Vec<RegistrationLimitExemption>>> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    Ok(Json(RegistrationLimitExemption::all(&mut auth.connection).await?))
+}
+
+#[rocket::post("/", data = "<exemption>")]
+pub async fn add_exemption(
+    mut auth: TokenAuth, exemption: Json<NewRegistrationLimitExemption>,
+) -> Result<Response2<Json<RegistrationLimitExemption>>> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    let exemption = RegistrationLimitExemption::create(exemption.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    Ok(Response2::json(exemption).status(Status::Created))
+}
+
+#[rocket::delete("/<exemption_id>")]
+pub async fn delete_exemption(mut auth: TokenAuth, exemption_id: i32) -> Result<Status> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    RegistrationLimitExemption::delete(exemption_id, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    Ok(Status::NoContent)
+}
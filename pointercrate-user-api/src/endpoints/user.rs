@@ -1,15 +1,28 @@
 use crate::auth::TokenAuth;
 use log::info;
-use pointercrate_core::error::CoreError;
+use pointercrate_core::{
+    error::CoreError,
+    permission::{Permission, PermissionsManager, Role},
+    pool::PointercratePool,
+};
 use pointercrate_core_api::{
     error::Result,
-    etag::{Precondition, Tagged},
+    etag::{Dated, Precondition, Tagged},
     pagination_response,
     query::Query,
     response::Response2,
 };
-use pointercrate_user::{error::UserError, PatchUser, User, UserPagination, ADMINISTRATOR, MODERATOR};
-use rocket::{http::Status, serde::json::Json};
+use pointercrate_user::{
+    config, error::UserError, AuthenticatedUser, PatchUser, SharedAccess, User, UserPagination, UserProfile, UserView, ADMINISTRATOR,
+    MODERATOR,
+};
+use rocket::{
+    http::Status,
+    serde::json::{serde_json, Json},
+    State,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[rocket::get("/")]
 pub async fn paginate(mut auth: TokenAuth, data: Query<UserPagination>) -> Result<Response2<Json<Vec<User>>>> {
@@ -40,72 +53,228 @@ pub async fn paginate(mut auth: TokenAuth, data: Query<UserPagination>) -> Resul
 }
 
 #[rocket::get("/<user_id>")]
-pub async fn get_user(mut auth: TokenAuth, user_id: i32) -> Result<Tagged<User>> {
+pub async fn get_user(mut auth: TokenAuth, user_id: i32) -> Result<Dated<Tagged<User>>> {
     let user = User::by_id(user_id, &mut auth.connection).await?;
 
-    // We are only allowed to retrieve users who already have permissions we can set.
-    if !auth.has_permission(MODERATOR) && !auth.has_permission(ADMINISTRATOR) {
-        let can_assign_any = auth.assignable_permissions().iter().any(|perm| user.has_permission(*perm));
+    require_visible(&auth, &user)?;
 
-        if !can_assign_any {
-            // don't leak information about what users exist
-            return Err(UserError::UserNotFound { user_id }.into())
-        }
-    }
+    let last_modified = User::last_modified(user_id, &mut auth.connection).await?;
 
-    Ok(Tagged(user))
+    Ok(Dated(Tagged(user), last_modified))
+}
+
+/// The profile of the given user. Everyone can see the public part, while users who could retrieve
+/// the user via [`get_user`] additionally see their login name and permissions.
+#[rocket::get("/<user_id>/profile")]
+pub async fn profile(
+    auth: Option<TokenAuth>, user_id: i32, pool: &State<PointercratePool>, permissions: &State<PermissionsManager>,
+) -> Result<Json<UserProfile>> {
+    let (user, view) = match auth {
+        Some(mut auth) => {
+            let user = User::by_id(user_id, &mut auth.connection).await?;
+            let view = if require_visible(&auth, &user).is_ok() {
+                UserView::Staff
+            } else {
+                UserView::Public
+            };
+
+            (user, view)
+        },
+        None => (User::by_id(user_id, &mut *pool.read_connection().await?).await?, UserView::Public),
+    };
+
+    let roles = permissions.roles_of_bits(user.permissions).iter().map(Role::name).collect();
+
+    Ok(Json(user.profile(view, roles)))
 }
 
 #[rocket::patch("/<user_id>", data = "<patch>")]
 pub async fn patch_user(mut auth: TokenAuth, precondition: Precondition, user_id: i32, mut patch: Json<PatchUser>) -> Result<Tagged<User>> {
     let user = User::by_id(user_id, &mut auth.connection).await?;
 
+    require_visible(&auth, &user)?;
+
+    if patch.youtube_channel.is_some() || patch.display_name.is_some() {
+        auth.require_permission(MODERATOR)?;
+    }
+
+    if let Some(ref mut permissions) = patch.permissions {
+        *permissions = reassign(&auth, &user, *permissions)?;
+    }
+
+    if user_id == auth.user.inner().id {
+        return Err(UserError::PatchSelf.into())
+    }
+
+    precondition.require_match_or_unmodified(&user, User::last_modified(user_id, &mut auth.connection).await?)?;
+
+    let user = user.apply_patch(patch.0, &mut auth.connection).await?;
+
+    auth.commit().await?;
+
+    Ok(Tagged(user))
+}
+
+/// Computes the new permissions of `user` after setting all permissions the authenticated user can
+/// assign to `requested`
+fn reassign(auth: &TokenAuth, user: &User, requested: u16) -> std::result::Result<u16, UserError> {
+    let permissions = auth
+        .permissions
+        .reassign(auth.user.inner().permissions, user.permissions, requested)
+        .map_err(|non_assignable| UserError::PermissionNotAssignable { non_assignable })?;
+
+    info!(
+        "Reassigning permissions of user {} from {:b} to {:b}",
+        user, user.permissions, permissions
+    );
+
+    Ok(permissions)
+}
+
+/// Makes sure the authenticated user is allowed to see and modify the permissions of `user`
+fn require_visible(auth: &TokenAuth, user: &User) -> std::result::Result<(), UserError> {
+    // We are only allowed to retrieve users who already have permissions we can set.
     if !auth.has_permission(MODERATOR) && !auth.has_permission(ADMINISTRATOR) {
         let can_assign_any = auth.assignable_permissions().iter().any(|perm| user.has_permission(*perm));
 
         if !can_assign_any {
             // don't leak information about what users exist
-            return Err(UserError::UserNotFound { user_id }.into())
+            return Err(UserError::UserNotFound { user_id: user.id })
         }
     }
 
-    if patch.youtube_channel.is_some() || patch.display_name.is_some() {
-        auth.require_permission(MODERATOR)?;
-    }
+    Ok(())
+}
 
-    if let Some(ref mut permissions) = patch.permissions {
-        let assignable_bitmask = auth.assignable_permissions().iter().fold(0x0, |mask, perm| mask | perm.bit());
+#[derive(Serialize)]
+pub struct UserPermissions {
+    /// The permissions the user has been granted directly, without implied ones
+    permissions: HashSet<Permission>,
+
+    /// The roles whose permissions the user has all been granted
+    roles: Vec<&'static str>,
 
-        if *permissions & assignable_bitmask != *permissions {
-            let unassignable_permissions = (*permissions & assignable_bitmask) ^ *permissions;
+    /// The permissions of this user the authenticated user can change
+    assignable: HashSet<Permission>,
+}
 
-            return Err(UserError::PermissionNotAssignable {
-                non_assignable: auth.permissions.bits_to_permissions(unassignable_permissions),
-            }
-            .into())
+impl UserPermissions {
+    fn of(user: &User, auth: &TokenAuth) -> Self {
+        UserPermissions {
+            permissions: auth.permissions.bits_to_permissions(user.permissions),
+            roles: auth.permissions.roles_of_bits(user.permissions).iter().map(Role::name).collect(),
+            assignable: auth.assignable_permissions(),
         }
+    }
+}
 
-        info!("assignable permissions are {:b}", assignable_bitmask);
-        info!("assigned permissions are {:b}", permissions);
-        info!("User currently has permissions {:b}", user.permissions);
+#[rocket::get("/<user_id>/permissions")]
+pub async fn get_permissions(mut auth: TokenAuth, user_id: i32) -> Result<Json<UserPermissions>> {
+    let user = User::by_id(user_id, &mut auth.connection).await?;
 
-        // we clear all the assignable bits in the user's permissions bitstring. Since we already verified
-        // that permissions is a subset of assignable_permissions, we can then set the new permissions via
-        // simple OR
-        *permissions = (user.permissions & !assignable_bitmask) | *permissions;
-    }
+    require_visible(&auth, &user)?;
+
+    Ok(Json(UserPermissions::of(&user, &auth)))
+}
+
+/// The permissions and roles to grant. All permissions the authenticated user can assign but that
+/// are not listed (neither directly, nor via a role) are revoked.
+#[derive(Deserialize)]
+pub struct PermissionAssignment {
+    #[serde(default)]
+    permissions: Vec<String>,
+
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+#[rocket::put("/<user_id>/permissions", data = "<assignment>")]
+pub async fn put_permissions(
+    mut auth: TokenAuth, precondition: Precondition, user_id: i32, assignment: Json<PermissionAssignment>,
+) -> Result<Json<UserPermissions>> {
+    let user = User::by_id(user_id, &mut auth.connection).await?;
+
+    require_visible(&auth, &user)?;
 
     if user_id == auth.user.inner().id {
         return Err(UserError::PatchSelf.into())
     }
 
-    precondition.require_etag_match(&user)?;
+    let mut requested = 0x0;
 
-    let user = user.apply_patch(patch.0, &mut auth.connection).await?;
+    for name in &assignment.permissions {
+        match auth.permissions.permission_by_name(name) {
+            Some(permission) => requested |= permission.bit(),
+            None => return Err(UserError::UnknownPermission { name: name.clone() }.into()),
+        }
+    }
+
+    for name in &assignment.roles {
+        match auth.permissions.role_by_name(name) {
+            Some(role) => requested |= role.bits(),
+            None => return Err(UserError::UnknownRole { name: name.clone() }.into()),
+        }
+    }
+
+    let permissions = reassign(&auth, &user, requested)?;
+
+    precondition.require_match_or_unmodified(&user, User::last_modified(user_id, &mut auth.connection).await?)?;
+
+    let user = user
+        .apply_patch(
+            PatchUser {
+                display_name: None,
+                youtube_channel: None,
+                permissions: Some(permissions),
+            },
+            &mut auth.connection,
+        )
+        .await?;
+
+    let response = UserPermissions::of(&user, &auth);
 
     auth.commit().await?;
 
-    Ok(Tagged(user))
+    Ok(Json(response))
+}
+
+/// Lists other accounts that were recently accessed from the same IP addresses as the given one, to
+/// help finding accounts used for ban evasion
+#[rocket::get("/<user_id>/shared_access")]
+pub async fn shared_access(mut auth: TokenAuth, user_id: i32) -> Result<Json<Vec<SharedAccess>>> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    let user = User::by_id(user_id, &mut auth.connection).await?;
+
+    Ok(Json(user.shared_access(&mut auth.connection).await?))
+}
+
+/// Issues a short-lived access token acting as the given user, for reproducing problems they
+/// reported without asking for their credentials. Changes made using the token are flagged with
+/// the administrator's id in the audit log.
+#[rocket::post("/<user_id>/impersonate")]
+pub async fn impersonate(mut auth: TokenAuth, user_id: i32) -> Result<Json<serde_json::Value>> {
+    auth.require_permission(ADMINISTRATOR)?;
+
+    let user = AuthenticatedUser::by_id(user_id, &mut auth.connection).await?;
+
+    // Impersonating an administrator would allow using their token to impersonate even more users
+    if user_id == auth.user.inner().id || user.inner().has_permission(ADMINISTRATOR) {
+        return Err(UserError::ImpersonationForbidden.into())
+    }
+
+    let token = user.generate_impersonation_token(auth.user.inner(), &pointercrate_core::config::signing_keys());
+
+    user.log_impersonation(auth.user.inner(), &mut auth.connection).await?;
+    auth.commit().await?;
+
+    Ok(Json(serde_json::json! {
+        {
+            "data": user.into_inner(),
+            "token": token,
+            "expires_in": config::impersonation_token_lifetime()
+        }
+    }))
 }
 
 #[rocket::delete("/<user_id>")]
@@ -118,7 +287,7 @@ pub async fn delete_user(mut auth: TokenAuth, precondition: Precondition, user_i
 
     let to_delete = User::by_id(user_id, &mut auth.connection).await?;
 
-    precondition.require_etag_match(&to_delete)?;
+    precondition.require_match_or_unmodified(&to_delete, User::last_modified(user_id, &mut auth.connection).await?)?;
 
     to_delete.delete(&mut auth.connection).await?;
 
@@ -1,31 +1,98 @@
-use crate::ratelimits::UserRatelimits;
+use crate::{discord::DiscordOAuth, mail::Mailer, ratelimits::UserRatelimits};
 
+use pointercrate_core::{permission::PermissionsManager, pool::PointercratePool};
 use rocket::{Build, Rocket};
 
 pub mod auth;
+pub(crate) mod config;
+pub(crate) mod discord;
+mod docs;
 mod endpoints;
+pub mod mail;
 mod pages;
 mod ratelimits;
+mod retention;
 
 pub fn setup(rocket: Rocket<Build>) -> Rocket<Build> {
+    // Report misconfiguration right away, instead of on the first request that needs the value in
+    // question
+    if let Err(err) = pointercrate_core::config::load() {
+        panic!("Invalid configuration: {}", err)
+    }
+
+    if let Err(err) = pointercrate_user::config::validate().and_then(|_| config::validate()) {
+        panic!("Invalid configuration: {}", err)
+    }
+
+    let rocket = pointercrate_core_api::setup(rocket);
+    let rocket = pointercrate_core_api::docs::register_schemas(rocket, docs::schemas());
+
     let ratelimits = UserRatelimits::new();
 
+    retention::spawn(rocket.state::<PointercratePool>().unwrap().clone_inner());
+
+    let permissions = rocket.state::<PermissionsManager>().unwrap();
+    for role in pointercrate_user::roles() {
+        permissions.register_role(role);
+    }
+
+    // Logging in via discord is only possible if we have a discord application to do so with
+    let rocket = match DiscordOAuth::from_config() {
+        Some(discord) =>
+            rocket.manage(discord).mount("/api/v1/auth/", rocket::routes![
+                endpoints::auth::discord_login,
+                endpoints::auth::discord_callback,
+                endpoints::auth::discord_totp
+            ]),
+        None => rocket,
+    };
+
     rocket
         .manage(ratelimits)
+        .manage(Mailer::spawn())
         .mount("/api/v1/auth/", rocket::routes![
             endpoints::auth::register,
             endpoints::auth::login,
+            endpoints::auth::refresh,
+            endpoints::auth::sessions,
+            endpoints::auth::revoke_session,
+            endpoints::auth::create_api_key,
+            endpoints::auth::api_keys,
+            endpoints::auth::revoke_api_key,
+            endpoints::auth::enroll_2fa,
+            endpoints::auth::confirm_2fa,
+            endpoints::auth::disable_2fa,
+            endpoints::auth::verify_email,
+            endpoints::auth::reset_password,
+            endpoints::auth::confirm_password_reset,
             endpoints::auth::invalidate,
             endpoints::auth::get_me,
+            endpoints::auth::notification_preferences,
+            endpoints::auth::notifications,
+            endpoints::auth::patch_notification,
             endpoints::auth::patch_me,
             endpoints::auth::delete_me
         ])
         .mount("/api/v1/users/", rocket::routes![
             endpoints::user::paginate,
             endpoints::user::get_user,
+            endpoints::user::profile,
             endpoints::user::patch_user,
+            endpoints::user::get_permissions,
+            endpoints::user::put_permissions,
+            endpoints::user::shared_access,
+            endpoints::user::impersonate,
             endpoints::user::delete_user
         ])
+        .mount("/api/v1/registration_exemptions/", rocket::routes![
+            endpoints::registration::exemptions,
+            endpoints::registration::add_exemption,
+            endpoints::registration::delete_exemption
+        ])
+        .mount("/api/v1/auditlog/", rocket::routes![
+            endpoints::audit::paginate,
+            endpoints::audit::export
+        ])
         .mount("/", rocket::routes![
             pages::login_page,
             pages::account_page,
@@ -0,0 +1,119 @@
+//! Module for sending emails
+//!
+//! All mails are handed to a background task owning the SMTP connection, so sending a mail never
+//! delays an API response. Delivery failures are only logged.
+
+use crate::config;
+use lettre::{message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{debug, error, info, warn};
+use rocket::tokio::{
+    self,
+    sync::mpsc::{self, UnboundedSender},
+};
+
+#[derive(Debug)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Clone)]
+pub struct Mailer(Option<UnboundedSender<Mail>>);
+
+impl Mailer {
+    /// Spawns the background task delivering mails, if an SMTP server has been configured
+    pub fn spawn() -> Self {
+        let host = match config::smtp_host() {
+            Some(host) => host,
+            None => {
+                warn!("No SMTP server configured, emails will not be sent!");
+
+                return Mailer(None)
+            },
+        };
+
+        let mut transport = match AsyncSmtpTransport::<Tokio1Executor>::relay(&host) {
+            Ok(transport) => transport,
+            Err(err) => {
+                error!("Invalid SMTP configuration, emails will not be sent: {:?}", err);
+
+                return Mailer(None)
+            },
+        };
+
+        if let (Some(username), Some(password)) = (config::smtp_username(), config::smtp_password()) {
+            transport = transport.credentials(Credentials::new(username, password));
+        }
+
+        let transport = transport.build();
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Mail>();
+
+        tokio::spawn(async move {
+            while let Some(mail) = receiver.recv().await {
+                deliver(&transport, mail).await
+            }
+        });
+
+        info!("Started mailer using SMTP server {}", host);
+
+        Mailer(Some(sender))
+    }
+
+    pub fn send(&self, mail: Mail) {
+        match self.0 {
+            Some(ref sender) =>
+                if sender.send(mail).is_err() {
+                    error!("INTERNAL SERVER ERROR: Mailer task is no longer running");
+                },
+            None => debug!("Dropping mail '{}', since no SMTP server is configured", mail.subject),
+        }
+    }
+}
+
+async fn deliver(transport: &AsyncSmtpTransport<Tokio1Executor>, mail: Mail) {
+    let builder = match (config::mail_from().parse::<Mailbox>(), mail.to.parse::<Mailbox>()) {
+        (Ok(from), Ok(to)) => Message::builder().from(from).to(to),
+        (from, to) => {
+            warn!("Not sending mail due to malformed address: {:?} {:?}", from.err(), to.err());
+
+            return
+        },
+    };
+
+    let message = match builder.subject(mail.subject).body(mail.body) {
+        Ok(message) => message,
+        Err(err) => return error!("Failed to construct mail: {:?}", err),
+    };
+
+    match transport.send(message).await {
+        Ok(_) => debug!("Successfully sent mail"),
+        Err(err) => error!("INTERNAL SERVER ERROR: Failed to send mail: {:?}", err),
+    }
+}
+
+pub fn verification_mail(to: &str, name: &str, token: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Hello {},\n\nplease verify your email address by submitting the following token to POST \
+             /api/v1/auth/verify_email/:\n\n{}\n\nThe token is valid for 24 hours. If you did not add this email address to a \
+             pointercrate account, you can ignore this email.",
+            name, token
+        ),
+    }
+}
+
+pub fn reset_mail(to: &str, name: &str, token: &str) -> Mail {
+    Mail {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Hello {},\n\nsomeone requested a password reset for your pointercrate account. To choose a new password, submit the \
+             following token together with your new password to POST /api/v1/auth/reset_password/confirm/:\n\n{}\n\nThe token is valid \
+             for one hour. If you did not request a password reset, you can ignore this email.",
+            name, token
+        ),
+    }
+}
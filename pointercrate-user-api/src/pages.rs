@@ -1,53 +1,67 @@
 use crate::{
-    auth::{BasicAuth, TokenAuth},
+    auth::{PasswordAuth, TokenAuth, UserAgent},
+    endpoints::auth::{add_session_cookies, DISCORD_TOTP_COOKIE},
     ratelimits::UserRatelimits,
 };
 use pointercrate_core::{config, permission::PermissionsManager, pool::PointercratePool};
 use pointercrate_core_api::response::Page;
-use pointercrate_user::{error::UserError, AuthenticatedUser, Registration, User};
+use pointercrate_user::{check_registration_limit, error::UserError, AccessKind, AuthenticatedUser, Registration, User};
 use pointercrate_user_pages::{
     account::{AccountPage, AccountPageConfig},
     login::LoginPage,
 };
 use rocket::{
-    http::{Cookie, CookieJar, SameSite, Status},
+    form::Form,
+    http::{CookieJar, Status},
     response::Redirect,
     serde::json::Json,
-    State,
+    FromForm, State,
 };
 use std::net::IpAddr;
 
 #[rocket::get("/login")]
-pub async fn login_page(auth: Option<TokenAuth>) -> Result<Redirect, Page<LoginPage>> {
-    auth.map(|_| Redirect::to(rocket::uri!(account_page)))
-        .ok_or_else(|| Page(LoginPage))
+pub async fn login_page(auth: Option<TokenAuth>, cookies: &CookieJar<'_>) -> Result<Redirect, Page<LoginPage>> {
+    auth.map(|_| Redirect::to(rocket::uri!(account_page))).ok_or_else(|| {
+        Page(LoginPage {
+            discord_totp: cookies.get(DISCORD_TOTP_COOKIE).is_some(),
+        })
+    })
 }
 
-#[rocket::post("/login")]
+/// The fields of the login form besides username and password, which are sent via the
+/// `Authorization` header
+#[derive(FromForm)]
+pub struct LoginForm {
+    /// The current two-factor authentication code, for users who enabled it
+    totp: Option<String>,
+}
+
+#[rocket::post("/login", data = "<form>")]
 pub async fn login(
-    auth: Result<BasicAuth, UserError>, ip: IpAddr, ratelimits: &State<UserRatelimits>, cookies: &CookieJar<'_>,
+    auth: Result<PasswordAuth, UserError>, form: Option<Form<LoginForm>>, ip: IpAddr, user_agent: UserAgent,
+    ratelimits: &State<UserRatelimits>, cookies: &CookieJar<'_>,
 ) -> pointercrate_core_api::error::Result<Status> {
     ratelimits.login_attempts(ip)?;
 
-    let auth = auth?;
+    let PasswordAuth(mut auth) = auth?;
+    let code = form.as_ref().and_then(|form| form.totp.as_deref()).filter(|code| !code.is_empty());
 
-    let mut cookie = Cookie::build("access_token", auth.user.generate_token(&config::secret()))
-        .http_only(true)
-        .same_site(SameSite::Strict)
-        .path("/");
+    auth.user.verify_totp(code, &config::secret(), &mut auth.connection).await?;
 
-    if !cfg!(debug_assertions) {
-        cookie = cookie.secure(true)
-    }
+    auth.user.log_access(AccessKind::Login, ip, &mut auth.connection).await?;
+
+    let (session, refresh_token) = auth.user.start_session(ip, user_agent.0.as_deref(), &mut auth.connection).await?;
 
-    cookies.add(cookie.finish());
+    auth.connection.commit().await.map_err(UserError::from)?;
+
+    add_session_cookies(cookies, &auth.user, &session, refresh_token);
 
     Ok(Status::NoContent)
 }
 
 #[rocket::post("/register", data = "<registration>")]
 pub async fn register(
-    ip: IpAddr, ratelimits: &State<UserRatelimits>, cookies: &CookieJar<'_>, registration: Json<Registration>,
+    ip: IpAddr, user_agent: UserAgent, ratelimits: &State<UserRatelimits>, cookies: &CookieJar<'_>, registration: Json<Registration>,
     pool: &State<PointercratePool>,
 ) -> pointercrate_core_api::error::Result<Status> {
     let mut connection = pool.transaction().await.map_err(UserError::from)?;
@@ -57,22 +71,17 @@ pub async fn register(
     AuthenticatedUser::validate_password(&registration.password)?;
     User::validate_name(&registration.name)?;
 
-    ratelimits.registrations(ip)?;
+    check_registration_limit(ip, &mut connection).await?;
 
     let user = AuthenticatedUser::register(registration.0, &mut connection).await?;
 
-    connection.commit().await.map_err(UserError::from)?;
+    user.log_access(AccessKind::Registration, ip, &mut connection).await?;
 
-    let mut cookie = Cookie::build("access_token", user.generate_token(&config::secret()))
-        .http_only(true)
-        .same_site(SameSite::Strict)
-        .path("/");
+    let (session, refresh_token) = user.start_session(ip, user_agent.0.as_deref(), &mut connection).await?;
 
-    if !cfg!(debug_assertions) {
-        cookie = cookie.secure(true)
-    }
+    connection.commit().await.map_err(UserError::from)?;
 
-    cookies.add(cookie.finish());
+    add_session_cookies(cookies, &user, &session, refresh_token);
 
     Ok(Status::Created)
 }
@@ -83,7 +92,7 @@ pub async fn account_page(
 ) -> Result<Page<AccountPage>, Redirect> {
     match auth {
         Some(mut auth) => {
-            let csrf_token = auth.user.generate_csrf_token(&config::secret());
+            let csrf_token = auth.user.generate_csrf_token(&config::signing_keys());
 
             Ok(Page(
                 tabs.account_page(csrf_token, auth.user.into_inner(), permissions, &mut auth.connection)
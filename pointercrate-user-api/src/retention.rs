@@ -0,0 +1,37 @@
+//! Module for periodically deleting data that is only kept for a limited time, such as old access
+//! log entries and the networks old sessions were started from
+
+use log::{error, info};
+use pointercrate_user::{error::Result, purge_access_log, Session};
+use rocket::tokio;
+use sqlx::{Pool, Postgres};
+use std::time::Duration;
+
+/// How often expired data is deleted
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+pub fn spawn(pool: Pool<Postgres>) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = purge(&pool).await {
+                error!("INTERNAL SERVER ERROR: Failure to purge expired data: {:?}", err);
+            }
+
+            tokio::time::sleep(PURGE_INTERVAL).await;
+        }
+    });
+}
+
+async fn purge(pool: &Pool<Postgres>) -> Result<()> {
+    let mut connection = pool.acquire().await?;
+
+    let purged = purge_access_log(&mut connection).await?;
+
+    info!("Purged {} expired access log entries", purged);
+
+    let purged = Session::purge_addresses(&mut connection).await?;
+
+    info!("Removed the network of {} old sessions", purged);
+
+    Ok(())
+}
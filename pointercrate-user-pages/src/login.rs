@@ -1,7 +1,11 @@
 use maud::{html, Markup};
 use pointercrate_core_pages::{PageFragment, Script};
 
-pub struct LoginPage;
+pub struct LoginPage {
+    /// Whether the user authorized via Discord, but still needs to provide their two-factor
+    /// authentication code
+    pub discord_totp: bool,
+}
 
 impl PageFragment for LoginPage {
     fn title(&self) -> String {
@@ -28,6 +32,26 @@ impl PageFragment for LoginPage {
     }
 
     fn body_fragment(&self) -> Markup {
+        if self.discord_totp {
+            return html! {
+                div.m-center.flex.panel.fade.col.wrap style = "margin: 100px 0px;"{
+                    h1.underlined.pad {
+                        "Two-factor authentication"
+                    }
+                    p {
+                        "Your account has two-factor authentication enabled. Please enter the current code from your authenticator app to finish logging in via Discord."
+                    }
+                    form.flex.col#discord-totp-form method = "post" action = "/api/v1/auth/discord/totp" {
+                        span.form-input#discord-totp {
+                            label for = "totp" {"Two-factor authentication code:"}
+                            input required = "" type = "text" name = "totp" inputmode = "numeric" autocomplete = "one-time-code" maxlength = "6";
+                        }
+                        input.button.blue.hover type = "submit" style = "margin: 15px auto 0px;" value="Log in";
+                    }
+                }
+            }
+        }
+
         html! {
             div.m-center.flex.panel.fade.col.wrap style = "margin: 100px 0px;"{
                 h1.underlined.pad {
@@ -54,6 +78,11 @@ impl PageFragment for LoginPage {
                                 input required = "" type = "password" name = "password" minlength = "10";
                                 p.error {}
                             }
+                            span.form-input#login-totp {
+                                label for = "totp" {"Two-factor authentication code (only if enabled):"}
+                                input type = "text" name = "totp" inputmode = "numeric" autocomplete = "one-time-code" maxlength = "6";
+                                p.error {}
+                            }
                             div.grow {}
                             input.button.blue.hover type = "submit" style = "margin: 15px auto 0px;" value="Log in";
                         }
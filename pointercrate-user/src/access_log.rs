@@ -0,0 +1,224 @@
+//! Module for the access log used to detect accounts operated by the same person
+//!
+//! Every registration and login is stored together with a keyed hash of the IP address it came from
+//! (for IPv6 addresses only the /64 prefix is considered, as the rest usually changes regularly).
+//! Entries older than [`config::access_log_retention`] days are deleted (see [`purge_access_log`]).
+//!
+//! The log is also used to limit the number of registrations per IP address (see
+//! [`check_registration_limit`]).
+
+use crate::{
+    auth::AuthenticatedUser,
+    config,
+    error::{Result, UserError},
+    User,
+};
+use chrono::{NaiveDateTime, Utc};
+use log::{debug, info};
+use pointercrate_core::error::CoreError;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::{net::IpAddr, time::Duration};
+
+#[derive(Debug, Clone, Copy)]
+pub enum AccessKind {
+    Registration,
+    Login,
+}
+
+impl AccessKind {
+    fn to_sql(self) -> &'static str {
+        match self {
+            AccessKind::Registration => "registration",
+            AccessKind::Login => "login",
+        }
+    }
+}
+
+/// Another account that was accessed from some of the IP addresses a given account was accessed
+/// from
+#[derive(Debug, Serialize)]
+pub struct SharedAccess {
+    pub user: User,
+
+    /// The number of distinct IP addresses both accounts were accessed from
+    pub shared_addresses: i64,
+
+    /// The last time the other account was accessed from any of the shared IP addresses
+    pub last_shared_access: NaiveDateTime,
+}
+
+fn address_key(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+
+            format!("{:x}:{:x}:{:x}:{:x}::/64", segments[0], segments[1], segments[2], segments[3])
+        },
+    }
+}
+
+/// The keyed hash of the given address stored in the database
+///
+/// Computed here instead of in the database, so that the application secret is never sent to it.
+fn address_hash(ip: IpAddr) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, &pointercrate_core::config::secret());
+
+    hmac::sign(&key, address_key(ip).as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl AuthenticatedUser {
+    /// Records that this account was accessed from the given IP address
+    pub async fn log_access(&self, kind: AccessKind, ip: IpAddr, connection: &mut PgConnection) -> Result<()> {
+        debug!("Logging {} of user {}", kind.to_sql(), self.inner());
+
+        sqlx::query!(
+            "INSERT INTO user_access_log (member, kind, ip_hash) VALUES ($1, $2, $3)",
+            self.inner().id,
+            kind.to_sql(),
+            address_hash(ip)
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Deletes all access log entries older than [`config::access_log_retention`] days. Meant to be
+/// called periodically.
+pub async fn purge_access_log(connection: &mut PgConnection) -> Result<u64> {
+    Ok(sqlx::query!(
+        "DELETE FROM user_access_log WHERE time < (NOW() AT TIME ZONE 'utc') - make_interval(days => $1)",
+        config::access_log_retention()
+    )
+    .execute(connection)
+    .await?
+    .rows_affected())
+}
+
+impl User {
+    /// Gets all other accounts that were accessed from any IP address this account was accessed
+    /// from, ordered by the number of shared addresses
+    pub async fn shared_access(&self, connection: &mut PgConnection) -> Result<Vec<SharedAccess>> {
+        Ok(sqlx::query!(
+            r#"SELECT member_id, members.name, permissions::integer, display_name, youtube_channel::text, COUNT(DISTINCT other.ip_hash) AS
+             "shared_addresses!", MAX(other.time) AS "last_shared_access!" FROM user_access_log AS own INNER JOIN user_access_log AS other ON
+             own.ip_hash = other.ip_hash AND own.member <> other.member INNER JOIN members ON members.member_id = other.member WHERE own.member =
+             $1 GROUP BY members.member_id ORDER BY 6 DESC, 7 DESC"#,
+            self.id
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|row| {
+            SharedAccess {
+                shared_addresses: row.shared_addresses,
+                last_shared_access: row.last_shared_access,
+                user: construct_from_row!(row),
+            }
+        })
+        .collect())
+    }
+}
+
+/// An IP address exempt from the limit on registrations per day. The address itself is not stored,
+/// only its hash.
+#[derive(Debug, Serialize)]
+pub struct RegistrationLimitExemption {
+    pub id: i32,
+
+    /// Why this address is exempt
+    pub note: String,
+    pub created: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewRegistrationLimitExemption {
+    pub ip: IpAddr,
+    pub note: String,
+}
+
+impl RegistrationLimitExemption {
+    pub async fn all(connection: &mut PgConnection) -> Result<Vec<RegistrationLimitExemption>> {
+        Ok(sqlx::query_as!(
+            RegistrationLimitExemption,
+            "SELECT id, note, created FROM registration_limit_exemptions ORDER BY id"
+        )
+        .fetch_all(connection)
+        .await?)
+    }
+
+    /// Exempts the given address. Exempting an address again only updates the note.
+    pub async fn create(exemption: NewRegistrationLimitExemption, connection: &mut PgConnection) -> Result<RegistrationLimitExemption> {
+        info!("Exempting an IP address from the registration limit ({})", exemption.note);
+
+        Ok(sqlx::query_as!(
+            RegistrationLimitExemption,
+            "INSERT INTO registration_limit_exemptions (ip_hash, note) VALUES ($1, $2) ON CONFLICT (ip_hash) DO UPDATE SET note = \
+             EXCLUDED.note RETURNING id, note, created",
+            address_hash(exemption.ip),
+            exemption.note
+        )
+        .fetch_one(connection)
+        .await?)
+    }
+
+    pub async fn delete(exemption_id: i32, connection: &mut PgConnection) -> Result<()> {
+        let deleted = sqlx::query!("DELETE FROM registration_limit_exemptions WHERE id = $1", exemption_id)
+            .execute(connection)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(UserError::RegistrationLimitExemptionNotFound { exemption_id })
+        }
+
+        Ok(())
+    }
+}
+
+/// Fails with `429 TOO MANY REQUESTS` if at least [`config::max_registrations_per_day`] accounts
+/// were registered from the given IP address within the last 24 hours, unless the address is exempt
+///
+/// Needs to be called inside the transaction that registers the account and logs the registration.
+/// Until that transaction ends, concurrent registrations from the same address wait here, so that
+/// they cannot all pass the check before any of them is logged.
+pub async fn check_registration_limit(ip: IpAddr, connection: &mut PgConnection) -> Result<()> {
+    let ip_hash = address_hash(ip);
+
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext($1))", ip_hash)
+        .execute(&mut *connection)
+        .await?;
+
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM registration_limit_exemptions WHERE ip_hash = $1) AS "exempt!", COUNT(*) AS "registrations!",
+         MIN(time) AS oldest FROM user_access_log WHERE kind = 'registration' AND ip_hash = $1 AND time > (NOW() AT TIME ZONE 'utc') -
+         INTERVAL '1 day'"#,
+        ip_hash
+    )
+    .fetch_one(connection)
+    .await?;
+
+    if row.exempt || row.registrations < config::max_registrations_per_day() {
+        return Ok(())
+    }
+
+    // Another registration is possible once the oldest one in the window is more than a day old
+    let remaining = row
+        .oldest
+        .map(|oldest| oldest + chrono::Duration::days(1) - Utc::now().naive_utc())
+        .and_then(|remaining| remaining.to_std().ok())
+        .unwrap_or_else(|| Duration::from_secs(0));
+
+    Err(CoreError::Ratelimited {
+        message: "Too many registrations from your IP address!".to_string(),
+        remaining,
+    }
+    .into())
+}
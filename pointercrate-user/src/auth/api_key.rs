@@ -0,0 +1,193 @@
+//! Module for long-lived, account-scoped API keys
+//!
+//! API keys are meant for bots and other automated clients. They are passed via the
+//! `Authorization: Key <key>` header and never expire, but each key is limited to a set of
+//! [`ApiKeyScope`]s and a subset of its owner's permissions. Like refresh tokens, only a hash of
+//! each key is stored.
+
+use crate::{
+    auth::AuthenticatedUser,
+    error::{Result, UserError},
+};
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use pointercrate_core::error::CoreError;
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgConnection};
+
+/// The kinds of requests an API key can be used for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Read-only access (`GET` requests)
+    Read,
+
+    /// Submitting records
+    Submit,
+
+    /// Any other request
+    Write,
+}
+
+impl ApiKeyScope {
+    fn to_sql(self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Submit => "submit",
+            ApiKeyScope::Write => "write",
+        }
+    }
+
+    fn from_sql(sql: &str) -> Self {
+        match sql {
+            "read" => ApiKeyScope::Read,
+            "submit" => ApiKeyScope::Submit,
+            "write" => ApiKeyScope::Write,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+
+    /// The permissions requests authenticated via this key have (at most, since they are
+    /// additionally limited by the owner's current permissions)
+    pub permissions: u16,
+    pub created_at: NaiveDateTime,
+    pub last_used: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewApiKey {
+    name: String,
+    scopes: Vec<ApiKeyScope>,
+
+    #[serde(default)]
+    permissions: u16,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    /// Gets all API keys of the user with the given id
+    pub async fn of_user(user_id: i32, connection: &mut PgConnection) -> Result<Vec<ApiKey>> {
+        Ok(sqlx::query!(
+            "SELECT id, name, scopes, permissions, created_at, last_used FROM api_keys WHERE member = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(connection)
+        .await?
+        .into_iter()
+        .map(|row| {
+            ApiKey {
+                id: row.id,
+                name: row.name,
+                scopes: row.scopes.iter().map(|scope| ApiKeyScope::from_sql(scope)).collect(),
+                permissions: row.permissions as u16,
+                created_at: row.created_at,
+                last_used: row.last_used,
+            }
+        })
+        .collect())
+    }
+
+    /// Revokes the API key with the given id, which has to belong to the given user
+    pub async fn revoke(user_id: i32, key_id: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Revoking API key {} of user {}", key_id, user_id);
+
+        let result = sqlx::query!("DELETE FROM api_keys WHERE id = $1 AND member = $2", key_id, user_id)
+            .execute(connection)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserError::ApiKeyNotFound { key_id })
+        }
+
+        Ok(())
+    }
+}
+
+impl AuthenticatedUser {
+    /// Creates a new API key for this user, returning it together with the actual key
+    ///
+    /// The key itself cannot be retrieved again later on
+    pub async fn create_api_key(&self, key: NewApiKey, connection: &mut PgConnection) -> Result<(ApiKey, String)> {
+        if key.permissions & !self.inner().permissions != 0 {
+            return Err(UserError::KeyPermissionsNotHeld)
+        }
+
+        info!("Creating API key '{}' for user {}", key.name, self.inner());
+
+        let scopes: Vec<&str> = key.scopes.iter().map(|scope| scope.to_sql()).collect();
+
+        let row = sqlx::query!(
+            r#"WITH api_key AS (SELECT encode(gen_random_bytes(32), 'hex') AS api_key) INSERT INTO api_keys (member, name, key_hash, scopes,
+             permissions) SELECT $1, $2, encode(sha256(convert_to(api_key, 'UTF8')), 'hex'), $3::TEXT[], $4 FROM api_key RETURNING id,
+             created_at, (SELECT api_key FROM api_key) AS "api_key!""#,
+            self.inner().id,
+            key.name,
+            &scopes as &[&str],
+            key.permissions as i32
+        )
+        .fetch_one(connection)
+        .await?;
+
+        Ok((
+            ApiKey {
+                id: row.id,
+                name: key.name,
+                scopes: key.scopes,
+                permissions: key.permissions,
+                created_at: row.created_at,
+                last_used: None,
+            },
+            row.api_key,
+        ))
+    }
+
+    /// Authenticates the owner of the given API key
+    ///
+    /// The returned user's permissions are restricted to those granted to the key
+    pub async fn api_key_auth(api_key: &str, connection: &mut PgConnection) -> Result<(AuthenticatedUser, ApiKey)> {
+        info!("We are expected to perform API key authentication");
+
+        let row = sqlx::query!(
+            "UPDATE api_keys SET last_used = (NOW() AT TIME ZONE 'utc') WHERE key_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex') \
+             RETURNING id, member, name, scopes, permissions, created_at, last_used",
+            api_key
+        )
+        .fetch_one(&mut *connection)
+        .await;
+
+        let row = match row {
+            Err(Error::RowNotFound) => {
+                warn!("Attempt to authenticate using unknown API key");
+
+                return Err(CoreError::Unauthorized.into())
+            },
+            Err(err) => return Err(err.into()),
+            Ok(row) => row,
+        };
+
+        let key = ApiKey {
+            id: row.id,
+            name: row.name,
+            scopes: row.scopes.iter().map(|scope| ApiKeyScope::from_sql(scope)).collect(),
+            permissions: row.permissions as u16,
+            created_at: row.created_at,
+            last_used: row.last_used,
+        };
+
+        let mut user = AuthenticatedUser::by_id(row.member, connection).await?;
+
+        user.user.permissions &= key.permissions;
+
+        Ok((user, key))
+    }
+}
@@ -10,4 +10,38 @@ impl AuthenticatedUser {
 
         self.user.delete(connection).await
     }
+
+    /// Removes all personal data from this account, without deleting it
+    ///
+    /// The account keeps its id, so that audit log entries and player claims referring to it stay
+    /// intact, but it is renamed to `deleted-user-<id>` and can never be logged into again. All
+    /// sessions and API keys are revoked, and its access log is cleared.
+    pub async fn anonymize(self, connection: &mut PgConnection) -> Result<()> {
+        warn!("Anonymizing user account {}", self.user);
+
+        // The password hash is replaced by the hash of a random password nobody knows. It still needs
+        // to be a valid bcrypt hash, since we extract the salt from it during token validation.
+        sqlx::query!(
+            "UPDATE members SET name = 'deleted-user-' || member_id, display_name = NULL, youtube_channel = NULL, email = NULL, \
+             email_verified = FALSE, totp_secret = NULL, totp_enabled = FALSE, discord_id = NULL, permissions = cast(0 as BIT(16)), \
+             password_hash = crypt(encode(gen_random_bytes(32), 'hex'), gen_salt('bf', 12)) WHERE member_id = $1",
+            self.user.id
+        )
+        .execute(&mut *connection)
+        .await?;
+
+        sqlx::query!("DELETE FROM sessions WHERE member = $1", self.user.id)
+            .execute(&mut *connection)
+            .await?;
+
+        sqlx::query!("DELETE FROM api_keys WHERE member = $1", self.user.id)
+            .execute(&mut *connection)
+            .await?;
+
+        sqlx::query!("DELETE FROM user_access_log WHERE member = $1", self.user.id)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
 }
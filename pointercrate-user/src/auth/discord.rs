@@ -0,0 +1,165 @@
+//! Module for logging in via Discord's OAuth2 flow
+//!
+//! A Discord account can be linked to at most one pointercrate account (via `members.discord_id`).
+//! The `state` parameter passed through the flow is a short-lived token signed by us. It records
+//! whether the flow was started by a logged in user wanting to link their Discord account, and is
+//! additionally bound to the browser that started the flow via a cookie.
+//!
+//! Users with two-factor authentication enabled are not logged in right away. Instead, they are
+//! handed a short-lived challenge token, which needs to be redeemed together with a valid
+//! two-factor authentication code.
+
+use crate::{
+    auth::{token, unix_timestamp, AuthenticatedUser},
+    error::{Result, UserError},
+    User,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+/// How long (in seconds) a user has to complete the OAuth2 flow
+const STATE_LIFETIME: u64 = 600;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DiscordStateClaims {
+    exp: u64,
+
+    /// The id of the user the Discord account should be linked to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link: Option<i32>,
+}
+
+/// Generates the `state` parameter for a new OAuth2 flow. If `link` is set, the Discord account
+/// will be linked to the given user instead of being used for logging in.
+pub fn discord_oauth_state(link: Option<&AuthenticatedUser>, signing_keys: &[Vec<u8>]) -> String {
+    token::sign(
+        &DiscordStateClaims {
+            exp: unix_timestamp() + STATE_LIFETIME,
+            link: link.map(|user| user.inner().id),
+        },
+        signing_keys,
+    )
+}
+
+/// Verifies the `state` parameter of an OAuth2 flow, returning the id of the user the Discord
+/// account should be linked to, if any
+pub fn verify_discord_oauth_state(state: &str, signing_keys: &[Vec<u8>]) -> Result<Option<i32>> {
+    let (claims, _) = token::verify::<DiscordStateClaims>(state, signing_keys, true)?;
+
+    Ok(claims.link)
+}
+
+/// How long (in seconds) a user has to provide their two-factor authentication code after
+/// authorizing via Discord
+const CHALLENGE_LIFETIME: u64 = 300;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DiscordTotpClaims {
+    exp: u64,
+
+    /// The id of the user that authorized via Discord, but still needs to provide a code
+    totp_challenge: i32,
+}
+
+/// Generates a token proving that the given user authorized via Discord, which can be redeemed
+/// for a session once they provide their two-factor authentication code
+pub fn discord_totp_challenge(user: &AuthenticatedUser, signing_keys: &[Vec<u8>]) -> String {
+    token::sign(
+        &DiscordTotpClaims {
+            exp: unix_timestamp() + CHALLENGE_LIFETIME,
+            totp_challenge: user.inner().id,
+        },
+        signing_keys,
+    )
+}
+
+/// Verifies a token generated by [`discord_totp_challenge`], returning the id of the user it was
+/// generated for
+pub fn verify_discord_totp_challenge(challenge: &str, signing_keys: &[Vec<u8>]) -> Result<i32> {
+    let (claims, _) = token::verify::<DiscordTotpClaims>(challenge, signing_keys, true)?;
+
+    Ok(claims.totp_challenge)
+}
+
+impl AuthenticatedUser {
+    /// Gets the user the given Discord account is linked to, if any
+    pub async fn by_discord_id(discord_id: i64, connection: &mut PgConnection) -> Result<Option<AuthenticatedUser>> {
+        let row = sqlx::query!(
+            r#"SELECT member_id, members.name, permissions::integer, display_name, youtube_channel::text, password_hash FROM members WHERE discord_id = $1"#,
+            discord_id
+        )
+        .fetch_optional(connection)
+        .await?;
+
+        Ok(row.map(|row| {
+            AuthenticatedUser {
+                user: construct_from_row!(row),
+                password_hash: row.password_hash,
+            }
+        }))
+    }
+
+    /// Links the given Discord account to this user, replacing any previously linked Discord
+    /// account
+    pub async fn link_discord(&self, discord_id: i64, connection: &mut PgConnection) -> Result<()> {
+        if let Some(linked) = Self::by_discord_id(discord_id, &mut *connection).await? {
+            if linked.inner().id != self.user.id {
+                warn!(
+                    "User {} tried to link Discord account {}, which is already linked to {}",
+                    self.user, discord_id, linked.user
+                );
+
+                return Err(UserError::DiscordAccountLinked)
+            }
+        }
+
+        info!("Linking Discord account {} to user {}", discord_id, self.user);
+
+        sqlx::query!("UPDATE members SET discord_id = $1 WHERE member_id = $2", discord_id, self.user.id)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Creates a new account for the given Discord account
+    ///
+    /// The account is named after the Discord user, unless that name is invalid or already taken,
+    /// in which case it is named `discord-<discord id>`. It has no usable password, so it can
+    /// only be logged into via Discord until a password is set via a password reset.
+    pub async fn register_discord(username: &str, discord_id: i64, connection: &mut PgConnection) -> Result<AuthenticatedUser> {
+        let name = match User::validate_name(username) {
+            Ok(()) =>
+                match User::by_name(username, &mut *connection).await {
+                    Err(UserError::UserNotFoundName { .. }) => username.to_string(),
+                    Ok(_) => format!("discord-{}", discord_id),
+                    Err(err) => return Err(err),
+                },
+            Err(_) => format!("discord-{}", discord_id),
+        };
+
+        info!("Registering new user {} for Discord account {}", name, discord_id);
+
+        // See `anonymize` for why this needs to be a valid bcrypt hash
+        let row = sqlx::query!(
+            "INSERT INTO members (name, password_hash, discord_id) VALUES ($1, crypt(encode(gen_random_bytes(32), 'hex'), gen_salt('bf', \
+             12)), $2) RETURNING member_id, password_hash",
+            name,
+            discord_id
+        )
+        .fetch_one(connection)
+        .await?;
+
+        Ok(AuthenticatedUser {
+            user: User {
+                id: row.member_id,
+                name,
+                permissions: 0,
+                display_name: None,
+                youtube_channel: None,
+            },
+            password_hash: row.password_hash,
+        })
+    }
+}
@@ -0,0 +1,152 @@
+//! Module for email address verification and password resets
+//!
+//! Both work via signed, time-limited tokens that are sent to the user's email address. Email
+//! verification tokens are bound to the address they were issued for. Password reset tokens carry
+//! a fingerprint of the user's password salt, just like access tokens, so they become invalid as
+//! soon as the password changes.
+
+use crate::{
+    auth::{token, AuthenticatedUser},
+    error::{Result, UserError},
+};
+use log::{info, warn};
+use pointercrate_core::error::CoreError;
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgConnection};
+
+/// How long (in seconds) an email verification token stays valid
+const VERIFICATION_TOKEN_LIFETIME: u64 = 24 * 3600;
+
+/// How long (in seconds) a password reset token stays valid
+const RESET_TOKEN_LIFETIME: u64 = 3600;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct VerificationClaims {
+    id: i32,
+    email: String,
+    exp: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ResetClaims {
+    id: i32,
+    exp: u64,
+    reset: bool,
+    #[serde(rename = "fpr")]
+    fingerprint: String,
+}
+
+impl AuthenticatedUser {
+    pub fn validate_email(email: &str) -> Result<()> {
+        match email.split_once('@') {
+            Some((local, domain))
+                if !local.is_empty() && domain.contains('.') && email.len() <= 254 && !email.contains(char::is_whitespace) =>
+                Ok(()),
+            _ => Err(UserError::InvalidEmail),
+        }
+    }
+
+    /// Sets this user's email address, which will be unverified until
+    /// [`AuthenticatedUser::verify_email`] is called with a token generated via
+    /// [`AuthenticatedUser::generate_verification_token`]
+    ///
+    /// Does not check whether the address is already in use by a different account, as that would
+    /// allow anyone to find out which addresses are registered. Uniqueness is only enforced once
+    /// the address gets verified.
+    pub async fn set_email(&self, email: &str, connection: &mut PgConnection) -> Result<()> {
+        Self::validate_email(email)?;
+
+        info!("Setting email address of user {}", self.inner());
+
+        sqlx::query!(
+            "UPDATE members SET email = $1::text, email_verified = FALSE WHERE member_id = $2",
+            email,
+            self.inner().id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    pub fn generate_verification_token(&self, email: &str, signing_keys: &[Vec<u8>]) -> String {
+        let claims = VerificationClaims {
+            id: self.user.id,
+            email: email.to_string(),
+            exp: super::unix_timestamp() + VERIFICATION_TOKEN_LIFETIME,
+        };
+
+        token::sign(&claims, signing_keys)
+    }
+
+    /// Marks the email address the given token was issued for as verified, provided it is still the
+    /// address of the user the token was issued to and it has not been verified for a different
+    /// account in the meantime
+    pub async fn verify_email(verification_token: &str, signing_keys: &[Vec<u8>], connection: &mut PgConnection) -> Result<()> {
+        let (claims, _) = token::verify::<VerificationClaims>(verification_token, signing_keys, true)?;
+
+        let result = sqlx::query!(
+            "UPDATE members SET email_verified = TRUE WHERE member_id = $1 AND email = $2::text",
+            claims.id,
+            claims.email
+        )
+        .execute(connection)
+        .await
+        .map_err(|err| {
+            match err {
+                Error::Database(ref database_error) if database_error.constraint() == Some("members_verified_email_key") =>
+                    UserError::EmailTaken,
+                _ => err.into(),
+            }
+        })?;
+
+        // The user changed their address after the token was issued
+        if result.rows_affected() == 0 {
+            return Err(CoreError::Unauthorized.into())
+        }
+
+        info!("Verified email address of user with id {}", claims.id);
+
+        Ok(())
+    }
+
+    /// Gets the user with the given email address, provided the address has been verified
+    pub async fn by_verified_email(email: &str, connection: &mut PgConnection) -> Result<Option<AuthenticatedUser>> {
+        let row = sqlx::query!("SELECT member_id FROM members WHERE email = $1::text AND email_verified", email)
+            .fetch_optional(&mut *connection)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(AuthenticatedUser::by_id(row.member_id, connection).await?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn generate_reset_token(&self, signing_keys: &[Vec<u8>]) -> String {
+        let claims = ResetClaims {
+            id: self.user.id,
+            exp: super::unix_timestamp() + RESET_TOKEN_LIFETIME,
+            reset: true,
+            fingerprint: self.salt_fingerprint(token::active_key(signing_keys)),
+        };
+
+        token::sign(&claims, signing_keys)
+    }
+
+    /// Sets a new password for the user the given password reset token was issued to
+    pub async fn reset_password(
+        reset_token: &str, password: String, signing_keys: &[Vec<u8>], connection: &mut PgConnection,
+    ) -> Result<AuthenticatedUser> {
+        let (claims, key) = token::verify::<ResetClaims>(reset_token, signing_keys, true)?;
+
+        let mut user = AuthenticatedUser::by_id(claims.id, &mut *connection).await?;
+
+        user.verify_salt_fingerprint(&claims.fingerprint, key)?;
+
+        warn!("Resetting password of user {}", user.inner());
+
+        user.set_password(password, connection).await?;
+
+        Ok(user)
+    }
+}
@@ -1,9 +1,9 @@
 use crate::{
-    auth::{AuthenticatedUser, Claims},
+    auth::{token, AuthenticatedUser, Claims, Session},
     error::Result,
     User,
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use pointercrate_core::error::CoreError;
 use sqlx::{Error, PgConnection};
 
@@ -12,37 +12,58 @@ impl AuthenticatedUser {
         info!("We are expected to perform basic authentication");
         debug!("Trying to authorize user {}", username);
 
-        Self::by_name(username, connection).await?.verify_password(password)
+        let mut user = Self::by_name(username, connection).await?.verify_password(password)?;
+
+        user.upgrade_password_hash(password, connection).await?;
+
+        Ok(user)
     }
 
+    /// Authenticates the user the given access token was issued for, returning them together with
+    /// the administrator impersonating them, if the token is an impersonation token
     pub async fn token_auth(
-        access_token: &str, csrf_token: Option<&str>, application_secret: &[u8], connection: &mut PgConnection,
-    ) -> Result<AuthenticatedUser> {
+        access_token: &str, csrf_token: Option<&str>, signing_keys: &[Vec<u8>], connection: &mut PgConnection,
+    ) -> Result<(AuthenticatedUser, Option<i32>)> {
         info!("We are expected to perform token authentication");
 
-        // Well this is reassuring. Also we directly deconstruct it and only save the ID
-        // so we don't accidentally use unsafe values later on
-        let Claims { id, .. } = jsonwebtoken::dangerous_insecure_decode::<Claims>(&access_token)
-            .map_err(|_| CoreError::Unauthorized)?
-            .claims;
+        let (
+            Claims {
+                id,
+                session,
+                fingerprint,
+                impersonated_by,
+                ..
+            },
+            key,
+        ) = token::verify::<Claims>(access_token, signing_keys, true)?;
 
         debug!("The token identified the user with id {}, validating...", id);
 
-        // Note that at this point we haven't validated the access token OR the csrf token yet.
-        // However, the key they are signed with encompasses the password salt for the user they supposedly
-        // identify, so we need to retrieve that.
-        let user = Self::by_id(id, connection)
-            .await?
-            .validate_token(&access_token, application_secret)?;
+        let user = Self::by_id(id, connection).await?;
+
+        // Tokens are invalidated by password changes
+        user.verify_salt_fingerprint(&fingerprint, key)?;
 
         if let Some(ref csrf_token) = csrf_token {
-            user.validate_csrf_token(csrf_token, application_secret)?
+            user.validate_csrf_token(csrf_token, signing_keys)?
         }
 
-        Ok(user)
+        if let Some(session) = session {
+            if !Session::is_active(session, connection).await? {
+                warn!("Access token of user {} belongs to revoked or expired session {}", id, session);
+
+                return Err(CoreError::Unauthorized.into())
+            }
+        }
+
+        if let Some(impersonated_by) = impersonated_by {
+            warn!("User {} is being impersonated by user {}", user.inner(), impersonated_by);
+        }
+
+        Ok((user, impersonated_by))
     }
 
-    async fn by_id(id: i32, connection: &mut PgConnection) -> Result<AuthenticatedUser> {
+    pub async fn by_id(id: i32, connection: &mut PgConnection) -> Result<AuthenticatedUser> {
         let row = sqlx::query!(
             r#"SELECT member_id, members.name, permissions::integer, display_name, youtube_channel::text, password_hash FROM members WHERE member_id = $1"#,
             id
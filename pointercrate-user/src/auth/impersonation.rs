@@ -0,0 +1,45 @@
+//! Module for keeping track of administrators impersonating users
+//!
+//! Changes made using an impersonation token are flagged in the audit log (see
+//! [`pointercrate_core::pool::impersonated_audit_connection`]), but not all modification logs know
+//! about impersonation, and read-only requests are not logged at all. Thus every issued
+//! impersonation token, and every request made using one, is additionally recorded in the
+//! `impersonation_log` table.
+
+use crate::{auth::AuthenticatedUser, error::Result, User};
+use log::warn;
+use sqlx::PgConnection;
+
+impl AuthenticatedUser {
+    /// Records that the given administrator was issued a token for impersonating this user
+    pub async fn log_impersonation(&self, administrator: &User, connection: &mut PgConnection) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO impersonation_log (administrator, member) VALUES ($1, $2)",
+            administrator.id,
+            self.inner().id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Records a request made by the given administrator while impersonating the given user
+pub async fn log_impersonated_request(user_id: i32, administrator_id: i32, request: &str, connection: &mut PgConnection) -> Result<()> {
+    warn!(
+        "User {} made request '{}' while impersonating user {}",
+        administrator_id, request, user_id
+    );
+
+    sqlx::query!(
+        "INSERT INTO impersonation_log (administrator, member, request) VALUES ($1, $2, $3)",
+        administrator_id,
+        user_id,
+        request
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
@@ -5,29 +5,65 @@
 //! * Deletion of own account
 //! * Modification of own account
 
-pub use self::{patch::PatchMe, post::Registration};
+pub use self::{
+    api_key::{ApiKey, ApiKeyScope, NewApiKey},
+    discord::{discord_oauth_state, discord_totp_challenge, verify_discord_oauth_state, verify_discord_totp_challenge},
+    impersonation::log_impersonated_request,
+    password::HashAlgorithm,
+    patch::PatchMe,
+    post::Registration,
+    session::Session,
+    totp::TotpEnrollment,
+};
 use crate::{
+    config,
     error::{Result, UserError},
     User,
 };
-use jsonwebtoken::{DecodingKey, EncodingKey};
 use log::{debug, warn};
 use pointercrate_core::error::CoreError;
 use serde::{Deserialize, Serialize};
 
+mod api_key;
 mod delete;
+mod discord;
+mod email;
 mod get;
+mod impersonation;
+mod password;
 mod patch;
 mod post;
+mod session;
+mod token;
+mod totp;
 
 pub struct AuthenticatedUser {
     user: User,
     password_hash: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Copy, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Claims {
     pub id: i32,
+
+    /// Expiry timestamp of the token. Every access token expires, tokens without an expiry are
+    /// rejected.
+    pub exp: u64,
+
+    /// The [`Session`] an access token was issued for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<i32>,
+
+    /// Fingerprint of the password salt of the user this token was issued for, see
+    /// [`AuthenticatedUser::salt_fingerprint`]
+    #[serde(rename = "fpr")]
+    pub fingerprint: String,
+
+    /// The id of the administrator this token was issued to, if it is an impersonation token (see
+    /// [`AuthenticatedUser::generate_impersonation_token`])
+    #[serde(rename = "imp", default, skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Copy, Clone)]
@@ -54,36 +90,40 @@ impl AuthenticatedUser {
         Ok(())
     }
 
-    fn jwt_secret(&self, application_secret: &[u8]) -> Vec<u8> {
-        let mut key: Vec<u8> = application_secret.into();
-        key.extend(self.password_salt());
-        key
-    }
-
-    pub fn generate_token(&self, application_secret: &[u8]) -> String {
-        jsonwebtoken::encode(
-            &jsonwebtoken::Header::default(),
-            &Claims { id: self.user.id },
-            &EncodingKey::from_secret(&self.jwt_secret(application_secret)),
+    /// Generates a short-lived access token for the given session
+    pub fn generate_access_token(&self, session: &Session, signing_keys: &[Vec<u8>]) -> String {
+        token::sign(
+            &Claims {
+                id: self.user.id,
+                exp: unix_timestamp() + config::access_token_lifetime(),
+                session: Some(session.id),
+                fingerprint: self.salt_fingerprint(token::active_key(signing_keys)),
+                impersonated_by: None,
+            },
+            signing_keys,
         )
-        .unwrap()
     }
 
-    pub fn validate_token(self, token: &str, application_secret: &[u8]) -> Result<Self> {
-        // TODO: maybe one day do something with this
-        let mut validation = jsonwebtoken::Validation::default();
-        validation.validate_exp = false;
-
-        jsonwebtoken::decode::<Claims>(token, &DecodingKey::from_secret(&self.jwt_secret(application_secret)), &validation)
-            .map_err(|err| {
-                warn!("Token validation FAILED for account {}: {}", self.user, err);
-
-                CoreError::Unauthorized.into()
-            })
-            .map(move |_| self)
+    /// Generates a short-lived access token acting as this user on behalf of the given
+    /// administrator. Changes made using it are attributed to this user, but flagged with the
+    /// administrator's id in the audit log. Issuing the token should be recorded via
+    /// [`AuthenticatedUser::log_impersonation`].
+    pub fn generate_impersonation_token(&self, impersonated_by: &User, signing_keys: &[Vec<u8>]) -> String {
+        warn!("User {} starts impersonating user {}", impersonated_by, self.user);
+
+        token::sign(
+            &Claims {
+                id: self.user.id,
+                exp: unix_timestamp() + config::impersonation_token_lifetime(),
+                session: None,
+                fingerprint: self.salt_fingerprint(token::active_key(signing_keys)),
+                impersonated_by: Some(impersonated_by.id),
+            },
+            signing_keys,
+        )
     }
 
-    pub fn generate_csrf_token(&self, application_secret: &[u8]) -> String {
+    pub fn generate_csrf_token(&self, signing_keys: &[Vec<u8>]) -> String {
         use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
         let start = SystemTime::now();
@@ -97,33 +137,28 @@ impl AuthenticatedUser {
             exp: (since_epoch + Duration::from_secs(3600)).as_secs(),
         };
 
-        jsonwebtoken::encode(
-            &jsonwebtoken::Header::default(),
-            &claim,
-            &EncodingKey::from_secret(application_secret),
-        )
-        .unwrap()
+        token::sign(&claim, signing_keys)
     }
 
-    pub fn validate_csrf_token(&self, token: &str, application_secret: &[u8]) -> Result<()> {
-        jsonwebtoken::decode::<CSRFClaims>(
-            token,
-            &DecodingKey::from_secret(application_secret),
-            &jsonwebtoken::Validation::default(),
-        )
-        .map_err(|err| {
-            warn!("Token validation FAILED for account {}: {}", self.user, err);
+    pub fn validate_csrf_token(&self, csrf_token: &str, signing_keys: &[Vec<u8>]) -> Result<()> {
+        let (claims, _) = token::verify::<CSRFClaims>(csrf_token, signing_keys, true)?;
+
+        if claims.id != self.user.id {
+            warn!("CSRF token of account {} was used by account {}", claims.id, self.user);
+
+            return Err(CoreError::Unauthorized.into())
+        }
 
-            CoreError::Unauthorized.into()
-        })
-        .map(|_| ())
+        Ok(())
     }
 
     fn password_salt(&self) -> Vec<u8> {
         let raw_parts: Vec<_> = self.password_hash.split('$').filter(|s| !s.is_empty()).collect();
 
-        match &raw_parts[..] {
-            [_, _, hash] => b64::decode(&hash[..22]),
+        match (self.hash_algorithm(), &raw_parts[..]) {
+            (Some(HashAlgorithm::Bcrypt), [_, _, hash]) => b64::decode(&hash[..22]),
+            // $argon2id$v=19$<parameters>$<salt>$<hash>, with the salt already being base64 encoded
+            (Some(HashAlgorithm::Argon2id), [_, _, _, salt, _]) => salt.as_bytes().to_vec(),
             _ => unreachable!(),
         }
     }
@@ -131,7 +166,13 @@ impl AuthenticatedUser {
     pub fn verify_password(self, password: &str) -> Result<Self> {
         debug!("Verifying a password!");
 
-        let valid = bcrypt::verify(&password, &self.password_hash).map_err(|err| {
+        let algorithm = self.hash_algorithm().ok_or_else(|| {
+            warn!("Password hash of account {} was created using an unknown algorithm", self.user);
+
+            UserError::Core(CoreError::Unauthorized)
+        })?;
+
+        let valid = algorithm.verify(password, &self.password_hash).map_err(|err| {
             warn!("Password verification FAILED for account {}: {}", self.user, err);
 
             UserError::Core(CoreError::Unauthorized)
@@ -149,6 +190,12 @@ impl AuthenticatedUser {
     }
 }
 
+fn unix_timestamp() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("time went backwards").as_secs()
+}
+
 // This code is copied from https://github.com/Keats/rust-bcrypt/blob/master/src/b64.rs
 // with slight modifications (removal of `encode` and error handling)
 mod b64 {
@@ -0,0 +1,106 @@
+//! Module for hashing and verifying passwords
+//!
+//! New passwords are hashed using [`HashAlgorithm::CURRENT`]. Hashes created using any other
+//! algorithm are still accepted, but are replaced by a hash using the current algorithm the next
+//! time their owner logs in (see [`AuthenticatedUser::basic_auth`]). Moving to a new algorithm
+//! only requires adding a variant here.
+
+use crate::{auth::AuthenticatedUser, error::Result};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use log::info;
+use rand_core::OsRng;
+use sqlx::PgConnection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// The algorithm all accounts created before Argon2id support was added use
+    Bcrypt,
+    Argon2id,
+}
+
+impl HashAlgorithm {
+    /// The algorithm all new hashes are created with
+    pub const CURRENT: HashAlgorithm = HashAlgorithm::Argon2id;
+
+    /// Determines the algorithm the given hash was created with from its prefix
+    pub fn of(hash: &str) -> Option<HashAlgorithm> {
+        if hash.starts_with("$argon2id$") {
+            Some(HashAlgorithm::Argon2id)
+        } else if hash.starts_with("$2a$") || hash.starts_with("$2b$") || hash.starts_with("$2y$") {
+            Some(HashAlgorithm::Bcrypt)
+        } else {
+            None
+        }
+    }
+
+    pub fn hash(self, password: &str) -> String {
+        // Unwrapping is fine in both cases, since the only possible errors are invalid parameters
+        // (we use the defaults) or bugs in the library itself, in which case we definitely want to
+        // panic since we're dealing with passwords
+        match self {
+            HashAlgorithm::Bcrypt => bcrypt::hash(password, bcrypt::DEFAULT_COST).unwrap(),
+            HashAlgorithm::Argon2id =>
+                Argon2::default()
+                    .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+                    .unwrap()
+                    .to_string(),
+        }
+    }
+
+    /// Checks whether the given password matches the given hash, which has to have been created
+    /// using this algorithm
+    pub fn verify(self, password: &str, hash: &str) -> std::result::Result<bool, String> {
+        match self {
+            HashAlgorithm::Bcrypt => bcrypt::verify(password, hash).map_err(|err| err.to_string()),
+            HashAlgorithm::Argon2id => {
+                let hash = PasswordHash::new(hash).map_err(|err| err.to_string())?;
+
+                Ok(Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            },
+        }
+    }
+}
+
+/// Hashes the given password using [`HashAlgorithm::CURRENT`]
+pub(crate) fn hash_password(password: &str) -> String {
+    HashAlgorithm::CURRENT.hash(password)
+}
+
+impl AuthenticatedUser {
+    /// The algorithm this user's password hash was created with
+    pub fn hash_algorithm(&self) -> Option<HashAlgorithm> {
+        HashAlgorithm::of(&self.password_hash)
+    }
+
+    /// Replaces this user's password hash by one created with [`HashAlgorithm::CURRENT`], if it was
+    /// created with any other algorithm
+    ///
+    /// The given password needs to have been verified against the current hash already.
+    pub(crate) async fn upgrade_password_hash(&mut self, password: &str, connection: &mut PgConnection) -> Result<()> {
+        if self.hash_algorithm() == Some(HashAlgorithm::CURRENT) {
+            return Ok(())
+        }
+
+        info!(
+            "Upgrading password hash of user {} from {:?} to {:?}",
+            self.user,
+            self.hash_algorithm(),
+            HashAlgorithm::CURRENT
+        );
+
+        self.password_hash = hash_password(password);
+
+        sqlx::query!(
+            "UPDATE members SET password_hash = $1 WHERE member_id = $2",
+            self.password_hash,
+            self.user.id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+}
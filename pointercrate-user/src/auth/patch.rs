@@ -1,4 +1,9 @@
-use crate::{auth::AuthenticatedUser, error::Result, patch::PatchUser};
+use crate::{
+    auth::{password::hash_password, AuthenticatedUser, Session},
+    error::Result,
+    notifications::{NotificationPreferences, PatchNotificationPreferences},
+    patch::PatchUser,
+};
 use log::info;
 use pointercrate_core::util::{non_nullable, nullable};
 use serde::Deserialize;
@@ -15,12 +20,23 @@ pub struct PatchMe {
 
     #[serde(default, deserialize_with = "nullable")]
     pub(super) youtube_channel: Option<Option<String>>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub(super) email: Option<String>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub(super) notifications: Option<PatchNotificationPreferences>,
 }
 
 impl PatchMe {
     pub fn changes_password(&self) -> bool {
         self.password.is_some()
     }
+
+    /// The new, yet to be verified, email address set by this patch
+    pub fn new_email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
 }
 
 // manual debug impl to ensure that the password field is never printed anywhere
@@ -29,6 +45,8 @@ impl Debug for PatchMe {
         f.debug_struct("PatchMe")
             .field("display_name", &self.display_name)
             .field("youtube_channel", &self.youtube_channel)
+            .field("email", &self.email)
+            .field("notifications", &self.notifications)
             .finish()
     }
 }
@@ -39,6 +57,17 @@ impl AuthenticatedUser {
             self.set_password(password, connection).await?;
         }
 
+        if let Some(ref email) = patch.email {
+            self.set_email(email, connection).await?;
+        }
+
+        if let Some(notifications) = patch.notifications {
+            NotificationPreferences::of_user(self.user.id, connection)
+                .await?
+                .apply_patch(self.user.id, notifications, connection)
+                .await?;
+        }
+
         self.user = self
             .user
             .apply_patch(
@@ -59,12 +88,11 @@ impl AuthenticatedUser {
 
         info!("Setting new password for user {}", self.inner());
 
-        // it is safe to unwrap here because the only errors that can happen are
-        // 'BcryptError::CostNotAllowed' (won't happen because DEFAULT_COST is obviously allowed)
-        // or errors that happen during internally parsing the hash the library itself just
-        // generated. Obviously, an error there is a bug in the library, so we definitely wanna panic since
-        // we're dealing with passwords
-        self.password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).unwrap();
+        self.password_hash = hash_password(&password);
+
+        // Changing the password already invalidates all access tokens, as they are signed using the
+        // password salt. Refresh tokens need to be revoked explicitly.
+        Session::revoke_all(self.user.id, &mut *connection).await?;
 
         sqlx::query!(
             "UPDATE members SET password_hash = $1 WHERE member_id = $2",
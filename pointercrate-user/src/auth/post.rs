@@ -1,5 +1,5 @@
 use crate::{
-    auth::{patch::PatchMe, AuthenticatedUser},
+    auth::{password::hash_password, patch::PatchMe, AuthenticatedUser},
     error::{Result, UserError},
     User,
 };
@@ -11,6 +11,11 @@ use sqlx::PgConnection;
 pub struct Registration {
     pub name: String,
     pub password: String,
+
+    /// Optional email address, which needs to be verified before it can be used for password
+    /// resets
+    #[serde(default)]
+    pub email: Option<String>,
 }
 
 impl AuthenticatedUser {
@@ -22,14 +27,14 @@ impl AuthenticatedUser {
         match User::by_name(&registration.name, connection).await {
             Ok(_) => Err(UserError::NameTaken),
             Err(UserError::UserNotFoundName { .. }) => {
-                let hash = bcrypt::hash(&registration.password, bcrypt::DEFAULT_COST).unwrap();
+                let hash = hash_password(&registration.password);
 
                 let id = sqlx::query!(
                     "INSERT INTO members (name, password_hash) VALUES ($1, $2) RETURNING member_id",
                     registration.name,
                     hash
                 )
-                .fetch_one(connection)
+                .fetch_one(&mut *connection)
                 .await?
                 .member_id;
 
@@ -39,7 +44,7 @@ impl AuthenticatedUser {
                     ratelimits.check(RatelimitScope::Registration)?;
                 }*/
 
-                Ok(AuthenticatedUser {
+                let user = AuthenticatedUser {
                     user: User {
                         id,
                         name: registration.name,
@@ -48,7 +53,13 @@ impl AuthenticatedUser {
                         youtube_channel: None,
                     },
                     password_hash: hash,
-                })
+                };
+
+                if let Some(ref email) = registration.email {
+                    user.set_email(email, connection).await?;
+                }
+
+                Ok(user)
             },
             Err(err) => Err(err),
         }
@@ -59,6 +70,8 @@ impl AuthenticatedUser {
             password: Some(password.to_string()),
             display_name: None,
             youtube_channel: None,
+            email: None,
+            notifications: None,
         };
 
         warn!("Invalidating all access tokens for user {}", self.inner());
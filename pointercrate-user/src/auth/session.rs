@@ -0,0 +1,249 @@
+//! Module for refresh token based sessions
+//!
+//! Logging in via the API starts a new session, which hands out a long-lived refresh token. The
+//! refresh token can be exchanged for short-lived access tokens (see
+//! [`AuthenticatedUser::generate_access_token`]) via `POST /api/v1/auth/refresh/`. Each exchange
+//! rotates the refresh token, so a refresh token can only ever be used once. The only exception is
+//! the grace period after a rotation (see [`config::refresh_token_grace_period`]), during which the
+//! previous token can still be exchanged for access tokens, so that concurrent requests all
+//! refreshing the same session do not log the client out.
+//!
+//! Only a hash of refresh tokens is stored. Revoking a session also invalidates all access tokens
+//! issued for it. Users can see their sessions together with the network and user agent each was
+//! started from, and revoke them individually. Only the network prefix of the IP address is stored,
+//! and even that is removed once the session is older than [`config::access_log_retention`] days.
+
+use crate::{
+    auth::AuthenticatedUser,
+    config,
+    error::{Result, UserError},
+};
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use pointercrate_core::error::CoreError;
+use serde::Serialize;
+use sqlx::{Error, PgConnection};
+use std::net::IpAddr;
+
+/// The maximal number of characters of a user agent stored alongside a session
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+/// The network an address belongs to, coarse enough to not identify a single connection, but still
+/// enough for users to recognize where they logged in from
+fn network_prefix(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+
+            format!("{}.{}.{}.0/24", octets[0], octets[1], octets[2])
+        },
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+
+            format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+        },
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+
+    /// The network (`/24` for IPv4, `/48` for IPv6) the session was started from, if the session is
+    /// recent enough for it to still be known
+    pub ip: Option<String>,
+
+    /// The user agent of the client that started the session
+    pub user_agent: Option<String>,
+}
+
+impl AuthenticatedUser {
+    /// Starts a new session for this user, returning it together with its refresh token
+    pub async fn start_session(&self, ip: IpAddr, user_agent: Option<&str>, connection: &mut PgConnection) -> Result<(Session, String)> {
+        info!("Starting new session for user {}", self.inner());
+
+        let user_agent = user_agent.map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect::<String>());
+
+        let row = sqlx::query!(
+            r#"WITH token AS (SELECT encode(gen_random_bytes(32), 'hex') AS token) INSERT INTO sessions (member, token_hash, expires_at, ip,
+             user_agent) SELECT $1, encode(sha256(convert_to(token, 'UTF8')), 'hex'), (NOW() AT TIME ZONE 'utc') + make_interval(secs => $2),
+             $3, $4 FROM token RETURNING id, created_at, last_used, expires_at, ip, user_agent, (SELECT token FROM token) AS "token!""#,
+            self.inner().id,
+            config::refresh_token_lifetime() as f64,
+            network_prefix(ip),
+            user_agent
+        )
+        .fetch_one(connection)
+        .await?;
+
+        Ok((
+            Session {
+                id: row.id,
+                created_at: row.created_at,
+                last_used: row.last_used,
+                expires_at: row.expires_at,
+                ip: row.ip,
+                user_agent: row.user_agent,
+            },
+            row.token,
+        ))
+    }
+}
+
+impl Session {
+    /// Exchanges the given refresh token for a new one, returning the user the session belongs to
+    ///
+    /// If the token was the session's current token up until at most
+    /// [`config::refresh_token_grace_period`] seconds ago, the session is not rotated again and no
+    /// new refresh token is returned. The client is expected to keep using the token handed out by
+    /// the concurrent refresh that rotated it.
+    ///
+    /// Fails with `401 UNAUTHORIZED` if the token does not belong to any session or the session has
+    /// expired
+    pub async fn refresh(refresh_token: &str, connection: &mut PgConnection) -> Result<(AuthenticatedUser, Session, Option<String>)> {
+        // Concurrent refreshes of the same session block on the row lock here. Once the first one
+        // commits, the others no longer match and fall back to the grace period below.
+        let row = sqlx::query!(
+            r#"WITH token AS (SELECT encode(gen_random_bytes(32), 'hex') AS token) UPDATE sessions SET previous_token_hash = token_hash,
+             rotated_at = (NOW() AT TIME ZONE 'utc'), token_hash = encode(sha256(convert_to(token.token, 'UTF8')), 'hex'), last_used = (NOW() AT TIME ZONE 'utc'), expires_at = (NOW() AT TIME ZONE 'utc') +
+             make_interval(secs => $2) FROM token WHERE token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex') AND expires_at > (NOW() AT
+             TIME ZONE 'utc') RETURNING id, member, created_at, last_used, expires_at, ip, user_agent, token.token AS "token!""#,
+            refresh_token,
+            config::refresh_token_lifetime() as f64
+        )
+        .fetch_one(&mut *connection)
+        .await;
+
+        let row = match row {
+            Err(Error::RowNotFound) => return Session::refresh_rotated(refresh_token, connection).await,
+            Err(err) => return Err(err.into()),
+            Ok(row) => row,
+        };
+
+        let user = AuthenticatedUser::by_id(row.member, connection).await?;
+
+        info!("Refreshed session {} of user {}", row.id, user.inner());
+
+        Ok((
+            user,
+            Session {
+                id: row.id,
+                created_at: row.created_at,
+                last_used: row.last_used,
+                expires_at: row.expires_at,
+                ip: row.ip,
+                user_agent: row.user_agent,
+            },
+            Some(row.token),
+        ))
+    }
+
+    /// Authenticates using a refresh token that was rotated at most
+    /// [`config::refresh_token_grace_period`] seconds ago, without rotating it again
+    async fn refresh_rotated(refresh_token: &str, connection: &mut PgConnection) -> Result<(AuthenticatedUser, Session, Option<String>)> {
+        let row = sqlx::query!(
+            "SELECT id, member, created_at, last_used, expires_at, ip, user_agent FROM sessions WHERE previous_token_hash = \
+             encode(sha256(convert_to($1, 'UTF8')), 'hex') AND rotated_at > (NOW() AT TIME ZONE 'utc') - make_interval(secs => $2) AND \
+             expires_at > (NOW() AT TIME ZONE 'utc')",
+            refresh_token,
+            config::refresh_token_grace_period() as f64
+        )
+        .fetch_one(&mut *connection)
+        .await;
+
+        let row = match row {
+            Err(Error::RowNotFound) => {
+                warn!("Attempt to refresh unknown or expired session");
+
+                return Err(CoreError::Unauthorized.into())
+            },
+            Err(err) => return Err(err.into()),
+            Ok(row) => row,
+        };
+
+        let user = AuthenticatedUser::by_id(row.member, connection).await?;
+
+        info!(
+            "Session {} of user {} was refreshed concurrently, accepting previous refresh token",
+            row.id,
+            user.inner()
+        );
+
+        Ok((
+            user,
+            Session {
+                id: row.id,
+                created_at: row.created_at,
+                last_used: row.last_used,
+                expires_at: row.expires_at,
+                ip: row.ip,
+                user_agent: row.user_agent,
+            },
+            None,
+        ))
+    }
+
+    /// Gets all sessions of the user with the given id that have not yet expired
+    pub async fn of_user(user_id: i32, connection: &mut PgConnection) -> Result<Vec<Session>> {
+        Ok(sqlx::query_as!(
+            Session,
+            "SELECT id, created_at, last_used, expires_at, ip, user_agent FROM sessions WHERE member = $1 AND expires_at > (NOW() AT TIME \
+             ZONE 'utc') ORDER BY last_used DESC",
+            user_id
+        )
+        .fetch_all(connection)
+        .await?)
+    }
+
+    /// Returns whether the session with the given id still exists and has not expired
+    pub async fn is_active(session_id: i32, connection: &mut PgConnection) -> Result<bool> {
+        Ok(sqlx::query!(
+            r#"SELECT EXISTS (SELECT 1 FROM sessions WHERE id = $1 AND expires_at > (NOW() AT TIME ZONE 'utc')) AS "active!""#,
+            session_id
+        )
+        .fetch_one(connection)
+        .await?
+        .active)
+    }
+
+    /// Revokes the session with the given id, which has to belong to the given user
+    pub async fn revoke(user_id: i32, session_id: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Revoking session {} of user {}", session_id, user_id);
+
+        let result = sqlx::query!("DELETE FROM sessions WHERE id = $1 AND member = $2", session_id, user_id)
+            .execute(connection)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserError::SessionNotFound { session_id })
+        }
+
+        Ok(())
+    }
+
+    /// Forgets where sessions older than [`config::access_log_retention`] days were started from.
+    /// Meant to be called periodically.
+    pub async fn purge_addresses(connection: &mut PgConnection) -> Result<u64> {
+        Ok(sqlx::query!(
+            "UPDATE sessions SET ip = NULL WHERE ip IS NOT NULL AND created_at < (NOW() AT TIME ZONE 'utc') - make_interval(days => $1)",
+            config::access_log_retention()
+        )
+        .execute(connection)
+        .await?
+        .rows_affected())
+    }
+
+    /// Revokes all sessions of the given user
+    pub async fn revoke_all(user_id: i32, connection: &mut PgConnection) -> Result<()> {
+        info!("Revoking all sessions of user {}", user_id);
+
+        sqlx::query!("DELETE FROM sessions WHERE member = $1", user_id)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+}
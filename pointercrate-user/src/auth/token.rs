@@ -0,0 +1,87 @@
+//! Module for signing and verifying the JSON Web Tokens handed out by pointercrate
+//!
+//! Tokens are signed via HS256 with the first of the configured signing keys (see
+//! [`pointercrate_core::config::signing_keys`]). All other keys are only accepted for verification,
+//! which allows rotating keys without invalidating all tokens at once: Prepend the new key, and
+//! remove the old one once the tokens signed with it are no longer needed.
+//!
+//! Tokens that have to become invalid once their user changes their password (access and password
+//! reset tokens) additionally carry a fingerprint of the user's password salt, keyed with the key
+//! the token was signed with (see [`AuthenticatedUser::salt_fingerprint`]).
+
+use crate::{auth::AuthenticatedUser, error::Result};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use log::warn;
+use pointercrate_core::error::CoreError;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The only algorithm tokens are ever signed with
+const ALGORITHM: Algorithm = Algorithm::HS256;
+
+/// Signs the given claims with the currently active signing key
+pub(crate) fn sign<C: Serialize>(claims: &C, signing_keys: &[Vec<u8>]) -> String {
+    jsonwebtoken::encode(&Header::new(ALGORITHM), claims, &EncodingKey::from_secret(active_key(signing_keys))).unwrap()
+}
+
+/// Verifies the signature of the given token against all signing keys, returning its claims
+/// together with the key that successfully verified it.
+///
+/// Tokens whose header specifies any algorithm other than HS256 are rejected before their
+/// signature is even looked at, so that a token can never make us verify it in a way we did not
+/// intend (e.g. using an asymmetric algorithm with a public key as HMAC secret, or no signature at
+/// all).
+pub(crate) fn verify<'k, C: DeserializeOwned>(token: &str, signing_keys: &'k [Vec<u8>], validate_exp: bool) -> Result<(C, &'k [u8])> {
+    let header = jsonwebtoken::decode_header(token).map_err(|_| CoreError::Unauthorized)?;
+
+    if header.alg != ALGORITHM {
+        warn!("Rejecting token signed with unexpected algorithm {:?}", header.alg);
+
+        return Err(CoreError::Unauthorized.into())
+    }
+
+    let validation = Validation {
+        algorithms: vec![ALGORITHM],
+        validate_exp,
+        ..Validation::default()
+    };
+
+    for key in signing_keys {
+        match jsonwebtoken::decode::<C>(token, &DecodingKey::from_secret(key), &validation) {
+            Ok(data) => return Ok((data.claims, key)),
+            Err(err) if matches!(err.kind(), jsonwebtoken::errors::ErrorKind::InvalidSignature) => continue,
+            Err(err) => {
+                warn!("Token validation FAILED: {}", err);
+
+                return Err(CoreError::Unauthorized.into())
+            },
+        }
+    }
+
+    warn!("Token validation FAILED: Not signed by any known key");
+
+    Err(CoreError::Unauthorized.into())
+}
+
+pub(crate) fn active_key(signing_keys: &[Vec<u8>]) -> &[u8] {
+    signing_keys.first().expect("No signing keys configured")
+}
+
+impl AuthenticatedUser {
+    /// Keyed fingerprint of this user's password salt. Changes whenever the user changes their
+    /// password.
+    pub(crate) fn salt_fingerprint(&self, key: &[u8]) -> String {
+        jsonwebtoken::crypto::sign(&base64::encode(self.password_salt()), &EncodingKey::from_secret(key), ALGORITHM).unwrap()
+    }
+
+    /// Checks whether the given fingerprint (as taken from a verified token) matches this user's
+    /// current password salt
+    pub(crate) fn verify_salt_fingerprint(&self, fingerprint: &str, key: &[u8]) -> Result<()> {
+        if self.salt_fingerprint(key) != fingerprint {
+            warn!("Token of account {} was issued for a different password", self.user);
+
+            return Err(CoreError::Unauthorized.into())
+        }
+
+        Ok(())
+    }
+}
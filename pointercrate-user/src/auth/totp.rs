@@ -0,0 +1,253 @@
+//! Module for TOTP based two-factor authentication
+//!
+//! Enrolling generates a new secret, which only takes effect once it has been confirmed by
+//! providing a valid code. From then on, every basic authentication needs to provide the current
+//! code, either via the `X-TOTP-Code` header or, when logging in to the website, via the login
+//! form.
+//!
+//! Secrets are encrypted (AES-256-GCM, with a key derived from the application secret) before being
+//! stored, so neither the secrets nor the key ever reach the database in plain text. To prevent a
+//! code from being used twice, the time step of the last accepted code is stored, and only codes of
+//! later time steps are accepted afterwards.
+
+use crate::{
+    auth::AuthenticatedUser,
+    error::{Result, UserError},
+};
+use log::{info, warn};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    constant_time::verify_slices_are_equal,
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use sqlx::PgConnection;
+use totp_lite::{totp_custom, Sha1};
+
+/// Length of a TOTP time step, in seconds
+const STEP: u64 = 30;
+
+/// How many time steps a code may be off, to account for clock drift
+const SKEW: u64 = 1;
+
+/// Length of a TOTP secret, in bytes
+const SECRET_LEN: usize = 20;
+
+/// A newly generated TOTP secret, which still needs to be confirmed
+pub struct TotpEnrollment {
+    /// The secret, base32 encoded
+    pub secret: String,
+
+    /// `otpauth://` URI for importing the secret into an authenticator app
+    pub provisioning_uri: String,
+}
+
+/// The key TOTP secrets are encrypted with before being stored in the database
+fn encryption_key(application_secret: &[u8]) -> LessSafeKey {
+    let derived = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, application_secret), b"pointercrate totp secret");
+
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, derived.as_ref()).expect("HMAC-SHA256 output is a valid AES-256 key"))
+}
+
+/// Encrypts the given secret, returning the nonce followed by the ciphertext
+fn seal(secret: &[u8], application_secret: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_LEN];
+
+    SystemRandom::new().fill(&mut nonce).expect("Failed to generate nonce");
+
+    let mut sealed = secret.to_vec();
+
+    encryption_key(application_secret)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+        .expect("Failed to encrypt TOTP secret");
+
+    let mut stored = nonce.to_vec();
+    stored.extend(sealed);
+    stored
+}
+
+/// Decrypts a secret encrypted by [`seal`]. Returns `None` if it was not encrypted with the
+/// current application secret
+fn open(stored: &[u8], application_secret: &[u8]) -> Option<Vec<u8>> {
+    if stored.len() < NONCE_LEN {
+        return None
+    }
+
+    let (nonce, sealed) = stored.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut sealed = sealed.to_vec();
+
+    encryption_key(application_secret)
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .ok()
+        .map(|secret| secret.to_vec())
+}
+
+impl AuthenticatedUser {
+    /// Generates a new TOTP secret for this user, replacing any previous one
+    ///
+    /// Two-factor authentication is disabled until the new secret has been confirmed via
+    /// [`AuthenticatedUser::confirm_totp`]
+    pub async fn enroll_totp(&self, application_secret: &[u8], connection: &mut PgConnection) -> Result<TotpEnrollment> {
+        info!("Enrolling user {} for two-factor authentication", self.inner());
+
+        let mut secret = [0u8; SECRET_LEN];
+
+        SystemRandom::new().fill(&mut secret).expect("Failed to generate TOTP secret");
+
+        sqlx::query!(
+            "UPDATE members SET totp_secret = $2, totp_enabled = FALSE, totp_last_step = NULL WHERE member_id = $1",
+            self.inner().id,
+            seal(&secret, application_secret)
+        )
+        .execute(connection)
+        .await?;
+
+        let secret = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &secret);
+
+        Ok(TotpEnrollment {
+            provisioning_uri: format!(
+                "otpauth://totp/pointercrate:{}?secret={}&issuer=pointercrate",
+                url::form_urlencoded::byte_serialize(self.inner().name.as_bytes()).collect::<String>(),
+                secret
+            ),
+            secret,
+        })
+    }
+
+    /// Enables two-factor authentication, given a valid code for the secret generated by
+    /// [`AuthenticatedUser::enroll_totp`]
+    pub async fn confirm_totp(&self, code: &str, application_secret: &[u8], connection: &mut PgConnection) -> Result<()> {
+        let step = match self.totp_secret(application_secret, &mut *connection).await? {
+            Some(secret) => matching_step(&secret.secret, code, secret.last_step),
+            None => None,
+        };
+
+        let step = step.ok_or(UserError::InvalidTotpCode)?;
+
+        info!("Enabling two-factor authentication for user {}", self.inner());
+
+        sqlx::query!(
+            "UPDATE members SET totp_enabled = TRUE, totp_last_step = $2 WHERE member_id = $1",
+            self.inner().id,
+            step
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn disable_totp(&self, connection: &mut PgConnection) -> Result<()> {
+        warn!("Disabling two-factor authentication for user {}", self.inner());
+
+        sqlx::query!(
+            "UPDATE members SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL WHERE member_id = $1",
+            self.inner().id
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whether this user has two-factor authentication enabled
+    pub async fn totp_enabled(&self, connection: &mut PgConnection) -> Result<bool> {
+        Ok(
+            sqlx::query!("SELECT totp_enabled FROM members WHERE member_id = $1", self.inner().id)
+                .fetch_one(connection)
+                .await?
+                .totp_enabled,
+        )
+    }
+
+    /// Checks the given code against this user's TOTP secret, if they have two-factor
+    /// authentication enabled
+    ///
+    /// A code is only accepted once. Should be called inside a transaction, since otherwise two
+    /// concurrent requests could both use the same code.
+    pub async fn verify_totp(&self, code: Option<&str>, application_secret: &[u8], connection: &mut PgConnection) -> Result<()> {
+        let secret = match self.totp_secret(application_secret, &mut *connection).await? {
+            Some(secret) if secret.enabled => secret,
+            _ => return Ok(()),
+        };
+
+        let code = code.ok_or(UserError::TotpRequired)?;
+
+        match matching_step(&secret.secret, code, secret.last_step) {
+            Some(step) => {
+                sqlx::query!("UPDATE members SET totp_last_step = $2 WHERE member_id = $1", self.inner().id, step)
+                    .execute(connection)
+                    .await?;
+
+                Ok(())
+            },
+            None => {
+                warn!("Invalid two-factor authentication code for account {}", self.inner());
+
+                Err(UserError::InvalidTotpCode)
+            },
+        }
+    }
+
+    async fn totp_secret(&self, application_secret: &[u8], connection: &mut PgConnection) -> Result<Option<StoredSecret>> {
+        // Lock the row, so that concurrent verifications of the same code are serialized
+        let row = sqlx::query!(
+            "SELECT totp_secret, totp_enabled, totp_last_step FROM members WHERE member_id = $1 FOR UPDATE",
+            self.inner().id
+        )
+        .fetch_one(connection)
+        .await?;
+
+        let stored = match row.totp_secret {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+
+        match open(&stored, application_secret) {
+            Some(secret) =>
+                Ok(Some(StoredSecret {
+                    secret,
+                    enabled: row.totp_enabled,
+                    last_step: row.totp_last_step,
+                })),
+            // An unconfirmed enrollment is simply discarded
+            None if !row.totp_enabled => Ok(None),
+            None => {
+                warn!(
+                    "TOTP secret of user {} cannot be decrypted, was the application secret changed?",
+                    self.inner()
+                );
+
+                // Failing closed: a user with two-factor authentication enabled cannot log in until
+                // an administrator resets it
+                Err(UserError::InvalidTotpCode)
+            },
+        }
+    }
+}
+
+struct StoredSecret {
+    secret: Vec<u8>,
+    enabled: bool,
+    last_step: Option<i64>,
+}
+
+/// Returns the time step the given code is valid for, if it is valid for any step within the
+/// allowed clock skew that is later than `last_step`
+fn matching_step(secret: &[u8], code: &str, last_step: Option<i64>) -> Option<i64> {
+    let now = super::unix_timestamp() / STEP;
+    let code = code.trim();
+    let mut matched = None;
+
+    // Check every candidate step, so that the time taken does not depend on which one matched
+    for step in now.saturating_sub(SKEW)..=now + SKEW {
+        let expected = totp_custom::<Sha1>(STEP, 6, secret, step * STEP);
+
+        if verify_slices_are_equal(expected.as_bytes(), code.as_bytes()).is_ok() {
+            matched = Some(step as i64);
+        }
+    }
+
+    matched.filter(|&step| last_step.map(|last| step > last).unwrap_or(true))
+}
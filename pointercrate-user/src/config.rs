@@ -0,0 +1,48 @@
+use pointercrate_core::{
+    config::{check, ConfigError},
+    util::from_env_or_default,
+};
+
+/// Checks the configuration of pointercrate-user, so that malformed values are reported at startup
+pub fn validate() -> Result<(), ConfigError> {
+    check::<u64>("ACCESS_TOKEN_LIFETIME")?;
+    check::<u64>("IMPERSONATION_TOKEN_LIFETIME")?;
+    check::<i64>("REFRESH_TOKEN_LIFETIME")?;
+    check::<u64>("REFRESH_TOKEN_GRACE_PERIOD")?;
+    check::<i32>("ACCESS_LOG_RETENTION")?;
+    check::<i64>("MAX_REGISTRATIONS_PER_DAY")
+}
+
+/// How long (in seconds) an access token issued for a session stays valid
+pub fn access_token_lifetime() -> u64 {
+    from_env_or_default("ACCESS_TOKEN_LIFETIME", 900)
+}
+
+/// How long (in seconds) an access token issued to an administrator for impersonating some user
+/// stays valid
+pub fn impersonation_token_lifetime() -> u64 {
+    from_env_or_default("IMPERSONATION_TOKEN_LIFETIME", 600)
+}
+
+/// How long (in seconds) a refresh token stays valid if it is not used
+pub fn refresh_token_lifetime() -> i64 {
+    from_env_or_default("REFRESH_TOKEN_LIFETIME", 30 * 24 * 3600)
+}
+
+/// For how long (in seconds) after a session was refreshed its previous refresh token is still
+/// accepted, so that concurrent refreshes do not log the client out
+pub fn refresh_token_grace_period() -> u64 {
+    from_env_or_default("REFRESH_TOKEN_GRACE_PERIOD", 30)
+}
+
+/// For how many days registrations and logins are kept in the access log, and the networks sessions
+/// were started from are remembered
+pub fn access_log_retention() -> i32 {
+    from_env_or_default("ACCESS_LOG_RETENTION", 90)
+}
+
+/// How many accounts can be registered from a single IP address within 24 hours, unless the address
+/// is exempt (see [`crate::RegistrationLimitExemption`])
+pub fn max_registrations_per_day() -> i64 {
+    from_env_or_default("MAX_REGISTRATIONS_PER_DAY", 3)
+}
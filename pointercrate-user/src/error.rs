@@ -5,7 +5,7 @@ use pointercrate_core::{
     permission::Permission,
 };
 use serde::Serialize;
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
 pub type Result<T> = std::result::Result<T, UserError>;
 
@@ -17,6 +17,29 @@ pub enum UserError {
     #[display(fmt = "Malformed channel URL")]
     MalformedChannelUrl,
 
+    /// `401 UNAUTHORIZED` error returned if a user with two-factor authentication enabled tries to
+    /// log in without providing a code
+    ///
+    /// Error Code `40101`
+    #[display(
+        fmt = "This account requires a two-factor authentication code. Provide it via the 'X-TOTP-Code' header, or via the login form \
+               when logging in to the website"
+    )]
+    TotpRequired,
+
+    /// `401 UNAUTHORIZED` error returned if the provided two-factor authentication code is wrong
+    ///
+    /// Error Code `40102`
+    #[display(fmt = "Invalid two-factor authentication code")]
+    InvalidTotpCode,
+
+    /// `401 UNAUTHORIZED` error returned if someone logs in via a Discord account that is not
+    /// linked to any account, and accounts are not automatically created for such logins
+    ///
+    /// Error Code `40103`
+    #[display(fmt = "This Discord account is not linked to any account. Log in and link it via your account settings first")]
+    DiscordAccountNotLinked,
+
     /// `403 FORBIDDEN` error returned when a user attempts to delete his own account via the admin
     /// panel
     ///
@@ -31,6 +54,20 @@ pub enum UserError {
     #[display(fmt = "You cannot modify your own account via this endpoint. Use PATCH /api/v1/auth/me/")]
     PatchSelf,
 
+    /// `403 FORBIDDEN` error returned when an administrator attempts to impersonate themselves or
+    /// another administrator
+    ///
+    /// Error Code `40312`
+    #[display(fmt = "You cannot impersonate yourself or other administrators")]
+    ImpersonationForbidden,
+
+    /// `403 FORBIDDEN` error returned when an impersonation token is used to access an endpoint
+    /// that manages the account's credentials or linked accounts
+    ///
+    /// Error Code `40313`
+    #[display(fmt = "This action cannot be performed while impersonating another user")]
+    NotAllowedWhileImpersonating,
+
     #[display(fmt = "You cannot assign the following permissions: {:?}", non_assignable)]
     PermissionNotAssignable { non_assignable: HashSet<Permission> },
 
@@ -40,12 +77,67 @@ pub enum UserError {
     #[display(fmt = "No user with name {} found", user_name)]
     UserNotFoundName { user_name: String },
 
+    /// `404 NOT FOUND` error returned if a user tries to revoke a session that does not exist or
+    /// does not belong to them
+    ///
+    /// Error Code `40401`
+    #[display(fmt = "No session with id {} found", session_id)]
+    SessionNotFound { session_id: i32 },
+
+    /// `404 NOT FOUND` error returned if a user tries to revoke an API key that does not exist or
+    /// does not belong to them
+    ///
+    /// Error Code `40401`
+    #[display(fmt = "No API key with id {} found", key_id)]
+    ApiKeyNotFound { key_id: i32 },
+
+    /// `404 NOT FOUND` variant
+    ///
+    /// Error Code `40401`
+    #[display(fmt = "No registration limit exemption with id {} found", exemption_id)]
+    RegistrationLimitExemptionNotFound { exemption_id: i32 },
+
+    /// `404 NOT FOUND` error returned if a user tries to modify a notification that does not exist
+    /// or does not belong to them
+    ///
+    /// Error Code `40401`
+    #[display(fmt = "No notification with id {} found", notification_id)]
+    NotificationNotFound { notification_id: i32 },
+
+    /// `403 FORBIDDEN` error returned if a request authenticated via an API key is not covered by
+    /// the key's scopes
+    ///
+    /// Error Code `40309`
+    #[display(fmt = "The API key used does not have the scope required for this request")]
+    InsufficientScope,
+
+    /// `403 FORBIDDEN` error returned if a user tries to create an API key with permissions they
+    /// do not have themselves
+    ///
+    /// Error Code `40310`
+    #[display(fmt = "API keys cannot have permissions their owner does not have")]
+    KeyPermissionsNotHeld,
+
     /// `409 CONFLICT` error returned if a user tries to register with a name that's already taken
     ///
     /// Error Code `40902`
     #[display(fmt = "The chosen username is already taken")]
     NameTaken,
 
+    /// `409 CONFLICT` error returned if a user tries to verify an email address that has already
+    /// been verified for a different account
+    ///
+    /// Error Code `40903`
+    #[display(fmt = "The chosen email address is already in use")]
+    EmailTaken,
+
+    /// `409 CONFLICT` error returned if a user tries to link a Discord account that is already
+    /// linked to a different account
+    ///
+    /// Error Code `40904`
+    #[display(fmt = "This Discord account is already linked to a different account")]
+    DiscordAccountLinked,
+
     /// `422 UNPROCESSABLE ENTITIY` variant returned if the username provided during registration
     /// is either shorter than 3 letters of contains trailing or leading whitespaces
     ///
@@ -65,6 +157,26 @@ pub enum UserError {
     /// Error Code `42226`
     #[display(fmt = "The given URL is no YouTube URL")]
     NotYouTube,
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a permission is referred to by a name that
+    /// does not belong to any permission
+    ///
+    /// Error Code `42244`
+    #[display(fmt = "No permission named '{}' exists", name)]
+    UnknownPermission { name: String },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if a role is referred to by a name that does
+    /// not belong to any role
+    ///
+    /// Error Code `42245`
+    #[display(fmt = "No role named '{}' exists", name)]
+    UnknownRole { name: String },
+
+    /// `422 UNPROCESSABLE ENTITY` variant returned if the provided email address is malformed
+    ///
+    /// Error Code `42243`
+    #[display(fmt = "Invalid email address")]
+    InvalidEmail,
 }
 
 impl std::error::Error for UserError {}
@@ -83,15 +195,38 @@ impl PointercrateError for UserError {
             Core(core) => core.error_code(),
 
             MalformedChannelUrl => 40001,
+            TotpRequired => 40101,
+            InvalidTotpCode => 40102,
+            DiscordAccountNotLinked => 40103,
             DeleteSelf => 40302,
             PatchSelf => 40303,
+            ImpersonationForbidden => 40312,
+            NotAllowedWhileImpersonating => 40313,
             PermissionNotAssignable { .. } => 40305,
             UserNotFound { .. } => 40401,
             UserNotFoundName { .. } => 40401,
+            SessionNotFound { .. } => 40401,
+            ApiKeyNotFound { .. } => 40401,
+            RegistrationLimitExemptionNotFound { .. } => 40401,
+            NotificationNotFound { .. } => 40401,
+            InsufficientScope => 40309,
+            KeyPermissionsNotHeld => 40310,
             NameTaken => 40902,
+            EmailTaken => 40903,
+            DiscordAccountLinked => 40904,
             InvalidUsername => 42202,
             InvalidPassword => 42204,
             NotYouTube => 42226,
+            InvalidEmail => 42243,
+            UnknownPermission { .. } => 42244,
+            UnknownRole { .. } => 42245,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            UserError::Core(core) => core.retry_after(),
+            _ => None,
         }
     }
 }
@@ -2,6 +2,8 @@ use crate::{
     error::{Result, UserError},
     User,
 };
+use chrono::NaiveDateTime;
+use futures::StreamExt;
 use sqlx::{Error, PgConnection};
 
 macro_rules! construct_from_row {
@@ -64,4 +66,42 @@ impl User {
             Ok(row) => Ok(construct_from_row!(row)),
         }
     }
+
+    /// Searches for users whose name or display name contains, or is similar to, the given term
+    ///
+    /// Results are ordered by similarity, best match first
+    pub async fn search(term: &str, limit: i64, connection: &mut PgConnection) -> Result<Vec<User>> {
+        let mut stream = sqlx::query!(
+            r#"SELECT member_id, members.name, permissions::integer, display_name, youtube_channel::text FROM members WHERE 
+             STRPOS(name, $1::CITEXT) > 0 OR STRPOS(display_name::CITEXT, $1::CITEXT) > 0 OR name::TEXT % $1 ORDER BY SIMILARITY(name::TEXT, $1) 
+             DESC, member_id LIMIT $2"#,
+            term,
+            limit
+        )
+        .fetch(connection);
+
+        let mut users = Vec::new();
+
+        while let Some(row) = stream.next().await {
+            let row = row?;
+
+            users.push(construct_from_row!(row))
+        }
+
+        Ok(users)
+    }
+}
+
+impl User {
+    /// Gets the point in time (in UTC) the user with the given id was last modified
+    pub async fn last_modified(id: i32, connection: &mut PgConnection) -> Result<NaiveDateTime> {
+        match sqlx::query!("SELECT last_modified FROM members WHERE member_id = $1", id)
+            .fetch_one(connection)
+            .await
+        {
+            Ok(row) => Ok(row.last_modified),
+            Err(Error::RowNotFound) => Err(UserError::UserNotFound { user_id: id }),
+            Err(err) => Err(err.into()),
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Module for the in-app notification inbox
+//!
+//! Unlike email notifications (see [`NotificationPreferences`](crate::NotificationPreferences)),
+//! inbox notifications are always created and do not require a verified email address. Users can
+//! page through their notifications and mark them as read.
+
+use crate::error::{Result, UserError};
+use chrono::NaiveDateTime;
+use log::info;
+use pointercrate_core::{error::CoreError, util::non_nullable};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Row};
+
+#[derive(Debug, Clone, Copy)]
+pub enum NotificationKind {
+    /// A record of the user's verified claimed player was approved or rejected
+    RecordDecision,
+
+    /// The user's permissions were changed by someone else
+    PermissionsChanged,
+
+    /// The user's claim on a player was verified
+    ClaimVerified,
+}
+
+impl NotificationKind {
+    fn to_sql(self) -> &'static str {
+        match self {
+            NotificationKind::RecordDecision => "record_decision",
+            NotificationKind::PermissionsChanged => "permissions_changed",
+            NotificationKind::ClaimVerified => "claim_verified",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Notification {
+    pub id: i32,
+
+    /// What caused this notification, as one of the snake case names of [`NotificationKind`]'s
+    /// variants
+    pub kind: String,
+    pub message: String,
+    pub created: NaiveDateTime,
+    pub read: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchNotification {
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub read: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationPagination {
+    #[serde(rename = "before", default, deserialize_with = "non_nullable")]
+    pub before_id: Option<i32>,
+
+    #[serde(rename = "after", default, deserialize_with = "non_nullable")]
+    pub after_id: Option<i32>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub limit: Option<u8>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub read: Option<bool>,
+}
+
+impl NotificationPagination {
+    /// Retrieves a page of the given user's notifications
+    pub async fn page(&self, user_id: i32, connection: &mut PgConnection) -> Result<Vec<Notification>> {
+        if let Some(limit) = self.limit {
+            if limit < 1 || limit > 100 {
+                return Err(CoreError::InvalidPaginationLimit.into())
+            }
+        }
+
+        if let (Some(after), Some(before)) = (self.before_id, self.after_id) {
+            if after < before {
+                return Err(CoreError::AfterSmallerBefore.into())
+            }
+        }
+
+        let query = if self.before_id.is_some() && self.after_id.is_none() {
+            "SELECT id, kind, message, created, read FROM notifications WHERE member = $1 AND (id < $2 OR $2 IS NULL) AND (id > $3 OR $3 \
+             IS NULL) AND (read = $4 OR $4 IS NULL) ORDER BY id DESC LIMIT $5"
+        } else {
+            "SELECT id, kind, message, created, read FROM notifications WHERE member = $1 AND (id < $2 OR $2 IS NULL) AND (id > $3 OR $3 \
+             IS NULL) AND (read = $4 OR $4 IS NULL) ORDER BY id ASC LIMIT $5"
+        };
+
+        let rows = sqlx::query(query)
+            .bind(user_id)
+            .bind(self.before_id)
+            .bind(self.after_id)
+            .bind(self.read)
+            .bind(self.limit.unwrap_or(50) as i32 + 1)
+            .fetch_all(connection)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                Notification {
+                    id: row.get("id"),
+                    kind: row.get("kind"),
+                    message: row.get("message"),
+                    created: row.get("created"),
+                    read: row.get("read"),
+                }
+            })
+            .collect())
+    }
+}
+
+impl Notification {
+    /// Puts a new, unread notification into the given user's inbox
+    pub async fn create(user_id: i32, kind: NotificationKind, message: &str, connection: &mut PgConnection) -> Result<()> {
+        info!("Notifying user {} ({}): {}", user_id, kind.to_sql(), message);
+
+        sqlx::query!(
+            "INSERT INTO notifications (member, kind, message) VALUES ($1, $2, $3)",
+            user_id,
+            kind.to_sql(),
+            message
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the given notification, failing if it does not belong to the given user
+    pub async fn by_id(notification_id: i32, user_id: i32, connection: &mut PgConnection) -> Result<Notification> {
+        sqlx::query_as!(
+            Notification,
+            "SELECT id, kind, message, created, read FROM notifications WHERE id = $1 AND member = $2",
+            notification_id,
+            user_id
+        )
+        .fetch_optional(connection)
+        .await?
+        .ok_or(UserError::NotificationNotFound { notification_id })
+    }
+
+    pub async fn apply_patch(mut self, patch: PatchNotification, connection: &mut PgConnection) -> Result<Notification> {
+        if let Some(read) = patch.read {
+            sqlx::query!("UPDATE notifications SET read = $1 WHERE id = $2", read, self.id)
+                .execute(connection)
+                .await?;
+
+            self.read = read;
+        }
+
+        Ok(self)
+    }
+
+    /// Gets the maximal and minimal id of the given user's notifications
+    ///
+    /// The returned tuple is of the form (max, min)
+    pub async fn extremal_ids(user_id: i32, connection: &mut PgConnection) -> Result<(i32, i32)> {
+        let row = sqlx::query!(
+            r#"SELECT COALESCE(MAX(id), 0) AS "max_id!: i32", COALESCE(MIN(id), 0) AS "min_id!: i32" FROM notifications WHERE member = $1"#,
+            user_id
+        )
+        .fetch_one(connection)
+        .await?;
+
+        Ok((row.max_id, row.min_id))
+    }
+}
@@ -6,12 +6,24 @@
 //! * Querying account information
 
 pub use self::{
-    auth::{AuthenticatedUser, PatchMe, Registration},
+    access_log::{
+        check_registration_limit, purge_access_log, AccessKind, NewRegistrationLimitExemption, RegistrationLimitExemption, SharedAccess,
+    },
+    auth::{
+        discord_oauth_state, discord_totp_challenge, log_impersonated_request, verify_discord_oauth_state, verify_discord_totp_challenge,
+        ApiKey, ApiKeyScope, AuthenticatedUser, HashAlgorithm, NewApiKey, PatchMe, Registration, Session, TotpEnrollment,
+    },
+    inbox::{Notification, NotificationKind, NotificationPagination, PatchNotification},
+    notifications::{NotificationPreferences, PatchNotificationPreferences},
     paginate::UserPagination,
     patch::PatchUser,
+    profile::{UserProfile, UserView},
 };
 use crate::error::{Result, UserError};
-use pointercrate_core::{etag::Taggable, permission::Permission};
+use pointercrate_core::{
+    etag::Taggable,
+    permission::{Permission, Role},
+};
 use serde::Serialize;
 pub use sqlx;
 use sqlx::PgConnection;
@@ -22,16 +34,27 @@ use std::{
 
 #[macro_use]
 mod get;
+mod access_log;
 mod auth;
+pub mod config;
 mod delete;
 pub mod error;
+mod inbox;
+mod notifications;
 mod paginate;
 mod patch;
+mod profile;
 mod video;
 
 pub const ADMINISTRATOR: Permission = Permission::new("Administrator", 0x4000);
 pub const MODERATOR: Permission = Permission::new("Moderator", 0x2000);
 
+/// The roles site staff is usually granted, to be registered with the
+/// [`PermissionsManager`](pointercrate_core::permission::PermissionsManager)
+pub fn roles() -> Vec<Role> {
+    vec![Role::new("Site Administration", vec![MODERATOR, ADMINISTRATOR])]
+}
+
 /// Model representing a user in the database
 #[derive(Debug, Serialize, Hash, Eq, PartialEq)]
 pub struct User {
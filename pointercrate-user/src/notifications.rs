@@ -0,0 +1,69 @@
+//! Module for users' notification preferences
+//!
+//! All notifications are opt-in, and are only sent to verified email addresses.
+
+use crate::error::Result;
+use log::info;
+use pointercrate_core::util::non_nullable;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+#[derive(Debug, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationPreferences {
+    /// Whether to be notified when records of the user's verified claimed player are approved or
+    /// rejected
+    pub record_decisions: bool,
+
+    /// Whether to be notified when many submissions are waiting for review. Only has an effect for
+    /// list moderators.
+    pub queue_size: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct PatchNotificationPreferences {
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub record_decisions: Option<bool>,
+
+    #[serde(default, deserialize_with = "non_nullable")]
+    pub queue_size: Option<bool>,
+}
+
+impl NotificationPreferences {
+    pub async fn of_user(user_id: i32, connection: &mut PgConnection) -> Result<NotificationPreferences> {
+        let preferences = sqlx::query_as!(
+            NotificationPreferences,
+            "SELECT record_decisions, queue_size FROM notification_preferences WHERE member = $1",
+            user_id
+        )
+        .fetch_optional(connection)
+        .await?;
+
+        Ok(preferences.unwrap_or_default())
+    }
+
+    pub async fn apply_patch(
+        mut self, user_id: i32, patch: PatchNotificationPreferences, connection: &mut PgConnection,
+    ) -> Result<NotificationPreferences> {
+        if let Some(record_decisions) = patch.record_decisions {
+            self.record_decisions = record_decisions;
+        }
+
+        if let Some(queue_size) = patch.queue_size {
+            self.queue_size = queue_size;
+        }
+
+        info!("Setting notification preferences of user {} to {:?}", user_id, self);
+
+        sqlx::query!(
+            "INSERT INTO notification_preferences (member, record_decisions, queue_size) VALUES ($1, $2, $3) ON CONFLICT (member) DO \
+             UPDATE SET record_decisions = EXCLUDED.record_decisions, queue_size = EXCLUDED.queue_size",
+            user_id,
+            self.record_decisions,
+            self.queue_size
+        )
+        .execute(connection)
+        .await?;
+
+        Ok(self)
+    }
+}
@@ -1,6 +1,9 @@
-use crate::{error::Result, User};
+use crate::{error::Result, Notification, NotificationKind, User};
 use log::info;
-use pointercrate_core::util::{non_nullable, nullable};
+use pointercrate_core::{
+    audit::PatchLog,
+    util::{non_nullable, nullable},
+};
 use serde::Deserialize;
 use sqlx::PgConnection;
 
@@ -23,7 +26,19 @@ impl User {
     pub async fn apply_patch(mut self, patch: PatchUser, connection: &mut PgConnection) -> Result<Self> {
         info!("Applying patch {:?} to {}", patch, self);
 
+        let log = PatchLog::start("user", self.id, &self);
+
         if let Some(permissions) = patch.permissions {
+            if permissions != self.permissions {
+                Notification::create(
+                    self.id,
+                    NotificationKind::PermissionsChanged,
+                    "Your permissions have been changed",
+                    &mut *connection,
+                )
+                .await?;
+            }
+
             self.set_permissions(permissions, connection).await?;
         }
 
@@ -41,6 +56,8 @@ impl User {
             }
         }
 
+        log.finish(&self, connection).await?;
+
         Ok(self)
     }
 
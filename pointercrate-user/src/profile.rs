@@ -0,0 +1,50 @@
+use crate::User;
+use serde::Serialize;
+
+/// Who a [`User`] is being shown to, which determines how much of their data is revealed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserView {
+    /// Anyone, including unauthenticated visitors
+    Public,
+
+    /// Someone allowed to manage this user (see the `GET /api/v1/users/<id>/` endpoint)
+    Staff,
+}
+
+/// The profile of a [`User`], as shown to a specific [`UserView`]
+#[derive(Debug, Serialize)]
+pub struct UserProfile {
+    pub id: i32,
+
+    /// The name the user is displayed under, see [`User::name`]
+    pub display_name: String,
+    pub youtube_channel: Option<String>,
+
+    /// The names of the roles whose permissions the user has all been granted
+    pub roles: Vec<&'static str>,
+
+    /// The user's login name. Only revealed to [`UserView::Staff`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The user's permission bitmask. Only revealed to [`UserView::Staff`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<u16>,
+}
+
+impl User {
+    /// Gets the profile of this user as seen by the given audience. Roles are not tracked in the
+    /// database, so they need to be passed in.
+    pub fn profile(&self, view: UserView, roles: Vec<&'static str>) -> UserProfile {
+        let is_staff = view == UserView::Staff;
+
+        UserProfile {
+            id: self.id,
+            display_name: self.name().to_string(),
+            youtube_channel: self.youtube_channel.clone(),
+            roles,
+            name: if is_staff { Some(self.name.clone()) } else { None },
+            permissions: if is_staff { Some(self.permissions) } else { None },
+        }
+    }
+}